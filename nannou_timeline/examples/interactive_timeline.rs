@@ -1,9 +1,12 @@
 //! Interactive Timeline Example - Shows the timeline with Flash-like controls
+//!
+//! The mock engine runs on its own thread behind a `HostedEngine`, so the
+//! timeline draws from engine snapshots and never waits for an edit.
 //! 
 //! Run with: cargo run --example interactive_timeline
 
 use eframe::egui::{self, Color32, Pos2, Rect, Response, Sense, Stroke, Ui, Vec2, Button};
-use nannou_timeline::{Timeline, TimelineConfig, ui::MockRiveEngine, RiveEngine, LayerId};
+use nannou_timeline::{Timeline, TimelineConfig, ui::MockRiveEngine, RiveEngine, LayerId, HostedEngine, LegacyRiveEngineAdapter};
 
 fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
@@ -43,7 +46,9 @@ enum ContextMenuType {
 
 impl Default for TimelineApp {
     fn default() -> Self {
-        let engine = Box::new(MockRiveEngine::new());
+        let engine = HostedEngine::spawn(Box::new(LegacyRiveEngineAdapter::new(MockRiveEngine::new())))
+            .expect("failed to spawn engine thread");
+        let engine = Box::new(engine);
         let layer_count = engine.get_layers().len();
         
         Self {
@@ -57,6 +57,8 @@ pub struct AudioSource {
 }
 
 impl AudioSource {
+    /// An unloaded source. Duration, sample rate and channels are placeholders
+    /// until an [`AudioEngine`] loads the file.
     pub fn new(file_path: PathBuf) -> Self {
         let filename = file_path
             .file_name()
@@ -123,10 +125,63 @@ impl AudioLayer {
     
     /// Get frame range this audio spans
     pub fn frame_range(&self, fps: f32) -> std::ops::Range<u32> {
-        let duration_frames = (self.effective_duration() * fps) as u32;
+        // Tolerate rounding in trims set from frame positions
+        let duration_frames = (self.effective_duration() * fps + 1e-3) as u32;
         self.start_frame..(self.start_frame + duration_frames)
     }
     
+    /// Move the clip's first frame to `frame`, trimming or revealing the
+    /// head of the sound while the tail stays put. At least one frame remains.
+    pub fn trim_head_to(&mut self, frame: u32, fps: f32) {
+        // Where the untrimmed sound would begin
+        let earliest = (self.start_frame as f32 - self.trim_start * fps).max(0.0).ceil();
+        let latest = self.frame_range(fps).end.saturating_sub(1).max(self.start_frame);
+        let frame = (frame as f32).clamp(earliest, latest as f32);
+        self.trim_start = (self.trim_start + (frame - self.start_frame as f32) / fps).max(0.0);
+        self.start_frame = frame as u32;
+    }
+    
+    /// Move the clip's end (exclusive) to `frame`, trimming or revealing the
+    /// tail of the sound. At least one frame remains.
+    pub fn trim_tail_to(&mut self, frame: u32, fps: f32) {
+        let start = self.start_frame as f32;
+        let untrimmed_end = start + (self.source.duration - self.trim_start) * fps;
+        let end = (frame as f32).clamp(start + 1.0, untrimmed_end.max(start + 1.0));
+        self.trim_end = (self.source.duration - self.trim_start - (end - start) / fps).max(0.0);
+    }
+    
+    /// Slide the sound under the clip by `seconds` (later when positive),
+    /// keeping the clip's frames. Stops where either end runs out of sound.
+    pub fn slip(&mut self, seconds: f32) {
+        let seconds = seconds.clamp(-self.trim_end, self.trim_start);
+        self.trim_start -= seconds;
+        self.trim_end += seconds;
+    }
+    
+    /// Cut the clip at timeline `frame`, keeping the part before it and
+    /// returning the part from it on. The envelope is cut with it. Returns
+    /// `None` unless `frame` is strictly inside the clip. Clips on a track
+    /// are split with [`AudioTrack::split_at`], which keeps the overlap rules.
+    pub fn split_at(&mut self, frame: u32, fps: f32) -> Option<AudioLayer> {
+        let range = self.frame_range(fps);
+        if frame <= range.start || frame >= range.end {
+            return None;
+        }
+        let cut = frame - self.start_frame;
+        let level = self.volume_envelope.volume_at_frame(cut);
+
+        let mut tail = self.clone();
+        tail.trim_head_to(frame, fps);
+        tail.volume_envelope.points = std::iter::once((0, level))
+            .chain(self.volume_envelope.points.iter().filter(|(f, _)| *f > cut).map(|&(f, v)| (f - cut, v)))
+            .collect();
+
+        self.trim_tail_to(frame, fps);
+        self.volume_envelope.points.retain(|(f, _)| *f < cut);
+        self.volume_envelope.points.push((cut, level));
+        Some(tail)
+    }
+    
     /// Get audio time at a given frame
     pub fn audio_time_at_frame(&self, frame: u32, fps: f32) -> Option<f32> {
         if frame < self.start_frame {
@@ -144,6 +199,169 @@ impl AudioLayer {
     }
 }
 
+/// The clips on one audio layer, ordered by start frame
+///
+/// A clip may overlap the clips on either side of it and the shared frames
+/// crossfade from one to the other. A clip can't start on the same frame as
+/// another, lie within another or overlap two others at once.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(try_from = "AudioTrackData")]
+pub struct AudioTrack {
+    clips: Vec<AudioLayer>,
+    /// Frame rate the overlap rules were last checked at, and are checked at
+    /// again when deserializing
+    fps: f32,
+}
+
+/// Serialized form of an [`AudioTrack`], checked before use
+#[derive(Deserialize)]
+struct AudioTrackData {
+    clips: Vec<AudioLayer>,
+    fps: f32,
+}
+
+impl TryFrom<AudioTrackData> for AudioTrack {
+    type Error = AudioError;
+
+    fn try_from(data: AudioTrackData) -> Result<Self, Self::Error> {
+        let mut track = Self::new();
+        track.set_clips(data.clips, data.fps, 0)?;
+        Ok(track)
+    }
+}
+
+/// Frames where one clip of a track fades out as the next fades in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crossfade {
+    pub frames: std::ops::Range<u32>,
+    /// Index of the clip fading out
+    pub from: usize,
+    /// Index of the clip fading in
+    pub to: usize,
+}
+
+impl AudioTrack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn clips(&self) -> &[AudioLayer] {
+        &self.clips
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.clips.is_empty()
+    }
+    
+    /// Add a clip, returning its index
+    pub fn add_clip(&mut self, clip: AudioLayer, fps: f32) -> Result<usize, AudioError> {
+        let mut clips = self.clips.clone();
+        clips.push(clip);
+        self.set_clips(clips, fps, self.clips.len())
+    }
+    
+    /// Replace the clip at `index`, returning its index after reordering.
+    /// The track is unchanged if the new clip breaks the overlap rules.
+    pub fn replace_clip(&mut self, index: usize, clip: AudioLayer, fps: f32) -> Result<usize, AudioError> {
+        if index >= self.clips.len() {
+            return Err(AudioError::ClipNotFound);
+        }
+        let mut clips = self.clips.clone();
+        clips[index] = clip;
+        self.set_clips(clips, fps, index)
+    }
+    
+    /// Envelope of the clip at `index`; editing it can't break the overlap rules
+    pub fn envelope_mut(&mut self, index: usize) -> Option<&mut VolumeEnvelope> {
+        self.clips.get_mut(index).map(|clip| &mut clip.volume_envelope)
+    }
+    
+    pub fn remove_clip(&mut self, index: usize) -> Option<AudioLayer> {
+        (index < self.clips.len()).then(|| self.clips.remove(index))
+    }
+    
+    /// Sort and validate `clips`, returning the new index of `clips[tracked]`
+    fn set_clips(&mut self, clips: Vec<AudioLayer>, fps: f32, tracked: usize) -> Result<usize, AudioError> {
+        let mut order: Vec<usize> = (0..clips.len()).collect();
+        order.sort_by_key(|&i| clips[i].start_frame);
+        let ranges: Vec<_> = order.iter().map(|&i| clips[i].frame_range(fps)).collect();
+        for (i, range) in ranges.iter().enumerate() {
+            let next_inside = ranges.get(i + 1).is_some_and(|next| next.start == range.start || next.end <= range.end);
+            let overlaps_two = ranges.get(i + 2).is_some_and(|after| after.start < range.end);
+            if next_inside || overlaps_two {
+                return Err(AudioError::ClipOverlap);
+            }
+        }
+        let index = order.iter().position(|&i| i == tracked).unwrap_or(0);
+        let mut clips: Vec<Option<AudioLayer>> = clips.into_iter().map(Some).collect();
+        self.clips = order.iter().filter_map(|&i| clips[i].take()).collect();
+        self.fps = fps;
+        Ok(index)
+    }
+    
+    /// Index of the clip playing at timeline `frame`; during a crossfade,
+    /// the one fading in
+    pub fn clip_at(&self, frame: u32, fps: f32) -> Option<usize> {
+        self.clips.iter().rposition(|clip| clip.frame_range(fps).contains(&frame))
+    }
+    
+    /// Split the clip playing at timeline `frame` in two, returning the
+    /// index of the second half. The track is unchanged if the halves would
+    /// break the overlap rules, as they do inside a crossfade.
+    pub fn split_at(&mut self, frame: u32, fps: f32) -> Option<usize> {
+        let index = self.clip_at(frame, fps)?;
+        self.split_clip(index, frame, fps)
+    }
+    
+    /// Split the clip at `index` in two at timeline `frame`, like
+    /// [`split_at`](Self::split_at)
+    pub fn split_clip(&mut self, index: usize, frame: u32, fps: f32) -> Option<usize> {
+        let mut clips = self.clips.clone();
+        let tail = clips.get_mut(index)?.split_at(frame, fps)?;
+        clips.push(tail);
+        let tracked = clips.len() - 1;
+        self.set_clips(clips, fps, tracked).ok()
+    }
+    
+    /// Overlaps between neighbouring clips
+    pub fn crossfades(&self, fps: f32) -> Vec<Crossfade> {
+        self.clips
+            .windows(2)
+            .enumerate()
+            .filter_map(|(from, pair)| {
+                let end = pair[0].frame_range(fps).end;
+                let start = pair[1].start_frame;
+                (start < end).then(|| Crossfade { frames: start..end, from, to: from + 1 })
+            })
+            .collect()
+    }
+    
+    /// Crossfade level of clip `index` at a (fractional) timeline frame:
+    /// rising linearly across an overlap with the previous clip, falling
+    /// across one with the next, and 1 elsewhere
+    pub fn crossfade_gain(&self, index: usize, frame: f32, fps: f32) -> f32 {
+        let fade = |frames: std::ops::Range<u32>| {
+            ((frame - frames.start as f32) / (frames.end - frames.start) as f32).clamp(0.0, 1.0)
+        };
+        let mut gain = 1.0;
+        for crossfade in self.crossfades(fps) {
+            let inside = frame >= crossfade.frames.start as f32 && frame < crossfade.frames.end as f32;
+            if inside && crossfade.to == index {
+                gain *= fade(crossfade.frames);
+            } else if inside && crossfade.from == index {
+                gain *= 1.0 - fade(crossfade.frames);
+            }
+        }
+        gain
+    }
+}
+
+impl From<AudioLayer> for AudioTrack {
+    fn from(clip: AudioLayer) -> Self {
+        Self { clips: vec![clip], fps: 0.0 }
+    }
+}
+
 /// Volume envelope for dynamic volume control
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VolumeEnvelope {
@@ -185,6 +403,33 @@ impl VolumeEnvelope {
         }
     }
     
+    /// Move the point at `from` to `to`, keeping it between its neighbours,
+    /// and set its volume. Returns the frame it ended up on.
+    pub fn move_point(&mut self, from: u32, to: u32, volume: f32) -> Option<u32> {
+        let index = self.points.iter().position(|(f, _)| *f == from)?;
+        let earliest = index.checked_sub(1).map_or(0, |i| self.points[i].0 + 1);
+        let latest = self.points.get(index + 1).map_or(u32::MAX, |(f, _)| f - 1);
+        let frame = to.clamp(earliest, latest);
+        self.points[index] = (frame, volume.clamp(0.0, 1.0));
+        Some(frame)
+    }
+    
+    /// Envelope of `preset` for a sound `length` frames long, fading over
+    /// `fade` frames
+    pub fn from_preset(preset: EnvelopePreset, length: u32, fade: u32) -> Self {
+        let points = match preset {
+            EnvelopePreset::None => vec![(0, 1.0)],
+            EnvelopePreset::FadeIn => vec![(0, 0.0), (fade.min(length).max(1), 1.0)],
+            EnvelopePreset::FadeOut => vec![(length.saturating_sub(fade.max(1)), 1.0), (length.max(1), 0.0)],
+            EnvelopePreset::FadeInOut => {
+                let fade = fade.min(length.saturating_sub(1) / 2).max(1);
+                let length = length.max(fade * 2 + 1);
+                vec![(0, 0.0), (fade, 1.0), (length - fade, 1.0), (length, 0.0)]
+            }
+        };
+        Self { points }
+    }
+    
     /// Get interpolated volume at a specific frame
     pub fn volume_at_frame(&self, frame: u32) -> f32 {
         if self.points.is_empty() {
@@ -223,6 +468,29 @@ impl Default for VolumeEnvelope {
     }
 }
 
+/// Envelope shapes offered by the Edit Envelope dialog
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvelopePreset {
+    /// Full volume throughout
+    None,
+    FadeIn,
+    FadeOut,
+    FadeInOut,
+}
+
+impl EnvelopePreset {
+    pub const ALL: [EnvelopePreset; 4] = [Self::None, Self::FadeIn, Self::FadeOut, Self::FadeInOut];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::FadeIn => "Fade in",
+            Self::FadeOut => "Fade out",
+            Self::FadeInOut => "Fade in and out",
+        }
+    }
+}
+
 /// Waveform data for visualization
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WaveformData {
@@ -234,6 +502,12 @@ pub struct WaveformData {
     pub fps: f32,
     /// Whether generation is complete
     pub complete: bool,
+    /// Sample rate the pyramids are indexed by
+    #[serde(default)]
+    pub sample_rate: u32,
+    /// Multi-resolution peaks per channel; empty if only `peaks` was generated
+    #[serde(default)]
+    pub channels: Vec<crate::waveform::PeakPyramid>,
 }
 
 impl WaveformData {
@@ -243,8 +517,18 @@ impl WaveformData {
             peaks: Vec::new(),
             fps,
             complete: false,
+            sample_rate: 0,
+            channels: Vec::new(),
         }
     }
+
+    /// Min/max of `channel` over `buckets` equal slices of `start..end`
+    /// seconds, read from the peak pyramid
+    pub fn peaks_between(&self, channel: usize, start: f32, end: f32, buckets: usize) -> Vec<(f32, f32)> {
+        let Some(pyramid) = self.channels.get(channel) else { return Vec::new() };
+        let to_sample = |time: f32| (time.max(0.0) as f64 * self.sample_rate as f64) as usize;
+        pyramid.peaks(to_sample(start), to_sample(end), buckets)
+    }
     
     /// Get peaks for a specific frame range
     pub fn peaks_for_range(&self, start_frame: u32, end_frame: u32) -> &[(f32, f32)] {
@@ -270,9 +554,21 @@ pub trait AudioEngine: Send + Sync {
     /// Play audio segment
     fn play_segment(&mut self, audio_id: &AudioId, start_time: f32, duration: f32, volume: f32) -> Result<(), AudioError>;
     
+    /// Play a short snippet while the playhead is scrubbed, fading it in and
+    /// out over `fade` seconds so back-to-back snippets don't click. Engines
+    /// that can't fade play the segment as is.
+    fn play_scrub_snippet(&mut self, audio_id: &AudioId, start_time: f32, duration: f32, volume: f32, _fade: f32) -> Result<(), AudioError> {
+        self.play_segment(audio_id, start_time, duration, volume)
+    }
+    
     /// Stop audio playback
     fn stop_audio(&mut self, audio_id: &AudioId) -> Result<(), AudioError>;
     
+    /// Decoded samples of a loaded sound, for engines that keep them
+    fn decoded_audio(&self, _audio_id: &AudioId) -> Option<std::sync::Arc<crate::wav::WavData>> {
+        None
+    }
+    
     /// Set global volume
     fn set_global_volume(&mut self, volume: f32);
     
@@ -290,7 +586,12 @@ pub enum AudioError {
     UnsupportedFormat,
     DecodingError(String),
     PlaybackError(String),
+    WriteError(String),
     AudioNotLoaded,
+    /// No clip at the given index of an [`AudioTrack`]
+    ClipNotFound,
+    /// A clip would break the [`AudioTrack`] overlap rules
+    ClipOverlap,
 }
 
 impl std::fmt::Display for AudioError {
@@ -300,7 +601,10 @@ impl std::fmt::Display for AudioError {
             AudioError::UnsupportedFormat => write!(f, "Unsupported audio format"),
             AudioError::DecodingError(msg) => write!(f, "Audio decoding error: {}", msg),
             AudioError::PlaybackError(msg) => write!(f, "Audio playback error: {}", msg),
+            AudioError::WriteError(msg) => write!(f, "Audio write error: {}", msg),
             AudioError::AudioNotLoaded => write!(f, "Audio not loaded"),
+            AudioError::ClipNotFound => write!(f, "Audio clip not found"),
+            AudioError::ClipOverlap => write!(f, "Audio clips overlap in a way that can't crossfade"),
         }
     }
 }
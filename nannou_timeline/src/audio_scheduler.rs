@@ -0,0 +1,358 @@
+//! Frame-accurate playback of audio layers
+//!
+//! [`AudioScheduler`] follows the playhead and turns the [`AudioSyncMode`] of
+//! each [`AudioLayer`] into [`AudioEngine`] calls, the way Flash does:
+//!
+//! - **Event** plays the whole sound when its start frame is reached. Every
+//!   pass over the frame starts another instance, so instances overlap.
+//! - **Start** is like Event but doesn't start the sound again while an
+//!   earlier instance is still playing.
+//! - **Stop** silences every instance of the sound when its start frame is reached.
+//! - **Stream** is tied to the playhead: it plays from the matching offset
+//!   while the playhead is inside the clip, restarts from the new offset on a
+//!   seek and stops with playback. While a stream plays, the audio is the
+//!   clock and video frames are dropped to keep up with it.
+//!
+//! The scheduler works on clips; for layers holding an [`AudioTrack`](crate::AudioTrack),
+//! pass the clips of every track.
+
+use crate::audio::{AudioEngine, AudioError, AudioId, AudioLayer, AudioSyncMode};
+use std::collections::HashSet;
+
+/// Where the playhead went on a [`AudioScheduler::tick`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStep {
+    /// Frame to display
+    pub frame: u32,
+    /// Frames skipped since the previous step to stay in sync with a stream
+    pub dropped_frames: u32,
+}
+
+/// An Event or Start sound that was started and may still be playing
+#[derive(Clone, Debug)]
+struct Voice {
+    audio_id: AudioId,
+    /// Scheduler time at which the sound runs out
+    ends_at: f64,
+}
+
+/// Decides which audio calls to make as the playhead moves
+#[derive(Clone, Debug)]
+pub struct AudioScheduler {
+    fps: f32,
+    playing: bool,
+    frame: u32,
+    /// Frame and playback time of the last play or seek; frames are counted from here
+    origin_frame: u32,
+    playback_time: f64,
+    /// Total time passed to `tick`, used to tell when voices end
+    time: f64,
+    voices: Vec<Voice>,
+    streams: HashSet<AudioId>,
+}
+
+impl AudioScheduler {
+    pub fn new(fps: f32) -> Self {
+        Self {
+            fps,
+            playing: false,
+            frame: 0,
+            origin_frame: 0,
+            playback_time: 0.0,
+            time: 0.0,
+            voices: Vec::new(),
+            streams: HashSet::new(),
+        }
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn current_frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whether a stream is playing and driving the playhead
+    pub fn is_streaming(&self) -> bool {
+        !self.streams.is_empty()
+    }
+
+    /// Start playback at `frame`, triggering the sounds that start there
+    pub fn play(&mut self, frame: u32, layers: &[AudioLayer], engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        self.playing = true;
+        self.reset_clock(frame);
+        self.trigger_frame(frame, layers, engine)?;
+        self.sync_streams(layers, engine)
+    }
+
+    /// Stop playback. Streams stop; Event and Start sounds play out.
+    pub fn stop(&mut self, engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        self.playing = false;
+        for audio_id in std::mem::take(&mut self.streams) {
+            engine.stop_audio(&audio_id)?;
+        }
+        Ok(())
+    }
+
+    /// Stop playback and every sound the scheduler started
+    pub fn stop_all(&mut self, engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        self.stop(engine)?;
+        let mut stopped = HashSet::new();
+        for voice in std::mem::take(&mut self.voices) {
+            if stopped.insert(voice.audio_id.clone()) {
+                engine.stop_audio(&voice.audio_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Jump to `frame`. Sounds starting between the old and new frame are
+    /// skipped; streams restart from the new position.
+    pub fn seek(&mut self, frame: u32, layers: &[AudioLayer], engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        self.reset_clock(frame);
+        if !self.playing {
+            return Ok(());
+        }
+        for audio_id in std::mem::take(&mut self.streams) {
+            engine.stop_audio(&audio_id)?;
+        }
+        self.sync_streams(layers, engine)
+    }
+
+    /// Advance playback by `dt` seconds.
+    ///
+    /// Returns the new frame once the playhead moves. Without a stream the
+    /// playhead moves at most one frame per tick, so every frame is shown;
+    /// with one it jumps to the stream's position and reports the skipped
+    /// frames. Sounds starting on skipped frames are still triggered.
+    pub fn tick(&mut self, dt: f32, layers: &[AudioLayer], engine: &mut dyn AudioEngine) -> Result<Option<FrameStep>, AudioError> {
+        self.time += dt as f64;
+        let time = self.time;
+        self.voices.retain(|voice| voice.ends_at > time);
+        if !self.playing {
+            return Ok(None);
+        }
+
+        self.playback_time += dt as f64;
+        let target = self.origin_frame + (self.playback_time * self.fps as f64 + 1e-6).floor() as u32;
+        if target <= self.frame {
+            return Ok(None);
+        }
+        let next = if self.is_streaming() {
+            target
+        } else {
+            // Drop the time we fell behind rather than catching up later
+            self.playback_time = self.playback_time.min((self.frame + 1 - self.origin_frame) as f64 / self.fps as f64);
+            self.frame + 1
+        };
+
+        let previous = self.frame;
+        for frame in previous + 1..=next {
+            self.trigger_frame(frame, layers, engine)?;
+        }
+        self.frame = next;
+        self.sync_streams(layers, engine)?;
+        Ok(Some(FrameStep { frame: next, dropped_frames: next - previous - 1 }))
+    }
+
+    fn reset_clock(&mut self, frame: u32) {
+        self.frame = frame;
+        self.origin_frame = frame;
+        self.playback_time = 0.0;
+    }
+
+    fn volume_at(layer: &AudioLayer, frame: u32) -> f32 {
+        layer.volume * layer.volume_envelope.volume_at_frame(frame.saturating_sub(layer.start_frame))
+    }
+
+    /// Handle Event, Start and Stop sounds that begin on `frame`
+    fn trigger_frame(&mut self, frame: u32, layers: &[AudioLayer], engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        for layer in layers.iter().filter(|layer| layer.start_frame == frame) {
+            let audio_id = &layer.source.id;
+            match layer.sync_mode {
+                AudioSyncMode::Start if self.voices.iter().any(|v| v.audio_id == *audio_id) => {}
+                AudioSyncMode::Event | AudioSyncMode::Start => {
+                    let duration = layer.effective_duration();
+                    engine.play_segment(audio_id, layer.trim_start, duration, Self::volume_at(layer, frame))?;
+                    self.voices.push(Voice { audio_id: audio_id.clone(), ends_at: self.time + duration as f64 });
+                }
+                AudioSyncMode::Stop => {
+                    engine.stop_audio(audio_id)?;
+                    self.voices.retain(|v| v.audio_id != *audio_id);
+                }
+                AudioSyncMode::Stream => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Start streams the playhead has entered and stop the ones it has left
+    fn sync_streams(&mut self, layers: &[AudioLayer], engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        for layer in layers.iter().filter(|layer| layer.sync_mode == AudioSyncMode::Stream) {
+            let audio_id = &layer.source.id;
+            let inside = layer.frame_range(self.fps).contains(&self.frame);
+            let playing = self.streams.contains(audio_id);
+            if inside && !playing {
+                let Some(start_time) = layer.audio_time_at_frame(self.frame, self.fps) else { continue };
+                let duration = layer.source.duration - layer.trim_end - start_time;
+                engine.play_segment(audio_id, start_time, duration, Self::volume_at(layer, self.frame))?;
+                self.streams.insert(audio_id.clone());
+            } else if !inside && playing {
+                engine.stop_audio(audio_id)?;
+                self.streams.remove(audio_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{MockAudioCall, MockAudioEngine};
+
+    const FPS: f32 = 24.0;
+    const FRAME: f32 = 1.0 / FPS;
+
+    fn layer(engine: &mut MockAudioEngine, duration: f32, start_frame: u32, sync_mode: AudioSyncMode) -> AudioLayer {
+        let source = engine.create_mock_audio("sound.wav", duration);
+        let mut layer = AudioLayer::new(source, start_frame);
+        layer.sync_mode = sync_mode;
+        layer
+    }
+
+    fn plays(calls: &[MockAudioCall]) -> Vec<f32> {
+        calls.iter()
+            .filter_map(|call| match call {
+                MockAudioCall::PlaySegment { start_time, .. } => Some(*start_time),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn stops(calls: &[MockAudioCall]) -> usize {
+        calls.iter().filter(|call| matches!(call, MockAudioCall::StopAudio { .. })).count()
+    }
+
+    #[test]
+    fn test_event_sounds_overlap() {
+        let mut engine = MockAudioEngine::new();
+        let layers = vec![layer(&mut engine, 10.0, 2, AudioSyncMode::Event)];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        for _ in 0..3 {
+            scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        }
+        scheduler.seek(0, &layers, &mut engine).unwrap();
+        for _ in 0..3 {
+            scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        }
+
+        let calls = engine.take_calls();
+        assert_eq!(plays(&calls), vec![0.0, 0.0]);
+        assert_eq!(stops(&calls), 0);
+    }
+
+    #[test]
+    fn test_start_sounds_play_once_at_a_time() {
+        let mut engine = MockAudioEngine::new();
+        let layers = vec![layer(&mut engine, 0.5, 1, AudioSyncMode::Start)];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        scheduler.seek(0, &layers, &mut engine).unwrap();
+        scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        assert_eq!(plays(engine.calls()).len(), 1, "still playing, so not started again");
+
+        // Once the half-second sound has ended it can start again
+        scheduler.tick(0.5, &layers, &mut engine).unwrap();
+        scheduler.seek(0, &layers, &mut engine).unwrap();
+        scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        assert_eq!(plays(engine.calls()).len(), 2);
+    }
+
+    #[test]
+    fn test_stop_silences_sound() {
+        let mut engine = MockAudioEngine::new();
+        let event = layer(&mut engine, 10.0, 0, AudioSyncMode::Event);
+        let mut stop = AudioLayer::new(event.source.clone(), 3);
+        stop.sync_mode = AudioSyncMode::Stop;
+        let layers = vec![event, stop];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        for _ in 0..3 {
+            scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        }
+
+        let calls = engine.take_calls();
+        assert!(matches!(calls.last(), Some(MockAudioCall::StopAudio { audio_id }) if *audio_id == layers[0].source.id));
+        assert!(!engine.is_playing(&layers[0].source.id));
+    }
+
+    #[test]
+    fn test_stream_follows_seeks_and_stops_with_playback() {
+        let mut engine = MockAudioEngine::new();
+        let layers = vec![layer(&mut engine, 4.0, 0, AudioSyncMode::Stream)];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        scheduler.seek(24, &layers, &mut engine).unwrap();
+        scheduler.stop(&mut engine).unwrap();
+
+        let calls = engine.take_calls();
+        assert_eq!(plays(&calls), vec![0.0, 1.0]);
+        assert!(matches!(calls[2], MockAudioCall::PlaySegment { duration, .. } if (duration - 3.0).abs() < 1e-4));
+        assert_eq!(stops(&calls), 2);
+        assert!(!engine.is_playing(&layers[0].source.id));
+    }
+
+    #[test]
+    fn test_stream_ends_with_its_clip() {
+        let mut engine = MockAudioEngine::new();
+        let layers = vec![layer(&mut engine, 0.25, 0, AudioSyncMode::Stream)];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        for _ in 0..6 {
+            scheduler.tick(FRAME, &layers, &mut engine).unwrap();
+        }
+        assert!(!scheduler.is_streaming(), "a quarter second is six frames");
+        assert_eq!(stops(engine.calls()), 1);
+    }
+
+    #[test]
+    fn test_stream_drops_frames_to_keep_sync() {
+        let mut engine = MockAudioEngine::new();
+        let event = layer(&mut engine, 1.0, 2, AudioSyncMode::Event);
+        let stream = layer(&mut engine, 4.0, 0, AudioSyncMode::Stream);
+        let layers = vec![event, stream];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        let step = scheduler.tick(3.0 * FRAME, &layers, &mut engine).unwrap();
+        assert_eq!(step, Some(FrameStep { frame: 3, dropped_frames: 2 }));
+        // The event on a dropped frame still plays
+        assert_eq!(plays(engine.calls()).len(), 2);
+    }
+
+    #[test]
+    fn test_without_stream_every_frame_is_shown() {
+        let mut engine = MockAudioEngine::new();
+        let layers = vec![layer(&mut engine, 1.0, 0, AudioSyncMode::Event)];
+        let mut scheduler = AudioScheduler::new(FPS);
+
+        scheduler.play(0, &layers, &mut engine).unwrap();
+        let step = scheduler.tick(3.0 * FRAME, &layers, &mut engine).unwrap();
+        assert_eq!(step, Some(FrameStep { frame: 1, dropped_frames: 0 }));
+        let step = scheduler.tick(0.0, &layers, &mut engine).unwrap();
+        assert_eq!(step, None, "lost time is not caught up");
+    }
+}
@@ -0,0 +1,179 @@
+//! Audio feedback while scrubbing the playhead
+//!
+//! Each time the scrubbed playhead lands on a new frame, [`AudioScrubber`]
+//! plays a short snippet of every clip under it through
+//! [`AudioEngine::play_scrub_snippet`]. Snippets get longer the faster the
+//! playhead moves: at normal playback speed a snippet lasts
+//! [`AudioScrubber::base_snippet`], and it scales with the scrub speed
+//! between the minimum and maximum lengths, and fades in and out over
+//! [`AudioScrubber::fade`].
+
+use crate::audio::{AudioEngine, AudioError, AudioId, AudioTrack};
+
+/// Turns playhead moves into audio snippets
+#[derive(Clone, Debug)]
+pub struct AudioScrubber {
+    /// Snippet length in seconds when scrubbing at playback speed
+    pub base_snippet: f32,
+    /// Shortest snippet in seconds
+    pub min_snippet: f32,
+    /// Longest snippet in seconds
+    pub max_snippet: f32,
+    /// Fade in and out of each snippet in seconds
+    pub fade: f32,
+    /// Frame and time of the previous snippet
+    last: Option<(u32, f64)>,
+    /// Sounds started by the previous snippet
+    sounding: Vec<AudioId>,
+}
+
+impl Default for AudioScrubber {
+    fn default() -> Self {
+        Self {
+            base_snippet: 0.08,
+            min_snippet: 0.03,
+            max_snippet: 0.25,
+            fade: 0.005,
+            last: None,
+            sounding: Vec::new(),
+        }
+    }
+}
+
+impl AudioScrubber {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snippet length for the playhead moving `frames` in `seconds`
+    pub fn snippet_length(&self, frames: u32, seconds: f64, fps: f32) -> f32 {
+        // Pointer events can arrive together; treat them as a 120 Hz update
+        let speed = frames as f64 / seconds.max(1.0 / 120.0) / fps as f64;
+        (self.base_snippet * speed as f32).clamp(self.min_snippet, self.max_snippet)
+    }
+
+    /// The scrubbed playhead is at `frame` at `time` seconds. Plays a snippet
+    /// of every clip of `tracks` there, replacing the previous snippet; does
+    /// nothing if the frame hasn't changed.
+    pub fn scrub<'a>(
+        &mut self,
+        frame: u32,
+        time: f64,
+        tracks: impl IntoIterator<Item = &'a AudioTrack>,
+        engine: &mut dyn AudioEngine,
+        fps: f32,
+    ) -> Result<(), AudioError> {
+        let length = match self.last {
+            Some((last_frame, _)) if last_frame == frame => return Ok(()),
+            Some((last_frame, last_time)) => self.snippet_length(frame.abs_diff(last_frame), time - last_time, fps),
+            None => self.min_snippet,
+        };
+        self.last = Some((frame, time));
+        self.stop(engine)?;
+
+        for track in tracks {
+            for (index, clip) in track.clips().iter().enumerate() {
+                if !clip.frame_range(fps).contains(&frame) {
+                    continue;
+                }
+                let Some(start_time) = clip.audio_time_at_frame(frame, fps) else { continue };
+                let duration = length.min(clip.source.duration - clip.trim_end - start_time);
+                let volume = clip.volume
+                    * clip.volume_envelope.volume_at_frame(frame - clip.start_frame)
+                    * track.crossfade_gain(index, frame as f32, fps);
+                engine.play_scrub_snippet(&clip.source.id, start_time, duration, volume, self.fade)?;
+                self.sounding.push(clip.source.id.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Silence the current snippet and forget the last scrub position
+    pub fn finish(&mut self, engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        self.last = None;
+        self.stop(engine)
+    }
+
+    fn stop(&mut self, engine: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        for audio_id in self.sounding.drain(..) {
+            engine.stop_audio(&audio_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioLayer;
+    use crate::ui::{MockAudioCall, MockAudioEngine};
+
+    fn track(engine: &mut MockAudioEngine) -> AudioTrack {
+        let mut source = engine.load_audio(std::path::Path::new("voice.wav")).unwrap();
+        source.duration = 2.0;
+        let mut clip = AudioLayer::new(source, 10);
+        clip.volume = 0.5;
+        AudioTrack::from(clip)
+    }
+
+    fn snippets(calls: &[MockAudioCall]) -> Vec<(f32, f32, f32)> {
+        calls
+            .iter()
+            .filter_map(|call| match call {
+                MockAudioCall::PlayScrubSnippet { start_time, duration, volume, .. } => Some((*start_time, *duration, *volume)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_snippets_follow_the_playhead() {
+        let mut engine = MockAudioEngine::new();
+        let tracks = [track(&mut engine)];
+        engine.take_calls();
+        let mut scrubber = AudioScrubber::new();
+
+        scrubber.scrub(5, 0.0, &tracks, &mut engine, 10.0).unwrap();
+        assert!(engine.take_calls().is_empty());
+
+        scrubber.scrub(12, 0.5, &tracks, &mut engine, 10.0).unwrap();
+        scrubber.scrub(12, 0.6, &tracks, &mut engine, 10.0).unwrap();
+        let calls = engine.take_calls();
+        // 7 frames in 0.5 s at 10 fps is 1.4x playback speed
+        assert_eq!(snippets(&calls), vec![(0.2, 0.08 * 1.4, 0.5)]);
+
+        // The previous snippet is stopped before the next starts
+        scrubber.fade = 0.01;
+        scrubber.scrub(13, 0.7, &tracks, &mut engine, 10.0).unwrap();
+        let calls = engine.take_calls();
+        assert!(matches!(calls[0], MockAudioCall::StopAudio { .. }));
+        assert!(matches!(calls[1], MockAudioCall::PlayScrubSnippet { fade, .. } if fade == 0.01));
+        assert_eq!(snippets(&calls).len(), 1);
+    }
+
+    #[test]
+    fn test_snippet_length_depends_on_speed() {
+        let scrubber = AudioScrubber::new();
+        assert_eq!(scrubber.snippet_length(1, 1.0, 24.0), 0.03);
+        assert_eq!(scrubber.snippet_length(24, 1.0, 24.0), 0.08);
+        assert_eq!(scrubber.snippet_length(2, 0.0, 24.0), 0.25);
+    }
+
+    #[test]
+    fn test_snippets_stop_at_the_clip_end() {
+        let mut engine = MockAudioEngine::new();
+        let tracks = [track(&mut engine)];
+        engine.take_calls();
+        let mut scrubber = AudioScrubber::new();
+
+        scrubber.scrub(20, 0.0, &tracks, &mut engine, 10.0).unwrap();
+        engine.take_calls();
+        scrubber.scrub(29, 0.1, &tracks, &mut engine, 10.0).unwrap();
+        let played = snippets(&engine.take_calls());
+        assert_eq!(played.len(), 1);
+        assert!((played[0].1 - 0.1).abs() < 1e-5);
+
+        scrubber.finish(&mut engine).unwrap();
+        assert!(matches!(engine.take_calls().as_slice(), [MockAudioCall::StopAudio { .. }]));
+    }
+}
@@ -0,0 +1,432 @@
+//! Automation lanes: property envelopes drawn under a layer
+//!
+//! An [`AutomationLane`] binds an envelope to a property of its layer:
+//!
+//! - a numeric envelope of [`CurvePoint`]s, interpolated with the `envelope`
+//!   crate's bezier curve so each segment can ease in or out;
+//! - a toggle envelope of on/off changes, shown as spans where it is on;
+//! - a bang envelope of trigger frames.
+//!
+//! The controller keeps lanes per layer and sends their values to the engine
+//! as the playhead moves (see
+//! [`TimelineController::apply_automation`](crate::TimelineController::apply_automation)).
+
+use crate::easing::PropertyId;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Height of an automation lane in the frame grid
+pub const LANE_HEIGHT: f32 = 40.0;
+
+/// A point of a numeric envelope
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    pub frame: u32,
+    pub value: f32,
+    /// Bend of the segment to the next point, from -1.0 (eases in) through
+    /// 0.0 (straight) to 1.0 (eases out)
+    pub curve: f32,
+}
+
+impl CurvePoint {
+    pub fn new(frame: u32, value: f32) -> Self {
+        Self { frame, value, curve: 0.0 }
+    }
+}
+
+impl envelope::Point for CurvePoint {
+    type X = u32;
+    type Y = f32;
+
+    fn x_to_scalar(x: u32) -> f32 {
+        x as f32
+    }
+
+    fn x(&self) -> u32 {
+        self.frame
+    }
+
+    fn y(&self) -> f32 {
+        self.value
+    }
+
+    fn interpolate(x: u32, start: &Self, end: &Self) -> f32 {
+        envelope::interpolate::bezier(x, start, end, start.curve)
+    }
+}
+
+/// Numeric values between `min` and `max` over time
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NumericEnvelope {
+    pub min: f32,
+    pub max: f32,
+    /// Sorted by frame, at most one per frame
+    pub points: Vec<CurvePoint>,
+}
+
+impl<'a> envelope::Envelope<'a> for NumericEnvelope {
+    type X = u32;
+    type Y = f32;
+    type Point = CurvePoint;
+    type Points = std::slice::Iter<'a, CurvePoint>;
+
+    fn points(&'a self) -> Self::Points {
+        self.points.iter()
+    }
+}
+
+impl NumericEnvelope {
+    /// An envelope holding `value` from frame 0. Bounds given the wrong way
+    /// round are swapped.
+    pub fn new(min: f32, max: f32, value: f32) -> Self {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        let mut envelope = Self { min, max, points: Vec::new() };
+        envelope.points.push(CurvePoint::new(0, envelope.clamp(value)));
+        envelope
+    }
+
+    /// `value` limited to the range. Unlike `f32::clamp` this doesn't panic
+    /// when the public bounds have been set the wrong way round.
+    fn clamp(&self, value: f32) -> f32 {
+        value.max(self.min).min(self.max)
+    }
+
+    /// Index of the point at `frame`
+    pub fn point_at(&self, frame: u32) -> Option<usize> {
+        self.points.binary_search_by_key(&frame, |point| point.frame).ok()
+    }
+
+    /// Value at `frame`; before the first point and after the last the
+    /// envelope holds their values
+    pub fn value_at(&self, frame: u32) -> f32 {
+        match (self.points.first(), self.points.last()) {
+            (Some(first), _) if frame <= first.frame => first.value,
+            (_, Some(last)) if frame >= last.frame => last.value,
+            (None, _) => self.min,
+            _ => envelope::Envelope::y(self, frame).unwrap_or(self.min),
+        }
+    }
+
+    /// Add a point or set the value of the point at `frame`. Returns its index.
+    pub fn set_point(&mut self, frame: u32, value: f32) -> usize {
+        let value = self.clamp(value);
+        match self.points.binary_search_by_key(&frame, |point| point.frame) {
+            Ok(index) => {
+                self.points[index].value = value;
+                index
+            }
+            Err(index) => {
+                self.points.insert(index, CurvePoint::new(frame, value));
+                index
+            }
+        }
+    }
+
+    /// Move a point to `frame`, keeping it between its neighbours, and set
+    /// its value. Returns the frame it ended up on, or `None` if there is no
+    /// point at `index`.
+    pub fn move_point(&mut self, index: usize, frame: u32, value: f32) -> Option<u32> {
+        let value = self.clamp(value);
+        let earliest = index.checked_sub(1).and_then(|i| self.points.get(i)).map_or(0, |point| point.frame + 1);
+        let latest = self.points.get(index + 1).map_or(u32::MAX, |point| point.frame - 1);
+        let point = self.points.get_mut(index)?;
+        point.frame = frame.clamp(earliest, latest);
+        point.value = value;
+        Some(point.frame)
+    }
+
+    /// Remove a point; the last point stays
+    pub fn remove_point(&mut self, index: usize) {
+        if self.points.len() > 1 && index < self.points.len() {
+            self.points.remove(index);
+        }
+    }
+
+    /// Set the bend of the segment after a point
+    pub fn set_curve(&mut self, index: usize, curve: f32) {
+        if let Some(point) = self.points.get_mut(index) {
+            point.curve = curve.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// Index of the point whose segment contains `frame`
+    pub fn segment_at(&self, frame: u32) -> Option<usize> {
+        let index = self.points.iter().rposition(|point| point.frame <= frame)?;
+        (index + 1 < self.points.len()).then_some(index)
+    }
+}
+
+/// On/off changes over time; off before the first change
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToggleEnvelope {
+    /// Sorted by frame: the state from each frame on
+    pub points: Vec<(u32, bool)>,
+}
+
+impl ToggleEnvelope {
+    pub fn value_at(&self, frame: u32) -> bool {
+        self.points.iter().rev().find(|(at, _)| *at <= frame).is_some_and(|&(_, on)| on)
+    }
+
+    /// Flip the state from `frame` to the next change. Clicking a change
+    /// removes it; changes left without effect are dropped, joining spans.
+    pub fn toggle_at(&mut self, frame: u32) {
+        if self.has_change_at(frame) {
+            self.remove_at(frame);
+        } else {
+            self.set_at(frame, !self.value_at(frame));
+        }
+    }
+
+    pub fn has_change_at(&self, frame: u32) -> bool {
+        self.points.binary_search_by_key(&frame, |&(at, _)| at).is_ok()
+    }
+
+    /// Change the state to `on` at `frame`
+    pub fn set_at(&mut self, frame: u32, on: bool) {
+        match self.points.binary_search_by_key(&frame, |&(at, _)| at) {
+            Ok(index) => self.points[index].1 = on,
+            Err(index) => self.points.insert(index, (frame, on)),
+        }
+        self.drop_redundant();
+    }
+
+    /// Remove the change at `frame`
+    pub fn remove_at(&mut self, frame: u32) {
+        self.points.retain(|&(at, _)| at != frame);
+        self.drop_redundant();
+    }
+
+    /// Drop changes to the state already in effect, joining spans
+    fn drop_redundant(&mut self) {
+        let mut state = false;
+        self.points.retain(|&(_, on)| std::mem::replace(&mut state, on) != on);
+    }
+
+    /// Frame ranges where the envelope is on, up to `end`
+    pub fn spans(&self, end: u32) -> Vec<Range<u32>> {
+        let mut spans = Vec::new();
+        let mut start = None;
+        for &(frame, on) in &self.points {
+            match (on, start) {
+                (true, None) => start = Some(frame),
+                (false, Some(from)) => {
+                    spans.push(from..frame);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(from) = start.filter(|&from| from < end) {
+            spans.push(from..end);
+        }
+        spans
+    }
+}
+
+/// Trigger frames
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BangEnvelope {
+    /// Sorted, without duplicates
+    pub frames: Vec<u32>,
+}
+
+impl BangEnvelope {
+    /// Add a bang at `frame`, or remove the one there
+    pub fn toggle_at(&mut self, frame: u32) {
+        match self.frames.binary_search(&frame) {
+            Ok(index) => {
+                self.frames.remove(index);
+            }
+            Err(index) => self.frames.insert(index, frame),
+        }
+    }
+
+    /// Add a bang at `frame` unless there is one
+    pub fn add(&mut self, frame: u32) {
+        if let Err(index) = self.frames.binary_search(&frame) {
+            self.frames.insert(index, frame);
+        }
+    }
+
+    pub fn remove(&mut self, frame: u32) {
+        self.frames.retain(|&at| at != frame);
+    }
+
+    /// Bangs within `frames`
+    pub fn in_range(&self, frames: Range<u32>) -> impl Iterator<Item = u32> + '_ {
+        self.frames.iter().copied().filter(move |frame| frames.contains(frame))
+    }
+}
+
+/// The envelope of a lane
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AutomationEnvelope {
+    Numeric(NumericEnvelope),
+    Toggle(ToggleEnvelope),
+    Bang(BangEnvelope),
+}
+
+impl AutomationEnvelope {
+    /// Whether a point, change or bang is at `frame`
+    pub fn has_point_at(&self, frame: u32) -> bool {
+        match self {
+            AutomationEnvelope::Numeric(envelope) => envelope.point_at(frame).is_some(),
+            AutomationEnvelope::Toggle(envelope) => envelope.has_change_at(frame),
+            AutomationEnvelope::Bang(envelope) => envelope.frames.binary_search(&frame).is_ok(),
+        }
+    }
+
+    /// Set a point, change or bang at `frame`. A value of another kind than
+    /// the envelope is ignored.
+    pub fn set_at(&mut self, frame: u32, value: AutomationValue) {
+        match (self, value) {
+            (AutomationEnvelope::Numeric(envelope), AutomationValue::Number(value)) => {
+                envelope.set_point(frame, value);
+            }
+            (AutomationEnvelope::Toggle(envelope), AutomationValue::Toggle(on)) => envelope.set_at(frame, on),
+            (AutomationEnvelope::Bang(envelope), AutomationValue::Bang) => envelope.add(frame),
+            _ => {}
+        }
+    }
+
+    /// Remove the point, change or bang at `frame`; a numeric envelope keeps
+    /// its last point
+    pub fn remove_at(&mut self, frame: u32) {
+        match self {
+            AutomationEnvelope::Numeric(envelope) => {
+                if let Some(index) = envelope.point_at(frame) {
+                    envelope.remove_point(index);
+                }
+            }
+            AutomationEnvelope::Toggle(envelope) => envelope.remove_at(frame),
+            AutomationEnvelope::Bang(envelope) => envelope.remove(frame),
+        }
+    }
+}
+
+/// A value sent to a layer property
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AutomationValue {
+    Number(f32),
+    Toggle(bool),
+    /// A trigger, with no value
+    Bang,
+}
+
+/// An envelope bound to a property of its layer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutomationLane {
+    pub property: PropertyId,
+    pub envelope: AutomationEnvelope,
+}
+
+impl AutomationLane {
+    /// A numeric lane holding `value` from frame 0
+    pub fn numeric(property: PropertyId, min: f32, max: f32, value: f32) -> Self {
+        Self { property, envelope: AutomationEnvelope::Numeric(NumericEnvelope::new(min, max, value)) }
+    }
+
+    /// A numeric lane with a range and starting value suited to `property`
+    pub fn numeric_for(property: PropertyId) -> Self {
+        let (min, max, value) = match property {
+            PropertyId::PositionX | PropertyId::PositionY => (-1000.0, 1000.0, 0.0),
+            PropertyId::Rotation => (-360.0, 360.0, 0.0),
+            PropertyId::ScaleX | PropertyId::ScaleY => (0.0, 4.0, 1.0),
+            PropertyId::Alpha => (0.0, 1.0, 1.0),
+            PropertyId::ColorR | PropertyId::ColorG | PropertyId::ColorB => (0.0, 255.0, 255.0),
+            PropertyId::Custom(_) => (0.0, 1.0, 0.0),
+        };
+        Self::numeric(property, min, max, value)
+    }
+
+    pub fn toggle(property: PropertyId) -> Self {
+        Self { property, envelope: AutomationEnvelope::Toggle(ToggleEnvelope::default()) }
+    }
+
+    pub fn bang(property: PropertyId) -> Self {
+        Self { property, envelope: AutomationEnvelope::Bang(BangEnvelope::default()) }
+    }
+
+    /// Value of a numeric or toggle lane at `frame`; bang lanes have none
+    pub fn value_at(&self, frame: u32) -> Option<AutomationValue> {
+        match &self.envelope {
+            AutomationEnvelope::Numeric(envelope) => Some(AutomationValue::Number(envelope.value_at(frame))),
+            AutomationEnvelope::Toggle(envelope) => Some(AutomationValue::Toggle(envelope.value_at(frame))),
+            AutomationEnvelope::Bang(_) => None,
+        }
+    }
+
+    /// Icon of the lane's kind
+    pub fn icon(&self) -> &'static str {
+        match self.envelope {
+            AutomationEnvelope::Numeric(_) => "〰",
+            AutomationEnvelope::Toggle(_) => "⏻",
+            AutomationEnvelope::Bang(_) => "❗",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_curves() {
+        let mut envelope = NumericEnvelope::new(0.0, 100.0, 0.0);
+        envelope.set_point(10, 100.0);
+        assert_eq!(envelope.value_at(5), 50.0);
+        assert_eq!(envelope.value_at(20), 100.0);
+
+        // Easing out rises faster at first, easing in slower
+        envelope.set_curve(0, 1.0);
+        assert!(envelope.value_at(5) > 70.0);
+        envelope.set_curve(0, -1.0);
+        assert!(envelope.value_at(5) < 30.0);
+        assert_eq!(envelope.segment_at(3), Some(0));
+        assert_eq!(envelope.segment_at(10), None);
+
+        // Points stay between their neighbours and within the range
+        envelope.set_point(20, 40.0);
+        assert_eq!(envelope.move_point(1, 30, 140.0), Some(19));
+        assert_eq!(envelope.points[1].value, 100.0);
+        assert_eq!(envelope.move_point(5, 30, 0.0), None);
+        envelope.remove_point(0);
+        envelope.remove_point(0);
+        envelope.remove_point(0);
+        assert_eq!(envelope.points, [CurvePoint::new(20, 40.0)]);
+    }
+
+    #[test]
+    fn test_numeric_bounds_are_swapped() {
+        let envelope = NumericEnvelope::new(1.0, -1.0, 5.0);
+        assert_eq!((envelope.min, envelope.max), (-1.0, 1.0));
+        assert_eq!(envelope.points, [CurvePoint::new(0, 1.0)]);
+    }
+
+    #[test]
+    fn test_toggle_spans() {
+        let mut envelope = ToggleEnvelope::default();
+        envelope.toggle_at(10);
+        envelope.toggle_at(20);
+        envelope.toggle_at(30);
+        assert_eq!(envelope.spans(100), [10..20, 30..100]);
+        assert!(envelope.value_at(15) && !envelope.value_at(25));
+
+        // Turning 20..30 on joins the spans
+        envelope.toggle_at(20);
+        assert_eq!(envelope.points, [(10, true)]);
+        envelope.toggle_at(50);
+        assert_eq!(envelope.points, [(10, true), (50, false)]);
+    }
+
+    #[test]
+    fn test_bangs() {
+        let mut envelope = BangEnvelope::default();
+        for frame in [12, 4, 8, 4] {
+            envelope.toggle_at(frame);
+        }
+        assert_eq!(envelope.frames, [8, 12]);
+        assert_eq!(envelope.in_range(5..12).collect::<Vec<_>>(), [8]);
+    }
+}
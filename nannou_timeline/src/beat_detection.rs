@@ -0,0 +1,238 @@
+//! Onset detection and tempo estimation for beat markers
+//!
+//! [`detect`] splits a sound into 10 ms steps and measures how much the
+//! loudness rises at each one. Steps that rise well above their surroundings
+//! are onsets; [`BeatSettings::sensitivity`] sets how far above. The tempo
+//! is the step interval at which the onset strength best repeats, and the
+//! beats are that interval's grid, placed to hit as many onsets as it can.
+//!
+//! Times are in seconds of the sound; the controller turns them into
+//! timeline frames for each clip (see
+//! [`TimelineController::detect_beats`](crate::TimelineController::detect_beats)).
+
+use crate::wav::WavData;
+
+/// Shortest gap between two onsets in seconds
+const MIN_ONSET_GAP: f64 = 0.05;
+
+/// Seconds either side of a step averaged for the adaptive threshold
+const THRESHOLD_WINDOW: f64 = 0.25;
+
+/// How beats are detected
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeatSettings {
+    /// 0.0 keeps only the strongest onsets, 1.0 keeps every local peak
+    pub sensitivity: f32,
+    /// Slowest tempo considered, in beats per minute
+    pub min_bpm: f32,
+    /// Fastest tempo considered, in beats per minute
+    pub max_bpm: f32,
+}
+
+impl Default for BeatSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.5,
+            min_bpm: 70.0,
+            max_bpm: 180.0,
+        }
+    }
+}
+
+/// Onsets, tempo and beats of a sound
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BeatAnalysis {
+    /// Onset times in seconds
+    pub onsets: Vec<f64>,
+    /// Estimated tempo in beats per minute, if the onsets repeat
+    pub tempo: Option<f64>,
+    /// Beat times in seconds, spaced by the tempo over the whole sound
+    pub beats: Vec<f64>,
+}
+
+impl BeatAnalysis {
+    /// Beat times as frames at `fps`
+    pub fn beat_frames(&self, fps: f32) -> Vec<u32> {
+        let mut frames: Vec<u32> = self.beats.iter().map(|&time| (time * fps as f64).round() as u32).collect();
+        frames.dedup();
+        frames
+    }
+}
+
+/// Samples per analysis step: 10 ms
+fn hop(sample_rate: u32) -> usize {
+    (sample_rate as usize / 100).max(1)
+}
+
+/// Rise in loudness at each step, zero where it falls
+pub fn onset_strength(data: &WavData) -> Vec<f32> {
+    let channels = data.channels.max(1) as usize;
+    let hop = hop(data.sample_rate);
+    let loudness: Vec<f32> = data
+        .samples
+        .chunks(hop * channels)
+        .map(|block| (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt())
+        .collect();
+
+    let mut strength = vec![0.0; loudness.len()];
+    for step in 1..loudness.len() {
+        strength[step] = (loudness[step] - loudness[step - 1]).max(0.0);
+    }
+    strength
+}
+
+/// Detect onsets, tempo and beats in decoded audio
+pub fn detect(data: &WavData, settings: &BeatSettings) -> BeatAnalysis {
+    if data.sample_rate == 0 || data.samples.is_empty() {
+        return BeatAnalysis::default();
+    }
+    let strength = onset_strength(data);
+    let step_seconds = hop(data.sample_rate) as f64 / data.sample_rate as f64;
+
+    let onsets = pick_onsets(&strength, step_seconds, settings.sensitivity);
+    let tempo = estimate_tempo(&strength, step_seconds, settings);
+    let beats = tempo.map_or_else(Vec::new, |bpm| place_beats(&onsets, 60.0 / bpm, data.duration() as f64));
+    BeatAnalysis { onsets, tempo, beats }
+}
+
+/// Local peaks of `strength` above an adaptive threshold
+fn pick_onsets(strength: &[f32], step_seconds: f64, sensitivity: f32) -> Vec<f64> {
+    let strictness = 1.0 - sensitivity.clamp(0.0, 1.0);
+    let strongest = strength.iter().copied().fold(0.0, f32::max);
+    if strongest <= 0.0 {
+        return Vec::new();
+    }
+    let floor = strongest * strictness * 0.5;
+    let window = (THRESHOLD_WINDOW / step_seconds).round() as usize;
+    let min_gap = (MIN_ONSET_GAP / step_seconds).round() as usize;
+
+    let mut onsets = Vec::new();
+    let mut last: Option<usize> = None;
+    for (step, &value) in strength.iter().enumerate() {
+        let neighbours = &strength[step.saturating_sub(3)..(step + 4).min(strength.len())];
+        if value <= 0.0 || neighbours.iter().any(|&other| other > value) {
+            continue;
+        }
+        let around = &strength[step.saturating_sub(window)..(step + window + 1).min(strength.len())];
+        let mean = around.iter().sum::<f32>() / around.len() as f32;
+        if value < floor || value < mean * (1.0 + 2.0 * strictness) {
+            continue;
+        }
+        if last.is_some_and(|last| step - last < min_gap) {
+            continue;
+        }
+        last = Some(step);
+        onsets.push(step as f64 * step_seconds);
+    }
+    onsets
+}
+
+/// Beats per minute at which `strength` best correlates with itself
+fn estimate_tempo(strength: &[f32], step_seconds: f64, settings: &BeatSettings) -> Option<f64> {
+    let min_lag = ((60.0 / settings.max_bpm as f64) / step_seconds).floor().max(1.0) as usize;
+    let max_lag = ((60.0 / settings.min_bpm as f64) / step_seconds).ceil() as usize;
+    if max_lag + 1 >= strength.len() || min_lag > max_lag {
+        return None;
+    }
+
+    let correlation = |lag: usize| -> f64 {
+        strength.iter().zip(&strength[lag..]).map(|(&a, &b)| a as f64 * b as f64).sum::<f64>() / (strength.len() - lag) as f64
+    };
+    let scores: Vec<f64> = (min_lag - 1..=max_lag + 1).map(correlation).collect();
+    let best = (1..scores.len() - 1).max_by(|&a, &b| scores[a].total_cmp(&scores[b]))?;
+    if scores[best] <= 0.0 {
+        return None;
+    }
+
+    // Parabolic interpolation between the neighbouring lags
+    let (before, peak, after) = (scores[best - 1], scores[best], scores[best + 1]);
+    let curvature = before - 2.0 * peak + after;
+    let shift = if curvature < 0.0 { (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+    let lag = (min_lag - 1 + best) as f64 + shift;
+    Some(60.0 / (lag * step_seconds))
+}
+
+/// Beats `period` seconds apart from 0 to `duration`, lined up with the
+/// onset that puts the most other onsets on the grid
+fn place_beats(onsets: &[f64], period: f64, duration: f64) -> Vec<f64> {
+    let tolerance = (period * 0.1).max(0.02);
+    let on_grid = |anchor: f64| {
+        onsets
+            .iter()
+            .filter(|&&onset| {
+                let offset = (onset - anchor).rem_euclid(period);
+                offset.min(period - offset) <= tolerance
+            })
+            .count()
+    };
+    let Some(anchor) = onsets.iter().copied().max_by_key(|&onset| on_grid(onset)) else {
+        return Vec::new();
+    };
+
+    let first = anchor.rem_euclid(period);
+    (0..)
+        .map(|beat| first + beat as f64 * period)
+        .take_while(|&time| time < duration)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 8000;
+
+    /// Decaying 440 Hz bursts of the given loudness at the given times
+    fn clicks(duration: f64, clicks: &[(f64, f32)]) -> WavData {
+        let mut samples = vec![0.0; (duration * RATE as f64) as usize];
+        for &(time, level) in clicks {
+            let start = (time * RATE as f64) as usize;
+            for n in 0..(RATE as usize / 25).min(samples.len() - start) {
+                let t = n as f32 / RATE as f32;
+                samples[start + n] += level * (t * 440.0 * std::f32::consts::TAU).sin() * (-t * 60.0).exp();
+            }
+        }
+        WavData { sample_rate: RATE, channels: 1, bits_per_sample: 32, samples }
+    }
+
+    fn quarter_notes(bpm: f64, count: usize, offset: f64, level: f32) -> Vec<(f64, f32)> {
+        (0..count).map(|beat| (offset + beat as f64 * 60.0 / bpm, level)).collect()
+    }
+
+    #[test]
+    fn test_detects_onsets_and_tempo() {
+        let data = clicks(4.0, &quarter_notes(120.0, 8, 0.25, 0.8));
+        let analysis = detect(&data, &BeatSettings::default());
+
+        assert_eq!(analysis.onsets.len(), 8);
+        for (onset, expected) in analysis.onsets.iter().zip(quarter_notes(120.0, 8, 0.25, 0.8)) {
+            assert!((onset - expected.0).abs() <= 0.011, "onset {onset} vs {}", expected.0);
+        }
+        let tempo = analysis.tempo.unwrap();
+        assert!((tempo - 120.0).abs() < 1.0, "tempo {tempo}");
+
+        // Beats cover the whole sound, including before the first onset
+        assert!(analysis.beats[0] < 0.3);
+        assert!(analysis.beats.len() >= 8);
+        assert_eq!(analysis.beat_frames(24.0)[..3], [6, 18, 30]);
+    }
+
+    #[test]
+    fn test_sensitivity_keeps_weaker_onsets() {
+        let mut notes = quarter_notes(100.0, 6, 0.2, 1.0);
+        notes.extend(quarter_notes(100.0, 6, 0.5, 0.15));
+        let data = clicks(4.0, &notes);
+
+        let strict = detect(&data, &BeatSettings { sensitivity: 0.1, ..Default::default() });
+        let sensitive = detect(&data, &BeatSettings { sensitivity: 0.9, ..Default::default() });
+        assert_eq!(strict.onsets.len(), 6);
+        assert_eq!(sensitive.onsets.len(), 12);
+        assert!((strict.tempo.unwrap() - 100.0).abs() < 1.5);
+    }
+
+    #[test]
+    fn test_silence_has_no_beats() {
+        let data = clicks(2.0, &[]);
+        assert_eq!(detect(&data, &BeatSettings::default()), BeatAnalysis::default());
+    }
+}
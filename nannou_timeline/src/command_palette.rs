@@ -0,0 +1,522 @@
+//! Command palette for searching and running timeline actions
+//!
+//! Every action the timeline exposes through its toolbar, context menus and
+//! keyboard shortcuts is registered here as a named [`Command`]. The
+//! [`CommandPalette`] popup (Ctrl+Shift+P) fuzzy-searches the registry, lists
+//! recently used commands first and shows each command's shortcut.
+
+#[cfg(feature = "ui")]
+use egui::{*, self};
+use std::collections::VecDeque;
+
+/// Maximum number of commands remembered as recently used
+const DEFAULT_MAX_RECENT: usize = 8;
+
+/// Snapshot of the timeline state used by command enable predicates
+#[derive(Clone, Debug, Default)]
+pub struct CommandContext {
+    /// At least one layer is selected
+    pub has_selected_layer: bool,
+    /// At least one keyframe is selected
+    pub has_selected_keyframe: bool,
+    /// The keyframe clipboard is not empty
+    pub has_clipboard: bool,
+    /// Playback is running
+    pub is_playing: bool,
+    /// Keyframes or a frame range are selected
+    pub has_selection: bool,
+}
+
+impl CommandContext {
+    /// Build a context from the current timeline state
+    pub fn from_state(state: &crate::TimelineState) -> Self {
+        Self {
+            has_selected_layer: !state.selected_layers.is_empty(),
+            has_selected_keyframe: !state.keyframe_selection.selected.is_empty(),
+            has_clipboard: !state.keyframe_selection.clipboard.is_empty(),
+            is_playing: state.is_playing,
+            has_selection: state.selection_frame_range().is_some(),
+        }
+    }
+}
+
+/// A named action that can be run from the command palette
+#[derive(Clone, Debug)]
+pub struct Command {
+    /// Stable identifier, e.g. `"frame.insert_keyframe"`
+    pub id: &'static str,
+    /// Human readable label
+    pub label: String,
+    /// Category shown as a prefix in the palette
+    pub category: &'static str,
+    /// Keyboard shortcut for display
+    pub shortcut: Option<&'static str>,
+    /// Whether the command can run in the given context
+    pub enabled: fn(&CommandContext) -> bool,
+}
+
+impl Command {
+    pub fn new(id: &'static str, category: &'static str, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            category,
+            shortcut: None,
+            enabled: |_| true,
+        }
+    }
+
+    pub fn with_shortcut(mut self, shortcut: &'static str) -> Self {
+        self.shortcut = Some(shortcut);
+        self
+    }
+
+    pub fn enabled_when(mut self, predicate: fn(&CommandContext) -> bool) -> Self {
+        self.enabled = predicate;
+        self
+    }
+
+    /// Text that the palette query is matched against
+    pub fn search_text(&self) -> String {
+        format!("{}: {}", self.category, self.label)
+    }
+}
+
+/// A command matched by a palette query
+#[derive(Clone, Debug)]
+pub struct CommandMatch<'a> {
+    pub command: &'a Command,
+    pub score: i32,
+    pub enabled: bool,
+}
+
+/// Registry of all commands known to the palette
+#[derive(Clone, Debug)]
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+    /// Most recently used command ids, newest first
+    recent: VecDeque<&'static str>,
+    max_recent: usize,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            recent: VecDeque::new(),
+            max_recent: DEFAULT_MAX_RECENT,
+        }
+    }
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry pre-populated with every built-in timeline action
+    pub fn timeline_defaults() -> Self {
+        let mut registry = Self::new();
+        for command in default_timeline_commands() {
+            registry.register(command);
+        }
+        registry
+    }
+
+    /// Register a command, replacing any existing command with the same id
+    pub fn register(&mut self, command: Command) {
+        if let Some(existing) = self.commands.iter_mut().find(|c| c.id == command.id) {
+            *existing = command;
+        } else {
+            self.commands.push(command);
+        }
+    }
+
+    /// All registered commands in registration order
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Look up a command by id
+    pub fn get(&self, id: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.id == id)
+    }
+
+    /// Check whether a command exists and is enabled in the given context
+    pub fn is_enabled(&self, id: &str, context: &CommandContext) -> bool {
+        self.get(id).is_some_and(|c| (c.enabled)(context))
+    }
+
+    /// Recently used command ids, newest first
+    pub fn recent(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.recent.iter().copied()
+    }
+
+    /// Move a command to the front of the recent list
+    pub fn record_used(&mut self, id: &str) {
+        let Some(command) = self.get(id) else { return };
+        let id = command.id;
+        self.recent.retain(|recent| *recent != id);
+        self.recent.push_front(id);
+        self.recent.truncate(self.max_recent);
+    }
+
+    /// Search commands matching `query`.
+    ///
+    /// Enabled commands come before disabled ones. Within each group results
+    /// are ordered by fuzzy score, then by recent use, then by registration
+    /// order. An empty query matches everything.
+    pub fn search(&self, query: &str, context: &CommandContext) -> Vec<CommandMatch<'_>> {
+        let mut matches: Vec<(usize, CommandMatch)> = self.commands.iter()
+            .enumerate()
+            .filter_map(|(index, command)| {
+                let score = fuzzy_score(query, &command.search_text())?;
+                Some((index, CommandMatch {
+                    command,
+                    score,
+                    enabled: (command.enabled)(context),
+                }))
+            })
+            .collect();
+
+        let recent_rank = |id: &str| {
+            self.recent.iter().position(|r| *r == id).unwrap_or(usize::MAX)
+        };
+
+        matches.sort_by(|(a_index, a), (b_index, b)| {
+            b.enabled.cmp(&a.enabled)
+                .then(b.score.cmp(&a.score))
+                .then(recent_rank(a.command.id).cmp(&recent_rank(b.command.id)))
+                .then(a_index.cmp(b_index))
+        });
+
+        matches.into_iter().map(|(_, m)| m).collect()
+    }
+}
+
+/// Score how well `query` fuzzy-matches `text`.
+///
+/// Returns `None` if the query characters don't appear in order in the text.
+/// Consecutive matches and matches at the start of words score higher, gaps
+/// between matched characters lower the score.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let text: Vec<char> = text.chars().flat_map(|c| c.to_lowercase()).collect();
+    let mut score = 0;
+    let mut query_idx = 0;
+    let mut last_match: Option<usize> = None;
+
+    for (idx, &ch) in text.iter().enumerate() {
+        if query_idx == query.len() {
+            break;
+        }
+        if ch != query[query_idx] {
+            continue;
+        }
+
+        score += 1;
+        let at_word_start = idx == 0 || !text[idx - 1].is_alphanumeric();
+        if at_word_start {
+            score += 8;
+        }
+        match last_match {
+            Some(last) if last + 1 == idx => score += 5,
+            Some(last) => score -= (idx - last - 1).min(5) as i32,
+            None => {}
+        }
+
+        last_match = Some(idx);
+        query_idx += 1;
+    }
+
+    (query_idx == query.len()).then_some(score)
+}
+
+fn has_selected_layer(context: &CommandContext) -> bool {
+    context.has_selected_layer
+}
+
+fn has_selected_keyframe(context: &CommandContext) -> bool {
+    context.has_selected_keyframe
+}
+
+fn has_clipboard(context: &CommandContext) -> bool {
+    context.has_clipboard
+}
+
+fn has_selection(context: &CommandContext) -> bool {
+    context.has_selection
+}
+
+/// Built-in timeline commands, executed by `Timeline::execute_command`
+pub fn default_timeline_commands() -> Vec<Command> {
+    vec![
+        // Playback
+        Command::new("playback.play_pause", "Playback", "Play / Pause").with_shortcut("Space"),
+        Command::new("playback.stop", "Playback", "Stop"),
+        Command::new("playback.first_frame", "Playback", "Go to First Frame").with_shortcut("Home"),
+        Command::new("playback.last_frame", "Playback", "Go to Last Frame").with_shortcut("End"),
+        Command::new("playback.previous_frame", "Playback", "Previous Frame").with_shortcut("←"),
+        Command::new("playback.next_frame", "Playback", "Next Frame").with_shortcut("→"),
+        Command::new("playback.toggle_loop", "Playback", "Toggle Loop Playback"),
+
+        // Frames and keyframes
+        Command::new("frame.insert_frame", "Frame", "Insert Frame")
+            .with_shortcut("F5").enabled_when(has_selected_layer),
+        Command::new("frame.remove_frame", "Frame", "Remove Frame")
+            .with_shortcut("Shift+F5").enabled_when(has_selected_layer),
+        Command::new("frame.insert_keyframe", "Frame", "Insert Keyframe")
+            .with_shortcut("F6").enabled_when(has_selected_layer),
+        Command::new("frame.clear_keyframe", "Frame", "Clear Keyframe")
+            .with_shortcut("Shift+F6").enabled_when(has_selected_layer),
+        Command::new("keyframe.copy", "Keyframe", "Copy Keyframes").enabled_when(has_selected_keyframe),
+        Command::new("keyframe.paste", "Keyframe", "Paste Keyframes at Playhead").enabled_when(has_clipboard),
+        Command::new("keyframe.delete", "Keyframe", "Delete Keyframes").enabled_when(has_selected_keyframe),
+        Command::new("keyframe.deselect_all", "Keyframe", "Deselect All Keyframes").enabled_when(has_selected_keyframe),
+
+        // Tweens
+        Command::new("tween.create_motion", "Tween", "Create Motion Tween").enabled_when(has_selected_keyframe),
+        Command::new("tween.create_shape", "Tween", "Create Shape Tween").enabled_when(has_selected_keyframe),
+        Command::new("tween.edit_easing", "Tween", "Edit Easing...").enabled_when(has_selected_keyframe),
+
+        // Layers
+        Command::new("layer.add", "Layer", "New Layer"),
+        Command::new("layer.add_folder", "Layer", "New Folder"),
+        Command::new("layer.add_motion_guide", "Layer", "Add Motion Guide"),
+        Command::new("layer.rename", "Layer", "Rename Layer...").enabled_when(has_selected_layer),
+        Command::new("layer.duplicate", "Layer", "Duplicate Layer").enabled_when(has_selected_layer),
+        Command::new("layer.delete", "Layer", "Delete Layer").enabled_when(has_selected_layer),
+        Command::new("layer.toggle_visibility_all", "Layer", "Show/Hide All Layers"),
+        Command::new("layer.toggle_lock_all", "Layer", "Lock/Unlock All Layers"),
+
+        // Import
+        Command::new("file.import_midi", "File", "Import MIDI..."),
+
+        // Labels and comments
+        Command::new("label.add_label", "Label", "Add Frame Label at Playhead"),
+        Command::new("label.add_comment", "Label", "Add Frame Comment at Playhead"),
+        Command::new("label.toggle_panel", "Label", "Toggle Labels & Comments Panel"),
+
+        // View
+        Command::new("view.toggle_onion_skin", "View", "Toggle Onion Skinning"),
+        Command::new("view.onion_settings", "View", "Onion Skin Settings..."),
+        Command::new("view.toggle_snap", "View", "Toggle Snapping"),
+        Command::new("view.toggle_frame_range_mode", "View", "Toggle Frame-based Selection"),
+        Command::new("view.zoom_in", "View", "Zoom In"),
+        Command::new("view.zoom_out", "View", "Zoom Out"),
+        Command::new("view.zoom_reset", "View", "Reset Zoom"),
+        Command::new("view.fit_all", "View", "Fit All Frames"),
+        Command::new("view.fit_selection", "View", "Fit Selection").enabled_when(has_selection),
+        Command::new("view.frame_size_tiny", "View", "Frame Size: Tiny"),
+        Command::new("view.frame_size_small", "View", "Frame Size: Small"),
+        Command::new("view.frame_size_normal", "View", "Frame Size: Normal"),
+        Command::new("view.frame_size_medium", "View", "Frame Size: Medium"),
+        Command::new("view.frame_size_large", "View", "Frame Size: Large"),
+        Command::new("view.toggle_minimap", "View", "Toggle Overview Minimap"),
+    ]
+}
+
+/// Searchable popup listing the commands of a [`CommandRegistry`]
+#[cfg(feature = "ui")]
+#[derive(Clone, Debug, Default)]
+pub struct CommandPalette {
+    /// Whether the palette is visible
+    pub open: bool,
+    /// Current search query
+    pub query: String,
+    /// Index of the highlighted entry in the filtered list
+    pub selected: usize,
+}
+
+#[cfg(feature = "ui")]
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the palette with an empty query
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Close the palette
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Open the palette if closed, close it if open
+    pub fn toggle(&mut self) {
+        if self.open {
+            self.close();
+        } else {
+            self.open();
+        }
+    }
+
+    /// Show the palette and return the id of the command the user picked
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        registry: &CommandRegistry,
+        context: &CommandContext,
+    ) -> Option<&'static str> {
+        if !self.open {
+            return None;
+        }
+
+        let mut chosen = None;
+
+        let (up, down, enter, escape) = ctx.input(|i| (
+            i.key_pressed(Key::ArrowUp),
+            i.key_pressed(Key::ArrowDown),
+            i.key_pressed(Key::Enter),
+            i.key_pressed(Key::Escape),
+        ));
+
+        let screen = ctx.screen_rect();
+        Area::new(Id::new("timeline_command_palette"))
+            .order(Order::Foreground)
+            .fixed_pos(pos2(screen.center().x - 200.0, screen.min.y + 60.0))
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(400.0);
+
+                    let response = ui.add(
+                        TextEdit::singleline(&mut self.query)
+                            .hint_text("Type a command...")
+                            .desired_width(f32::INFINITY)
+                    );
+                    response.request_focus();
+                    if response.changed() {
+                        self.selected = 0;
+                    }
+
+                    let matches = registry.search(&self.query, context);
+                    if matches.is_empty() {
+                        ui.weak("No matching commands");
+                        return;
+                    }
+
+                    let last = matches.len() - 1;
+                    if down {
+                        self.selected = (self.selected + 1).min(last);
+                    }
+                    if up {
+                        self.selected = self.selected.saturating_sub(1);
+                    }
+                    self.selected = self.selected.min(last);
+
+                    ui.separator();
+
+                    ScrollArea::vertical()
+                        .max_height(320.0)
+                        .show(ui, |ui| {
+                            for (index, m) in matches.iter().enumerate() {
+                                let is_selected = index == self.selected;
+                                let row = ui.add_enabled_ui(m.enabled, |ui| {
+                                    ui.horizontal(|ui| {
+                                        let response = ui.selectable_label(
+                                            is_selected,
+                                            format!("{}: {}", m.command.category, m.command.label),
+                                        );
+                                        if let Some(shortcut) = m.command.shortcut {
+                                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                                ui.weak(shortcut);
+                                            });
+                                        }
+                                        response
+                                    }).inner
+                                }).inner;
+
+                                if is_selected && (up || down) {
+                                    row.scroll_to_me(None);
+                                }
+                                if row.clicked() && m.enabled {
+                                    chosen = Some(m.command.id);
+                                }
+                            }
+                        });
+
+                    if enter {
+                        if let Some(m) = matches.get(self.selected).filter(|m| m.enabled) {
+                            chosen = Some(m.command.id);
+                        }
+                    }
+                });
+            });
+
+        if escape || chosen.is_some() {
+            self.close();
+        }
+
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_matches_subsequence() {
+        assert!(fuzzy_score("motw", "Tween: Create Motion Tween").is_some());
+        assert!(fuzzy_score("cmt", "Tween: Create Motion Tween").is_some());
+        assert!(fuzzy_score("xyz", "Tween: Create Motion Tween").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts() {
+        let word_starts = fuzzy_score("nl", "Layer: New Layer").unwrap();
+        let scattered = fuzzy_score("nl", "Layer: Rename Layer...").unwrap();
+        assert!(word_starts > scattered);
+    }
+
+    #[test]
+    fn test_disabled_commands_sort_last() {
+        let registry = CommandRegistry::timeline_defaults();
+        let context = CommandContext::default();
+        let results = registry.search("tween", &context);
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|m| !m.enabled));
+
+        let context = CommandContext { has_selected_keyframe: true, ..Default::default() };
+        assert!(registry.is_enabled("tween.create_motion", &context));
+        let results = registry.search("motion tween", &context);
+        assert_eq!(results[0].command.id, "tween.create_motion");
+        assert!(results[0].enabled);
+    }
+
+    #[test]
+    fn test_recent_commands_come_first() {
+        let mut registry = CommandRegistry::timeline_defaults();
+        let context = CommandContext::default();
+        registry.record_used("view.zoom_in");
+        registry.record_used("layer.add");
+
+        let results = registry.search("", &context);
+        assert_eq!(results[0].command.id, "layer.add");
+        assert_eq!(results[1].command.id, "view.zoom_in");
+
+        registry.record_used("view.zoom_in");
+        assert_eq!(registry.recent().collect::<Vec<_>>(), vec!["view.zoom_in", "layer.add"]);
+    }
+
+    #[test]
+    fn test_register_replaces_existing_id() {
+        let mut registry = CommandRegistry::new();
+        registry.register(Command::new("custom.action", "Custom", "First"));
+        registry.register(Command::new("custom.action", "Custom", "Second"));
+        assert_eq!(registry.commands().len(), 1);
+        assert_eq!(registry.get("custom.action").unwrap().label, "Second");
+    }
+}
@@ -0,0 +1,2287 @@
+//! Headless timeline logic
+//!
+//! [`TimelineController`] owns the [`TimelineState`] and exposes the timeline's
+//! behaviour — selection, snapping, playhead navigation, frame/layer edits and
+//! label management — as plain methods that need no egui `Context`. The egui
+//! [`Timeline`](crate::Timeline) widget is a view on top of a controller; other
+//! hosts can drive a controller directly.
+
+use ecolor::Color32;
+use emath::Pos2;
+use crate::{TimelineConfig, RiveEngine, LayerId, KeyframeId, FrameLabel, FrameComment};
+use crate::command_palette::{CommandContext, CommandRegistry};
+use crate::events::TimelineEvent;
+use crate::frame::FrameType;
+use crate::layer::{LayerType, LayerInfo, LayerDisplayMode};
+use crate::audio::{AudioEngine, AudioError, AudioId, AudioLayer, AudioTrack, EnvelopePreset, VolumeEnvelope, WaveformData};
+use crate::audio_scrub::AudioScrubber;
+use crate::automation::{self, AutomationEnvelope, AutomationLane, AutomationValue};
+use crate::beat_detection::{self, BeatAnalysis, BeatSettings};
+use crate::lip_sync::{self, LipSyncKey, MouthSet, PhonemeCue};
+use crate::midi::{self, MidiFile, MidiSelection, MidiTarget};
+use crate::piano_roll::{Note, PianoRoll};
+use crate::tempo::MusicalPosition;
+use crate::wav::WavData;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Smallest zoom level
+pub const MIN_ZOOM: f32 = 0.1;
+/// Largest zoom level
+pub const MAX_ZOOM: f32 = 5.0;
+
+/// Flash's frame view sizes, as zoom levels relative to the configured frame width
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameSizePreset {
+    Tiny,
+    Small,
+    Normal,
+    Medium,
+    Large,
+}
+
+impl FrameSizePreset {
+    pub fn all() -> [FrameSizePreset; 5] {
+        [
+            FrameSizePreset::Tiny,
+            FrameSizePreset::Small,
+            FrameSizePreset::Normal,
+            FrameSizePreset::Medium,
+            FrameSizePreset::Large,
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrameSizePreset::Tiny => "Tiny",
+            FrameSizePreset::Small => "Small",
+            FrameSizePreset::Normal => "Normal",
+            FrameSizePreset::Medium => "Medium",
+            FrameSizePreset::Large => "Large",
+        }
+    }
+
+    pub fn zoom_level(&self) -> f32 {
+        match self {
+            FrameSizePreset::Tiny => 0.5,
+            FrameSizePreset::Small => 0.75,
+            FrameSizePreset::Normal => 1.0,
+            FrameSizePreset::Medium => 1.5,
+            FrameSizePreset::Large => 2.0,
+        }
+    }
+
+    /// Preset matching a zoom level exactly, if any
+    pub fn from_zoom_level(zoom_level: f32) -> Option<FrameSizePreset> {
+        Self::all().into_iter().find(|p| (p.zoom_level() - zoom_level).abs() < 1e-4)
+    }
+}
+
+/// What the ruler counts in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RulerMode {
+    /// Frame numbers
+    #[default]
+    Frames,
+    /// Bars and beats of the document tempo map
+    BarsBeats,
+}
+
+/// Keyframe selection state for interactive manipulation
+#[derive(Clone, Debug, Default)]
+pub struct KeyframeSelection {
+    /// Currently selected keyframes (layer_id, frame) -> keyframe_id
+    pub selected: HashMap<(LayerId, u32), KeyframeId>,
+    /// Drag operation state
+    pub drag_state: Option<DragState>,
+    /// Copied keyframes for paste operations
+    pub clipboard: Vec<KeyframeClipboardItem>,
+}
+
+/// State tracking an active drag operation
+#[derive(Clone, Debug)]
+pub struct DragState {
+    /// Original positions of all selected keyframes
+    pub original_positions: HashMap<KeyframeId, (LayerId, u32)>,
+    /// Current drag offset in frames
+    pub frame_offset: i32,
+    /// Mouse position where drag started
+    pub start_pos: Pos2,
+}
+
+/// Part of an audio clip being dragged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioDragMode {
+    /// Left edge: trims the start of the sound
+    Head,
+    /// Right edge: trims the end of the sound
+    Tail,
+    /// Body: moves the clip along the timeline
+    Move,
+    /// Body with Alt: slides the sound under the clip
+    Slip,
+}
+
+/// State of an audio clip drag
+#[derive(Clone, Debug)]
+pub struct AudioDrag {
+    pub layer_id: LayerId,
+    /// Index of the clip in the layer's track
+    pub clip: usize,
+    pub mode: AudioDragMode,
+    /// The clip as it was when the drag started
+    pub original: AudioLayer,
+    /// Pointer x in frame grid content coordinates where the drag started
+    pub origin_x: f32,
+    /// The layer's track with the drag applied, shown until the drag ends
+    pub preview: AudioTrack,
+    /// Index of the dragged clip in `preview`
+    pub preview_clip: usize,
+}
+
+/// Automation point being dragged
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutomationDrag {
+    pub layer_id: LayerId,
+    /// Index of the lane under the layer
+    pub lane: usize,
+    /// Index of the point in a numeric envelope
+    pub point: usize,
+    /// Bending the segment after the point instead of moving the point
+    pub bend: bool,
+}
+
+/// What dragging a piano roll note changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteDragMode {
+    /// Body: moves the note in time and pitch
+    Move,
+    /// Right edge: changes the note's length
+    Resize,
+    /// Body with Alt: changes the velocity, louder upwards
+    Velocity,
+}
+
+/// State of a piano roll note drag
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteDrag {
+    pub layer_id: LayerId,
+    /// Index of the note in the layer's roll
+    pub note: usize,
+    pub mode: NoteDragMode,
+    /// The note as it was when the drag started
+    pub original: Note,
+    /// Pointer position in frame grid content coordinates where the drag started
+    pub origin: Pos2,
+}
+
+/// Clipboard item for copy/paste operations
+#[derive(Clone, Debug)]
+pub struct KeyframeClipboardItem {
+    pub layer_id: LayerId,
+    pub relative_frame: u32,
+    pub data: crate::frame::FrameData,
+}
+
+impl KeyframeSelection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Clear all selections
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.drag_state = None;
+    }
+    
+    /// Add a keyframe to selection
+    pub fn add(&mut self, layer_id: LayerId, frame: u32, keyframe_id: KeyframeId) {
+        self.selected.insert((layer_id, frame), keyframe_id);
+    }
+    
+    /// Remove a keyframe from selection
+    pub fn remove(&mut self, layer_id: LayerId, frame: u32) {
+        self.selected.remove(&(layer_id, frame));
+    }
+    
+    /// Check if a keyframe is selected
+    pub fn is_selected(&self, layer_id: LayerId, frame: u32) -> bool {
+        self.selected.contains_key(&(layer_id, frame))
+    }
+    
+    /// Get selected keyframes as list
+    pub fn get_selected(&self) -> Vec<(LayerId, u32, KeyframeId)> {
+        self.selected.iter()
+            .map(|((layer_id, frame), keyframe_id)| (layer_id.clone(), *frame, keyframe_id.clone()))
+            .collect()
+    }
+}
+
+/// Persistent state for the timeline
+#[derive(Clone, Debug)]
+pub struct TimelineState {
+    /// Currently selected layers
+    pub selected_layers: Vec<LayerId>,
+    /// Currently selected frames (layer_id -> frame numbers)
+    pub selected_frames: HashMap<LayerId, Vec<u32>>,
+    /// Current playhead position
+    pub playhead_frame: u32,
+    /// Is timeline playing
+    pub is_playing: bool,
+    /// Current zoom level
+    pub zoom_level: f32,
+    /// Horizontal scroll position
+    pub scroll_x: f32,
+    /// Width of the frame grid when it was last drawn, used by the fit commands
+    pub view_width: f32,
+    /// Frames to fit once the view width is known
+    pub pending_fit: Option<(u32, u32)>,
+    /// Vertical scroll position  
+    pub scroll_y: f32,
+    /// Counts edits to the document's frames and layers; views cache what
+    /// they read from the engine until it changes
+    pub document_revision: u64,
+    /// Track heights that have been manually adjusted
+    pub track_heights: HashMap<LayerId, f32>,
+    /// Right-click context menu state
+    pub context_menu: Option<ContextMenuState>,
+    /// Active snap guides (frame positions)
+    pub snap_guides: Vec<f32>,
+    /// Keyframe selection and manipulation state
+    pub keyframe_selection: KeyframeSelection,
+    /// Layer panel state
+    pub layer_panel_state: LayerPanelState,
+    /// Show onion skinning
+    pub onion_skinning: bool,
+    /// Loop playback
+    pub loop_playback: bool,
+    /// Show label management panel
+    pub show_label_panel: bool,
+    /// Frame range selection (start_frame, end_frame)
+    pub frame_range_selection: Option<(u32, u32)>,
+    /// Frame range selection mode enabled
+    pub frame_range_mode: bool,
+    /// Onion skin settings
+    pub onion_skin_frames_before: u32,
+    pub onion_skin_frames_after: u32,
+    pub onion_skin_opacity: f32,
+    /// Show onion skin settings panel
+    pub show_onion_settings: bool,
+    /// Onion skin outline mode
+    pub onion_skin_outline_mode: bool,
+    /// Currently scrubbing the timeline
+    pub is_scrubbing: bool,
+    /// Was playing before scrubbing started
+    pub was_playing: bool,
+    /// Play audio snippets while scrubbing
+    pub audio_scrubbing: bool,
+    /// Frames or bars|beats on the ruler and grid
+    pub ruler_mode: RulerMode,
+    /// Volume envelope point being dragged: audio layer, clip index and the
+    /// point's timeline frame
+    pub envelope_drag: Option<(LayerId, usize, u32)>,
+    /// Audio clip being trimmed, moved or slipped
+    pub audio_drag: Option<AudioDrag>,
+    /// Automation point being moved or bent
+    pub automation_drag: Option<AutomationDrag>,
+    /// Frame automation was last applied at
+    pub automation_frame: Option<u32>,
+    /// Piano roll note being moved, resized or made louder
+    pub note_drag: Option<NoteDrag>,
+    /// Frame piano roll notes were last played at
+    pub note_frame: Option<u32>,
+    /// Onset sensitivity for beat detection, 0.0 to 1.0
+    pub beat_sensitivity: f32,
+}
+
+/// State for the enhanced layer panel
+#[derive(Clone, Debug, Default)]
+pub struct LayerPanelState {
+    /// Layer visibility states
+    pub layer_visibility: HashMap<LayerId, bool>,
+    /// Layer lock states
+    pub layer_locked: HashMap<LayerId, bool>,
+    /// Layer outline mode
+    pub layer_outline: HashMap<LayerId, bool>,
+    /// Expanded folders (track by LayerId)
+    pub expanded_folders: Vec<LayerId>,
+    /// Layers showing their automation lanes
+    pub automation_shown: Vec<LayerId>,
+    /// Layers showing their piano roll
+    pub piano_roll_shown: Vec<LayerId>,
+    /// Layer being dragged for reordering (layer_id, initial_y_position)
+    pub dragging_layer: Option<(LayerId, f32)>,
+    /// Drop target position during drag
+    pub drop_target_index: Option<usize>,
+    /// Layer being renamed (layer_id, new_name)
+    pub renaming_layer: Option<(LayerId, String)>,
+    /// Newly created layer that should be focused and renamed
+    pub newly_created_layer: Option<LayerId>,
+}
+
+impl Default for TimelineState {
+    fn default() -> Self {
+        let mut layer_panel_state = LayerPanelState::default();
+        // Expand the Effects folder by default (layer3 in mock data)
+        layer_panel_state.expanded_folders.push(LayerId::new("layer3"));
+        
+        Self {
+            selected_layers: Vec::new(),
+            selected_frames: HashMap::new(),
+            playhead_frame: 0,
+            is_playing: false,
+            zoom_level: 1.0,
+            scroll_x: 0.0,
+            view_width: 0.0,
+            pending_fit: None,
+            scroll_y: 0.0,
+            document_revision: 0,
+            track_heights: HashMap::new(),
+            context_menu: None,
+            snap_guides: Vec::new(),
+            keyframe_selection: KeyframeSelection::new(),
+            layer_panel_state,
+            onion_skinning: false,
+            loop_playback: false,
+            show_label_panel: false,
+            frame_range_selection: None,
+            frame_range_mode: false,
+            onion_skin_frames_before: 3,
+            onion_skin_frames_after: 3,
+            onion_skin_opacity: 0.3,
+            show_onion_settings: false,
+            onion_skin_outline_mode: false,
+            is_scrubbing: false,
+            was_playing: false,
+            audio_scrubbing: true,
+            ruler_mode: RulerMode::Frames,
+            envelope_drag: None,
+            audio_drag: None,
+            automation_drag: None,
+            automation_frame: None,
+            note_drag: None,
+            note_frame: None,
+            beat_sensitivity: 0.5,
+        }
+    }
+}
+
+impl TimelineState {
+    /// Multiply the zoom by `factor`, keeping the frame under `anchor_x`
+    /// (pixels from the left edge of the frame grid) at the same position
+    /// where the `total_frames` allow
+    pub fn zoom_around(&mut self, factor: f32, anchor_x: f32, base_frame_width: f32, total_frames: u32) {
+        let anchor_frame = (self.scroll_x + anchor_x) / (base_frame_width * self.zoom_level);
+        self.zoom_level = (self.zoom_level * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.scroll_x = anchor_frame * base_frame_width * self.zoom_level - anchor_x;
+        self.clamp_scroll(base_frame_width, total_frames);
+    }
+
+    /// Keep `scroll_x` within the frames, as the frame grid's scroll area does
+    pub fn clamp_scroll(&mut self, base_frame_width: f32, total_frames: u32) {
+        let content_width = total_frames as f32 * base_frame_width * self.zoom_level;
+        self.scroll_x = self.scroll_x.clamp(0.0, (content_width - self.view_width).max(0.0));
+    }
+
+    /// Record the frame grid's width, then run a fit requested before it was known
+    pub fn set_view_width(&mut self, view_width: f32, base_frame_width: f32) {
+        self.view_width = view_width;
+        if let Some((start, end)) = self.pending_fit.take() {
+            self.zoom_to_frames(start, end, base_frame_width, view_width);
+        }
+    }
+
+    /// Zoom and scroll so the frames `start..=end` fill `view_width`. With no
+    /// width yet (before the first draw) the fit waits for
+    /// [`set_view_width`](Self::set_view_width).
+    pub fn zoom_to_frames(&mut self, start: u32, end: u32, base_frame_width: f32, view_width: f32) {
+        if view_width <= 0.0 {
+            self.pending_fit = Some((start, end));
+            return;
+        }
+        let (start, end) = (start.min(end), start.max(end));
+        let span = (end - start + 1) as f32;
+        self.zoom_level = (view_width / (span * base_frame_width)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.scroll_x = start as f32 * base_frame_width * self.zoom_level;
+    }
+
+    /// Selected keyframes as (layer, frame) pairs, sorted by frame then layer
+    pub fn selected_keyframe_positions(&self) -> Vec<(LayerId, u32)> {
+        let mut positions: Vec<_> = self.keyframe_selection.selected.keys().cloned().collect();
+        positions.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.0.cmp(&b.0.0)));
+        positions
+    }
+
+    /// First and last frame of the selected keyframes, or of the frame range selection
+    pub fn selection_frame_range(&self) -> Option<(u32, u32)> {
+        let frames = self.keyframe_selection.selected.keys().map(|(_, frame)| *frame);
+        match (frames.clone().min(), frames.max()) {
+            (Some(first), Some(last)) => Some((first, last)),
+            _ => self.frame_range_selection.map(|(a, b)| (a.min(b), a.max(b))),
+        }
+    }
+
+    /// Check whether a layer is shown in outline mode
+    pub fn is_layer_outline(&self, layer_id: &LayerId) -> bool {
+        *self.layer_panel_state.layer_outline.get(layer_id).unwrap_or(&false)
+    }
+    
+    /// How a layer's content should be drawn on the stage, based on its
+    /// visibility and outline toggles in the layer panel
+    pub fn layer_display_mode(&self, layer: &LayerInfo) -> LayerDisplayMode {
+        let is_visible = *self.layer_panel_state.layer_visibility
+            .get(&layer.id)
+            .unwrap_or(&layer.visible);
+        if !is_visible {
+            LayerDisplayMode::Hidden
+        } else if self.is_layer_outline(&layer.id) {
+            LayerDisplayMode::Outline(layer.outline_color)
+        } else {
+            LayerDisplayMode::Normal
+        }
+    }
+}
+
+/// State for the right-click context menu
+#[derive(Clone, Debug)]
+pub struct ContextMenuState {
+    pub position: Pos2,
+    pub menu_type: ContextMenuType,
+}
+
+/// Type of context menu to show
+#[derive(Clone, Debug)]
+pub enum ContextMenuType {
+    Layer(LayerId),
+    Frame { layer_id: LayerId, frame: u32 },
+}
+
+
+/// UI-agnostic timeline logic holding the timeline state
+pub struct TimelineController {
+    pub config: TimelineConfig,
+    pub state: TimelineState,
+    /// Named actions available from the command palette
+    pub commands: CommandRegistry,
+    /// Controlled mode: report edits as events without applying them to the engine
+    pub controlled: bool,
+    /// Clips placed on each audio layer
+    pub audio_tracks: HashMap<LayerId, AudioTrack>,
+    /// Waveforms by sound, drawn on the audio layers that use them
+    pub waveforms: HashMap<AudioId, Arc<WaveformData>>,
+    /// Plays audio under the playhead while it is scrubbed
+    pub audio_scrubber: AudioScrubber,
+    /// Automation lanes under each layer
+    pub automation: HashMap<LayerId, Vec<AutomationLane>>,
+    /// Piano roll note tracks under layers
+    pub piano_rolls: HashMap<LayerId, PianoRoll>,
+    /// Events not yet taken by the host
+    events: Vec<TimelineEvent>,
+}
+
+impl Default for TimelineController {
+    fn default() -> Self {
+        Self::with_config(TimelineConfig::default())
+    }
+}
+
+impl TimelineController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: TimelineConfig) -> Self {
+        Self {
+            config,
+            state: TimelineState::default(),
+            commands: CommandRegistry::timeline_defaults(),
+            controlled: false,
+            audio_tracks: HashMap::new(),
+            waveforms: HashMap::new(),
+            audio_scrubber: AudioScrubber::new(),
+            automation: HashMap::new(),
+            piano_rolls: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Record an event and, unless in controlled mode, apply it to the engine
+    /// and the controller. Returns the id of a newly created layer when the
+    /// engine made one.
+    pub fn emit(&mut self, event: TimelineEvent, engine: &mut dyn RiveEngine) -> Option<LayerId> {
+        if event.edits_document() {
+            self.mark_document_changed();
+        }
+        let created = if self.controlled { None } else { self.apply_event(&event, engine) };
+        self.events.push(event);
+        created
+    }
+
+    /// Apply an event to the audio clips and automation lanes kept here, or
+    /// else to the engine
+    /// with [`TimelineEvent::apply`]. Hosts in controlled mode apply the
+    /// events they accept with this.
+    pub fn apply_event(&mut self, event: &TimelineEvent, engine: &mut dyn RiveEngine) -> Option<LayerId> {
+        let fps = self.config.fps.to_fps();
+        match event {
+            TimelineEvent::EnvelopePointSet { layer_id, clip, frame, volume } => {
+                if let Some((start, envelope)) = self.clip_envelope_mut(layer_id, *clip) {
+                    if let Some(frame) = frame.checked_sub(start) {
+                        envelope.set_point(frame, *volume);
+                    }
+                }
+            }
+            TimelineEvent::EnvelopePointMoved { layer_id, clip, from, to, volume } => {
+                if let Some((start, envelope)) = self.clip_envelope_mut(layer_id, *clip) {
+                    if let Some(from) = from.checked_sub(start) {
+                        envelope.move_point(from, to.saturating_sub(start), *volume);
+                    }
+                }
+            }
+            TimelineEvent::EnvelopePointRemoved { layer_id, clip, frame } => {
+                if let Some((start, envelope)) = self.clip_envelope_mut(layer_id, *clip) {
+                    if let Some(frame) = frame.checked_sub(start) {
+                        envelope.remove_point(frame);
+                    }
+                }
+            }
+            TimelineEvent::EnvelopePresetApplied { layer_id, clip, preset, fade_frames } => {
+                let range = self.audio_clip(layer_id, *clip).map(|audio| audio.frame_range(fps));
+                if let (Ok(range), Some((_, envelope))) = (range, self.clip_envelope_mut(layer_id, *clip)) {
+                    *envelope = VolumeEnvelope::from_preset(*preset, range.end - range.start, *fade_frames);
+                }
+            }
+            TimelineEvent::AudioClipChanged { layer_id, clip, start_frame, trim_start, trim_end } => {
+                let Ok(mut audio) = self.audio_clip(layer_id, *clip) else { return None };
+                audio.start_frame = *start_frame;
+                audio.trim_start = *trim_start;
+                audio.trim_end = *trim_end;
+                if let Some(track) = self.audio_tracks.get_mut(layer_id) {
+                    // Rejected if the clip no longer fits between its neighbours
+                    let _ = track.replace_clip(*clip, audio, fps);
+                }
+            }
+            TimelineEvent::AudioClipSplit { layer_id, clip, frame } => {
+                if let Some(track) = self.audio_tracks.get_mut(layer_id) {
+                    track.split_clip(*clip, *frame, fps);
+                }
+            }
+            TimelineEvent::AutomationPointSet { layer_id, lane, frame, value } => {
+                if let Some(envelope) = self.automation_envelope_mut(layer_id, *lane) {
+                    envelope.set_at(*frame, *value);
+                }
+            }
+            TimelineEvent::AutomationPointMoved { layer_id, lane, from, to, value } => {
+                if let Some(AutomationEnvelope::Numeric(envelope)) = self.automation_envelope_mut(layer_id, *lane) {
+                    if let Some(index) = envelope.point_at(*from) {
+                        envelope.move_point(index, *to, *value);
+                    }
+                }
+            }
+            TimelineEvent::AutomationPointRemoved { layer_id, lane, frame } => {
+                if let Some(envelope) = self.automation_envelope_mut(layer_id, *lane) {
+                    envelope.remove_at(*frame);
+                }
+            }
+            TimelineEvent::AutomationCurveSet { layer_id, lane, frame, curve } => {
+                if let Some(AutomationEnvelope::Numeric(envelope)) = self.automation_envelope_mut(layer_id, *lane) {
+                    if let Some(index) = envelope.point_at(*frame) {
+                        envelope.set_curve(index, *curve);
+                    }
+                }
+            }
+            TimelineEvent::NoteAdded { layer_id, note } => {
+                if let Some(roll) = self.piano_rolls.get_mut(layer_id) {
+                    roll.add_note(*note);
+                }
+            }
+            TimelineEvent::NoteMoved { layer_id, index, frame, key } => {
+                if let Some(roll) = self.piano_rolls.get_mut(layer_id) {
+                    roll.move_note(*index, *frame, *key);
+                }
+            }
+            TimelineEvent::NoteResized { layer_id, index, length } => {
+                if let Some(roll) = self.piano_rolls.get_mut(layer_id) {
+                    roll.resize_note(*index, *length);
+                }
+            }
+            TimelineEvent::NoteVelocitySet { layer_id, index, velocity } => {
+                if let Some(roll) = self.piano_rolls.get_mut(layer_id) {
+                    roll.set_velocity(*index, *velocity);
+                }
+            }
+            TimelineEvent::NoteRemoved { layer_id, index } => {
+                if let Some(roll) = self.piano_rolls.get_mut(layer_id) {
+                    roll.remove_note(*index);
+                }
+            }
+            _ => return event.apply(engine),
+        }
+        None
+    }
+
+    /// Note that the engine's frames or layers changed. Emitted edits do this
+    /// themselves; hosts call it after changing the engine some other way,
+    /// such as loading a document or applying events in controlled mode.
+    pub fn mark_document_changed(&mut self) {
+        self.state.document_revision += 1;
+    }
+
+    /// Take the events emitted since the last call
+    pub fn take_events(&mut self) -> Vec<TimelineEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Audio
+
+    /// Place a single sound on an audio layer, replacing its clips
+    pub fn set_audio_layer(&mut self, layer_id: LayerId, audio: AudioLayer) {
+        self.audio_tracks.insert(layer_id, AudioTrack::from(audio));
+    }
+
+    /// Add a clip to an audio layer's track, returning its index
+    pub fn add_audio_clip(&mut self, layer_id: LayerId, clip: AudioLayer) -> Result<usize, AudioError> {
+        let fps = self.config.fps.to_fps();
+        self.audio_tracks.entry(layer_id).or_default().add_clip(clip, fps)
+    }
+
+    /// Split the clip under the playhead on an audio layer, returning the
+    /// index of the second half
+    pub fn split_audio_at_playhead(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId) -> Option<usize> {
+        let fps = self.config.fps.to_fps();
+        let frame = self.state.playhead_frame;
+        let mut track = self.audio_tracks.get(layer_id)?.clone();
+        let clip = track.clip_at(frame, fps)?;
+        let second = track.split_clip(clip, frame, fps)?;
+        self.emit(TimelineEvent::AudioClipSplit { layer_id: layer_id.clone(), clip, frame }, engine);
+        Some(second)
+    }
+
+    /// The playhead was scrubbed to `frame` at `time` seconds: play a snippet
+    /// of the visible audio layers there, unless audio scrubbing is off
+    pub fn scrub_audio(&mut self, audio: &mut dyn AudioEngine, frame: u32, time: f64) -> Result<(), AudioError> {
+        if !self.state.audio_scrubbing {
+            return Ok(());
+        }
+        let visibility = &self.state.layer_panel_state.layer_visibility;
+        let tracks = self
+            .audio_tracks
+            .iter()
+            .filter(|(id, _)| visibility.get(*id) != Some(&false))
+            .map(|(_, track)| track);
+        self.audio_scrubber.scrub(frame, time, tracks, audio, self.config.fps.to_fps())
+    }
+
+    /// Scrubbing ended: silence the last snippet
+    pub fn end_scrub_audio(&mut self, audio: &mut dyn AudioEngine) -> Result<(), AudioError> {
+        self.audio_scrubber.finish(audio)
+    }
+
+    /// Replace the beat markers with the beats of an audio layer's clips.
+    /// `sources` returns the decoded audio of a clip's source; looped
+    /// repeats are not marked. Returns the tempo of the first clip.
+    pub fn detect_beats(
+        &mut self,
+        layer_id: &LayerId,
+        sources: impl Fn(&AudioId) -> Option<Arc<WavData>>,
+    ) -> Result<Option<f64>, AudioError> {
+        let track = self.audio_tracks.get(layer_id).ok_or(AudioError::ClipNotFound)?;
+        let fps = self.config.fps.to_fps();
+        let settings = BeatSettings { sensitivity: self.state.beat_sensitivity, ..Default::default() };
+        let mut analyses: HashMap<AudioId, BeatAnalysis> = HashMap::new();
+        let mut markers = Vec::new();
+        let mut tempo = None;
+
+        for clip in track.clips() {
+            if !analyses.contains_key(&clip.source.id) {
+                let data = sources(&clip.source.id).ok_or(AudioError::AudioNotLoaded)?;
+                analyses.insert(clip.source.id.clone(), beat_detection::detect(&data, &settings));
+            }
+            let analysis = &analyses[&clip.source.id];
+            tempo = tempo.or(analysis.tempo);
+            let end = (clip.source.duration - clip.trim_end) as f64;
+            markers.extend(
+                analysis
+                    .beats
+                    .iter()
+                    .filter(|&&time| time >= clip.trim_start as f64 && time < end)
+                    .map(|&time| clip.start_frame + ((time - clip.trim_start as f64) * fps as f64).round() as u32),
+            );
+        }
+        markers.sort_unstable();
+        markers.dedup();
+        self.config.beat_markers = markers;
+        Ok(tempo)
+    }
+
+    /// Turn the beat markers into frame labels named "Beat 1", "Beat 2", ...,
+    /// skipping frames that already have a label. Returns how many were added.
+    pub fn beat_markers_to_labels(&mut self, engine: &mut dyn RiveEngine) -> usize {
+        let markers = std::mem::take(&mut self.config.beat_markers);
+        let mut added = 0;
+        for (beat, frame) in markers.into_iter().enumerate() {
+            if self.config.frame_labels.iter().any(|label| label.frame == frame) {
+                continue;
+            }
+            self.add_label(engine, frame, &format!("Beat {}", beat + 1));
+            added += 1;
+        }
+        added
+    }
+
+    /// Place the selected notes of a MIDI file from `start_frame` on, as
+    /// keyframes, beat markers or labels named after the note. Notes that
+    /// land on a frame already used are skipped. Returns how many were placed.
+    pub fn import_midi(
+        &mut self,
+        engine: &mut dyn RiveEngine,
+        file: &MidiFile,
+        selection: &MidiSelection,
+        target: &MidiTarget,
+        start_frame: u32,
+    ) -> usize {
+        let fps = self.config.fps.to_fps();
+        let mut placed: Vec<u32> = Vec::new();
+        for (_, note) in file.selected_notes(selection) {
+            let frame = start_frame + file.frame_at(note.tick, fps);
+            if placed.contains(&frame) {
+                continue;
+            }
+            match target {
+                MidiTarget::Keyframes(layer_id) => self.insert_keyframe(engine, layer_id.clone(), frame),
+                MidiTarget::Markers => {
+                    if self.config.beat_markers.contains(&frame) {
+                        continue;
+                    }
+                    self.config.beat_markers.push(frame);
+                }
+                MidiTarget::Labels => {
+                    if self.config.frame_labels.iter().any(|label| label.frame == frame) {
+                        continue;
+                    }
+                    self.add_label(engine, frame, &midi::note_name(note.key));
+                }
+            }
+            placed.push(frame);
+        }
+        self.config.beat_markers.sort_unstable();
+        placed.len()
+    }
+
+    /// Key mouth shapes on `target`: a keyframe at each key, showing the
+    /// key's frame of the mouth symbol
+    pub fn apply_lip_sync(&mut self, engine: &mut dyn RiveEngine, target: &LayerId, keys: &[LipSyncKey]) {
+        for key in keys {
+            self.insert_keyframe(engine, target.clone(), key.frame);
+            let event = TimelineEvent::SymbolFrameSet { layer_id: target.clone(), frame: key.frame, symbol_frame: key.symbol_frame };
+            self.emit(event, engine);
+        }
+    }
+
+    /// Lip sync `target` to the loudness of an audio clip's sound. `sources`
+    /// returns the decoded audio of the clip's source. Returns the number of
+    /// keyframes made.
+    pub fn lip_sync_from_audio(
+        &mut self,
+        engine: &mut dyn RiveEngine,
+        layer_id: &LayerId,
+        clip: usize,
+        target: &LayerId,
+        mouths: &MouthSet,
+        sources: impl Fn(&AudioId) -> Option<Arc<WavData>>,
+    ) -> Result<usize, AudioError> {
+        let audio = self.audio_clip(layer_id, clip)?;
+        let data = sources(&audio.source.id).ok_or(AudioError::AudioNotLoaded)?;
+        let fps = self.config.fps.to_fps();
+        let keys = lip_sync::clip_keys(&lip_sync::amplitude_cues(&data, mouths, fps), mouths, &audio, fps);
+        self.apply_lip_sync(engine, target, &keys);
+        Ok(keys.len())
+    }
+
+    /// Lip sync `target` to a phoneme transcript of an audio clip's sound.
+    /// Returns the number of keyframes made.
+    pub fn lip_sync_from_transcript(
+        &mut self,
+        engine: &mut dyn RiveEngine,
+        layer_id: &LayerId,
+        clip: usize,
+        target: &LayerId,
+        mouths: &MouthSet,
+        cues: &[PhonemeCue],
+    ) -> Result<usize, AudioError> {
+        let audio = self.audio_clip(layer_id, clip)?;
+        let keys = lip_sync::clip_keys(cues, mouths, &audio, self.config.fps.to_fps());
+        self.apply_lip_sync(engine, target, &keys);
+        Ok(keys.len())
+    }
+
+    fn audio_clip(&self, layer_id: &LayerId, clip: usize) -> Result<AudioLayer, AudioError> {
+        self.audio_tracks
+            .get(layer_id)
+            .and_then(|track| track.clips().get(clip))
+            .cloned()
+            .ok_or(AudioError::ClipNotFound)
+    }
+
+    /// Provide the waveform drawn for a sound. While a
+    /// [`WaveformJob`](crate::WaveformJob) runs, call again with each newer result.
+    pub fn set_waveform(&mut self, waveform: impl Into<Arc<WaveformData>>) {
+        let waveform = waveform.into();
+        self.waveforms.insert(waveform.audio_id.clone(), waveform);
+    }
+
+    /// Waveform of a clip's sound, if known
+    pub fn clip_waveform(&self, clip: &AudioLayer) -> Option<&WaveformData> {
+        self.waveforms.get(&clip.source.id).map(|waveform| &**waveform)
+    }
+
+    /// Snap an envelope level to 5% steps unless snapping is off or bypassed
+    pub fn snap_envelope_level(&self, level: f32, bypass: bool) -> f32 {
+        let level = level.clamp(0.0, 1.0);
+        if bypass || !self.config.snap.enabled {
+            return level;
+        }
+        (level * 20.0).round() / 20.0
+    }
+
+    /// First frame and envelope of a clip
+    fn clip_envelope_mut(&mut self, layer_id: &LayerId, clip: usize) -> Option<(u32, &mut VolumeEnvelope)> {
+        let track = self.audio_tracks.get_mut(layer_id)?;
+        let start = track.clips().get(clip)?.start_frame;
+        Some((start, track.envelope_mut(clip)?))
+    }
+
+    /// Add or update a volume envelope point at timeline `frame` on the clip
+    /// playing there. Returns the clip's index.
+    pub fn set_envelope_point(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, frame: u32, volume: f32) -> Option<usize> {
+        let fps = self.config.fps.to_fps();
+        let clip = self.audio_tracks.get(layer_id)?.clip_at(frame, fps)?;
+        self.emit(TimelineEvent::EnvelopePointSet { layer_id: layer_id.clone(), clip, frame, volume }, engine);
+        Some(clip)
+    }
+
+    /// Move a clip's envelope point at timeline frame `from` to `to`, keeping
+    /// it between its neighbours. Returns the timeline frame it ends up on.
+    pub fn move_envelope_point(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, clip: usize, from: u32, to: u32, volume: f32) -> Option<u32> {
+        let audio = self.audio_tracks.get(layer_id)?.clips().get(clip)?;
+        let start = audio.start_frame;
+        let moved = audio.volume_envelope.clone().move_point(from.checked_sub(start)?, to.saturating_sub(start), volume)?;
+        let to = start + moved;
+        self.emit(TimelineEvent::EnvelopePointMoved { layer_id: layer_id.clone(), clip, from, to, volume }, engine);
+        Some(to)
+    }
+
+    /// Remove a clip's envelope point at timeline `frame`
+    pub fn remove_envelope_point(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, clip: usize, frame: u32) -> bool {
+        let Some(audio) = self.audio_tracks.get(layer_id).and_then(|track| track.clips().get(clip)) else { return false };
+        let exists = frame.checked_sub(audio.start_frame)
+            .is_some_and(|frame| audio.volume_envelope.points.iter().any(|(f, _)| *f == frame));
+        if exists {
+            self.emit(TimelineEvent::EnvelopePointRemoved { layer_id: layer_id.clone(), clip, frame }, engine);
+        }
+        exists
+    }
+
+    /// Replace a clip's envelope with a preset spanning the clip
+    pub fn apply_envelope_preset(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, clip: usize, preset: EnvelopePreset, fade_frames: u32) -> bool {
+        if self.audio_tracks.get(layer_id).and_then(|track| track.clips().get(clip)).is_none() {
+            return false;
+        }
+        self.emit(TimelineEvent::EnvelopePresetApplied { layer_id: layer_id.clone(), clip, preset, fade_frames }, engine);
+        true
+    }
+
+    /// A layer's audio track as drawn: while one of its clips is dragged,
+    /// with the drag applied
+    pub fn audio_track(&self, layer_id: &LayerId) -> Option<&AudioTrack> {
+        match &self.state.audio_drag {
+            Some(drag) if &drag.layer_id == layer_id => Some(&drag.preview),
+            _ => self.audio_tracks.get(layer_id),
+        }
+    }
+
+    /// Start dragging part of an audio clip at pointer `x`
+    pub fn begin_audio_drag(&mut self, layer_id: &LayerId, clip: usize, mode: AudioDragMode, x: f32) -> bool {
+        let Some(track) = self.audio_tracks.get(layer_id) else { return false };
+        let Some(original) = track.clips().get(clip) else { return false };
+        self.state.audio_drag = Some(AudioDrag {
+            layer_id: layer_id.clone(),
+            clip,
+            mode,
+            original: original.clone(),
+            origin_x: x,
+            preview: track.clone(),
+            preview_clip: clip,
+        });
+        true
+    }
+
+    /// Preview the audio clip drag for the pointer now at `x`. A position
+    /// that would break the track's overlap rules is skipped.
+    pub fn update_audio_drag(&mut self, x: f32) {
+        let Some(drag) = &mut self.state.audio_drag else { return };
+        let Some(track) = self.audio_tracks.get(&drag.layer_id) else { return };
+        let fps = self.config.fps.to_fps();
+        let frames = (x - drag.origin_x) / (self.config.frame_width * self.state.zoom_level);
+        let shifted = |frame: u32| (frame as f32 + frames).round().max(0.0) as u32;
+
+        let mut audio = drag.original.clone();
+        match drag.mode {
+            AudioDragMode::Head => audio.trim_head_to(shifted(audio.start_frame), fps),
+            AudioDragMode::Tail => audio.trim_tail_to(shifted(audio.frame_range(fps).end), fps),
+            AudioDragMode::Move => audio.start_frame = shifted(audio.start_frame),
+            AudioDragMode::Slip => audio.slip(frames / fps),
+        }
+        let mut preview = track.clone();
+        if let Ok(index) = preview.replace_clip(drag.clip, audio, fps) {
+            drag.preview = preview;
+            drag.preview_clip = index;
+        }
+    }
+
+    /// Finish the audio clip drag, emitting its result
+    pub fn end_audio_drag(&mut self, engine: &mut dyn RiveEngine) {
+        let Some(drag) = self.state.audio_drag.take() else { return };
+        let Some(audio) = drag.preview.clips().get(drag.preview_clip) else { return };
+        let original = &drag.original;
+        if (audio.start_frame, audio.trim_start, audio.trim_end) != (original.start_frame, original.trim_start, original.trim_end) {
+            let event = TimelineEvent::AudioClipChanged {
+                layer_id: drag.layer_id.clone(),
+                clip: drag.clip,
+                start_frame: audio.start_frame,
+                trim_start: audio.trim_start,
+                trim_end: audio.trim_end,
+            };
+            self.emit(event, engine);
+        }
+    }
+
+    // Automation
+
+    /// Add a lane under a layer and show the layer's lanes. Returns the
+    /// lane's index.
+    pub fn add_automation_lane(&mut self, layer_id: LayerId, lane: AutomationLane) -> usize {
+        let shown = &mut self.state.layer_panel_state.automation_shown;
+        if !shown.contains(&layer_id) {
+            shown.push(layer_id.clone());
+        }
+        let lanes = self.automation.entry(layer_id).or_default();
+        lanes.push(lane);
+        lanes.len() - 1
+    }
+
+    pub fn remove_automation_lane(&mut self, layer_id: &LayerId, index: usize) {
+        if let Some(lanes) = self.automation.get_mut(layer_id).filter(|lanes| index < lanes.len()) {
+            lanes.remove(index);
+            if lanes.is_empty() {
+                self.automation.remove(layer_id);
+            }
+        }
+    }
+
+    pub fn automation_lanes(&self, layer_id: &LayerId) -> &[AutomationLane] {
+        self.automation.get(layer_id).map_or(&[], Vec::as_slice)
+    }
+
+    fn automation_envelope_mut(&mut self, layer_id: &LayerId, lane: usize) -> Option<&mut AutomationEnvelope> {
+        self.automation.get_mut(layer_id)?.get_mut(lane).map(|lane| &mut lane.envelope)
+    }
+
+    /// Set a numeric point, toggle change or bang on a lane at `frame`
+    pub fn set_automation_point(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, lane: usize, frame: u32, value: AutomationValue) {
+        self.emit(TimelineEvent::AutomationPointSet { layer_id: layer_id.clone(), lane, frame, value }, engine);
+    }
+
+    /// Move point `point` of a numeric lane to `frame`, keeping it between
+    /// its neighbours, and set its value. Returns the frame it ends up on.
+    pub fn move_automation_point(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, lane: usize, point: usize, frame: u32, value: f32) -> Option<u32> {
+        let AutomationEnvelope::Numeric(envelope) = &self.automation_lanes(layer_id).get(lane)?.envelope else { return None };
+        let from = envelope.points.get(point)?.frame;
+        let to = envelope.clone().move_point(point, frame, value)?;
+        self.emit(TimelineEvent::AutomationPointMoved { layer_id: layer_id.clone(), lane, from, to, value }, engine);
+        Some(to)
+    }
+
+    /// Remove the point, toggle change or bang at `frame` from a lane
+    pub fn remove_automation_point(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, lane: usize, frame: u32) -> bool {
+        let exists = self.automation_lanes(layer_id).get(lane).is_some_and(|lane| lane.envelope.has_point_at(frame));
+        if exists {
+            self.emit(TimelineEvent::AutomationPointRemoved { layer_id: layer_id.clone(), lane, frame }, engine);
+        }
+        exists
+    }
+
+    /// Set the bend of the segment after the numeric point at `frame`
+    pub fn set_automation_curve(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, lane: usize, frame: u32, curve: f32) {
+        let curve = curve.clamp(-1.0, 1.0);
+        self.emit(TimelineEvent::AutomationCurveSet { layer_id: layer_id.clone(), lane, frame, curve }, engine);
+    }
+
+    /// Flip a toggle lane's state or a bang lane's trigger at `frame`;
+    /// clicking an existing change or bang removes it
+    pub fn toggle_automation_at(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, lane: usize, frame: u32) {
+        let Some(envelope) = self.automation_lanes(layer_id).get(lane).map(|lane| &lane.envelope) else { return };
+        let value = match envelope {
+            AutomationEnvelope::Numeric(_) => return,
+            _ if envelope.has_point_at(frame) => None,
+            AutomationEnvelope::Toggle(toggle) => Some(AutomationValue::Toggle(!toggle.value_at(frame))),
+            AutomationEnvelope::Bang(_) => Some(AutomationValue::Bang),
+        };
+        match value {
+            Some(value) => self.set_automation_point(engine, layer_id, lane, frame, value),
+            None => {
+                self.remove_automation_point(engine, layer_id, lane, frame);
+            }
+        }
+    }
+
+    /// Show or hide a layer's automation lanes
+    pub fn toggle_automation_lanes(&mut self, layer_id: &LayerId) {
+        let shown = &mut self.state.layer_panel_state.automation_shown;
+        if let Some(index) = shown.iter().position(|id| id == layer_id) {
+            shown.remove(index);
+        } else {
+            shown.push(layer_id.clone());
+        }
+    }
+
+    /// Send the value of every numeric and toggle lane at `frame` to the
+    /// engine, and fire the bangs passed since the last applied frame (only
+    /// the bang at `frame` after a jump backwards). Does nothing if `frame`
+    /// was the last applied.
+    pub fn apply_automation(&mut self, engine: &mut dyn RiveEngine, frame: u32) {
+        let passed = match self.state.automation_frame {
+            Some(last) if last == frame => return,
+            Some(last) if last < frame => last + 1..frame + 1,
+            _ => frame..frame + 1,
+        };
+        self.state.automation_frame = Some(frame);
+
+        let mut events = Vec::new();
+        for (layer_id, lanes) in &self.automation {
+            for lane in lanes {
+                let values: Vec<(u32, AutomationValue)> = match &lane.envelope {
+                    AutomationEnvelope::Bang(bangs) => bangs.in_range(passed.clone()).map(|bang| (bang, AutomationValue::Bang)).collect(),
+                    _ => lane.value_at(frame).map(|value| (frame, value)).into_iter().collect(),
+                };
+                events.extend(values.into_iter().map(|(frame, value)| TimelineEvent::AutomationApplied {
+                    layer_id: layer_id.clone(),
+                    frame,
+                    property: lane.property.clone(),
+                    value,
+                }));
+            }
+        }
+        for event in events {
+            self.emit(event, engine);
+        }
+    }
+
+    // Piano roll
+
+    /// Give a layer an empty piano roll, if it has none, and show it
+    pub fn add_piano_roll(&mut self, layer_id: LayerId) {
+        let shown = &mut self.state.layer_panel_state.piano_roll_shown;
+        if !shown.contains(&layer_id) {
+            shown.push(layer_id.clone());
+        }
+        self.piano_rolls.entry(layer_id).or_default();
+    }
+
+    pub fn remove_piano_roll(&mut self, layer_id: &LayerId) -> Option<PianoRoll> {
+        self.state.layer_panel_state.piano_roll_shown.retain(|id| id != layer_id);
+        if self.state.note_drag.as_ref().is_some_and(|drag| &drag.layer_id == layer_id) {
+            self.state.note_drag = None;
+        }
+        self.piano_rolls.remove(layer_id)
+    }
+
+    pub fn piano_roll(&self, layer_id: &LayerId) -> Option<&PianoRoll> {
+        self.piano_rolls.get(layer_id)
+    }
+
+    /// Show or hide a layer's piano roll
+    pub fn toggle_piano_roll(&mut self, layer_id: &LayerId) {
+        let shown = &mut self.state.layer_panel_state.piano_roll_shown;
+        if let Some(index) = shown.iter().position(|id| id == layer_id) {
+            shown.remove(index);
+        } else {
+            shown.push(layer_id.clone());
+        }
+    }
+
+    /// Add a note to a layer's piano roll. Returns the index it gets.
+    pub fn add_note(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, note: Note) -> Option<usize> {
+        let index = self.piano_roll(layer_id)?.clone().add_note(note);
+        self.emit(TimelineEvent::NoteAdded { layer_id: layer_id.clone(), note }, engine);
+        Some(index)
+    }
+
+    /// Move note `index` to `frame` and `key`. Returns its new index.
+    pub fn move_note(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, index: usize, frame: u32, key: u8) -> Option<usize> {
+        let note = *self.piano_roll(layer_id)?.notes.get(index)?;
+        let moved = self.piano_roll(layer_id)?.clone().move_note(index, frame, key)?;
+        if (note.frame, note.key) != (frame, key.min(127)) {
+            self.emit(TimelineEvent::NoteMoved { layer_id: layer_id.clone(), index, frame, key }, engine);
+        }
+        Some(moved)
+    }
+
+    /// Set the length of note `index`, at least one frame
+    pub fn resize_note(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, index: usize, length: u32) {
+        let length = length.max(1);
+        if self.piano_roll(layer_id).and_then(|roll| roll.notes.get(index)).is_some_and(|note| note.length != length) {
+            self.emit(TimelineEvent::NoteResized { layer_id: layer_id.clone(), index, length }, engine);
+        }
+    }
+
+    pub fn set_note_velocity(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, index: usize, velocity: u8) {
+        let velocity = velocity.clamp(1, 127);
+        if self.piano_roll(layer_id).and_then(|roll| roll.notes.get(index)).is_some_and(|note| note.velocity != velocity) {
+            self.emit(TimelineEvent::NoteVelocitySet { layer_id: layer_id.clone(), index, velocity }, engine);
+        }
+    }
+
+    pub fn remove_note(&mut self, engine: &mut dyn RiveEngine, layer_id: &LayerId, index: usize) -> bool {
+        let exists = self.piano_roll(layer_id).is_some_and(|roll| index < roll.notes.len());
+        if exists {
+            self.emit(TimelineEvent::NoteRemoved { layer_id: layer_id.clone(), index }, engine);
+        }
+        exists
+    }
+
+    /// Start and stop piano roll notes as the playhead moves to `frame`.
+    /// Stepping forward plays the note starts and ends passed since the last
+    /// frame, ends first; a jump stops the notes left and starts the notes
+    /// landed in. Does nothing if `frame` was the last played.
+    pub fn play_notes(&mut self, engine: &mut dyn RiveEngine, frame: u32) {
+        let last = self.state.note_frame;
+        if last == Some(frame) {
+            return;
+        }
+        self.state.note_frame = Some(frame);
+
+        let mut played: Vec<(u32, bool, LayerId, Note)> = Vec::new();
+        for (layer_id, roll) in &self.piano_rolls {
+            for note in &roll.notes {
+                let (on, off) = match last {
+                    Some(last) if last < frame => {
+                        let passed = last + 1..frame + 1;
+                        (passed.contains(&note.frame).then_some(note.frame), passed.contains(&note.end()).then_some(note.end()))
+                    }
+                    _ => {
+                        let was = last.is_some_and(|last| note.contains(last));
+                        let is = note.contains(frame);
+                        ((!was && is).then_some(frame), (was && !is).then_some(frame))
+                    }
+                };
+                played.extend(on.map(|frame| (frame, true, layer_id.clone(), *note)));
+                played.extend(off.map(|frame| (frame, false, layer_id.clone(), *note)));
+            }
+        }
+        played.sort_by_key(|(frame, on, _, _)| (*frame, *on));
+        for (frame, on, layer_id, note) in played {
+            let event = if on {
+                TimelineEvent::NoteOn { layer_id, frame, note }
+            } else {
+                TimelineEvent::NoteOff { layer_id, frame, note }
+            };
+            self.emit(event, engine);
+        }
+    }
+
+    // Geometry
+
+    /// Current width of one frame in pixels
+    pub fn frame_width(&self) -> f32 {
+        self.config.frame_width * self.state.zoom_level
+    }
+
+    /// Frame under a horizontal position measured from the left edge of the frame grid
+    pub fn frame_at_x(&self, x: f32) -> u32 {
+        ((x + self.state.scroll_x) / self.frame_width()).max(0.0) as u32
+    }
+
+    /// Layer under a vertical position measured from the top of the frame grid
+    pub fn layer_at_y(&self, y: f32, layers: &[LayerInfo]) -> Option<LayerId> {
+        let mut y_offset = self.state.scroll_y;
+        for layer in layers {
+            let layer_height = self.track_height(&layer.id);
+            if y >= y_offset && y < y_offset + layer_height {
+                return Some(layer.id.clone());
+            }
+            y_offset += layer_height;
+        }
+        None
+    }
+
+    /// Height of a layer's track, including its shown piano roll and
+    /// automation lanes
+    pub fn track_height(&self, layer_id: &LayerId) -> f32 {
+        self.frame_row_height(layer_id) + self.piano_roll_height(layer_id) + self.automation_height(layer_id)
+    }
+
+    /// Height of a layer's frame row, above its piano roll and automation lanes
+    pub fn frame_row_height(&self, layer_id: &LayerId) -> f32 {
+        self.state.track_heights
+            .get(layer_id)
+            .copied()
+            .unwrap_or(self.config.default_track_height)
+    }
+
+    /// Height of the piano roll shown under a layer's frame row
+    pub fn piano_roll_height(&self, layer_id: &LayerId) -> f32 {
+        if !self.state.layer_panel_state.piano_roll_shown.contains(layer_id) {
+            return 0.0;
+        }
+        self.piano_roll(layer_id).map_or(0.0, PianoRoll::height)
+    }
+
+    /// Height of the automation lanes shown under a layer
+    pub fn automation_height(&self, layer_id: &LayerId) -> f32 {
+        if !self.state.layer_panel_state.automation_shown.contains(layer_id) {
+            return 0.0;
+        }
+        self.automation_lanes(layer_id).len() as f32 * automation::LANE_HEIGHT
+    }
+
+    // Snapping
+
+    /// Snap a horizontal grid position to the nearest marker, or else the
+    /// nearest beat grid line in bars|beats mode, or else the nearest frame,
+    /// when within the snap threshold. `bypass` (Shift in the egui widget)
+    /// disables snapping.
+    pub fn snap(&self, pos: f32, bypass: bool) -> f32 {
+        if bypass || !self.config.snap.enabled {
+            return pos;
+        }
+        if let Some(marker_pos) = self.marker_snap(pos).or_else(|| self.beat_snap(pos)) {
+            return marker_pos;
+        }
+
+        let frame_width = self.frame_width();
+        if self.config.snap.snap_to_frames {
+            let snapped_pos = (pos / frame_width).round() * frame_width;
+            if (pos - snapped_pos).abs() < self.config.snap.threshold_pixels {
+                return snapped_pos;
+            }
+        }
+
+        pos
+    }
+
+    /// Update snap guides for visual feedback
+    pub fn update_snap_guides(&mut self, pos: f32) {
+        self.state.snap_guides.clear();
+
+        if !self.config.snap.enabled || !self.config.snap.show_guides {
+            return;
+        }
+
+        if let Some(marker_pos) = self.marker_snap(pos).or_else(|| self.beat_snap(pos)) {
+            self.state.snap_guides.push(marker_pos);
+            return;
+        }
+        let frame_width = self.frame_width();
+        if self.config.snap.snap_to_frames {
+            let snapped_pos = (pos / frame_width).round() * frame_width;
+            if (pos - snapped_pos).abs() < self.config.snap.threshold_pixels {
+                self.state.snap_guides.push(snapped_pos);
+            }
+        }
+    }
+
+    /// Frames of beat markers and frame labels
+    pub fn marker_frames(&self) -> impl Iterator<Item = u32> + '_ {
+        self.config.beat_markers.iter().copied().chain(self.config.frame_labels.iter().map(|label| label.frame))
+    }
+
+    /// Position of the nearest marker within the snap threshold
+    fn marker_snap(&self, pos: f32) -> Option<f32> {
+        if !self.config.snap.snap_to_markers {
+            return None;
+        }
+        let frame_width = self.frame_width();
+        self.marker_frames()
+            .map(|frame| frame as f32 * frame_width)
+            .filter(|marker_pos| (pos - marker_pos).abs() < self.config.snap.threshold_pixels)
+            .min_by(|a, b| (pos - a).abs().total_cmp(&(pos - b).abs()))
+    }
+
+    /// Position of the nearest beat grid line within the snap threshold,
+    /// when the ruler shows bars and beats
+    fn beat_snap(&self, pos: f32) -> Option<f32> {
+        if !self.config.snap.snap_to_beats || self.state.ruler_mode != RulerMode::BarsBeats {
+            return None;
+        }
+        let frame_width = self.frame_width();
+        let frame = self.config.tempo_map.snap((pos / frame_width) as f64, self.config.fps.to_fps(), self.config.snap.beat_subdivision);
+        let beat_pos = frame as f32 * frame_width;
+        ((pos - beat_pos).abs() < self.config.snap.threshold_pixels).then_some(beat_pos)
+    }
+
+    /// Bar and beat of a frame in the document tempo map
+    pub fn musical_position(&self, frame: u32) -> MusicalPosition {
+        self.config.tempo_map.position_at(frame as f64, self.config.fps.to_fps())
+    }
+
+    // Playback and navigation
+
+    pub fn toggle_playback(&mut self, engine: &mut dyn RiveEngine) {
+        self.state.is_playing = !self.state.is_playing;
+        let event = if self.state.is_playing { TimelineEvent::PlayRequested } else { TimelineEvent::PauseRequested };
+        self.emit(event, engine);
+    }
+
+    pub fn stop(&mut self, engine: &mut dyn RiveEngine) {
+        self.state.is_playing = false;
+        self.state.playhead_frame = 0;
+        self.emit(TimelineEvent::PauseRequested, engine);
+        self.emit(TimelineEvent::SeekRequested { frame: 0 }, engine);
+    }
+
+    /// Move the playhead, clamped to the document
+    pub fn seek(&mut self, engine: &mut dyn RiveEngine, frame: u32) {
+        let frame = frame.min(engine.get_total_frames().saturating_sub(1));
+        self.state.playhead_frame = frame;
+        self.emit(TimelineEvent::SeekRequested { frame }, engine);
+    }
+
+    pub fn go_to_first_frame(&mut self, engine: &mut dyn RiveEngine) {
+        self.seek(engine, 0);
+    }
+
+    pub fn go_to_last_frame(&mut self, engine: &mut dyn RiveEngine) {
+        self.seek(engine, u32::MAX);
+    }
+
+    pub fn go_to_previous_frame(&mut self, engine: &mut dyn RiveEngine) {
+        let current = engine.get_current_frame();
+        if current > 0 {
+            self.seek(engine, current - 1);
+        }
+    }
+
+    pub fn go_to_next_frame(&mut self, engine: &mut dyn RiveEngine) {
+        let current = engine.get_current_frame();
+        if current + 1 < engine.get_total_frames() {
+            self.seek(engine, current + 1);
+        }
+    }
+
+    /// Scroll so the playhead is in the middle of the frame grid
+    pub fn center_playhead(&mut self, engine: &dyn RiveEngine) {
+        let playhead_x = engine.get_current_frame() as f32 * self.frame_width();
+        self.state.scroll_x = (playhead_x - self.state.view_width / 2.0).max(0.0);
+    }
+
+    /// Start dragging the playhead, pausing playback until the drag ends
+    pub fn begin_scrub(&mut self, engine: &mut dyn RiveEngine) {
+        self.state.is_scrubbing = true;
+        self.state.was_playing = self.state.is_playing;
+        if self.state.was_playing {
+            self.emit(TimelineEvent::PauseRequested, engine);
+        }
+    }
+
+    /// Move the scrubbed playhead to a grid position (including scroll), returning the frame
+    pub fn scrub_to(&mut self, engine: &mut dyn RiveEngine, x: f32, bypass_snap: bool) -> u32 {
+        let snapped_x = self.snap(x, bypass_snap);
+        let frame = (snapped_x / self.frame_width()).round().max(0.0) as u32;
+        let frame = frame.min(engine.get_total_frames().saturating_sub(1));
+        self.state.playhead_frame = frame;
+        self.emit(TimelineEvent::SeekRequested { frame }, engine);
+        frame
+    }
+
+    pub fn end_scrub(&mut self, engine: &mut dyn RiveEngine) {
+        self.state.is_scrubbing = false;
+        if self.state.was_playing {
+            self.emit(TimelineEvent::PlayRequested, engine);
+        }
+    }
+
+    // Selection
+
+    /// Select a layer; `additive` (Ctrl/Cmd) toggles it in the current selection
+    pub fn select_layer(&mut self, layer_id: &LayerId, additive: bool) {
+        if additive {
+            if self.state.selected_layers.contains(layer_id) {
+                self.state.selected_layers.retain(|id| id != layer_id);
+            } else {
+                self.state.selected_layers.push(layer_id.clone());
+            }
+        } else {
+            self.state.selected_layers = vec![layer_id.clone()];
+        }
+    }
+
+    /// Shift-click: select every layer between the last selected one and `layer_id`
+    pub fn select_layer_range(&mut self, layers: &[LayerInfo], layer_id: &LayerId) {
+        let Some(last_selected) = self.state.selected_layers.last() else {
+            return self.select_layer(layer_id, false);
+        };
+        let last_idx = layers.iter().position(|l| &l.id == last_selected).unwrap_or(0);
+        let idx = layers.iter().position(|l| &l.id == layer_id).unwrap_or(last_idx);
+        let (start, end) = (last_idx.min(idx), last_idx.max(idx));
+        self.state.selected_layers = layers[start..=end].iter().map(|l| l.id.clone()).collect();
+    }
+
+    /// Click on a frame cell: selects the keyframe there, or clears the
+    /// selection when clicking elsewhere. `additive` toggles instead.
+    pub fn click_frame(&mut self, engine: &dyn RiveEngine, layer_id: &LayerId, frame: u32, additive: bool) {
+        let frame_data = engine.get_frame_data(layer_id.clone(), frame);
+        let selection = &mut self.state.keyframe_selection;
+        if matches!(frame_data.frame_type, FrameType::Keyframe) {
+            if !additive {
+                selection.clear();
+                selection.add(layer_id.clone(), frame, frame_data.id);
+            } else if selection.is_selected(layer_id.clone(), frame) {
+                selection.remove(layer_id.clone(), frame);
+            } else {
+                selection.add(layer_id.clone(), frame, frame_data.id);
+            }
+        } else if !additive {
+            selection.clear();
+        }
+    }
+
+    /// Select every keyframe on a layer
+    pub fn select_layer_keyframes(&mut self, engine: &dyn RiveEngine, layer_id: &LayerId) {
+        self.state.keyframe_selection.clear();
+        for frame in 0..engine.get_total_frames() {
+            let frame_data = engine.get_frame_data(layer_id.clone(), frame);
+            if matches!(frame_data.frame_type, FrameType::Keyframe) {
+                self.state.keyframe_selection.add(layer_id.clone(), frame, frame_data.id);
+            }
+        }
+    }
+
+    pub fn toggle_frame_range_mode(&mut self) {
+        self.state.frame_range_mode = !self.state.frame_range_mode;
+        if !self.state.frame_range_mode {
+            self.state.frame_range_selection = None;
+        }
+    }
+
+    // Frame edits
+
+    pub fn insert_frame(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.emit(TimelineEvent::FrameInserted { layer_id, frame }, engine);
+    }
+
+    pub fn remove_frame(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.emit(TimelineEvent::FrameRemoved { layer_id, frame }, engine);
+    }
+
+    pub fn insert_keyframe(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.emit(TimelineEvent::KeyframeInserted { layer_id, frame }, engine);
+    }
+
+    pub fn clear_keyframe(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.emit(TimelineEvent::KeyframeCleared { layer_id, frame }, engine);
+    }
+
+    /// Replace whatever is at `frame` with a keyframe
+    pub fn convert_to_keyframe(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.remove_frame(engine, layer_id.clone(), frame);
+        self.insert_keyframe(engine, layer_id, frame);
+    }
+
+    pub fn create_motion_tween(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.emit(TimelineEvent::MotionTweenCreated { layer_id, frame }, engine);
+    }
+
+    pub fn create_shape_tween(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, frame: u32) {
+        self.emit(TimelineEvent::ShapeTweenCreated { layer_id, frame }, engine);
+    }
+
+    /// Copy the selected keyframes, relative to the earliest one
+    pub fn copy_selected_keyframes(&mut self, engine: &mut dyn RiveEngine) {
+        let selected = self.state.keyframe_selection.get_selected();
+        let first_frame = selected.iter().map(|(_, frame, _)| *frame).min().unwrap_or(0);
+        self.state.keyframe_selection.clipboard = selected.into_iter()
+            .filter_map(|(layer_id, frame, _)| {
+                let data = engine.copy_keyframe(layer_id.clone(), frame)?;
+                Some(KeyframeClipboardItem {
+                    layer_id,
+                    relative_frame: frame - first_frame,
+                    data,
+                })
+            })
+            .collect();
+    }
+
+    /// Paste the clipboard starting at `frame`, dropping keyframes past the end
+    pub fn paste_keyframes(&mut self, engine: &mut dyn RiveEngine, frame: u32) {
+        let total_frames = engine.get_total_frames();
+        for item in self.state.keyframe_selection.clipboard.clone() {
+            let frame = frame + item.relative_frame;
+            if frame < total_frames {
+                self.emit(TimelineEvent::KeyframePasted { layer_id: item.layer_id, frame, data: item.data }, engine);
+            }
+        }
+    }
+
+    pub fn delete_selected_keyframes(&mut self, engine: &mut dyn RiveEngine) {
+        for (layer_id, frame, _) in self.state.keyframe_selection.get_selected() {
+            self.emit(TimelineEvent::KeyframeDeleted { layer_id, frame }, engine);
+        }
+        self.state.keyframe_selection.clear();
+    }
+
+    // Layer edits
+
+    /// The engine's layers, with palette colors for those without an outline
+    /// color. The widget draws from these rather than the engine's list.
+    pub fn layers(&self, engine: &dyn RiveEngine) -> Vec<LayerInfo> {
+        let mut layers = engine.get_layers();
+        crate::layer::assign_outline_colors(&mut layers);
+        layers
+    }
+
+    /// Add a layer and start renaming it. Returns `None` in controlled mode,
+    /// where the host creates the layer.
+    pub fn add_layer(&mut self, engine: &mut dyn RiveEngine, name: &str, layer_type: LayerType) -> Option<LayerId> {
+        let event = match layer_type {
+            LayerType::Folder => TimelineEvent::FolderAdded { name: name.to_string() },
+            LayerType::MotionGuide => TimelineEvent::MotionGuideAdded { name: name.to_string() },
+            _ => TimelineEvent::LayerAdded { name: name.to_string(), layer_type },
+        };
+        let layer_id = self.emit(event, engine)?;
+        self.state.layer_panel_state.newly_created_layer = Some(layer_id.clone());
+        self.state.layer_panel_state.renaming_layer = Some((layer_id.clone(), name.to_string()));
+        self.state.selected_layers = vec![layer_id.clone()];
+        Some(layer_id)
+    }
+
+    pub fn delete_layer(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId) {
+        self.state.selected_layers.retain(|id| id != &layer_id);
+        self.emit(TimelineEvent::LayerDeleted { layer_id }, engine);
+    }
+
+    pub fn delete_selected_layers(&mut self, engine: &mut dyn RiveEngine) {
+        for layer_id in std::mem::take(&mut self.state.selected_layers) {
+            self.emit(TimelineEvent::LayerDeleted { layer_id }, engine);
+        }
+    }
+
+    pub fn duplicate_layer(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId) -> Option<LayerId> {
+        self.emit(TimelineEvent::LayerDuplicated { layer_id }, engine)
+    }
+
+    pub fn duplicate_selected_layers(&mut self, engine: &mut dyn RiveEngine) {
+        for layer_id in self.state.selected_layers.clone() {
+            self.duplicate_layer(engine, layer_id);
+        }
+    }
+
+    /// Start renaming a layer in the layer panel
+    pub fn begin_rename(&mut self, layer_id: LayerId, current_name: String) {
+        self.state.layer_panel_state.renaming_layer = Some((layer_id, current_name));
+    }
+
+    /// Finish the rename in progress with its edited name
+    pub fn commit_rename(&mut self, engine: &mut dyn RiveEngine) {
+        if let Some((layer_id, new_name)) = self.state.layer_panel_state.renaming_layer.take() {
+            self.emit(TimelineEvent::LayerRenamed { layer_id, new_name }, engine);
+        }
+    }
+
+    pub fn cancel_rename(&mut self) {
+        self.state.layer_panel_state.renaming_layer = None;
+    }
+
+    pub fn set_layer_outline_color(&mut self, engine: &mut dyn RiveEngine, layer_id: LayerId, color: Color32) {
+        self.emit(TimelineEvent::LayerOutlineColorChanged { layer_id, color }, engine);
+    }
+
+    /// Move `layer_id` to `drop_index` (an index into `layers`, before the move)
+    /// and report the new order
+    pub fn reorder_layer(&mut self, engine: &mut dyn RiveEngine, layers: &[LayerInfo], layer_id: &LayerId, drop_index: usize) {
+        let mut layer_ids: Vec<LayerId> = layers.iter().map(|l| l.id.clone()).collect();
+        let Some(from) = layer_ids.iter().position(|id| id == layer_id) else { return };
+        let moved = layer_ids.remove(from);
+        let to = if from < drop_index { drop_index - 1 } else { drop_index };
+        layer_ids.insert(to.min(layer_ids.len()), moved);
+        self.emit(TimelineEvent::LayersReordered { layer_ids }, engine);
+    }
+
+    pub fn toggle_layer_visibility(&mut self, layer_id: &LayerId) {
+        let visibility = &mut self.state.layer_panel_state.layer_visibility;
+        let visible = *visibility.get(layer_id).unwrap_or(&true);
+        visibility.insert(layer_id.clone(), !visible);
+    }
+
+    pub fn toggle_layer_lock(&mut self, layer_id: &LayerId) {
+        let locked = &mut self.state.layer_panel_state.layer_locked;
+        let is_locked = *locked.get(layer_id).unwrap_or(&false);
+        locked.insert(layer_id.clone(), !is_locked);
+    }
+
+    pub fn toggle_layer_outline(&mut self, layer_id: &LayerId) {
+        let outline = self.state.is_layer_outline(layer_id);
+        self.state.layer_panel_state.layer_outline.insert(layer_id.clone(), !outline);
+    }
+
+    /// Show every layer, or hide them all if they are all visible
+    pub fn toggle_visibility_all(&mut self, layers: &[LayerInfo]) {
+        let visibility = &mut self.state.layer_panel_state.layer_visibility;
+        let all_visible = layers.iter().all(|l| *visibility.get(&l.id).unwrap_or(&true));
+        for layer in layers {
+            visibility.insert(layer.id.clone(), !all_visible);
+        }
+    }
+
+    /// Lock every layer, or unlock them all if they are all locked
+    pub fn toggle_lock_all(&mut self, layers: &[LayerInfo]) {
+        let locked = &mut self.state.layer_panel_state.layer_locked;
+        let all_locked = layers.iter().all(|l| *locked.get(&l.id).unwrap_or(&false));
+        for layer in layers {
+            locked.insert(layer.id.clone(), !all_locked);
+        }
+    }
+
+    /// Expand or collapse a folder layer
+    pub fn toggle_folder(&mut self, layer_id: &LayerId) {
+        let expanded = &mut self.state.layer_panel_state.expanded_folders;
+        if let Some(index) = expanded.iter().position(|id| id == layer_id) {
+            expanded.remove(index);
+        } else {
+            expanded.push(layer_id.clone());
+        }
+    }
+
+    // Labels and comments
+
+    pub fn add_label(&mut self, engine: &mut dyn RiveEngine, frame: u32, name: &str) {
+        self.config.frame_labels.push(FrameLabel::new(frame, name));
+        self.emit(TimelineEvent::LabelAdded { frame, name: name.to_string() }, engine);
+    }
+
+    pub fn remove_label(&mut self, engine: &mut dyn RiveEngine, index: usize) {
+        if index < self.config.frame_labels.len() {
+            let label = self.config.frame_labels.remove(index);
+            self.emit(TimelineEvent::LabelRemoved { frame: label.frame, name: label.label }, engine);
+        }
+    }
+
+    pub fn add_comment(&mut self, engine: &mut dyn RiveEngine, frame: u32, text: &str) {
+        self.config.frame_comments.push(FrameComment::new(frame, text));
+        self.emit(TimelineEvent::CommentAdded { frame, text: text.to_string() }, engine);
+    }
+
+    pub fn remove_comment(&mut self, engine: &mut dyn RiveEngine, index: usize) {
+        if index < self.config.frame_comments.len() {
+            let comment = self.config.frame_comments.remove(index);
+            self.emit(TimelineEvent::CommentRemoved { frame: comment.frame, text: comment.comment }, engine);
+        }
+    }
+
+    // Commands
+
+    /// Run a command from the command registry.
+    ///
+    /// Returns `false` if the command is unknown, disabled in the current
+    /// state, or needs the UI (see `Timeline::execute_command`).
+    pub fn execute_command(&mut self, command_id: &str, engine: &mut dyn RiveEngine) -> bool {
+        let context = CommandContext::from_state(&self.state);
+        if !self.commands.is_enabled(command_id, &context) {
+            return false;
+        }
+
+        let current_frame = engine.get_current_frame();
+        let total_frames = engine.get_total_frames();
+        let selected_layer = self.state.selected_layers.first().cloned();
+
+        match command_id {
+            // Playback
+            "playback.play_pause" => self.toggle_playback(engine),
+            "playback.stop" => self.stop(engine),
+            "playback.first_frame" => self.go_to_first_frame(engine),
+            "playback.last_frame" => self.go_to_last_frame(engine),
+            "playback.previous_frame" => self.go_to_previous_frame(engine),
+            "playback.next_frame" => self.go_to_next_frame(engine),
+            "playback.toggle_loop" => self.state.loop_playback = !self.state.loop_playback,
+
+            // Frames and keyframes
+            "frame.insert_frame" | "frame.remove_frame" | "frame.insert_keyframe" | "frame.clear_keyframe" => {
+                let Some(layer_id) = selected_layer else { return false };
+                match command_id {
+                    "frame.insert_frame" => self.insert_frame(engine, layer_id, current_frame),
+                    "frame.remove_frame" => self.remove_frame(engine, layer_id, current_frame),
+                    "frame.insert_keyframe" => self.insert_keyframe(engine, layer_id, current_frame),
+                    _ => self.clear_keyframe(engine, layer_id, current_frame),
+                }
+            }
+            "keyframe.copy" => self.copy_selected_keyframes(engine),
+            "keyframe.paste" => self.paste_keyframes(engine, current_frame),
+            "keyframe.delete" => self.delete_selected_keyframes(engine),
+            "keyframe.deselect_all" => self.state.keyframe_selection.clear(),
+
+            // Tweens
+            "tween.create_motion" => {
+                for (layer_id, frame, _) in self.state.keyframe_selection.get_selected() {
+                    self.create_motion_tween(engine, layer_id, frame);
+                }
+            }
+            "tween.create_shape" => {
+                for (layer_id, frame, _) in self.state.keyframe_selection.get_selected() {
+                    self.create_shape_tween(engine, layer_id, frame);
+                }
+            }
+
+            // Layers
+            "layer.add" => { self.add_layer(engine, "New Layer", LayerType::Normal); }
+            "layer.add_folder" => { self.add_layer(engine, "New Folder", LayerType::Folder); }
+            "layer.add_motion_guide" => {
+                self.emit(TimelineEvent::MotionGuideAdded { name: "Motion Guide".to_string() }, engine);
+            }
+            "layer.rename" => {
+                let Some(layer_id) = selected_layer else { return false };
+                if let Some(layer) = engine.get_layers().into_iter().find(|l| l.id == layer_id) {
+                    self.begin_rename(layer_id, layer.name);
+                }
+            }
+            "layer.duplicate" => self.duplicate_selected_layers(engine),
+            "layer.delete" => self.delete_selected_layers(engine),
+            "layer.toggle_visibility_all" => self.toggle_visibility_all(&engine.get_layers()),
+            "layer.toggle_lock_all" => self.toggle_lock_all(&engine.get_layers()),
+
+            // Labels and comments
+            "label.add_label" => self.add_label(engine, current_frame, "New Label"),
+            "label.add_comment" => self.add_comment(engine, current_frame, "New comment"),
+            "label.toggle_panel" => self.state.show_label_panel = !self.state.show_label_panel,
+
+            // View
+            "view.toggle_onion_skin" => self.state.onion_skinning = !self.state.onion_skinning,
+            "view.onion_settings" => self.state.show_onion_settings = !self.state.show_onion_settings,
+            "view.toggle_snap" => self.config.snap.enabled = !self.config.snap.enabled,
+            "view.toggle_frame_range_mode" => self.toggle_frame_range_mode(),
+            "view.zoom_in" => self.state.zoom_level = (self.state.zoom_level * 1.2).min(MAX_ZOOM),
+            "view.zoom_out" => self.state.zoom_level = (self.state.zoom_level * 0.8).max(MIN_ZOOM),
+            "view.zoom_reset" => self.state.zoom_level = 1.0,
+            "view.fit_all" => {
+                self.state.zoom_to_frames(0, total_frames.saturating_sub(1), self.config.frame_width, self.state.view_width);
+            }
+            "view.fit_selection" => {
+                if let Some((start, end)) = self.state.selection_frame_range() {
+                    self.state.zoom_to_frames(start, end, self.config.frame_width, self.state.view_width);
+                }
+            }
+            "view.frame_size_tiny" => self.state.zoom_level = FrameSizePreset::Tiny.zoom_level(),
+            "view.frame_size_small" => self.state.zoom_level = FrameSizePreset::Small.zoom_level(),
+            "view.frame_size_normal" => self.state.zoom_level = FrameSizePreset::Normal.zoom_level(),
+            "view.frame_size_medium" => self.state.zoom_level = FrameSizePreset::Medium.zoom_level(),
+            "view.frame_size_large" => self.state.zoom_level = FrameSizePreset::Large.zoom_level(),
+
+            _ => return false,
+        }
+
+        self.commands.record_used(command_id);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::MockRiveEngine;
+    use crate::PropertyId;
+
+    #[test]
+    fn test_snap_to_frames() {
+        let controller = TimelineController::new();
+        // 10px frames, 8px threshold
+        assert_eq!(controller.snap(23.0, false), 20.0);
+        assert_eq!(controller.snap(23.0, true), 23.0);
+    }
+
+    #[test]
+    fn test_snap_prefers_markers() {
+        let mut controller = TimelineController::new();
+        controller.config.beat_markers = vec![3];
+        assert_eq!(controller.snap(36.0, false), 30.0);
+        controller.update_snap_guides(36.0);
+        assert_eq!(controller.state.snap_guides, vec![30.0]);
+
+        // Beat markers follow the marker flag, not the beat grid one
+        controller.config.snap.snap_to_beats = false;
+        assert_eq!(controller.snap(36.0, false), 30.0);
+        controller.config.snap.snap_to_markers = false;
+        assert_eq!(controller.snap(36.0, false), 40.0);
+    }
+
+    #[test]
+    fn test_snap_to_beats_in_bars_mode() {
+        // 90 BPM in 3/4 at 24 fps: a beat is 16 frames, an eighth 8 frames
+        let mut controller = TimelineController::new();
+        controller.config.tempo_map = crate::TempoMap::new(90.0, 3, 4).unwrap();
+        controller.config.snap.beat_subdivision = 2;
+        assert_eq!(controller.snap(805.0, false), 810.0);
+
+        controller.state.ruler_mode = RulerMode::BarsBeats;
+        assert_eq!(controller.snap(805.0, false), 800.0);
+        controller.update_snap_guides(805.0);
+        assert_eq!(controller.state.snap_guides, vec![800.0]);
+        assert_eq!(controller.musical_position(48).to_string(), "2|1");
+        assert_eq!(controller.musical_position(88).to_string(), "2|3");
+    }
+
+    #[test]
+    fn test_detect_beats_marks_clip_frames() {
+        // A click every half second at 8 kHz
+        let mut samples = vec![0.0; 32000];
+        for beat in 0..8 {
+            for n in 0..200 {
+                samples[beat * 4000 + n] = (n as f32 * 0.35).sin() * (1.0 - n as f32 / 200.0);
+            }
+        }
+        let data = Arc::new(WavData { sample_rate: 8000, channels: 1, bits_per_sample: 32, samples });
+        let mut clip = music(4.0, 10);
+        clip.trim_start = 0.5;
+        let layer_id = LayerId::new("music");
+        let mut controller = TimelineController::new();
+        controller.set_audio_layer(layer_id.clone(), clip);
+
+        assert!(matches!(controller.detect_beats(&layer_id, |_| None), Err(AudioError::AudioNotLoaded)));
+        let tempo = controller.detect_beats(&layer_id, |_| Some(data.clone())).unwrap().unwrap();
+        assert!((tempo - 120.0).abs() < 1.0);
+        assert_eq!(controller.config.beat_markers[..4], [10, 22, 34, 46]);
+
+        let mut engine = MockRiveEngine::new();
+        controller.add_label(&mut engine, 22, "chorus");
+        let markers = controller.config.beat_markers.len();
+        assert_eq!(controller.beat_markers_to_labels(&mut engine), markers - 1);
+        assert!(controller.config.beat_markers.is_empty());
+        assert_eq!(controller.config.frame_labels[1].label, "Beat 1");
+        assert_eq!(controller.config.frame_labels[2].frame, 34);
+    }
+
+    #[test]
+    fn test_lip_sync_keys_mouth_frames() {
+        let mut controller = TimelineController::new();
+        let mut engine = MockRiveEngine::new();
+        let voice = LayerId::new("voice");
+        let mouth = LayerId::new("mouth");
+        controller.set_audio_layer(voice.clone(), music(1.0, 12));
+        let mouths = MouthSet::preston_blair();
+        let cues = [PhonemeCue::new(0.0, "rest"), PhonemeCue::new(0.25, "AI"), PhonemeCue::new(0.5, "MBP")];
+
+        let keyed = controller.lip_sync_from_transcript(&mut engine, &voice, 0, &mouth, &mouths, &cues).unwrap();
+        assert_eq!(keyed, 4);
+        for (frame, symbol_frame) in [(12, 9), (18, 0), (24, 7), (36, 9)] {
+            assert_eq!(engine.get_frame_data(mouth.clone(), frame).frame_type, FrameType::Keyframe);
+            assert_eq!(engine.symbol_frame(&mouth, frame), Some(symbol_frame));
+        }
+
+        let missing = controller.lip_sync_from_audio(&mut engine, &voice, 1, &mouth, &mouths, |_| None);
+        assert!(matches!(missing, Err(AudioError::ClipNotFound)));
+    }
+
+    #[test]
+    fn test_automation_lanes_drive_properties() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let layer_id = LayerId::new("layer1");
+        let mut alpha = AutomationLane::numeric(PropertyId::Alpha, 0.0, 1.0, 0.0);
+        if let AutomationEnvelope::Numeric(envelope) = &mut alpha.envelope {
+            envelope.set_point(10, 1.0);
+        }
+        let mut flash = AutomationLane::bang(PropertyId::Custom("Flash".into()));
+        if let AutomationEnvelope::Bang(bangs) = &mut flash.envelope {
+            bangs.toggle_at(3);
+            bangs.toggle_at(6);
+        }
+        controller.add_automation_lane(layer_id.clone(), alpha);
+        controller.add_automation_lane(layer_id.clone(), flash);
+
+        let base = controller.config.default_track_height;
+        assert_eq!(controller.track_height(&layer_id), base + 2.0 * automation::LANE_HEIGHT);
+        controller.toggle_automation_lanes(&layer_id);
+        assert_eq!(controller.track_height(&layer_id), base);
+
+        // Stepping from 0 to 5 fires the bang passed on the way
+        controller.apply_automation(&mut engine, 0);
+        controller.apply_automation(&mut engine, 5);
+        controller.apply_automation(&mut engine, 5);
+        let applied: Vec<(u32, &str, AutomationValue)> = engine
+            .automated()
+            .iter()
+            .map(|(_, frame, property, value)| (*frame, property.name(), *value))
+            .collect();
+        assert_eq!(
+            applied,
+            [
+                (0, "Alpha", AutomationValue::Number(0.0)),
+                (5, "Alpha", AutomationValue::Number(0.5)),
+                (3, "Flash", AutomationValue::Bang),
+            ]
+        );
+
+        controller.remove_automation_lane(&layer_id, 0);
+        controller.remove_automation_lane(&layer_id, 0);
+        assert!(controller.automation.is_empty());
+    }
+
+    #[test]
+    fn test_automation_edits_are_events() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let layer_id = LayerId::new("layer1");
+        controller.add_automation_lane(layer_id.clone(), AutomationLane::numeric(PropertyId::Alpha, 0.0, 1.0, 0.0));
+        controller.add_automation_lane(layer_id.clone(), AutomationLane::toggle(PropertyId::Custom("Glow".into())));
+        let lane = |controller: &TimelineController, index: usize| controller.automation_lanes(&layer_id)[index].envelope.clone();
+
+        controller.set_automation_point(&mut engine, &layer_id, 0, 10, AutomationValue::Number(0.5));
+        controller.set_automation_point(&mut engine, &layer_id, 0, 20, AutomationValue::Number(1.0));
+        assert_eq!(controller.move_automation_point(&mut engine, &layer_id, 0, 1, 30, 0.75), Some(19));
+        assert_eq!(controller.move_automation_point(&mut engine, &layer_id, 0, 7, 30, 0.75), None);
+        controller.set_automation_curve(&mut engine, &layer_id, 0, 19, 2.0);
+        assert!(controller.remove_automation_point(&mut engine, &layer_id, 0, 0));
+        let AutomationEnvelope::Numeric(numeric) = lane(&controller, 0) else { panic!() };
+        assert_eq!(numeric.points, [automation::CurvePoint { frame: 19, value: 0.75, curve: 1.0 }, automation::CurvePoint::new(20, 1.0)]);
+
+        controller.toggle_automation_at(&mut engine, &layer_id, 1, 5);
+        controller.toggle_automation_at(&mut engine, &layer_id, 1, 8);
+        controller.toggle_automation_at(&mut engine, &layer_id, 1, 8);
+        assert_eq!(lane(&controller, 1), AutomationEnvelope::Toggle(automation::ToggleEnvelope { points: vec![(5, true)] }));
+
+        let events = controller.take_events();
+        assert_eq!(events.len(), 8);
+        assert!(events.iter().all(|event| event.edits_document() && !event.mutates_engine()));
+    }
+
+    #[test]
+    fn test_piano_roll_plays_notes() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let layer_id = LayerId::new("layer1");
+        controller.add_piano_roll(layer_id.clone());
+        let roll = controller.piano_rolls.get_mut(&layer_id).unwrap();
+        roll.add_note(Note::new(2, 4, 60, 100));
+        roll.add_note(Note::new(6, 2, 64, 80));
+
+        let base = controller.config.default_track_height;
+        assert_eq!(controller.track_height(&layer_id), base + PianoRoll::default().height());
+        controller.toggle_piano_roll(&layer_id);
+        assert_eq!(controller.track_height(&layer_id), base);
+
+        // Stepping plays starts and ends in order, ending a note before the next starts
+        controller.play_notes(&mut engine, 0);
+        controller.play_notes(&mut engine, 7);
+        controller.play_notes(&mut engine, 7);
+        // Jumping back stops the note left and starts the one landed in
+        controller.play_notes(&mut engine, 3);
+        let played: Vec<(u32, u8, bool)> = engine.played_notes().iter().map(|(_, frame, note, on)| (*frame, note.key, *on)).collect();
+        assert_eq!(played, [(2, 60, true), (6, 60, false), (6, 64, true), (3, 64, false), (3, 60, true)]);
+
+        assert!(controller.remove_piano_roll(&layer_id).is_some());
+        assert!(controller.piano_roll(&layer_id).is_none());
+    }
+
+    #[test]
+    fn test_note_edits_are_events() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let layer_id = LayerId::new("layer1");
+        assert_eq!(controller.add_note(&mut engine, &layer_id, Note::new(0, 4, 60, 100)), None);
+        controller.add_piano_roll(layer_id.clone());
+
+        assert_eq!(controller.add_note(&mut engine, &layer_id, Note::new(8, 4, 60, 100)), Some(0));
+        assert_eq!(controller.add_note(&mut engine, &layer_id, Note::new(0, 4, 64, 100)), Some(0));
+        assert_eq!(controller.move_note(&mut engine, &layer_id, 0, 12, 62), Some(1));
+        // Moving a note where it already is reports nothing
+        assert_eq!(controller.move_note(&mut engine, &layer_id, 1, 12, 62), Some(1));
+        controller.resize_note(&mut engine, &layer_id, 1, 0);
+        controller.set_note_velocity(&mut engine, &layer_id, 0, 200);
+        assert!(controller.remove_note(&mut engine, &layer_id, 0));
+        assert!(!controller.remove_note(&mut engine, &layer_id, 5));
+        assert_eq!(controller.piano_roll(&layer_id).unwrap().notes, [Note::new(12, 1, 62, 100)]);
+
+        let events = controller.take_events();
+        assert_eq!(events.len(), 6);
+        assert!(events.iter().all(|event| event.edits_document() && !event.mutates_engine()));
+
+        // In controlled mode the roll waits for the host to apply the events
+        controller.controlled = true;
+        controller.set_note_velocity(&mut engine, &layer_id, 0, 20);
+        assert_eq!(controller.piano_roll(&layer_id).unwrap().notes[0].velocity, 100);
+        for event in controller.take_events() {
+            controller.apply_event(&event, &mut engine);
+        }
+        assert_eq!(controller.piano_roll(&layer_id).unwrap().notes[0].velocity, 20);
+    }
+
+    #[test]
+    fn test_import_midi_places_notes() {
+        // One track at 480 ticks per quarter and 120 BPM: C4 at 0 and 1/4,
+        // a quiet E4 at 1/2
+        let events: &[u8] = &[
+            0x00, 0x90, 60, 100, 0x83, 0x60, 0x80, 60, 0,
+            0x00, 0x90, 60, 100, 0x83, 0x60, 0x80, 60, 0,
+            0x00, 0x90, 64, 10, 0x83, 0x60, 0x80, 64, 0,
+            0x00, 0xFF, 0x2F, 0,
+        ];
+        let mut bytes = b"MThd\0\0\0\x06\0\0\0\x01\x01\xE0MTrk".to_vec();
+        bytes.extend((events.len() as u32).to_be_bytes());
+        bytes.extend(events);
+        let file = midi::parse(&bytes).unwrap();
+
+        let mut controller = TimelineController::new();
+        let mut engine = MockRiveEngine::new();
+        let loud = MidiSelection { min_velocity: 50, ..Default::default() };
+        assert_eq!(controller.import_midi(&mut engine, &file, &loud, &MidiTarget::Markers, 10), 2);
+        assert_eq!(controller.config.beat_markers, vec![10, 22]);
+
+        assert_eq!(controller.import_midi(&mut engine, &file, &MidiSelection::default(), &MidiTarget::Labels, 0), 3);
+        let labels: Vec<(u32, &str)> = controller.config.frame_labels.iter().map(|l| (l.frame, l.label.as_str())).collect();
+        assert_eq!(labels, [(0, "C4"), (12, "C4"), (24, "E4")]);
+
+        let layer_id = LayerId::new("drums");
+        controller.import_midi(&mut engine, &file, &loud, &MidiTarget::Keyframes(layer_id.clone()), 5);
+        assert_eq!(engine.get_frame_data(layer_id.clone(), 17).frame_type, FrameType::Keyframe);
+    }
+
+    #[test]
+    fn test_hit_testing() {
+        let mut controller = TimelineController::new();
+        let layers = crate::layer::create_mock_layers();
+        controller.state.scroll_x = 50.0;
+
+        assert_eq!(controller.frame_at_x(25.0), 7);
+        assert_eq!(controller.layer_at_y(45.0, &layers), Some(layers[1].id.clone()));
+        assert_eq!(controller.layer_at_y(-1.0, &layers), None);
+    }
+
+    #[test]
+    fn test_layer_selection() {
+        let mut controller = TimelineController::new();
+        let layers = crate::layer::create_mock_layers();
+
+        controller.select_layer(&layers[0].id, false);
+        controller.select_layer(&layers[2].id, true);
+        assert_eq!(controller.state.selected_layers, vec![layers[0].id.clone(), layers[2].id.clone()]);
+
+        controller.select_layer(&layers[0].id, true);
+        assert_eq!(controller.state.selected_layers, vec![layers[2].id.clone()]);
+
+        controller.select_layer_range(&layers, &layers[0].id);
+        assert_eq!(controller.state.selected_layers.len(), 3);
+    }
+
+    #[test]
+    fn test_reorder_layer() {
+        let mut controller = TimelineController::new();
+        let mut engine = MockRiveEngine::new();
+        let layers = crate::layer::create_mock_layers();
+
+        controller.reorder_layer(&mut engine, &layers, &layers[0].id, 2);
+        match controller.take_events().as_slice() {
+            [TimelineEvent::LayersReordered { layer_ids }] => {
+                assert_eq!(layer_ids[0], layers[1].id);
+                assert_eq!(layer_ids[1], layers[0].id);
+            }
+            events => panic!("unexpected events: {:?}", events),
+        }
+        assert_eq!(engine.get_layers()[0].id, layers[1].id);
+    }
+
+    #[test]
+    fn test_seek_is_clamped() {
+        let mut controller = TimelineController::new();
+        let mut engine = MockRiveEngine::new();
+
+        controller.go_to_last_frame(&mut engine);
+        assert_eq!(engine.get_current_frame(), engine.get_total_frames() - 1);
+        assert_eq!(controller.state.playhead_frame, engine.get_total_frames() - 1);
+
+        controller.go_to_next_frame(&mut engine);
+        assert_eq!(engine.get_current_frame(), engine.get_total_frames() - 1);
+    }
+
+    #[test]
+    fn test_edits_bump_document_revision() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        controller.seek(&mut engine, 10);
+        assert_eq!(controller.state.document_revision, 0);
+        controller.insert_keyframe(&mut engine, LayerId::new("layer1"), 12);
+        assert_eq!(controller.state.document_revision, 1);
+    }
+
+    #[test]
+    fn test_controlled_mode_only_reports() {
+        let mut controller = TimelineController::new();
+        controller.controlled = true;
+        let mut engine = MockRiveEngine::new();
+
+        assert!(controller.execute_command("playback.last_frame", &mut engine));
+        assert_eq!(engine.get_current_frame(), 0);
+        assert!(matches!(controller.take_events().as_slice(), [TimelineEvent::SeekRequested { frame: 99 }]));
+    }
+
+    fn music(duration: f32, start_frame: u32) -> AudioLayer {
+        let mut source = crate::audio::AudioSource::new("music.wav".into());
+        source.duration = duration;
+        AudioLayer::new(source, start_frame)
+    }
+
+    #[test]
+    fn test_clip_waveform_follows_audio_source() {
+        let mut controller = TimelineController::new();
+        let clip = music(1.0, 0);
+        let audio_id = clip.source.id.clone();
+        assert!(controller.clip_waveform(&clip).is_none());
+
+        controller.set_waveform(WaveformData::new(audio_id.clone(), 24.0));
+        assert_eq!(controller.clip_waveform(&clip).unwrap().audio_id, audio_id);
+    }
+
+    #[test]
+    fn test_envelope_editing_uses_timeline_frames() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let layer_id = LayerId::new("music");
+        controller.set_audio_layer(layer_id.clone(), music(2.0, 10));
+        let points = |controller: &TimelineController| controller.audio_tracks[&layer_id].clips()[0].volume_envelope.points.clone();
+
+        assert_eq!(controller.set_envelope_point(&mut engine, &layer_id, 20, 0.5), Some(0));
+        assert_eq!(points(&controller), vec![(0, 1.0), (10, 0.5)]);
+
+        // Can't be dragged past the point at the sound's start
+        assert_eq!(controller.move_envelope_point(&mut engine, &layer_id, 0, 20, 5, 0.25), Some(11));
+        assert!(controller.remove_envelope_point(&mut engine, &layer_id, 0, 11));
+        assert!(!controller.remove_envelope_point(&mut engine, &layer_id, 0, 11));
+        assert_eq!(controller.set_envelope_point(&mut engine, &layer_id, 5, 1.0), None);
+        assert_eq!(controller.set_envelope_point(&mut engine, &LayerId::new("video"), 20, 1.0), None);
+
+        assert_eq!(controller.snap_envelope_level(0.52, false), 0.5);
+        assert_eq!(controller.snap_envelope_level(0.52, true), 0.52);
+
+        // 2 seconds at 24 fps
+        controller.apply_envelope_preset(&mut engine, &layer_id, 0, EnvelopePreset::FadeOut, 12);
+        assert_eq!(points(&controller), vec![(36, 1.0), (48, 0.0)]);
+        assert_eq!(controller.take_events().len(), 4);
+    }
+
+    #[test]
+    fn test_controlled_envelope_edits_wait_for_the_host() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        controller.controlled = true;
+        let layer_id = LayerId::new("music");
+        controller.set_audio_layer(layer_id.clone(), music(2.0, 10));
+        let revision = controller.state.document_revision;
+
+        assert_eq!(controller.set_envelope_point(&mut engine, &layer_id, 20, 0.5), Some(0));
+        assert_eq!(controller.audio_tracks[&layer_id].clips()[0].volume_envelope.points, vec![(0, 1.0)]);
+        assert!(controller.state.document_revision > revision);
+
+        for event in controller.take_events() {
+            assert!(event.edits_document() && !event.mutates_engine());
+            controller.apply_event(&event, &mut engine);
+        }
+        assert_eq!(controller.audio_tracks[&layer_id].clips()[0].volume_envelope.points, vec![(0, 1.0), (10, 0.5)]);
+    }
+
+    #[test]
+    fn test_audio_drag_trims_moves_and_slips() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let frame_width = controller.frame_width();
+        let layer_id = LayerId::new("music");
+        let mut audio = music(2.0, 10);
+        audio.trim_start = 0.5;
+        controller.set_audio_layer(layer_id.clone(), audio);
+        let clip = |controller: &TimelineController| controller.audio_track(&layer_id).unwrap().clips()[0].clone();
+
+        // Revealing more head than was trimmed stops at the sound's start
+        controller.begin_audio_drag(&layer_id, 0, AudioDragMode::Head, 0.0);
+        controller.update_audio_drag(-20.0 * frame_width);
+        assert_eq!(clip(&controller).frame_range(24.0), 0..46);
+        controller.update_audio_drag(6.0 * frame_width);
+        assert_eq!(clip(&controller).frame_range(24.0), 16..46);
+        controller.end_audio_drag(&mut engine);
+
+        controller.begin_audio_drag(&layer_id, 0, AudioDragMode::Tail, 0.0);
+        controller.update_audio_drag(-6.0 * frame_width);
+        assert_eq!(clip(&controller).frame_range(24.0), 16..40);
+        controller.end_audio_drag(&mut engine);
+
+        // Slipping keeps the clip's frames and is limited by the trims
+        let before = clip(&controller);
+        controller.begin_audio_drag(&layer_id, 0, AudioDragMode::Slip, 0.0);
+        controller.update_audio_drag(1000.0 * frame_width);
+        let slipped = clip(&controller);
+        assert_eq!(slipped.frame_range(24.0), 16..40);
+        assert_eq!(slipped.trim_start, 0.0);
+        assert!((slipped.trim_end - before.trim_end - before.trim_start).abs() < 1e-6);
+        controller.end_audio_drag(&mut engine);
+
+        controller.begin_audio_drag(&layer_id, 0, AudioDragMode::Move, 0.0);
+        controller.update_audio_drag(4.4 * frame_width);
+        assert_eq!(clip(&controller).frame_range(24.0), 20..44);
+        assert!(controller.state.audio_drag.is_some());
+        // The track itself only changes when the drag ends
+        assert_eq!(controller.audio_tracks[&layer_id].clips()[0].start_frame, 16);
+        controller.end_audio_drag(&mut engine);
+        assert!(controller.state.audio_drag.is_none());
+        assert_eq!(controller.audio_tracks[&layer_id].clips()[0].start_frame, 20);
+        let events = controller.take_events();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[3], TimelineEvent::AudioClipChanged { clip: 0, start_frame: 20, .. }));
+    }
+
+    #[test]
+    fn test_scrub_audio_plays_visible_layers() {
+        let mut controller = TimelineController::new();
+        let mut audio = crate::ui::MockAudioEngine::new();
+        let source = audio.load_audio(std::path::Path::new("music.wav")).unwrap();
+        let layer_id = LayerId::new("music");
+        controller.set_audio_layer(layer_id.clone(), AudioLayer::new(source, 0));
+        let played = |audio: &mut crate::ui::MockAudioEngine| {
+            audio.take_calls().iter().filter(|call| matches!(call, crate::ui::MockAudioCall::PlayScrubSnippet { .. })).count()
+        };
+        audio.take_calls();
+
+        controller.scrub_audio(&mut audio, 5, 0.0).unwrap();
+        assert_eq!(played(&mut audio), 1);
+
+        controller.state.layer_panel_state.layer_visibility.insert(layer_id, false);
+        controller.scrub_audio(&mut audio, 6, 0.1).unwrap();
+        assert_eq!(played(&mut audio), 0);
+
+        controller.state.layer_panel_state.layer_visibility.clear();
+        controller.state.audio_scrubbing = false;
+        controller.scrub_audio(&mut audio, 7, 0.2).unwrap();
+        assert_eq!(played(&mut audio), 0);
+        controller.end_scrub_audio(&mut audio).unwrap();
+    }
+
+    #[test]
+    fn test_clips_split_and_stop_at_neighbours() {
+        let mut engine = MockRiveEngine::new();
+        let mut controller = TimelineController::new();
+        let frame_width = controller.frame_width();
+        let layer_id = LayerId::new("footsteps");
+        controller.add_audio_clip(layer_id.clone(), music(1.0, 0)).unwrap();
+        controller.add_audio_clip(layer_id.clone(), music(1.0, 48)).unwrap();
+        assert!(matches!(controller.add_audio_clip(layer_id.clone(), music(0.5, 4)), Err(AudioError::ClipOverlap)));
+
+        controller.state.playhead_frame = 10;
+        assert_eq!(controller.split_audio_at_playhead(&mut engine, &layer_id), Some(1));
+        assert!(matches!(controller.take_events()[..], [TimelineEvent::AudioClipSplit { clip: 0, frame: 10, .. }]));
+        let ranges: Vec<_> = controller.audio_tracks[&layer_id].clips().iter().map(|c| c.frame_range(24.0)).collect();
+        assert_eq!(ranges, vec![0..10, 10..24, 48..72]);
+
+        // Dragged onto its neighbour, a clip stops where it would start with it
+        controller.begin_audio_drag(&layer_id, 2, AudioDragMode::Move, 0.0);
+        controller.update_audio_drag(-30.0 * frame_width);
+        assert_eq!(controller.audio_track(&layer_id).unwrap().clips()[2].start_frame, 18);
+        controller.update_audio_drag(-40.0 * frame_width);
+        assert_eq!(controller.audio_track(&layer_id).unwrap().clips()[2].start_frame, 18);
+        controller.end_audio_drag(&mut engine);
+        assert_eq!(controller.audio_tracks[&layer_id].clips()[2].start_frame, 18);
+    }
+}
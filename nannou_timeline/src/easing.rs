@@ -1,6 +1,6 @@
 //! Bezier curve easing editor for Flash-style animation control
 
-use egui::Vec2;
+use emath::Vec2;
 use serde::{Deserialize, Serialize};
 
 /// A bezier curve used for easing animation properties
@@ -0,0 +1,1046 @@
+//! Engine hosted on a worker thread
+//!
+//! [`EngineHost`] moves a [`RiveEngine`] onto its own thread. The timeline talks
+//! to it through three channels:
+//!
+//! - [`EngineCommand`]s, one per [`RiveController`] method, are queued to the
+//!   worker and applied in order.
+//! - [`TimelineEventHandler`] callbacks fired by the engine come back as
+//!   [`EngineEvent`]s, drained with [`EngineHost::poll_events`].
+//! - After each batch of commands the worker updates its [`EngineSnapshot`] and
+//!   publishes a copy. Only the layers the batch touched are read again; the
+//!   others share their frame data with the previous snapshot. Reads only ever
+//!   clone the published snapshot, so a slow engine operation never blocks the
+//!   UI thread.
+//!
+//! `EngineHost` implements [`RiveController`] and [`RiveDataProvider`] itself.
+//! Queries are answered from the snapshot. Commands that return a value
+//! (`add_layer`, `add_keyframe`, `copy_keyframe`) wait for the worker; all
+//! others return immediately. When the worker has stopped they report
+//! [`RiveError::EngineStopped`] as an [`EngineEvent::AnimationError`].
+//!
+//! [`HostedEngine`] puts the timeline widget's [`crate::RiveEngine`] on top of
+//! a host, so the widget draws from the snapshot and queues its edits.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::frame::{FrameData, FrameType};
+use crate::layer::{LayerInfo, LayerType};
+use crate::rive_integration::{
+    AnimationInfo, EasingFunction, KeyframeData, KeyframeInfo, KeyframeType, LayerHierarchyNode,
+    RiveController, RiveDataProvider, RiveEngine, RiveError, RiveFrameData, TimelineEventHandler,
+    TweenData, TweenId, TweenInfo, TweenType,
+};
+use crate::{KeyframeId, LayerId};
+
+/// How often the worker refreshes the snapshot while the engine is playing
+pub const PLAYBACK_REFRESH_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A [`RiveController`] call queued for the engine thread
+#[derive(Clone, Debug)]
+pub enum EngineCommand {
+    // Playback
+    Play,
+    Pause,
+    Stop,
+    SeekToFrame { frame: u32 },
+    SetFps { fps: f32 },
+
+    // Layers
+    AddLayer { name: String },
+    RemoveLayer { layer_id: LayerId },
+    ReorderLayers { layer_ids: Vec<LayerId> },
+    SetLayerVisibility { layer_id: LayerId, visible: bool },
+    SetLayerLocked { layer_id: LayerId, locked: bool },
+    RenameLayer { layer_id: LayerId, new_name: String },
+
+    // Keyframes
+    AddKeyframe { layer_id: LayerId, frame: u32 },
+    RemoveKeyframe { keyframe_id: KeyframeId },
+    MoveKeyframe { keyframe_id: KeyframeId, new_frame: u32 },
+    CopyKeyframe { keyframe_id: KeyframeId },
+    PasteKeyframe { layer_id: LayerId, frame: u32, data: KeyframeData },
+
+    // Frames
+    InsertFrame { layer_id: LayerId, frame: u32 },
+    RemoveFrame { layer_id: LayerId, frame: u32 },
+    ExtendFrame { layer_id: LayerId, frame: u32 },
+
+    // Tweens
+    CreateTween { layer_id: LayerId, start_frame: u32, end_frame: u32, tween_type: TweenType },
+    SetEasing { tween_id: TweenId, easing: EasingFunction },
+    RemoveTween { tween_id: TweenId },
+}
+
+/// Value returned by an [`EngineCommand`]
+#[derive(Clone, Debug)]
+pub enum CommandOutput {
+    None,
+    Layer(LayerId),
+    Keyframe(KeyframeId),
+    KeyframeData(Option<KeyframeData>),
+}
+
+impl EngineCommand {
+    /// Run the command against an engine
+    pub fn apply(self, engine: &mut dyn RiveEngine) -> CommandOutput {
+        match self {
+            EngineCommand::Play => engine.play(),
+            EngineCommand::Pause => engine.pause(),
+            EngineCommand::Stop => engine.stop(),
+            EngineCommand::SeekToFrame { frame } => engine.seek_to_frame(frame),
+            EngineCommand::SetFps { fps } => engine.set_fps(fps),
+
+            EngineCommand::AddLayer { name } => return CommandOutput::Layer(engine.add_layer(&name)),
+            EngineCommand::RemoveLayer { layer_id } => engine.remove_layer(layer_id),
+            EngineCommand::ReorderLayers { layer_ids } => engine.reorder_layers(layer_ids),
+            EngineCommand::SetLayerVisibility { layer_id, visible } => engine.set_layer_visibility(layer_id, visible),
+            EngineCommand::SetLayerLocked { layer_id, locked } => engine.set_layer_locked(layer_id, locked),
+            EngineCommand::RenameLayer { layer_id, new_name } => engine.rename_layer(layer_id, new_name),
+
+            EngineCommand::AddKeyframe { layer_id, frame } => {
+                return CommandOutput::Keyframe(engine.add_keyframe(layer_id, frame))
+            }
+            EngineCommand::RemoveKeyframe { keyframe_id } => engine.remove_keyframe(keyframe_id),
+            EngineCommand::MoveKeyframe { keyframe_id, new_frame } => engine.move_keyframe(keyframe_id, new_frame),
+            EngineCommand::CopyKeyframe { keyframe_id } => {
+                return CommandOutput::KeyframeData(engine.copy_keyframe(keyframe_id))
+            }
+            EngineCommand::PasteKeyframe { layer_id, frame, data } => engine.paste_keyframe(layer_id, frame, data),
+
+            EngineCommand::InsertFrame { layer_id, frame } => engine.insert_frame(layer_id, frame),
+            EngineCommand::RemoveFrame { layer_id, frame } => engine.remove_frame(layer_id, frame),
+            EngineCommand::ExtendFrame { layer_id, frame } => engine.extend_frame(layer_id, frame),
+
+            EngineCommand::CreateTween { layer_id, start_frame, end_frame, tween_type } => {
+                engine.create_tween(layer_id, start_frame, end_frame, tween_type)
+            }
+            EngineCommand::SetEasing { tween_id, easing } => engine.set_easing(tween_id, easing),
+            EngineCommand::RemoveTween { tween_id } => engine.remove_tween(tween_id),
+        }
+        CommandOutput::None
+    }
+}
+
+/// A [`TimelineEventHandler`] callback, forwarded from the engine thread
+#[derive(Clone, Debug)]
+pub enum EngineEvent {
+    FrameChanged { frame: u32 },
+    PlaybackStarted,
+    PlaybackStopped,
+    LayerAdded { layer: LayerInfo },
+    LayerRemoved { layer_id: LayerId },
+    LayerReordered { layer_ids: Vec<LayerId> },
+    KeyframeAdded { layer_id: LayerId, frame: u32, keyframe_id: KeyframeId },
+    KeyframeRemoved { keyframe_id: KeyframeId },
+    AnimationLoaded { animation_info: AnimationInfo },
+    AnimationError { error: RiveError },
+}
+
+/// Event handler installed on the hosted engine; sends every callback over a channel
+pub(crate) struct ChannelEventHandler {
+    sender: Sender<EngineEvent>,
+}
+
+impl ChannelEventHandler {
+    pub(crate) fn new(sender: Sender<EngineEvent>) -> Self {
+        Self { sender }
+    }
+
+    fn send(&self, event: EngineEvent) {
+        // The host may already be gone during shutdown
+        let _ = self.sender.send(event);
+    }
+}
+
+impl TimelineEventHandler for ChannelEventHandler {
+    fn on_frame_changed(&mut self, frame: u32) {
+        self.send(EngineEvent::FrameChanged { frame });
+    }
+
+    fn on_playback_started(&mut self) {
+        self.send(EngineEvent::PlaybackStarted);
+    }
+
+    fn on_playback_stopped(&mut self) {
+        self.send(EngineEvent::PlaybackStopped);
+    }
+
+    fn on_layer_added(&mut self, layer: LayerInfo) {
+        self.send(EngineEvent::LayerAdded { layer });
+    }
+
+    fn on_layer_removed(&mut self, layer_id: LayerId) {
+        self.send(EngineEvent::LayerRemoved { layer_id });
+    }
+
+    fn on_layer_reordered(&mut self, layer_ids: Vec<LayerId>) {
+        self.send(EngineEvent::LayerReordered { layer_ids });
+    }
+
+    fn on_keyframe_added(&mut self, layer_id: LayerId, frame: u32, keyframe_id: KeyframeId) {
+        self.send(EngineEvent::KeyframeAdded { layer_id, frame, keyframe_id });
+    }
+
+    fn on_keyframe_removed(&mut self, keyframe_id: KeyframeId) {
+        self.send(EngineEvent::KeyframeRemoved { keyframe_id });
+    }
+
+    fn on_animation_loaded(&mut self, animation_info: AnimationInfo) {
+        self.send(EngineEvent::AnimationLoaded { animation_info });
+    }
+
+    fn on_animation_error(&mut self, error: RiveError) {
+        self.send(EngineEvent::AnimationError { error });
+    }
+}
+
+/// Everything [`RiveDataProvider`] can answer, captured after a batch of commands
+#[derive(Clone, Debug, Default)]
+pub struct EngineSnapshot {
+    /// Incremented on every capture
+    pub version: u64,
+    /// Incremented when layers, keyframes, tweens or frames change. Playback
+    /// alone leaves it unchanged.
+    pub content_version: u64,
+    pub layers: Vec<LayerInfo>,
+    pub keyframes: HashMap<LayerId, Vec<KeyframeInfo>>,
+    pub tweens: HashMap<LayerId, Vec<TweenInfo>>,
+    /// Per-layer frame data, indexed by frame number. Shared with earlier
+    /// snapshots until the layer changes.
+    pub frames: HashMap<LayerId, Arc<Vec<RiveFrameData>>>,
+    pub layer_hierarchy: Vec<LayerHierarchyNode>,
+    pub current_frame: u32,
+    pub total_frames: u32,
+    pub fps: f32,
+    pub animation_bounds: (u32, u32),
+    pub is_playing: bool,
+}
+
+impl EngineSnapshot {
+    /// Read everything from `engine` again
+    pub fn capture(&mut self, engine: &dyn RiveEngine) {
+        self.update(engine, &SnapshotChanges { everything: true, ..Default::default() });
+    }
+
+    /// Read the playback state and whatever `changes` may have touched
+    fn update(&mut self, engine: &dyn RiveEngine, changes: &SnapshotChanges) {
+        self.version += 1;
+        self.current_frame = engine.get_current_frame();
+        self.fps = engine.get_fps();
+        self.animation_bounds = engine.get_animation_bounds();
+        self.is_playing = engine.is_playing();
+
+        // Every layer's frame data has one entry per frame
+        let total_frames = engine.get_total_frames();
+        let everything = changes.everything || total_frames != self.total_frames;
+        self.total_frames = total_frames;
+        if !everything && !changes.layers && changes.dirty.is_empty() {
+            return;
+        }
+        self.content_version += 1;
+
+        if everything || changes.layers {
+            self.layers = engine.get_layers();
+            self.layer_hierarchy = engine.get_layer_hierarchy();
+            let ids: HashSet<&LayerId> = self.layers.iter().map(|l| &l.id).collect();
+            self.keyframes.retain(|layer_id, _| ids.contains(layer_id));
+            self.tweens.retain(|layer_id, _| ids.contains(layer_id));
+            self.frames.retain(|layer_id, _| ids.contains(layer_id));
+        }
+
+        for layer in &self.layers {
+            if !everything && !changes.dirty.contains(&layer.id) && self.frames.contains_key(&layer.id) {
+                continue;
+            }
+            self.keyframes.insert(layer.id.clone(), engine.get_keyframes(layer.id.clone()));
+            self.tweens.insert(layer.id.clone(), engine.get_tweens(layer.id.clone()));
+            let frames = (0..self.total_frames).map_while(|frame| engine.get_frame_data(layer.id.clone(), frame));
+            self.frames.insert(layer.id.clone(), Arc::new(frames.collect()));
+        }
+    }
+
+    /// Frame data for one cell of the grid
+    pub fn frame_data(&self, layer_id: &LayerId, frame: u32) -> Option<&RiveFrameData> {
+        self.frames.get(layer_id)?.get(frame as usize)
+    }
+
+    /// Layer of a keyframe in this snapshot
+    pub fn keyframe_layer(&self, keyframe_id: &KeyframeId) -> Option<&LayerId> {
+        self.keyframes
+            .iter()
+            .find(|(_, keyframes)| keyframes.iter().any(|k| &k.id == keyframe_id))
+            .map(|(layer_id, _)| layer_id)
+    }
+
+    /// Layer of a tween in this snapshot
+    pub fn tween_layer(&self, tween_id: &TweenId) -> Option<&LayerId> {
+        self.tweens
+            .iter()
+            .find(|(_, tweens)| tweens.iter().any(|t| &t.id == tween_id))
+            .map(|(layer_id, _)| layer_id)
+    }
+}
+
+/// What a batch of commands may have changed besides the playback state
+#[derive(Debug, Default)]
+struct SnapshotChanges {
+    /// The layer list or a layer's name, visibility or lock
+    layers: bool,
+    /// Layers whose keyframes, tweens or frames changed
+    dirty: HashSet<LayerId>,
+    everything: bool,
+}
+
+impl SnapshotChanges {
+    /// Note what `command` may change. Keyframe and tween ids are looked up in
+    /// `snapshot`, taken before the batch; unknown ids mark everything.
+    fn record(&mut self, command: &EngineCommand, snapshot: &EngineSnapshot) {
+        let layer_id = match command {
+            EngineCommand::Play
+            | EngineCommand::Pause
+            | EngineCommand::Stop
+            | EngineCommand::SeekToFrame { .. }
+            | EngineCommand::SetFps { .. }
+            | EngineCommand::CopyKeyframe { .. } => return,
+
+            EngineCommand::AddLayer { .. }
+            | EngineCommand::RemoveLayer { .. }
+            | EngineCommand::ReorderLayers { .. }
+            | EngineCommand::SetLayerVisibility { .. }
+            | EngineCommand::SetLayerLocked { .. }
+            | EngineCommand::RenameLayer { .. } => {
+                self.layers = true;
+                return;
+            }
+
+            EngineCommand::AddKeyframe { layer_id, .. }
+            | EngineCommand::PasteKeyframe { layer_id, .. }
+            | EngineCommand::InsertFrame { layer_id, .. }
+            | EngineCommand::RemoveFrame { layer_id, .. }
+            | EngineCommand::ExtendFrame { layer_id, .. }
+            | EngineCommand::CreateTween { layer_id, .. } => Some(layer_id),
+
+            EngineCommand::RemoveKeyframe { keyframe_id } | EngineCommand::MoveKeyframe { keyframe_id, .. } => {
+                snapshot.keyframe_layer(keyframe_id)
+            }
+            EngineCommand::SetEasing { tween_id, .. } | EngineCommand::RemoveTween { tween_id } => {
+                snapshot.tween_layer(tween_id)
+            }
+        };
+        match layer_id {
+            Some(layer_id) => {
+                self.dirty.insert(layer_id.clone());
+            }
+            None => self.everything = true,
+        }
+    }
+}
+
+/// The latest published snapshot. The worker updates its own copy without
+/// holding the lock and only locks to swap a new copy in.
+struct SnapshotBuffer {
+    front: Mutex<Arc<EngineSnapshot>>,
+}
+
+impl SnapshotBuffer {
+    fn read(&self) -> Arc<EngineSnapshot> {
+        self.front.lock().unwrap().clone()
+    }
+
+    /// Make `snapshot` the one new reads get. Readers keep the one they hold.
+    fn publish(&self, snapshot: EngineSnapshot) {
+        *self.front.lock().unwrap() = Arc::new(snapshot);
+    }
+}
+
+enum HostMessage {
+    Command(EngineCommand),
+    /// Command whose output is sent back once the resulting snapshot is published
+    Call(EngineCommand, Sender<CommandOutput>),
+    /// Sent back once every earlier command is in the published snapshot
+    Sync(Sender<()>),
+    Shutdown,
+}
+
+/// Runs a [`RiveEngine`] on a worker thread; see the [module docs](self)
+pub struct EngineHost {
+    commands: Sender<HostMessage>,
+    events: Mutex<Receiver<EngineEvent>>,
+    /// For errors the host reports itself
+    event_sender: Sender<EngineEvent>,
+    snapshot: Arc<SnapshotBuffer>,
+    worker: Option<JoinHandle<Box<dyn RiveEngine>>>,
+}
+
+impl EngineHost {
+    /// Move `engine` onto a new thread. The engine's event handler is replaced
+    /// by one that forwards callbacks to [`poll_events`](Self::poll_events).
+    /// Fails when the OS can't create the thread.
+    pub fn spawn(mut engine: Box<dyn RiveEngine>) -> std::io::Result<Self> {
+        let (command_sender, command_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = mpsc::channel();
+        engine.set_event_handler(Box::new(ChannelEventHandler::new(event_sender.clone())));
+
+        let mut initial = EngineSnapshot::default();
+        initial.capture(engine.as_ref());
+        let snapshot = Arc::new(SnapshotBuffer { front: Mutex::new(Arc::new(initial)) });
+
+        let worker_snapshot = snapshot.clone();
+        let worker = thread::Builder::new()
+            .name("timeline-engine".to_string())
+            .spawn(move || run_engine(engine, command_receiver, worker_snapshot))?;
+
+        Ok(Self {
+            commands: command_sender,
+            events: Mutex::new(event_receiver),
+            event_sender,
+            snapshot,
+            worker: Some(worker),
+        })
+    }
+
+    /// Queue a command without waiting for it
+    pub fn send(&self, command: EngineCommand) -> Result<(), RiveError> {
+        self.commands.send(HostMessage::Command(command)).map_err(|_| RiveError::EngineStopped)
+    }
+
+    /// Run a command and wait for its output. When this returns, the snapshot
+    /// already reflects the command.
+    pub fn call(&self, command: EngineCommand) -> Result<CommandOutput, RiveError> {
+        let (reply_sender, reply_receiver) = mpsc::channel();
+        self.commands
+            .send(HostMessage::Call(command, reply_sender))
+            .map_err(|_| RiveError::EngineStopped)?;
+        reply_receiver.recv().map_err(|_| RiveError::EngineStopped)
+    }
+
+    /// Wait until the snapshot reflects every command queued so far
+    pub fn sync(&self) -> Result<(), RiveError> {
+        let (reply_sender, reply_receiver) = mpsc::channel();
+        self.commands.send(HostMessage::Sync(reply_sender)).map_err(|_| RiveError::EngineStopped)?;
+        reply_receiver.recv().map_err(|_| RiveError::EngineStopped)
+    }
+
+    /// Events fired by the engine since the last poll, oldest first
+    pub fn poll_events(&self) -> Vec<EngineEvent> {
+        self.events.lock().unwrap().try_iter().collect()
+    }
+
+    /// Latest published snapshot. Never waits for the engine.
+    pub fn snapshot(&self) -> Arc<EngineSnapshot> {
+        self.snapshot.read()
+    }
+
+    /// Whether the worker thread is still running
+    pub fn is_running(&self) -> bool {
+        self.worker.as_ref().is_some_and(|worker| !worker.is_finished())
+    }
+
+    /// Stop the worker after it drains queued commands and hand the engine back
+    pub fn shutdown(mut self) -> Option<Box<dyn RiveEngine>> {
+        self.stop_worker()
+    }
+
+    fn stop_worker(&mut self) -> Option<Box<dyn RiveEngine>> {
+        let _ = self.commands.send(HostMessage::Shutdown);
+        self.worker.take()?.join().ok()
+    }
+
+    fn send_or_report(&self, command: EngineCommand) {
+        if let Err(error) = self.send(command) {
+            self.report(error);
+        }
+    }
+
+    /// Pass `error` on to [`poll_events`](Self::poll_events)
+    fn report(&self, error: RiveError) {
+        let _ = self.event_sender.send(EngineEvent::AnimationError { error });
+    }
+}
+
+impl Drop for EngineHost {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
+
+/// Worker loop: apply each batch of queued commands, then publish a snapshot
+fn run_engine(
+    mut engine: Box<dyn RiveEngine>,
+    commands: Receiver<HostMessage>,
+    snapshot: Arc<SnapshotBuffer>,
+) -> Box<dyn RiveEngine> {
+    let mut current = EngineSnapshot::clone(&snapshot.read());
+
+    loop {
+        // Keep the snapshot following playback even when no commands arrive
+        let first = if current.is_playing {
+            match commands.recv_timeout(PLAYBACK_REFRESH_INTERVAL) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            }
+        };
+
+        let mut changes = SnapshotChanges::default();
+        let mut replies = Vec::new();
+        let mut synced = Vec::new();
+        let mut shutdown = false;
+        for message in first.into_iter().chain(std::iter::from_fn(|| commands.try_recv().ok())) {
+            match message {
+                HostMessage::Command(command) => {
+                    changes.record(&command, &current);
+                    command.apply(engine.as_mut());
+                }
+                HostMessage::Call(command, reply) => {
+                    changes.record(&command, &current);
+                    replies.push((reply, command.apply(engine.as_mut())));
+                }
+                HostMessage::Sync(reply) => synced.push(reply),
+                HostMessage::Shutdown => {
+                    shutdown = true;
+                    break;
+                }
+            }
+        }
+
+        // Unchanged layers' frame data is shared, so the copy is cheap
+        current.update(engine.as_ref(), &changes);
+        snapshot.publish(current.clone());
+
+        for (reply, output) in replies {
+            let _ = reply.send(output);
+        }
+        for reply in synced {
+            let _ = reply.send(());
+        }
+        if shutdown {
+            break;
+        }
+    }
+
+    engine.shutdown();
+    engine
+}
+
+impl RiveController for EngineHost {
+    fn play(&mut self) {
+        self.send_or_report(EngineCommand::Play);
+    }
+
+    fn pause(&mut self) {
+        self.send_or_report(EngineCommand::Pause);
+    }
+
+    fn stop(&mut self) {
+        self.send_or_report(EngineCommand::Stop);
+    }
+
+    fn seek_to_frame(&mut self, frame: u32) {
+        self.send_or_report(EngineCommand::SeekToFrame { frame });
+    }
+
+    fn set_fps(&mut self, fps: f32) {
+        self.send_or_report(EngineCommand::SetFps { fps });
+    }
+
+    fn add_layer(&mut self, name: &str) -> LayerId {
+        match self.call(EngineCommand::AddLayer { name: name.to_string() }) {
+            Ok(CommandOutput::Layer(layer_id)) => layer_id,
+            // An id no layer has, so later commands on it are ignored
+            _ => {
+                self.report(RiveError::EngineStopped);
+                LayerId::new(format!("layer_{}", uuid::Uuid::new_v4()))
+            }
+        }
+    }
+
+    fn remove_layer(&mut self, layer_id: LayerId) {
+        self.send_or_report(EngineCommand::RemoveLayer { layer_id });
+    }
+
+    fn reorder_layers(&mut self, layer_ids: Vec<LayerId>) {
+        self.send_or_report(EngineCommand::ReorderLayers { layer_ids });
+    }
+
+    fn set_layer_visibility(&mut self, layer_id: LayerId, visible: bool) {
+        self.send_or_report(EngineCommand::SetLayerVisibility { layer_id, visible });
+    }
+
+    fn set_layer_locked(&mut self, layer_id: LayerId, locked: bool) {
+        self.send_or_report(EngineCommand::SetLayerLocked { layer_id, locked });
+    }
+
+    fn rename_layer(&mut self, layer_id: LayerId, new_name: String) {
+        self.send_or_report(EngineCommand::RenameLayer { layer_id, new_name });
+    }
+
+    fn add_keyframe(&mut self, layer_id: LayerId, frame: u32) -> KeyframeId {
+        match self.call(EngineCommand::AddKeyframe { layer_id, frame }) {
+            Ok(CommandOutput::Keyframe(keyframe_id)) => keyframe_id,
+            _ => {
+                self.report(RiveError::EngineStopped);
+                KeyframeId::new()
+            }
+        }
+    }
+
+    fn remove_keyframe(&mut self, keyframe_id: KeyframeId) {
+        self.send_or_report(EngineCommand::RemoveKeyframe { keyframe_id });
+    }
+
+    fn move_keyframe(&mut self, keyframe_id: KeyframeId, new_frame: u32) {
+        self.send_or_report(EngineCommand::MoveKeyframe { keyframe_id, new_frame });
+    }
+
+    fn copy_keyframe(&mut self, keyframe_id: KeyframeId) -> Option<KeyframeData> {
+        match self.call(EngineCommand::CopyKeyframe { keyframe_id }) {
+            Ok(CommandOutput::KeyframeData(data)) => data,
+            _ => {
+                self.report(RiveError::EngineStopped);
+                None
+            }
+        }
+    }
+
+    fn paste_keyframe(&mut self, layer_id: LayerId, frame: u32, data: KeyframeData) {
+        self.send_or_report(EngineCommand::PasteKeyframe { layer_id, frame, data });
+    }
+
+    fn insert_frame(&mut self, layer_id: LayerId, frame: u32) {
+        self.send_or_report(EngineCommand::InsertFrame { layer_id, frame });
+    }
+
+    fn remove_frame(&mut self, layer_id: LayerId, frame: u32) {
+        self.send_or_report(EngineCommand::RemoveFrame { layer_id, frame });
+    }
+
+    fn extend_frame(&mut self, layer_id: LayerId, frame: u32) {
+        self.send_or_report(EngineCommand::ExtendFrame { layer_id, frame });
+    }
+
+    fn create_tween(&mut self, layer_id: LayerId, start_frame: u32, end_frame: u32, tween_type: TweenType) {
+        self.send_or_report(EngineCommand::CreateTween { layer_id, start_frame, end_frame, tween_type });
+    }
+
+    fn set_easing(&mut self, tween_id: TweenId, easing: EasingFunction) {
+        self.send_or_report(EngineCommand::SetEasing { tween_id, easing });
+    }
+
+    fn remove_tween(&mut self, tween_id: TweenId) {
+        self.send_or_report(EngineCommand::RemoveTween { tween_id });
+    }
+}
+
+impl RiveDataProvider for EngineHost {
+    fn get_layers(&self) -> Vec<LayerInfo> {
+        self.snapshot().layers.clone()
+    }
+
+    fn get_keyframes(&self, layer_id: LayerId) -> Vec<KeyframeInfo> {
+        self.snapshot().keyframes.get(&layer_id).cloned().unwrap_or_default()
+    }
+
+    fn get_frame_data(&self, layer_id: LayerId, frame: u32) -> Option<RiveFrameData> {
+        self.snapshot().frame_data(&layer_id, frame).cloned()
+    }
+
+    fn get_current_frame(&self) -> u32 {
+        self.snapshot().current_frame
+    }
+
+    fn get_total_frames(&self) -> u32 {
+        self.snapshot().total_frames
+    }
+
+    fn get_fps(&self) -> f32 {
+        self.snapshot().fps
+    }
+
+    fn get_animation_bounds(&self) -> (u32, u32) {
+        self.snapshot().animation_bounds
+    }
+
+    fn get_layer_hierarchy(&self) -> Vec<LayerHierarchyNode> {
+        self.snapshot().layer_hierarchy.clone()
+    }
+
+    fn get_tweens(&self, layer_id: LayerId) -> Vec<TweenInfo> {
+        self.snapshot().tweens.get(&layer_id).cloned().unwrap_or_default()
+    }
+
+    fn is_playing(&self) -> bool {
+        self.snapshot().is_playing
+    }
+}
+
+/// The timeline widget's [`crate::RiveEngine`] on top of an [`EngineHost`]
+///
+/// Reads come from the host's snapshot and edits are queued to the worker, so
+/// drawing the timeline never waits for the engine. Edits show up once the
+/// worker publishes them; [`content_version`](crate::RiveEngine::content_version)
+/// tells views when that happened. Only adding or duplicating a layer waits,
+/// for the new layer's id.
+///
+/// The hosted engine has no layer types or outline colors: folders and motion
+/// guides are added as normal layers, and outline colors are kept here.
+pub struct HostedEngine {
+    host: EngineHost,
+    outline_colors: HashMap<LayerId, ecolor::Color32>,
+}
+
+impl HostedEngine {
+    pub fn new(host: EngineHost) -> Self {
+        Self { host, outline_colors: HashMap::new() }
+    }
+
+    /// Host `engine` on a new thread; see [`EngineHost::spawn`]
+    pub fn spawn(engine: Box<dyn RiveEngine>) -> std::io::Result<Self> {
+        EngineHost::spawn(engine).map(Self::new)
+    }
+
+    pub fn host(&self) -> &EngineHost {
+        &self.host
+    }
+
+    /// Id of the keyframe at `frame`, if the snapshot has one
+    fn keyframe_at(&self, layer_id: &LayerId, frame: u32) -> Option<KeyframeId> {
+        let snapshot = self.host.snapshot();
+        let keyframes = snapshot.keyframes.get(layer_id)?;
+        keyframes.iter().find(|k| k.frame == frame).map(|k| k.id.clone())
+    }
+
+    /// Tween from the keyframe at or before `frame` to the next keyframe or the end
+    fn create_tween_at(&mut self, layer_id: LayerId, frame: u32, tween_type: TweenType) {
+        let snapshot = self.host.snapshot();
+        let frames: Vec<u32> = snapshot.keyframes.get(&layer_id).into_iter().flatten().map(|k| k.frame).collect();
+        let start_frame = frames.iter().copied().filter(|&f| f <= frame).max().unwrap_or(frame);
+        let end_frame = frames.iter().copied().filter(|&f| f > frame).min().unwrap_or(snapshot.total_frames);
+        self.host.create_tween(layer_id, start_frame, end_frame, tween_type);
+    }
+}
+
+impl crate::RiveEngine for HostedEngine {
+    fn get_layers(&self) -> Vec<LayerInfo> {
+        let mut layers = self.host.get_layers();
+        for layer in &mut layers {
+            if let Some(&color) = self.outline_colors.get(&layer.id) {
+                layer.outline_color = color;
+            }
+        }
+        layers
+    }
+
+    fn get_frame_data(&self, layer_id: LayerId, frame: u32) -> FrameData {
+        let snapshot = self.host.snapshot();
+        let Some(data) = snapshot.frame_data(&layer_id, frame) else {
+            return FrameData { frame_number: frame, ..Default::default() };
+        };
+        let tweened = snapshot
+            .tweens
+            .get(&layer_id)
+            .is_some_and(|tweens| tweens.iter().any(|t| t.start_frame < frame && frame < t.end_frame));
+        let frame_type = match data.frame_type {
+            KeyframeType::Keyframe | KeyframeType::BlankKeyframe => FrameType::Keyframe,
+            KeyframeType::Empty if tweened => FrameType::Tween,
+            KeyframeType::Empty => FrameType::Empty,
+        };
+        FrameData { frame_number: frame, frame_type, has_content: data.has_content, id: data.id.clone() }
+    }
+
+    fn play(&mut self) {
+        self.host.play();
+    }
+
+    fn pause(&mut self) {
+        self.host.pause();
+    }
+
+    fn seek(&mut self, frame: u32) {
+        self.host.seek_to_frame(frame);
+    }
+
+    fn get_current_frame(&self) -> u32 {
+        self.host.get_current_frame()
+    }
+
+    fn get_total_frames(&self) -> u32 {
+        self.host.get_total_frames()
+    }
+
+    fn get_fps(&self) -> f32 {
+        self.host.get_fps()
+    }
+
+    fn content_version(&self) -> u64 {
+        self.host.snapshot().content_version
+    }
+
+    fn insert_frame(&mut self, layer_id: LayerId, frame: u32) {
+        self.host.insert_frame(layer_id, frame);
+    }
+
+    fn remove_frame(&mut self, layer_id: LayerId, frame: u32) {
+        self.host.remove_frame(layer_id, frame);
+    }
+
+    fn insert_keyframe(&mut self, layer_id: LayerId, frame: u32) {
+        self.host.send_or_report(EngineCommand::AddKeyframe { layer_id, frame });
+    }
+
+    fn clear_keyframe(&mut self, layer_id: LayerId, frame: u32) {
+        self.delete_keyframe(layer_id, frame);
+    }
+
+    fn create_motion_tween(&mut self, layer_id: LayerId, frame: u32) {
+        self.create_tween_at(layer_id, frame, TweenType::Motion);
+    }
+
+    fn create_shape_tween(&mut self, layer_id: LayerId, frame: u32) {
+        self.create_tween_at(layer_id, frame, TweenType::Shape);
+    }
+
+    fn move_keyframe(&mut self, layer_id: LayerId, from_frame: u32, to_frame: u32) {
+        if let Some(keyframe_id) = self.keyframe_at(&layer_id, from_frame) {
+            self.host.move_keyframe(keyframe_id, to_frame);
+        }
+    }
+
+    fn copy_keyframe(&mut self, layer_id: LayerId, frame: u32) -> Option<FrameData> {
+        let data = crate::RiveEngine::get_frame_data(self, layer_id, frame);
+        (data.frame_type == FrameType::Keyframe).then_some(data)
+    }
+
+    fn paste_keyframe(&mut self, layer_id: LayerId, frame: u32, data: FrameData) {
+        let keyframe_type = match data.frame_type {
+            FrameType::Empty => KeyframeType::Empty,
+            _ if data.has_content => KeyframeType::Keyframe,
+            _ => KeyframeType::BlankKeyframe,
+        };
+        let data = KeyframeData { keyframe_type, properties: HashMap::new(), tween_data: None };
+        self.host.paste_keyframe(layer_id, frame, data);
+    }
+
+    fn delete_keyframe(&mut self, layer_id: LayerId, frame: u32) {
+        if let Some(keyframe_id) = self.keyframe_at(&layer_id, frame) {
+            self.host.remove_keyframe(keyframe_id);
+        }
+    }
+
+    fn set_property(&mut self, layer_id: LayerId, _frame: u32, property: &str, value: bool) {
+        match property {
+            "visible" => self.host.set_layer_visibility(layer_id, value),
+            "locked" => self.host.set_layer_locked(layer_id, value),
+            _ => {}
+        }
+    }
+
+    fn get_property(&self, layer_id: LayerId, _frame: u32, property: &str) -> bool {
+        let snapshot = self.host.snapshot();
+        let Some(layer) = snapshot.layers.iter().find(|l| l.id == layer_id) else {
+            return false;
+        };
+        match property {
+            "visible" => layer.visible,
+            "locked" => layer.locked,
+            _ => false,
+        }
+    }
+
+    fn rename_layer(&mut self, layer_id: LayerId, new_name: String) {
+        self.host.rename_layer(layer_id, new_name);
+    }
+
+    fn set_layer_outline_color(&mut self, layer_id: LayerId, color: ecolor::Color32) {
+        self.outline_colors.insert(layer_id, color);
+    }
+
+    fn reorder_layers(&mut self, layer_ids: Vec<LayerId>) {
+        self.host.reorder_layers(layer_ids);
+    }
+
+    fn add_layer(&mut self, name: String, _layer_type: LayerType) -> LayerId {
+        self.host.add_layer(&name)
+    }
+
+    fn delete_layer(&mut self, layer_id: LayerId) {
+        self.outline_colors.remove(&layer_id);
+        self.host.remove_layer(layer_id);
+    }
+
+    /// Copies the keyframes and tweens; keyframe properties stay with the original
+    fn duplicate_layer(&mut self, layer_id: LayerId) -> LayerId {
+        let snapshot = self.host.snapshot();
+        let name = snapshot.layers.iter().find(|l| l.id == layer_id).map_or("Layer", |l| l.name.as_str());
+        let copy_id = self.host.add_layer(&format!("{} copy", name));
+
+        let tweens = snapshot.tweens.get(&layer_id).map_or(&[][..], Vec::as_slice);
+        for keyframe in snapshot.keyframes.get(&layer_id).into_iter().flatten() {
+            let tween_data = tweens.iter().find(|t| t.start_frame == keyframe.frame).map(|tween| TweenData {
+                tween_type: tween.tween_type,
+                duration_frames: tween.end_frame - tween.start_frame,
+                easing: tween.easing.clone(),
+            });
+            let data = KeyframeData { keyframe_type: keyframe.keyframe_type, properties: HashMap::new(), tween_data };
+            self.host.paste_keyframe(copy_id.clone(), keyframe.frame, data);
+        }
+        if let Some(&color) = self.outline_colors.get(&layer_id) {
+            self.outline_colors.insert(copy_id.clone(), color);
+        }
+        copy_id
+    }
+
+    fn add_folder_layer(&mut self, name: String) -> LayerId {
+        self.host.add_layer(&name)
+    }
+
+    fn add_motion_guide_layer(&mut self, name: String) -> LayerId {
+        self.host.add_layer(&name)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rive_integration::LegacyRiveEngineAdapter;
+    use crate::ui::MockRiveEngine;
+
+    fn spawn_mock() -> EngineHost {
+        EngineHost::spawn(Box::new(LegacyRiveEngineAdapter::new(MockRiveEngine::new()))).unwrap()
+    }
+
+    #[test]
+    fn test_initial_snapshot() {
+        let host = spawn_mock();
+        let snapshot = host.snapshot();
+        assert_eq!(snapshot.layers.len(), crate::layer::create_mock_layers().len());
+        assert_eq!(snapshot.total_frames, 100);
+        assert_eq!(snapshot.frames[&snapshot.layers[0].id].len(), 100);
+    }
+
+    #[test]
+    fn test_commands_update_snapshot() {
+        let mut host = spawn_mock();
+        let layer_count = host.get_layers().len();
+
+        host.seek_to_frame(42);
+        let layer_id = host.add_layer("Hosted");
+
+        // `call` returns once the snapshot includes every earlier command
+        let snapshot = host.snapshot();
+        assert_eq!(snapshot.current_frame, 42);
+        assert_eq!(snapshot.layers.len(), layer_count + 1);
+        assert!(snapshot.layers.iter().any(|l| l.id == layer_id));
+    }
+
+    #[test]
+    fn test_events_are_forwarded() {
+        let mut host = spawn_mock();
+        host.play();
+        host.seek_to_frame(7);
+        host.call(EngineCommand::Pause).unwrap();
+
+        let events = host.poll_events();
+        assert!(matches!(
+            events.as_slice(),
+            [EngineEvent::PlaybackStarted, EngineEvent::FrameChanged { frame: 7 }, EngineEvent::PlaybackStopped]
+        ));
+        assert!(host.poll_events().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_reads_do_not_wait_for_engine() {
+        let host = spawn_mock();
+        let held = host.snapshot();
+        host.call(EngineCommand::SeekToFrame { frame: 3 }).unwrap();
+
+        // A reader holding an old snapshot keeps it; new reads see the update
+        assert_eq!(held.current_frame, 0);
+        assert_eq!(host.snapshot().current_frame, 3);
+        assert!(host.snapshot().version > held.version);
+    }
+
+    #[test]
+    fn test_shutdown_returns_engine() {
+        let host = spawn_mock();
+        host.send(EngineCommand::SeekToFrame { frame: 9 }).unwrap();
+        let engine = host.shutdown().expect("engine thread panicked");
+        assert_eq!(engine.get_current_frame(), 9);
+    }
+
+    #[test]
+    fn test_snapshot_rereads_only_changed_layers() {
+        let host = spawn_mock();
+        let before = host.snapshot();
+        let (edited, other) = (before.layers[0].id.clone(), before.layers[1].id.clone());
+
+        host.call(EngineCommand::InsertFrame { layer_id: edited.clone(), frame: 3 }).unwrap();
+        let after = host.snapshot();
+        assert!(!Arc::ptr_eq(&before.frames[&edited], &after.frames[&edited]));
+        assert!(Arc::ptr_eq(&before.frames[&other], &after.frames[&other]));
+        assert!(after.content_version > before.content_version);
+
+        // Playback only refreshes the playhead
+        host.call(EngineCommand::SeekToFrame { frame: 5 }).unwrap();
+        let seeked = host.snapshot();
+        assert_eq!(seeked.current_frame, 5);
+        assert_eq!(seeked.content_version, after.content_version);
+        assert!(Arc::ptr_eq(&after.frames[&edited], &seeked.frames[&edited]));
+    }
+
+    #[test]
+    fn test_stopped_engine_reports_errors() {
+        let mut host = spawn_mock();
+        host.stop_worker();
+
+        let layer_id = host.add_layer("Late");
+        let keyframe_id = host.add_keyframe(layer_id.clone(), 0);
+        assert!(!host.get_layers().iter().any(|l| l.id == layer_id));
+        assert!(host.copy_keyframe(keyframe_id).is_none());
+
+        let errors = host
+            .poll_events()
+            .into_iter()
+            .filter(|event| matches!(event, EngineEvent::AnimationError { error: RiveError::EngineStopped }))
+            .count();
+        assert_eq!(errors, 3);
+    }
+
+    #[test]
+    fn test_hosted_engine_drives_controller() {
+        let mut engine = HostedEngine::spawn(Box::new(LegacyRiveEngineAdapter::new(MockRiveEngine::new()))).unwrap();
+        let mut controller = crate::TimelineController::new();
+        let layer_id = crate::RiveEngine::get_layers(&engine)[0].id.clone();
+        let version = crate::RiveEngine::content_version(&engine);
+
+        controller.insert_keyframe(&mut engine, layer_id.clone(), 12);
+        controller.seek(&mut engine, 30);
+        engine.host().sync().unwrap();
+
+        let engine: &mut dyn crate::RiveEngine = &mut engine;
+        assert_eq!(engine.get_frame_data(layer_id.clone(), 12).frame_type, FrameType::Keyframe);
+        assert_eq!(engine.get_current_frame(), 30);
+        assert!(engine.content_version() > version);
+
+        // Adding a layer waits for its id
+        let added = engine.add_layer("Hosted".to_string(), LayerType::Normal);
+        assert!(engine.get_layers().iter().any(|l| l.id == added));
+        engine.set_layer_outline_color(added.clone(), ecolor::Color32::RED);
+        let copy = engine.duplicate_layer(added);
+        assert_eq!(engine.get_layers().iter().find(|l| l.id == copy).unwrap().outline_color, ecolor::Color32::RED);
+    }
+}
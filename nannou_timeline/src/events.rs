@@ -0,0 +1,228 @@
+//! Structured output of the timeline widget
+//!
+//! Every edit made in [`Timeline::show`](crate::Timeline::show) is reported as a
+//! [`TimelineEvent`]. By default the widget also applies each event to the engine
+//! as it happens. In controlled mode it only reports them, so the host can
+//! validate, log or route them through its own state store before applying them
+//! (for example with [`TimelineEvent::apply`]). Audio clips, automation lanes
+//! and piano rolls are kept by the [`TimelineController`](crate::TimelineController)
+//! rather than the engine;
+//! [`TimelineController::apply_event`](crate::TimelineController::apply_event)
+//! applies events to both.
+
+use ecolor::Color32;
+use crate::{LayerId, PropertyId, RiveEngine};
+use crate::audio::EnvelopePreset;
+use crate::automation::AutomationValue;
+use crate::frame::FrameData;
+use crate::piano_roll::Note;
+use crate::layer::LayerType;
+
+/// A user intent emitted by the timeline widget
+#[derive(Clone, Debug)]
+pub enum TimelineEvent {
+    // Playback
+    PlayRequested,
+    PauseRequested,
+    SeekRequested { frame: u32 },
+
+    // Frames and keyframes
+    FrameInserted { layer_id: LayerId, frame: u32 },
+    FrameRemoved { layer_id: LayerId, frame: u32 },
+    KeyframeInserted { layer_id: LayerId, frame: u32 },
+    KeyframeCleared { layer_id: LayerId, frame: u32 },
+    KeyframeDeleted { layer_id: LayerId, frame: u32 },
+    KeyframeMoved { layer_id: LayerId, from_frame: u32, to_frame: u32 },
+    KeyframePasted { layer_id: LayerId, frame: u32, data: FrameData },
+    /// A keyframe's symbol now shows `symbol_frame`
+    SymbolFrameSet { layer_id: LayerId, frame: u32, symbol_frame: u32 },
+    /// An automation lane set or triggered a layer property
+    AutomationApplied { layer_id: LayerId, frame: u32, property: PropertyId, value: AutomationValue },
+    /// The playhead reached a note of a layer's piano roll
+    NoteOn { layer_id: LayerId, frame: u32, note: Note },
+    /// The playhead left a note of a layer's piano roll
+    NoteOff { layer_id: LayerId, frame: u32, note: Note },
+    MotionTweenCreated { layer_id: LayerId, frame: u32 },
+    ShapeTweenCreated { layer_id: LayerId, frame: u32 },
+
+    // Layers
+    LayerAdded { name: String, layer_type: LayerType },
+    FolderAdded { name: String },
+    MotionGuideAdded { name: String },
+    LayerDeleted { layer_id: LayerId },
+    LayerDuplicated { layer_id: LayerId },
+    LayerRenamed { layer_id: LayerId, new_name: String },
+    LayerOutlineColorChanged { layer_id: LayerId, color: Color32 },
+    /// New top-to-bottom layer order
+    LayersReordered { layer_ids: Vec<LayerId> },
+
+    // Labels and comments (stored in the timeline config, not the engine)
+    LabelAdded { frame: u32, name: String },
+    LabelRemoved { frame: u32, name: String },
+    CommentAdded { frame: u32, text: String },
+    CommentRemoved { frame: u32, text: String },
+
+    // Audio clips (stored in the timeline controller, not the engine); frames
+    // are timeline frames
+    /// A volume envelope point was added or changed
+    EnvelopePointSet { layer_id: LayerId, clip: usize, frame: u32, volume: f32 },
+    /// A volume envelope point moved, already kept between its neighbours
+    EnvelopePointMoved { layer_id: LayerId, clip: usize, from: u32, to: u32, volume: f32 },
+    EnvelopePointRemoved { layer_id: LayerId, clip: usize, frame: u32 },
+    /// A clip's envelope was replaced by a preset
+    EnvelopePresetApplied { layer_id: LayerId, clip: usize, preset: EnvelopePreset, fade_frames: u32 },
+    /// A clip was moved, trimmed or slipped; trims are in seconds
+    AudioClipChanged { layer_id: LayerId, clip: usize, start_frame: u32, trim_start: f32, trim_end: f32 },
+    /// A clip was cut in two at `frame`
+    AudioClipSplit { layer_id: LayerId, clip: usize, frame: u32 },
+
+    // Automation lanes (stored in the timeline controller, not the engine)
+    /// A numeric point, toggle change or bang was set on a lane
+    AutomationPointSet { layer_id: LayerId, lane: usize, frame: u32, value: AutomationValue },
+    /// A numeric point moved, already kept between its neighbours
+    AutomationPointMoved { layer_id: LayerId, lane: usize, from: u32, to: u32, value: f32 },
+    AutomationPointRemoved { layer_id: LayerId, lane: usize, frame: u32 },
+    /// The segment after a numeric point was bent
+    AutomationCurveSet { layer_id: LayerId, lane: usize, frame: u32, curve: f32 },
+
+    // Piano roll notes (stored in the timeline controller, not the engine);
+    // indices are into the roll's notes before the edit
+    NoteAdded { layer_id: LayerId, note: Note },
+    NoteMoved { layer_id: LayerId, index: usize, frame: u32, key: u8 },
+    /// A note's length changed, at least one frame
+    NoteResized { layer_id: LayerId, index: usize, length: u32 },
+    NoteVelocitySet { layer_id: LayerId, index: usize, velocity: u8 },
+    NoteRemoved { layer_id: LayerId, index: usize },
+
+    /// Layer or keyframe selection changed during the frame
+    SelectionChanged {
+        layers: Vec<LayerId>,
+        keyframes: Vec<(LayerId, u32)>,
+    },
+}
+
+impl TimelineEvent {
+    /// Whether applying this event changes the engine
+    pub fn mutates_engine(&self) -> bool {
+        !matches!(
+            self,
+            TimelineEvent::LabelAdded { .. }
+                | TimelineEvent::LabelRemoved { .. }
+                | TimelineEvent::CommentAdded { .. }
+                | TimelineEvent::CommentRemoved { .. }
+                | TimelineEvent::SelectionChanged { .. }
+        ) && !self.edits_controller()
+    }
+
+    /// Whether applying this event changes the audio clips, automation lanes
+    /// or piano rolls kept by the [`TimelineController`](crate::TimelineController)
+    pub fn edits_controller(&self) -> bool {
+        matches!(
+            self,
+            TimelineEvent::EnvelopePointSet { .. }
+                | TimelineEvent::EnvelopePointMoved { .. }
+                | TimelineEvent::EnvelopePointRemoved { .. }
+                | TimelineEvent::EnvelopePresetApplied { .. }
+                | TimelineEvent::AudioClipChanged { .. }
+                | TimelineEvent::AudioClipSplit { .. }
+                | TimelineEvent::AutomationPointSet { .. }
+                | TimelineEvent::AutomationPointMoved { .. }
+                | TimelineEvent::AutomationPointRemoved { .. }
+                | TimelineEvent::AutomationCurveSet { .. }
+                | TimelineEvent::NoteAdded { .. }
+                | TimelineEvent::NoteMoved { .. }
+                | TimelineEvent::NoteResized { .. }
+                | TimelineEvent::NoteVelocitySet { .. }
+                | TimelineEvent::NoteRemoved { .. }
+        )
+    }
+
+    /// Whether the event changes the document's frames, layers or audio, as
+    /// opposed to playback, selection, labels or property values applied
+    /// while playing
+    pub fn edits_document(&self) -> bool {
+        let engine_edit = self.mutates_engine()
+            && !matches!(
+                self,
+                TimelineEvent::PlayRequested
+                    | TimelineEvent::PauseRequested
+                    | TimelineEvent::SeekRequested { .. }
+                    | TimelineEvent::AutomationApplied { .. }
+                    | TimelineEvent::NoteOn { .. }
+                    | TimelineEvent::NoteOff { .. }
+            );
+        engine_edit || self.edits_controller()
+    }
+
+    /// Apply the event to an engine. Returns the id of the layer created by
+    /// add and duplicate events. Events that only change the controller's
+    /// data do nothing here.
+    pub fn apply(&self, engine: &mut dyn RiveEngine) -> Option<LayerId> {
+        match self.clone() {
+            TimelineEvent::PlayRequested => engine.play(),
+            TimelineEvent::PauseRequested => engine.pause(),
+            TimelineEvent::SeekRequested { frame } => engine.seek(frame),
+
+            TimelineEvent::FrameInserted { layer_id, frame } => engine.insert_frame(layer_id, frame),
+            TimelineEvent::FrameRemoved { layer_id, frame } => engine.remove_frame(layer_id, frame),
+            TimelineEvent::KeyframeInserted { layer_id, frame } => engine.insert_keyframe(layer_id, frame),
+            TimelineEvent::KeyframeCleared { layer_id, frame } => engine.clear_keyframe(layer_id, frame),
+            TimelineEvent::KeyframeDeleted { layer_id, frame } => engine.delete_keyframe(layer_id, frame),
+            TimelineEvent::KeyframeMoved { layer_id, from_frame, to_frame } => {
+                engine.move_keyframe(layer_id, from_frame, to_frame)
+            }
+            TimelineEvent::KeyframePasted { layer_id, frame, data } => engine.paste_keyframe(layer_id, frame, data),
+            TimelineEvent::SymbolFrameSet { layer_id, frame, symbol_frame } => {
+                engine.set_symbol_frame(layer_id, frame, symbol_frame)
+            }
+            TimelineEvent::AutomationApplied { layer_id, frame, property, value } => {
+                engine.apply_automation(layer_id, frame, &property, value)
+            }
+            TimelineEvent::NoteOn { layer_id, frame, note } => engine.note_on(layer_id, frame, &note),
+            TimelineEvent::NoteOff { layer_id, frame, note } => engine.note_off(layer_id, frame, &note),
+            TimelineEvent::MotionTweenCreated { layer_id, frame } => engine.create_motion_tween(layer_id, frame),
+            TimelineEvent::ShapeTweenCreated { layer_id, frame } => engine.create_shape_tween(layer_id, frame),
+
+            TimelineEvent::LayerAdded { name, layer_type } => return Some(engine.add_layer(name, layer_type)),
+            TimelineEvent::FolderAdded { name } => return Some(engine.add_folder_layer(name)),
+            TimelineEvent::MotionGuideAdded { name } => return Some(engine.add_motion_guide_layer(name)),
+            TimelineEvent::LayerDeleted { layer_id } => engine.delete_layer(layer_id),
+            TimelineEvent::LayerDuplicated { layer_id } => return Some(engine.duplicate_layer(layer_id)),
+            TimelineEvent::LayerRenamed { layer_id, new_name } => engine.rename_layer(layer_id, new_name),
+            TimelineEvent::LayerOutlineColorChanged { layer_id, color } => {
+                engine.set_layer_outline_color(layer_id, color)
+            }
+            TimelineEvent::LayersReordered { layer_ids } => engine.reorder_layers(layer_ids),
+
+            TimelineEvent::LabelAdded { .. }
+            | TimelineEvent::LabelRemoved { .. }
+            | TimelineEvent::CommentAdded { .. }
+            | TimelineEvent::CommentRemoved { .. }
+            | TimelineEvent::SelectionChanged { .. }
+            | TimelineEvent::EnvelopePointSet { .. }
+            | TimelineEvent::EnvelopePointMoved { .. }
+            | TimelineEvent::EnvelopePointRemoved { .. }
+            | TimelineEvent::EnvelopePresetApplied { .. }
+            | TimelineEvent::AudioClipChanged { .. }
+            | TimelineEvent::AudioClipSplit { .. }
+            | TimelineEvent::AutomationPointSet { .. }
+            | TimelineEvent::AutomationPointMoved { .. }
+            | TimelineEvent::AutomationPointRemoved { .. }
+            | TimelineEvent::AutomationCurveSet { .. }
+            | TimelineEvent::NoteAdded { .. }
+            | TimelineEvent::NoteMoved { .. }
+            | TimelineEvent::NoteResized { .. }
+            | TimelineEvent::NoteVelocitySet { .. }
+            | TimelineEvent::NoteRemoved { .. } => {}
+        }
+        None
+    }
+}
+
+/// Result of drawing the timeline for one frame
+#[cfg(feature = "ui")]
+pub struct TimelineOutput {
+    pub response: egui::Response,
+    /// Events emitted this frame, in the order they happened
+    pub events: Vec<TimelineEvent>,
+}
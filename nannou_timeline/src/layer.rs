@@ -1,7 +1,36 @@
 //! Layer management for the timeline
 
+use ecolor::Color32;
 use serde::{Deserialize, Serialize};
 
+/// Outline colors assigned to new layers in turn, like Flash's layer colors
+pub const LAYER_OUTLINE_PALETTE: [Color32; 8] = [
+    Color32::from_rgb(74, 255, 255),
+    Color32::from_rgb(255, 79, 255),
+    Color32::from_rgb(79, 255, 79),
+    Color32::from_rgb(255, 159, 0),
+    Color32::from_rgb(0, 127, 255),
+    Color32::from_rgb(255, 79, 79),
+    Color32::from_rgb(159, 79, 255),
+    Color32::from_rgb(255, 255, 79),
+];
+
+/// Outline color at `index` in the palette, wrapping around. Engines give a
+/// new layer the color of its index in the document.
+pub fn palette_color(index: usize) -> Color32 {
+    LAYER_OUTLINE_PALETTE[index % LAYER_OUTLINE_PALETTE.len()]
+}
+
+/// Give every layer without an outline color (loaded from a document saved
+/// before layers had one) the palette color of its index
+pub fn assign_outline_colors(layers: &mut [LayerInfo]) {
+    for (index, layer) in layers.iter_mut().enumerate() {
+        if layer.outline_color == Color32::TRANSPARENT {
+            layer.outline_color = palette_color(index);
+        }
+    }
+}
+
 /// Unique identifier for a layer
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LayerId(pub String);
@@ -33,6 +62,11 @@ pub struct LayerInfo {
     pub locked: bool,
     pub parent_id: Option<LayerId>,
     pub children: Vec<LayerId>,
+    /// Color used for the layer swatch, keyframe spans and outline display.
+    /// Transparent until assigned (new or loaded layers); the timeline shows
+    /// those in the palette color of their index, see [`assign_outline_colors`].
+    #[serde(default)]
+    pub outline_color: Color32,
 }
 
 impl LayerInfo {
@@ -47,6 +81,7 @@ impl LayerInfo {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: Color32::TRANSPARENT,
         }
     }
 
@@ -107,6 +142,7 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: palette_color(0),
         },
         LayerInfo {
             id: LayerId::new("layer2"),
@@ -116,6 +152,7 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: palette_color(1),
         },
         LayerInfo {
             id: LayerId::new("layer3"),
@@ -125,6 +162,7 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: false,
             parent_id: None,
             children: vec![LayerId::new("layer4"), LayerId::new("layer5")],
+            outline_color: palette_color(2),
         },
         LayerInfo {
             id: LayerId::new("layer4"),
@@ -134,6 +172,7 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: false,
             parent_id: Some(LayerId::new("layer3")),
             children: vec![],
+            outline_color: palette_color(3),
         },
         LayerInfo {
             id: LayerId::new("layer5"),
@@ -143,6 +182,7 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: true,
             parent_id: Some(LayerId::new("layer3")),
             children: vec![],
+            outline_color: palette_color(4),
         },
         LayerInfo {
             id: LayerId::new("layer6"),
@@ -152,6 +192,7 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: palette_color(5),
         },
         LayerInfo {
             id: LayerId::new("layer7"),
@@ -161,9 +202,39 @@ pub fn create_mock_layers() -> Vec<LayerInfo> {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: palette_color(6),
         },
     ]
 }
 
+/// How a layer's content should be drawn on the stage
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerDisplayMode {
+    /// Draw content normally
+    Normal,
+    /// Draw only the outlines of shapes, in the given color
+    Outline(Color32),
+    /// Don't draw the layer
+    Hidden,
+}
+
+impl LayerDisplayMode {
+    /// Fill for content of `color`: none in outline mode or when hidden
+    pub fn fill(&self, color: Color32) -> Option<Color32> {
+        matches!(self, LayerDisplayMode::Normal).then_some(color)
+    }
+
+    /// Color to stroke content with: its own stroke `color` normally, the
+    /// layer's outline color in outline mode, none when hidden. In outline
+    /// mode filled shapes are stroked too.
+    pub fn stroke(&self, color: Color32) -> Option<Color32> {
+        match self {
+            LayerDisplayMode::Normal => Some(color),
+            LayerDisplayMode::Outline(outline) => Some(*outline),
+            LayerDisplayMode::Hidden => None,
+        }
+    }
+}
+
 // Add uuid dependency for generating unique IDs
 use uuid;
\ No newline at end of file
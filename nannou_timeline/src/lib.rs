@@ -2,35 +2,81 @@
 //!
 //! The primary type is **Timeline** - an egui widget that mimics Adobe Flash's
 //! timeline interface with layers, keyframes, and playback controls.
+//!
+//! # Cargo features
+//!
+//! - `ui` (default): the egui widgets — `Timeline`, the motion and curve
+//!   editors and scene tabs.
+//! - `dock` (default): the Flash-style `DockManager` workspace (implies `ui`).
+//! - `scripting` (default): the Rhai scripting API.
+//! - `testkit`: reusable tests for [`RiveEngine`] implementations, see
+//!   `rive_engine_tests!`, and the `rive_conformance` checks for the new engine API.
+//!
+//! With `default-features = false` the data model, [`TimelineController`],
+//! time, easing and audio types build without egui or Rhai.
 
-use egui::Color32;
-
+#[cfg(feature = "ui")]
 pub use playhead_egui::Playhead;
+#[cfg(feature = "ui")]
 pub use ruler_egui::Ruler;
-pub use timeline_egui::{Timeline, TimelineState, KeyframeSelection, DragState, KeyframeClipboardItem};
-pub use ui::{MockRiveEngine, MockAudioEngine};
+#[cfg(feature = "ui")]
+pub use minimap_egui::{Minimap, MinimapPosition};
+#[cfg(feature = "ui")]
+pub use timeline_egui::{Timeline, LipSyncDialog, LipSyncSource, MidiImportDialog};
+pub use controller::{TimelineController, TimelineState, KeyframeSelection, DragState, KeyframeClipboardItem, FrameSizePreset, AudioDrag, AudioDragMode, AutomationDrag, NoteDrag, NoteDragMode, RulerMode};
+pub use ui::{MockRiveEngine, MockAudioEngine, MockAudioCall};
 pub use layer::{Layer, LayerId, LayerType};
 pub use frame::{Frame, FrameType, KeyframeId};
 pub use track_simple::Track;
 
+#[cfg(feature = "ui")]
 pub mod playhead_egui;
+#[cfg(feature = "ui")]
 pub mod ruler_egui;
+#[cfg(feature = "ui")]
+pub mod minimap_egui;
+#[cfg(feature = "ui")]
 pub mod timeline_egui;
+pub mod controller;
 pub mod layer;
 pub mod frame;
 pub mod track_simple;
 pub mod ui;
 pub mod time;
 pub mod easing;
+#[cfg(feature = "ui")]
 pub mod motion_editor;
 pub mod audio;
+pub mod audio_scheduler;
+pub mod audio_scrub;
+pub mod automation;
+pub mod beat_detection;
+pub mod lip_sync;
+pub mod midi;
+pub mod piano_roll;
+pub mod tempo;
+pub mod wav;
+pub mod mixdown;
+pub mod waveform;
 pub mod i18n;
+#[cfg(feature = "scripting")]
 pub mod scripting;
+#[cfg(feature = "dock")]
 pub mod dock_manager;
+#[cfg(feature = "ui")]
 pub mod curve_editor;
 pub mod rive_integration;
 pub mod scene;
+#[cfg(feature = "ui")]
 pub mod scene_tabs;
+pub mod command_palette;
+pub mod theme;
+pub mod events;
+pub mod engine_host;
+#[cfg(any(test, feature = "testkit"))]
+pub mod rive_conformance;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
 // Re-export time types
 pub use time::{FrameTime, FpsPreset, FrameLabel, FrameComment};
@@ -39,22 +85,54 @@ pub use time::{FrameTime, FpsPreset, FrameLabel, FrameComment};
 pub use easing::{BezierCurve, BezierPoint, EasingPreset, PropertyId};
 
 // Re-export motion editor
+#[cfg(feature = "ui")]
 pub use motion_editor::MotionEditor;
 
 // Re-export audio types
-pub use audio::{AudioId, AudioSource, AudioLayer, AudioSyncMode, AudioEngine, AudioError, WaveformData, VolumeEnvelope};
+pub use audio::{AudioId, AudioSource, AudioLayer, AudioSyncMode, AudioEngine, AudioError, WaveformData, VolumeEnvelope, EnvelopePreset, AudioTrack, Crossfade};
+pub use audio_scheduler::{AudioScheduler, FrameStep};
+pub use audio_scrub::AudioScrubber;
+pub use automation::{AutomationEnvelope, AutomationLane, AutomationValue, BangEnvelope, CurvePoint, NumericEnvelope, ToggleEnvelope};
+pub use beat_detection::{BeatAnalysis, BeatSettings};
+pub use lip_sync::{LipSyncError, LipSyncKey, MouthSet, PhonemeCue};
+pub use midi::{MidiError, MidiFile, MidiSelection, MidiTarget};
+pub use piano_roll::{Note, PianoRoll};
+pub use tempo::{GridKind, GridLine, MeterMark, MusicalPosition, TempoMap, TempoMapError, TempoMark};
+pub use wav::{WavAudioEngine, WavData};
+pub use mixdown::{mixdown, mixdown_to_file, MixdownSettings};
+pub use waveform::{PeakPyramid, WaveformBuilder, WaveformJob};
 
 // Re-export scripting types
+#[cfg(feature = "scripting")]
 pub use scripting::{ScriptContext, ScriptStage, ScriptDisplayObject, ScriptEvent, ScriptManager};
 
 // Re-export dock manager
+#[cfg(feature = "dock")]
 pub use dock_manager::{DockManager, TabType, FlashTabViewer};
 
 // Re-export scene management
 pub use scene::{Scene, SceneId, SceneProperties, SceneSummary, SceneManager};
+#[cfg(feature = "ui")]
 pub use scene_tabs::{SceneTabs, SceneTabState, SceneTabEvent, SceneNavigation, SceneContextMenuState};
 
+// Re-export theme types
+pub use theme::{TimelineStyle, BuiltinTheme, ThemeError};
+
+// Re-export command palette
+pub use command_palette::{Command, CommandContext, CommandRegistry};
+#[cfg(feature = "ui")]
+pub use command_palette::CommandPalette;
+
+// Re-export widget events
+pub use events::TimelineEvent;
+#[cfg(feature = "ui")]
+pub use events::TimelineOutput;
+
+// Re-export engine host
+pub use engine_host::{EngineHost, HostedEngine, EngineCommand, EngineEvent, EngineSnapshot, CommandOutput};
+
 // Re-export curve editor
+#[cfg(feature = "ui")]
 pub use curve_editor::{CurveEditor, CurveEditorPanel};
 
 // Re-export rive integration
@@ -76,6 +154,14 @@ pub trait RiveEngine: Send + Sync {
     fn get_current_frame(&self) -> u32;
     fn get_total_frames(&self) -> u32;
     fn get_fps(&self) -> f32;
+
+    /// Changes whenever layers or frames change other than through the
+    /// timeline's own events, for example when edits are applied on another
+    /// thread. Views reading the engine cache until this or the controller's
+    /// document revision changes.
+    fn content_version(&self) -> u64 {
+        0
+    }
     
     // Frame operations
     fn insert_frame(&mut self, layer_id: LayerId, frame: u32);
@@ -99,8 +185,29 @@ pub trait RiveEngine: Send + Sync {
     fn set_property(&mut self, layer_id: LayerId, frame: u32, property: &str, value: bool);
     fn get_property(&self, layer_id: LayerId, frame: u32, property: &str) -> bool;
     
+    /// Show `symbol_frame` of the symbol in a keyframe (a graphic
+    /// instance's first frame), as lip sync does for mouth symbols.
+    /// Engines without symbols can ignore it.
+    fn set_symbol_frame(&mut self, _layer_id: LayerId, _frame: u32, _symbol_frame: u32) {}
+    
+    /// Set a property of a layer from its automation lane at `frame`, or
+    /// trigger it for a bang. Engines without the property can ignore it.
+    fn apply_automation(&mut self, _layer_id: LayerId, _frame: u32, _property: &PropertyId, _value: automation::AutomationValue) {}
+    
+    /// The playhead reached the start of a note in the layer's piano roll.
+    /// Engines can start an action of the layer here, or ignore it.
+    fn note_on(&mut self, _layer_id: LayerId, _frame: u32, _note: &piano_roll::Note) {}
+    
+    /// The playhead left a note started with `note_on`
+    fn note_off(&mut self, _layer_id: LayerId, _frame: u32, _note: &piano_roll::Note) {}
+    
     // Layer operations
     fn rename_layer(&mut self, layer_id: LayerId, new_name: String);
+    /// Engines without outline colors can ignore it; the timeline then
+    /// shows each layer in the palette color of its index.
+    fn set_layer_outline_color(&mut self, _layer_id: LayerId, _color: ecolor::Color32) {}
+    /// Engines with a fixed layer order can ignore it
+    fn reorder_layers(&mut self, _layer_ids: Vec<LayerId>) {}
     fn add_layer(&mut self, name: String, layer_type: layer::LayerType) -> LayerId;
     fn delete_layer(&mut self, layer_id: LayerId);
     fn duplicate_layer(&mut self, layer_id: LayerId) -> LayerId;
@@ -130,6 +237,10 @@ pub struct TimelineConfig {
     pub frame_labels: Vec<FrameLabel>,
     /// Frame comments
     pub frame_comments: Vec<FrameComment>,
+    /// Beat markers from audio analysis or MIDI, drawn on the ruler and snapped to
+    pub beat_markers: Vec<u32>,
+    /// Tempo and time signature changes for the bars|beats ruler
+    pub tempo_map: TempoMap,
     /// Colors and styling
     pub style: TimelineStyle,
     /// Snap-to-grid configuration
@@ -142,7 +253,14 @@ pub struct SnapConfig {
     pub enabled: bool,
     pub snap_to_frames: bool,
     pub snap_to_keyframes: bool,
+    /// Snap to beat markers and frame labels. Detected or imported beats are
+    /// markers, so this flag is the one that turns snapping to them off.
     pub snap_to_markers: bool,
+    /// Snap to the tempo map's beat grid. The grid is only drawn when the
+    /// ruler shows bars and beats, so it is only snapped to then.
+    pub snap_to_beats: bool,
+    /// Beat grid lines per beat: 2 for eighths in 4/4, 4 for sixteenths
+    pub beat_subdivision: u32,
     pub threshold_pixels: f32,
     pub show_guides: bool,
 }
@@ -158,6 +276,8 @@ impl Default for TimelineConfig {
             fps: FpsPreset::default(),
             frame_labels: Vec::new(),
             frame_comments: Vec::new(),
+            beat_markers: Vec::new(),
+            tempo_map: TempoMap::default(),
             style: TimelineStyle::default(),
             snap: SnapConfig::default(),
         }
@@ -171,41 +291,10 @@ impl Default for SnapConfig {
             snap_to_frames: true,
             snap_to_keyframes: true,
             snap_to_markers: true,
+            snap_to_beats: true,
+            beat_subdivision: 1,
             threshold_pixels: 8.0,
             show_guides: true,
         }
     }
 }
-
-/// Visual styling for the timeline
-#[derive(Clone, Debug)]
-pub struct TimelineStyle {
-    pub background_color: Color32,
-    pub grid_color: Color32,
-    pub layer_background: Color32,
-    pub layer_selected: Color32,
-    pub frame_empty: Color32,
-    pub frame_keyframe: Color32,
-    pub frame_tween: Color32,
-    pub playhead_color: Color32,
-    pub border_color: Color32,
-    pub text_color: Color32,
-}
-
-impl Default for TimelineStyle {
-    fn default() -> Self {
-        Self {
-            background_color: Color32::from_gray(40),
-            grid_color: Color32::from_gray(60),
-            layer_background: Color32::from_gray(50),
-            layer_selected: Color32::from_rgb(70, 130, 180),
-            frame_empty: Color32::from_gray(45),
-            frame_keyframe: Color32::from_gray(20),
-            frame_tween: Color32::from_rgb(100, 100, 150),
-            playhead_color: Color32::from_rgb(255, 0, 0),
-            border_color: Color32::from_gray(80),
-            text_color: Color32::from_gray(220),
-        }
-    }
-}
-
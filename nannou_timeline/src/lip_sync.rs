@@ -0,0 +1,280 @@
+//! Lip-sync keyframes from dialogue audio
+//!
+//! A [`MouthSet`] names the mouth shapes an artist drew and the frame of the
+//! mouth symbol that shows each one. Mouth cues come from either:
+//!
+//! - [`amplitude_cues`]: the louder the sound, the more open the mouth, using
+//!   the set's [`amplitude_shapes`](MouthSet::amplitude_shapes);
+//! - a phoneme transcript with timings, read by [`parse_transcript`]: Rhubarb
+//!   Lip Sync's TSV output (`0.27<TAB>D`) or a Papagayo MOHO export
+//!   (`MohoSwitch1` followed by `frame phoneme` lines).
+//!
+//! [`clip_keys`] places the cues of a sound under an audio clip, giving one
+//! [`LipSyncKey`] wherever the mouth changes.
+
+use crate::audio::AudioLayer;
+use crate::wav::WavData;
+
+/// Loudness, relative to the loudest frame, below which the mouth rests
+const SILENCE: f32 = 0.1;
+
+/// A mouth shape starting at a time in the sound
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhonemeCue {
+    /// Seconds from the start of the sound
+    pub time: f64,
+    pub shape: String,
+}
+
+impl PhonemeCue {
+    pub fn new(time: f64, shape: impl Into<String>) -> Self {
+        Self { time, shape: shape.into() }
+    }
+}
+
+/// Mouth shapes and the mouth symbol frame showing each
+#[derive(Clone, Debug, PartialEq)]
+pub struct MouthSet {
+    /// Shape names and their symbol frames
+    pub shapes: Vec<(String, u32)>,
+    /// Shape shown in silence and for unknown names
+    pub rest: String,
+    /// Shapes from least to most open, used for amplitude lip sync
+    pub amplitude_shapes: Vec<String>,
+}
+
+impl Default for MouthSet {
+    fn default() -> Self {
+        Self::preston_blair()
+    }
+}
+
+impl MouthSet {
+    fn numbered(names: &[&str], rest: &str, amplitude_shapes: &[&str]) -> Self {
+        Self {
+            shapes: names.iter().enumerate().map(|(frame, name)| (name.to_string(), frame as u32)).collect(),
+            rest: rest.to_string(),
+            amplitude_shapes: amplitude_shapes.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Papagayo's Preston Blair phoneme set on symbol frames 0 to 9
+    pub fn preston_blair() -> Self {
+        Self::numbered(
+            &["AI", "E", "O", "U", "etc", "L", "WQ", "MBP", "FV", "rest"],
+            "rest",
+            &["MBP", "etc", "E", "AI"],
+        )
+    }
+
+    /// Rhubarb Lip Sync's shapes A to H and X on symbol frames 0 to 8
+    pub fn rhubarb() -> Self {
+        Self::numbered(&["A", "B", "C", "D", "E", "F", "G", "H", "X"], "X", &["B", "C", "D"])
+    }
+
+    /// Symbol frame of a shape, matching names case-insensitively; unknown
+    /// shapes show the rest shape
+    pub fn symbol_frame(&self, shape: &str) -> u32 {
+        let find = |name: &str| self.shapes.iter().find(|(shape, _)| shape.eq_ignore_ascii_case(name)).map(|&(_, frame)| frame);
+        find(shape).or_else(|| find(&self.rest)).unwrap_or(0)
+    }
+}
+
+/// A mouth keyframe on the timeline
+#[derive(Clone, Debug, PartialEq)]
+pub struct LipSyncKey {
+    pub frame: u32,
+    pub shape: String,
+    /// Frame of the mouth symbol to show
+    pub symbol_frame: u32,
+}
+
+/// Errors reading a phoneme transcript
+#[derive(Debug, Clone, PartialEq)]
+pub enum LipSyncError {
+    /// A line that isn't a time or frame followed by a shape (1-based line number)
+    InvalidLine(usize),
+    /// The transcript has no cues
+    Empty,
+}
+
+impl std::fmt::Display for LipSyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LipSyncError::InvalidLine(line) => write!(f, "Invalid transcript line {}", line),
+            LipSyncError::Empty => write!(f, "Transcript has no mouth cues"),
+        }
+    }
+}
+
+impl std::error::Error for LipSyncError {}
+
+/// Read a Rhubarb TSV or Papagayo MOHO transcript. MOHO frame numbers
+/// count from 1 at `fps`.
+pub fn parse_transcript(text: &str, fps: f32) -> Result<Vec<PhonemeCue>, LipSyncError> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).peekable();
+    let moho = lines.peek().is_some_and(|(_, line)| line.trim() == "MohoSwitch1");
+    if moho {
+        lines.next();
+    }
+
+    let mut cues = Vec::new();
+    for (index, line) in lines {
+        let mut fields = line.split_whitespace();
+        let (Some(position), Some(shape), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(LipSyncError::InvalidLine(index + 1));
+        };
+        let time = if moho {
+            let frame: u32 = position.parse().map_err(|_| LipSyncError::InvalidLine(index + 1))?;
+            frame.saturating_sub(1) as f64 / fps as f64
+        } else {
+            position.parse().map_err(|_| LipSyncError::InvalidLine(index + 1))?
+        };
+        cues.push(PhonemeCue::new(time, shape));
+    }
+    if cues.is_empty() {
+        return Err(LipSyncError::Empty);
+    }
+    cues.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(cues)
+}
+
+/// Mouth cues from the loudness of each `fps` frame of a sound. A shape that
+/// would last a single frame is held from the frame before, so the mouth
+/// doesn't flicker.
+pub fn amplitude_cues(data: &WavData, mouths: &MouthSet, fps: f32) -> Vec<PhonemeCue> {
+    let channels = data.channels.max(1) as usize;
+    let frame_samples = ((data.sample_rate as f32 / fps).round() as usize).max(1) * channels;
+    let loudness: Vec<f32> = data
+        .samples
+        .chunks(frame_samples)
+        .map(|block| (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt())
+        .collect();
+    let loudest = loudness.iter().copied().fold(0.0, f32::max);
+
+    let levels = mouths.amplitude_shapes.len();
+    let shapes: Vec<&str> = loudness
+        .iter()
+        .map(|&level| {
+            let level = if loudest > 0.0 { level / loudest } else { 0.0 };
+            if level < SILENCE || levels == 0 {
+                return mouths.rest.as_str();
+            }
+            let index = ((level - SILENCE) / (1.0 - SILENCE) * levels as f32) as usize;
+            mouths.amplitude_shapes[index.min(levels - 1)].as_str()
+        })
+        .collect();
+
+    let mut cues: Vec<PhonemeCue> = Vec::new();
+    for (frame, &shape) in shapes.iter().enumerate() {
+        let held = frame > 0 && shapes.get(frame + 1) != Some(&shape) && shapes[frame - 1] != shape;
+        if held || cues.last().is_some_and(|cue| cue.shape == shape) {
+            continue;
+        }
+        cues.push(PhonemeCue::new(frame as f64 / fps as f64, shape));
+    }
+    cues
+}
+
+/// Keys for the cues of a sound played by `clip`: cues are shifted past the
+/// trimmed head, the mouth shape at the clip's first frame is keyed, and the
+/// mouth returns to rest after the clip ends.
+pub fn clip_keys(cues: &[PhonemeCue], mouths: &MouthSet, clip: &AudioLayer, fps: f32) -> Vec<LipSyncKey> {
+    let range = clip.frame_range(fps);
+    let mut keys: Vec<LipSyncKey> = Vec::new();
+    let mut push = |frame: u32, shape: &str| {
+        if keys.last().is_some_and(|key| key.frame == frame) {
+            keys.pop();
+        }
+        if keys.last().is_some_and(|key| key.shape == shape) {
+            return;
+        }
+        keys.push(LipSyncKey { frame, shape: shape.to_string(), symbol_frame: mouths.symbol_frame(shape) });
+    };
+
+    let trim = clip.trim_start as f64;
+    let first = cues.iter().rposition(|cue| cue.time <= trim).unwrap_or(0);
+    for cue in &cues[first..] {
+        let offset = ((cue.time - trim).max(0.0) * fps as f64).round() as u32;
+        let frame = range.start + offset;
+        if frame >= range.end {
+            break;
+        }
+        push(frame, &cue.shape);
+    }
+    if !range.is_empty() {
+        push(range.end, &mouths.rest);
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSource;
+
+    fn clip(duration: f32, start_frame: u32) -> AudioLayer {
+        let mut source = AudioSource::new("line.wav".into());
+        source.duration = duration;
+        AudioLayer::new(source, start_frame)
+    }
+
+    #[test]
+    fn test_parse_rhubarb_and_moho() {
+        let rhubarb = "0.00\tX\n0.27\tD\n\n0.41\tB\n";
+        assert_eq!(
+            parse_transcript(rhubarb, 24.0).unwrap(),
+            vec![PhonemeCue::new(0.0, "X"), PhonemeCue::new(0.27, "D"), PhonemeCue::new(0.41, "B")]
+        );
+
+        let moho = "MohoSwitch1\n1 rest\n13 AI\n25 MBP\n";
+        let cues = parse_transcript(moho, 24.0).unwrap();
+        assert_eq!(cues[1], PhonemeCue::new(0.5, "AI"));
+        assert_eq!(cues[2], PhonemeCue::new(1.0, "MBP"));
+
+        assert_eq!(parse_transcript("0.00 X\nhello\n", 24.0), Err(LipSyncError::InvalidLine(2)));
+        assert_eq!(parse_transcript("MohoSwitch1\n", 24.0), Err(LipSyncError::Empty));
+    }
+
+    #[test]
+    fn test_symbol_frames() {
+        let mouths = MouthSet::preston_blair();
+        assert_eq!(mouths.symbol_frame("AI"), 0);
+        assert_eq!(mouths.symbol_frame("mbp"), 7);
+        assert_eq!(mouths.symbol_frame("schwa"), 9);
+        assert_eq!(MouthSet::rhubarb().symbol_frame("X"), 8);
+    }
+
+    #[test]
+    fn test_amplitude_opens_the_mouth() {
+        // Frames at 10 fps: silence, quiet, quiet, loud, loud, a one-frame
+        // quiet blip and silence
+        let levels = [0.0, 0.3, 0.3, 1.0, 1.0, 0.3, 1.0, 0.0, 0.0];
+        let samples = levels.iter().flat_map(|&level| [level, -level].repeat(50)).collect();
+        let data = WavData { sample_rate: 1000, channels: 1, bits_per_sample: 32, samples };
+
+        let shapes: Vec<(f64, String)> = amplitude_cues(&data, &MouthSet::rhubarb(), 10.0)
+            .into_iter()
+            .map(|cue| ((cue.time * 10.0).round(), cue.shape))
+            .collect();
+        let expected = [(0.0, "X"), (1.0, "B"), (3.0, "D"), (7.0, "X")];
+        assert_eq!(shapes, expected.map(|(frame, shape)| (frame, shape.to_string())));
+    }
+
+    #[test]
+    fn test_clip_keys_follow_trims() {
+        let mouths = MouthSet::rhubarb();
+        let cues = [PhonemeCue::new(0.0, "X"), PhonemeCue::new(0.4, "D"), PhonemeCue::new(0.65, "B"), PhonemeCue::new(1.5, "C")];
+        let mut clip = clip(2.0, 100);
+        clip.trim_start = 0.45;
+        clip.trim_end = 1.0;
+
+        // D is already showing when the trimmed clip starts; C is cut off
+        let keys = clip_keys(&cues, &mouths, &clip, 10.0);
+        let keys: Vec<(u32, &str, u32)> = keys
+            .iter()
+            .map(|key| (key.frame, key.shape.as_str(), key.symbol_frame))
+            .collect();
+        assert_eq!(keys, [(100, "D", 3), (102, "B", 1), (105, "X", 8)]);
+    }
+}
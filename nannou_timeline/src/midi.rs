@@ -0,0 +1,462 @@
+//! Standard MIDI File import
+//!
+//! [`parse`] reads format 0 and 1 files: the notes of every track, the tempo
+//! map and the time signatures. Note times stay in ticks; [`MidiFile`]
+//! converts them to seconds through the tempo map, and to timeline frames
+//! for the document's frame rate. A [`MidiSelection`] picks the tracks,
+//! pitches and velocities to import, and
+//! [`TimelineController::import_midi`](crate::TimelineController::import_midi)
+//! turns the chosen notes into keyframes, beat markers or frame labels.
+
+use crate::LayerId;
+use std::path::Path;
+
+/// Tempo until the file sets one: 120 beats per minute
+const DEFAULT_MICROS_PER_QUARTER: u32 = 500_000;
+
+/// How ticks relate to time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Division {
+    /// Ticks per quarter note, timed by the tempo map
+    TicksPerQuarter(u16),
+    /// Ticks per SMPTE frame at a fixed frame rate
+    Smpte { fps: f32, ticks_per_frame: u8 },
+}
+
+/// A note from note-on to note-off
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiNote {
+    pub tick: u64,
+    pub duration_ticks: u64,
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+}
+
+/// One track of a MIDI file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MidiTrack {
+    pub name: Option<String>,
+    /// Notes in start order
+    pub notes: Vec<MidiNote>,
+}
+
+/// A tempo from a tick on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TempoChange {
+    pub tick: u64,
+    pub micros_per_quarter: u32,
+}
+
+impl TempoChange {
+    pub fn bpm(&self) -> f64 {
+        60_000_000.0 / self.micros_per_quarter as f64
+    }
+}
+
+/// A time signature from a tick on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeSignature {
+    pub tick: u64,
+    pub numerator: u8,
+    /// Note value of a beat: 4 for quarter notes, 8 for eighths
+    pub denominator: u8,
+}
+
+/// A parsed Standard MIDI File
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiFile {
+    /// 0 for a single track, 1 for simultaneous tracks
+    pub format: u16,
+    pub division: Division,
+    pub tracks: Vec<MidiTrack>,
+    /// Tempo changes of all tracks in tick order
+    pub tempo_map: Vec<TempoChange>,
+    /// Time signature changes of all tracks in tick order
+    pub time_signatures: Vec<TimeSignature>,
+}
+
+/// Errors reading a MIDI file
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiError {
+    Io(String),
+    /// The data doesn't start with a MIDI header
+    NotMidi,
+    /// Format 2 (independent sequences) and unknown formats
+    UnsupportedFormat(u16),
+    /// The data ends inside a chunk or event
+    Truncated,
+    /// A track has a byte that can't start an event (track index, byte offset)
+    InvalidEvent(usize, usize),
+}
+
+impl std::fmt::Display for MidiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiError::Io(msg) => write!(f, "MIDI file error: {}", msg),
+            MidiError::NotMidi => write!(f, "Not a Standard MIDI File"),
+            MidiError::UnsupportedFormat(format) => write!(f, "Unsupported MIDI format {}", format),
+            MidiError::Truncated => write!(f, "MIDI file ends unexpectedly"),
+            MidiError::InvalidEvent(track, offset) => write!(f, "Invalid MIDI event in track {} at byte {}", track, offset),
+        }
+    }
+}
+
+impl std::error::Error for MidiError {}
+
+/// Name of a MIDI key, with middle C (60) as "C4"
+pub fn note_name(key: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[key as usize % 12], key as i32 / 12 - 1)
+}
+
+/// Read a MIDI file from disk
+pub fn read(path: &Path) -> Result<MidiFile, MidiError> {
+    let bytes = std::fs::read(path).map_err(|e| MidiError::Io(e.to_string()))?;
+    parse(&bytes)
+}
+
+/// Parse the bytes of a format 0 or 1 MIDI file
+pub fn parse(bytes: &[u8]) -> Result<MidiFile, MidiError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(4).ok() != Some(b"MThd".as_slice()) {
+        return Err(MidiError::NotMidi);
+    }
+    let header_length = reader.u32()? as usize;
+    let header = reader.take(header_length)?;
+    if header.len() < 6 {
+        return Err(MidiError::Truncated);
+    }
+    let format = u16::from_be_bytes([header[0], header[1]]);
+    if format > 1 {
+        return Err(MidiError::UnsupportedFormat(format));
+    }
+    let track_count = u16::from_be_bytes([header[2], header[3]]) as usize;
+    let division = match header[4] {
+        smpte if smpte & 0x80 != 0 => {
+            let fps = match (smpte as i8).unsigned_abs() {
+                29 => 29.97,
+                fps => fps as f32,
+            };
+            Division::Smpte { fps, ticks_per_frame: header[5] }
+        }
+        high => Division::TicksPerQuarter(u16::from_be_bytes([high, header[5]])),
+    };
+
+    let mut file = MidiFile { format, division, tracks: Vec::new(), tempo_map: Vec::new(), time_signatures: Vec::new() };
+    while file.tracks.len() < track_count && reader.position < bytes.len() {
+        let id = reader.take(4)?;
+        let length = reader.u32()? as usize;
+        let chunk = reader.take(length)?;
+        // Unknown chunks are skipped as the spec asks
+        if id == b"MTrk" {
+            let index = file.tracks.len();
+            let track = parse_track(chunk, index, &mut file)?;
+            file.tracks.push(track);
+        }
+    }
+    file.tempo_map.sort_by_key(|tempo| tempo.tick);
+    file.time_signatures.sort_by_key(|signature| signature.tick);
+    Ok(file)
+}
+
+/// Notes of one track; tempo and time signature events go to `file`
+fn parse_track(chunk: &[u8], index: usize, file: &mut MidiFile) -> Result<MidiTrack, MidiError> {
+    let mut reader = Reader { bytes: chunk, position: 0 };
+    let mut track = MidiTrack::default();
+    let mut tick = 0u64;
+    let mut running_status = None;
+    // Sounding notes by channel and key: index into `track.notes`
+    let mut sounding: Vec<((u8, u8), usize)> = Vec::new();
+
+    while reader.position < chunk.len() {
+        tick += reader.vlq()? as u64;
+        let offset = reader.position;
+        let status = match reader.peek()? {
+            byte if byte & 0x80 != 0 => {
+                reader.position += 1;
+                byte
+            }
+            _ => running_status.ok_or(MidiError::InvalidEvent(index, offset))?,
+        };
+
+        match status {
+            0xFF => {
+                let kind = reader.byte()?;
+                let length = reader.vlq()? as usize;
+                let data = reader.take(length)?;
+                match (kind, data) {
+                    (0x03, name) => track.name = Some(String::from_utf8_lossy(name).into_owned()),
+                    (0x51, &[a, b, c]) => file.tempo_map.push(TempoChange { tick, micros_per_quarter: u32::from_be_bytes([0, a, b, c]) }),
+                    (0x58, &[numerator, power, ..]) => file.time_signatures.push(TimeSignature {
+                        tick,
+                        numerator,
+                        denominator: 1u8.checked_shl(power as u32).unwrap_or(4),
+                    }),
+                    (0x2F, _) => break,
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                let length = reader.vlq()? as usize;
+                reader.take(length)?;
+            }
+            0x80..=0xEF => {
+                running_status = Some(status);
+                let channel = status & 0x0F;
+                let first = reader.byte()?;
+                let second = if matches!(status & 0xF0, 0xC0 | 0xD0) { 0 } else { reader.byte()? };
+                match (status & 0xF0, second) {
+                    (0x90, velocity) if velocity > 0 => {
+                        sounding.push(((channel, first), track.notes.len()));
+                        track.notes.push(MidiNote { tick, duration_ticks: 0, channel, key: first, velocity });
+                    }
+                    (0x80, _) | (0x90, _) => {
+                        if let Some(position) = sounding.iter().position(|&(note, _)| note == (channel, first)) {
+                            let (_, note) = sounding.remove(position);
+                            track.notes[note].duration_ticks = tick - track.notes[note].tick;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => return Err(MidiError::InvalidEvent(index, offset)),
+        }
+    }
+
+    // Notes never released last to the end of the track
+    for (_, note) in sounding {
+        track.notes[note].duration_ticks = tick - track.notes[note].tick;
+    }
+    Ok(track)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], MidiError> {
+        let end = self.position.checked_add(count).filter(|&end| end <= self.bytes.len()).ok_or(MidiError::Truncated)?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn peek(&self) -> Result<u8, MidiError> {
+        self.bytes.get(self.position).copied().ok_or(MidiError::Truncated)
+    }
+
+    fn byte(&mut self) -> Result<u8, MidiError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, MidiError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Variable-length quantity: 7 bits per byte, high bit set on all but the last
+    fn vlq(&mut self) -> Result<u32, MidiError> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(MidiError::Truncated)
+    }
+}
+
+impl MidiFile {
+    /// Seconds from the start of the file to `tick`
+    pub fn seconds_at(&self, tick: u64) -> f64 {
+        let ticks_per_quarter = match self.division {
+            Division::Smpte { fps, ticks_per_frame } => return tick as f64 / (fps as f64 * ticks_per_frame.max(1) as f64),
+            Division::TicksPerQuarter(ticks) => ticks.max(1) as f64,
+        };
+        let mut seconds = 0.0;
+        let mut from = 0;
+        let mut micros = DEFAULT_MICROS_PER_QUARTER;
+        for change in self.tempo_map.iter().take_while(|change| change.tick < tick) {
+            seconds += (change.tick - from) as f64 * micros as f64 / ticks_per_quarter / 1e6;
+            from = change.tick;
+            micros = change.micros_per_quarter;
+        }
+        seconds + (tick - from) as f64 * micros as f64 / ticks_per_quarter / 1e6
+    }
+
+    /// Timeline frame at `fps` of `tick`
+    pub fn frame_at(&self, tick: u64, fps: f32) -> u32 {
+        (self.seconds_at(tick) * fps as f64).round() as u32
+    }
+
+    /// 1-based bar and beat of `tick` by the time signatures, for files timed
+    /// in ticks per quarter note. The beat is fractional between beats.
+    pub fn bar_beat_at(&self, tick: u64) -> (u32, f64) {
+        let Division::TicksPerQuarter(ticks_per_quarter) = self.division else { return (1, 1.0) };
+        let ticks_per_quarter = ticks_per_quarter.max(1) as f64;
+        let beat_ticks = |signature: &TimeSignature| ticks_per_quarter * 4.0 / signature.denominator.max(1) as f64;
+
+        let mut bar = 1;
+        let mut signature = TimeSignature { tick: 0, numerator: 4, denominator: 4 };
+        for change in self.time_signatures.iter().take_while(|change| change.tick <= tick) {
+            let bar_ticks = beat_ticks(&signature) * signature.numerator.max(1) as f64;
+            bar += ((change.tick - signature.tick) as f64 / bar_ticks).ceil() as u32;
+            signature = *change;
+        }
+        let beats = (tick - signature.tick) as f64 / beat_ticks(&signature);
+        let numerator = signature.numerator.max(1) as f64;
+        (bar + (beats / numerator).floor() as u32, beats % numerator + 1.0)
+    }
+
+    /// Selected notes with their track index, in time order
+    pub fn selected_notes<'a>(&'a self, selection: &'a MidiSelection) -> Vec<(usize, &'a MidiNote)> {
+        let mut notes: Vec<(usize, &MidiNote)> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| selection.tracks.is_empty() || selection.tracks.contains(index))
+            .flat_map(|(index, track)| track.notes.iter().map(move |note| (index, note)))
+            .filter(|(_, note)| selection.includes(note))
+            .collect();
+        notes.sort_by_key(|(_, note)| note.tick);
+        notes
+    }
+}
+
+/// Which notes of a MIDI file to import
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiSelection {
+    /// Track indices; empty selects every track
+    pub tracks: Vec<usize>,
+    pub lowest_key: u8,
+    pub highest_key: u8,
+    /// Quieter notes are skipped
+    pub min_velocity: u8,
+}
+
+impl Default for MidiSelection {
+    fn default() -> Self {
+        Self {
+            tracks: Vec::new(),
+            lowest_key: 0,
+            highest_key: 127,
+            min_velocity: 1,
+        }
+    }
+}
+
+impl MidiSelection {
+    pub fn includes(&self, note: &MidiNote) -> bool {
+        (self.lowest_key..=self.highest_key).contains(&note.key) && note.velocity >= self.min_velocity
+    }
+}
+
+/// What imported notes become
+#[derive(Clone, Debug, PartialEq)]
+pub enum MidiTarget {
+    /// A keyframe on a layer at each note
+    Keyframes(LayerId),
+    /// A beat marker on the ruler at each note
+    Markers,
+    /// A frame label named after each note
+    Labels,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(mut value: u32) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    }
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        [id, &(data.len() as u32).to_be_bytes(), data].concat()
+    }
+
+    /// A file with `tracks` of (delta ticks, event bytes)
+    fn smf(format: u16, division: [u8; 2], tracks: &[Vec<(u32, Vec<u8>)>]) -> Vec<u8> {
+        let header = [&format.to_be_bytes()[..], &(tracks.len() as u16).to_be_bytes(), &division].concat();
+        let mut bytes = chunk(b"MThd", &header);
+        for events in tracks {
+            let data: Vec<u8> = events.iter().flat_map(|(delta, event)| [vlq(*delta), event.clone()].concat()).collect();
+            bytes.extend(chunk(b"MTrk", &data));
+        }
+        bytes
+    }
+
+    fn conductor() -> Vec<(u32, Vec<u8>)> {
+        vec![
+            (0, vec![0xFF, 0x58, 4, 3, 2, 24, 8]),
+            // 120 BPM, then 60 BPM from the second bar of 3/4
+            (0, vec![0xFF, 0x51, 3, 0x07, 0xA1, 0x20]),
+            (1440, vec![0xFF, 0x51, 3, 0x0F, 0x42, 0x40]),
+            (0, vec![0xFF, 0x2F, 0]),
+        ]
+    }
+
+    fn drums() -> Vec<(u32, Vec<u8>)> {
+        vec![
+            (0, vec![0xFF, 0x03, 5, b'D', b'r', b'u', b'm', b's']),
+            (0, vec![0x99, 36, 100]),
+            // Running status, and note-on with zero velocity as note-off
+            (240, vec![36, 0]),
+            (240, vec![38, 90]),
+            (720, vec![0x89, 38, 0]),
+            (0, vec![0x99, 42, 20]),
+            (480, vec![0xFF, 0x2F, 0]),
+        ]
+    }
+
+    #[test]
+    fn test_parse_tracks_tempo_and_meter() {
+        let file = parse(&smf(1, [0x01, 0xE0], &[conductor(), drums()])).unwrap();
+        assert_eq!(file.division, Division::TicksPerQuarter(480));
+        assert_eq!(file.tracks[1].name.as_deref(), Some("Drums"));
+        let notes: Vec<(u64, u64, u8)> = file.tracks[1].notes.iter().map(|n| (n.tick, n.duration_ticks, n.key)).collect();
+        assert_eq!(notes, [(0, 240, 36), (480, 720, 38), (1200, 480, 42)]);
+        assert_eq!(file.tempo_map.iter().map(TempoChange::bpm).collect::<Vec<_>>(), [120.0, 60.0]);
+        assert_eq!(file.time_signatures[0], TimeSignature { tick: 0, numerator: 3, denominator: 4 });
+
+        // 1440 ticks at 120 BPM is 1.5 s, then a quarter note takes 1 s
+        assert_eq!(file.seconds_at(960), 1.0);
+        assert_eq!(file.seconds_at(1920), 2.5);
+        assert_eq!(file.frame_at(1920, 24.0), 60);
+        assert_eq!(file.bar_beat_at(1440), (2, 1.0));
+        assert_eq!(file.bar_beat_at(1680), (2, 1.5));
+    }
+
+    #[test]
+    fn test_selection_and_smpte_timing() {
+        let file = parse(&smf(0, [0xE7, 40], &[drums()])).unwrap();
+        assert_eq!(file.division, Division::Smpte { fps: 25.0, ticks_per_frame: 40 });
+        assert_eq!(file.seconds_at(1000), 1.0);
+
+        let loud_kick_and_snare = MidiSelection { lowest_key: 36, highest_key: 40, min_velocity: 50, ..Default::default() };
+        let keys: Vec<u8> = file.selected_notes(&loud_kick_and_snare).iter().map(|(_, note)| note.key).collect();
+        assert_eq!(keys, [36, 38]);
+        assert_eq!(note_name(60), "C4");
+        assert_eq!(note_name(42), "F#2");
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        assert_eq!(parse(b"RIFF....").unwrap_err(), MidiError::NotMidi);
+        assert_eq!(parse(&smf(2, [0, 96], &[])).unwrap_err(), MidiError::UnsupportedFormat(2));
+        let mut truncated = smf(0, [0, 96], &[drums()]);
+        truncated.truncate(truncated.len() - 3);
+        assert_eq!(parse(&truncated).unwrap_err(), MidiError::Truncated);
+        assert_eq!(parse(&smf(0, [0, 96], &[vec![(0, vec![0x40])]])).unwrap_err(), MidiError::InvalidEvent(0, 1));
+    }
+}
@@ -0,0 +1,383 @@
+//! Overview strip showing the whole timeline at once
+//!
+//! The minimap draws per-layer keyframe density, frame labels and the playhead
+//! for the full document, plus a viewport rectangle for the part of the timeline
+//! currently visible in the frame grid. Dragging the viewport pans (`scroll_x`),
+//! dragging its edges resizes it (`zoom_level`), and clicking elsewhere centers
+//! the viewport on the clicked frame.
+
+use egui::{*, self};
+use crate::{LayerId, RiveEngine, TimelineConfig, TimelineState};
+use crate::frame::FrameType;
+use crate::layer::LayerType;
+use crate::timeline_egui::{MIN_ZOOM, MAX_ZOOM};
+
+/// Width of the grab zone at each edge of the viewport rectangle
+const EDGE_GRAB_WIDTH: f32 = 5.0;
+
+/// Where the minimap is placed relative to the frame grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MinimapPosition {
+    /// Between the toolbar and the ruler
+    Top,
+    /// Between the frame grid and the playback controls
+    #[default]
+    Bottom,
+}
+
+/// Part of the viewport being dragged
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MinimapDrag {
+    /// Panning; stores the grab point as a frame offset from the viewport start
+    Pan { grab_offset: f32 },
+    /// Resizing from the left edge
+    ResizeStart,
+    /// Resizing from the right edge
+    ResizeEnd,
+}
+
+/// Maps between frames, minimap pixels and the frame grid's scroll/zoom
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinimapMapping {
+    /// Number of frames in the document
+    pub total_frames: u32,
+    /// Frame width at 100% zoom
+    pub base_frame_width: f32,
+    /// Width of the visible frame grid in pixels
+    pub view_width: f32,
+}
+
+impl MinimapMapping {
+    pub fn new(total_frames: u32, base_frame_width: f32, view_width: f32) -> Self {
+        Self {
+            total_frames: total_frames.max(1),
+            base_frame_width,
+            view_width,
+        }
+    }
+
+    /// Visible frame range `(start, end)` for a scroll position and zoom level
+    pub fn visible_range(&self, scroll_x: f32, zoom_level: f32) -> (f32, f32) {
+        let frame_width = self.base_frame_width * zoom_level;
+        let start = scroll_x / frame_width;
+        (start, start + self.view_width / frame_width)
+    }
+
+    /// Scroll position and zoom level that show the frames `start..end`.
+    /// The zoom is clamped to the timeline's limits and the range is kept
+    /// inside the document.
+    pub fn scroll_and_zoom_for(&self, start: f32, end: f32) -> (f32, f32) {
+        let span = (end - start).max(1.0);
+        let zoom_level = (self.view_width / (span * self.base_frame_width)).clamp(MIN_ZOOM, MAX_ZOOM);
+        let frame_width = self.base_frame_width * zoom_level;
+        let visible_frames = self.view_width / frame_width;
+        let max_start = (self.total_frames as f32 - visible_frames).max(0.0);
+        (start.clamp(0.0, max_start) * frame_width, zoom_level)
+    }
+
+    /// Scroll position that keeps the current zoom and starts the view at `start`
+    pub fn scroll_for_start(&self, start: f32, zoom_level: f32) -> f32 {
+        let frame_width = self.base_frame_width * zoom_level;
+        let visible_frames = self.view_width / frame_width;
+        let max_start = (self.total_frames as f32 - visible_frames).max(0.0);
+        start.clamp(0.0, max_start) * frame_width
+    }
+
+    /// Horizontal minimap position of a frame
+    pub fn frame_to_x(&self, rect: Rect, frame: f32) -> f32 {
+        rect.min.x + frame / self.total_frames as f32 * rect.width()
+    }
+
+    /// Frame under a horizontal minimap position
+    pub fn x_to_frame(&self, rect: Rect, x: f32) -> f32 {
+        ((x - rect.min.x) / rect.width() * self.total_frames as f32).clamp(0.0, self.total_frames as f32)
+    }
+}
+
+/// Keyframe density of a layer, bucketed across the document.
+/// Each bucket is the fraction of its frames that are keyframes (0.0 - 1.0).
+pub fn keyframe_density(engine: &dyn RiveEngine, layer_id: &LayerId, total_frames: u32, buckets: usize) -> Vec<f32> {
+    let mut counts = vec![0u32; buckets];
+    let mut sizes = vec![0u32; buckets];
+    if buckets == 0 || total_frames == 0 {
+        return vec![0.0; buckets];
+    }
+
+    for frame in 0..total_frames {
+        let bucket = ((frame as usize * buckets) / total_frames as usize).min(buckets - 1);
+        sizes[bucket] += 1;
+        if matches!(engine.get_frame_data(layer_id.clone(), frame).frame_type, FrameType::Keyframe) {
+            counts[bucket] += 1;
+        }
+    }
+
+    counts.iter()
+        .zip(&sizes)
+        .map(|(&count, &size)| if size == 0 { 0.0 } else { count as f32 / size as f32 })
+        .collect()
+}
+
+/// Keyframe densities of the minimap's layers, kept until the document,
+/// its length or the strip's width changes
+#[derive(Clone, Debug, PartialEq)]
+struct DensityCache {
+    revision: u64,
+    total_frames: u32,
+    buckets: usize,
+    layers: Vec<LayerId>,
+    densities: Vec<Vec<f32>>,
+}
+
+/// Timeline overview strip with a draggable viewport
+#[derive(Clone, Debug)]
+pub struct Minimap {
+    /// Show the minimap
+    pub visible: bool,
+    /// Height of the strip
+    pub height: f32,
+    /// Placement relative to the frame grid
+    pub position: MinimapPosition,
+    drag: Option<MinimapDrag>,
+    density_cache: Option<DensityCache>,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            height: 40.0,
+            position: MinimapPosition::default(),
+            drag: None,
+            density_cache: None,
+        }
+    }
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Height taken from the timeline layout (zero when hidden)
+    pub fn layout_height(&self) -> f32 {
+        if self.visible { self.height } else { 0.0 }
+    }
+
+    /// Keyframe density of each layer, read from the engine only when the
+    /// document or engine content changed, or the frame count, bucket count
+    /// or layers
+    fn layer_densities(&mut self, engine: &dyn RiveEngine, layers: &[LayerId], total_frames: u32, buckets: usize, revision: u64) -> &[Vec<f32>] {
+        let cached = self.density_cache.as_ref().is_some_and(|cache| {
+            cache.revision == revision && cache.total_frames == total_frames && cache.buckets == buckets && cache.layers == layers
+        });
+        if !cached {
+            self.density_cache = Some(DensityCache {
+                revision,
+                total_frames,
+                buckets,
+                layers: layers.to_vec(),
+                densities: layers.iter().map(|layer| keyframe_density(engine, layer, total_frames, buckets)).collect(),
+            });
+        }
+        self.density_cache.as_ref().map_or(&[], |cache| cache.densities.as_slice())
+    }
+
+    /// Draw the minimap into `rect` and apply viewport drags to the timeline state.
+    /// `view_width` is the width of the frame grid the viewport represents.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        rect: Rect,
+        view_width: f32,
+        engine: &dyn RiveEngine,
+        config: &TimelineConfig,
+        state: &mut TimelineState,
+    ) -> Response {
+        let style = &config.style;
+        let total_frames = engine.get_total_frames();
+        let mapping = MinimapMapping::new(total_frames, config.frame_width, view_width);
+        let painter = ui.painter_at(rect);
+
+        painter.rect_filled(rect, 0.0, style.minimap_background);
+        painter.line_segment([rect.left_top(), rect.right_top()], Stroke::new(1.0, style.border_color));
+
+        // Label strip along the top, layer density rows below it
+        let label_strip = 6.0;
+        let rows_rect = Rect::from_min_max(rect.min + vec2(0.0, label_strip), rect.max);
+        let mut layers = engine.get_layers();
+        crate::layer::assign_outline_colors(&mut layers);
+        let layers: Vec<_> = layers
+            .into_iter()
+            .filter(|l| l.layer_type != LayerType::Folder)
+            .collect();
+        if !layers.is_empty() {
+            let row_height = rows_rect.height() / layers.len() as f32;
+            let buckets = rows_rect.width().max(1.0) as usize;
+            let bucket_width = rows_rect.width() / buckets as f32;
+            let layer_ids: Vec<LayerId> = layers.iter().map(|layer| layer.id.clone()).collect();
+            // Both only grow, so their sum changes whenever either does
+            let revision = state.document_revision + engine.content_version();
+            let densities = self.layer_densities(engine, &layer_ids, total_frames, buckets, revision);
+            for ((i, layer), density) in layers.iter().enumerate().zip(densities) {
+                let y = rows_rect.min.y + i as f32 * row_height;
+                for (bucket, amount) in density.iter().enumerate() {
+                    if *amount <= 0.0 {
+                        continue;
+                    }
+                    let x = rows_rect.min.x + bucket as f32 * bucket_width;
+                    painter.rect_filled(
+                        Rect::from_min_size(pos2(x, y + 1.0), vec2(bucket_width.max(1.0), (row_height - 1.0).max(1.0))),
+                        0.0,
+                        layer.outline_color.gamma_multiply(0.35 + 0.65 * amount),
+                    );
+                }
+            }
+        }
+
+        // Frame labels
+        for label in &config.frame_labels {
+            let x = mapping.frame_to_x(rect, label.frame as f32);
+            painter.line_segment(
+                [pos2(x, rect.min.y), pos2(x, rect.min.y + label_strip)],
+                Stroke::new(2.0, label.color.unwrap_or(style.label_color)),
+            );
+        }
+
+        // Playhead
+        let playhead_x = mapping.frame_to_x(rect, engine.get_current_frame() as f32);
+        painter.line_segment(
+            [pos2(playhead_x, rect.min.y), pos2(playhead_x, rect.max.y)],
+            Stroke::new(1.0, style.playhead_color),
+        );
+
+        // Viewport rectangle
+        let (start, end) = mapping.visible_range(state.scroll_x, state.zoom_level);
+        let viewport_rect = Rect::from_x_y_ranges(
+            mapping.frame_to_x(rect, start)..=mapping.frame_to_x(rect, end.min(total_frames as f32)).max(mapping.frame_to_x(rect, start) + 2.0),
+            rect.y_range(),
+        );
+        painter.rect_filled(viewport_rect, 0.0, style.minimap_viewport_color.gamma_multiply(0.2));
+        painter.rect_stroke(viewport_rect, 0.0, Stroke::new(1.0, style.minimap_viewport_color), StrokeKind::Inside);
+
+        let response = ui.interact(rect, ui.id().with("timeline_minimap"), Sense::click_and_drag());
+
+        // Cursor feedback
+        if let Some(hover) = response.hover_pos() {
+            let on_edge = (hover.x - viewport_rect.min.x).abs() <= EDGE_GRAB_WIDTH
+                || (hover.x - viewport_rect.max.x).abs() <= EDGE_GRAB_WIDTH;
+            if on_edge || matches!(self.drag, Some(MinimapDrag::ResizeStart | MinimapDrag::ResizeEnd)) {
+                ui.ctx().set_cursor_icon(CursorIcon::ResizeHorizontal);
+            } else if viewport_rect.contains(hover) {
+                ui.ctx().set_cursor_icon(CursorIcon::Grab);
+            }
+        }
+
+        if response.drag_started() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let frame = mapping.x_to_frame(rect, pos.x);
+                self.drag = if (pos.x - viewport_rect.min.x).abs() <= EDGE_GRAB_WIDTH {
+                    Some(MinimapDrag::ResizeStart)
+                } else if (pos.x - viewport_rect.max.x).abs() <= EDGE_GRAB_WIDTH {
+                    Some(MinimapDrag::ResizeEnd)
+                } else if viewport_rect.contains(pos) {
+                    Some(MinimapDrag::Pan { grab_offset: frame - start })
+                } else {
+                    // Jump so the grabbed point is the viewport center, then pan
+                    Some(MinimapDrag::Pan { grab_offset: (end - start) / 2.0 })
+                };
+            }
+        }
+
+        if response.dragged() {
+            if let (Some(drag), Some(pos)) = (self.drag, response.interact_pointer_pos()) {
+                let frame = mapping.x_to_frame(rect, pos.x);
+                match drag {
+                    MinimapDrag::Pan { grab_offset } => {
+                        state.scroll_x = mapping.scroll_for_start(frame - grab_offset, state.zoom_level);
+                    }
+                    MinimapDrag::ResizeStart => {
+                        let (scroll_x, zoom_level) = mapping.scroll_and_zoom_for(frame.min(end - 1.0), end);
+                        state.scroll_x = scroll_x;
+                        state.zoom_level = zoom_level;
+                    }
+                    MinimapDrag::ResizeEnd => {
+                        let (scroll_x, zoom_level) = mapping.scroll_and_zoom_for(start, frame.max(start + 1.0));
+                        state.scroll_x = scroll_x;
+                        state.zoom_level = zoom_level;
+                    }
+                }
+            }
+        }
+
+        if response.drag_stopped() {
+            self.drag = None;
+        }
+
+        // Plain click centers the viewport on the clicked frame
+        if response.clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let frame = mapping.x_to_frame(rect, pos.x);
+                state.scroll_x = mapping.scroll_for_start(frame - (end - start) / 2.0, state.zoom_level);
+            }
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::MockRiveEngine;
+
+    #[test]
+    fn test_visible_range_round_trip() {
+        let mapping = MinimapMapping::new(1000, 10.0, 500.0);
+        let (start, end) = mapping.visible_range(200.0, 2.0);
+        assert_eq!((start, end), (10.0, 35.0));
+
+        let (scroll_x, zoom_level) = mapping.scroll_and_zoom_for(start, end);
+        assert!((scroll_x - 200.0).abs() < 1e-3);
+        assert!((zoom_level - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_viewport_clamped_to_document() {
+        let mapping = MinimapMapping::new(100, 10.0, 500.0);
+        // 50 frames visible at 100%, so the view can't start past frame 50
+        assert_eq!(mapping.scroll_for_start(80.0, 1.0), 500.0);
+        assert_eq!(mapping.scroll_for_start(-10.0, 1.0), 0.0);
+
+        // Resizing can't zoom past the timeline limits
+        let (_, zoom_level) = mapping.scroll_and_zoom_for(0.0, 1.0);
+        assert_eq!(zoom_level, MAX_ZOOM);
+        let (scroll_x, zoom_level) = mapping.scroll_and_zoom_for(0.0, 100_000.0);
+        assert_eq!((scroll_x, zoom_level), (0.0, MIN_ZOOM));
+    }
+
+    #[test]
+    fn test_keyframe_density() {
+        let engine = MockRiveEngine::new();
+        // Mock layers have a keyframe every 5th frame (except frame 0)
+        let density = keyframe_density(&engine, &LayerId::new("layer1"), 100, 10);
+        assert_eq!(density.len(), 10);
+        assert_eq!(density[0], 0.1);
+        assert!(density[1..].iter().all(|&d| (d - 0.2).abs() < 1e-6));
+        assert!(keyframe_density(&engine, &LayerId::new("layer1"), 0, 4).iter().all(|&d| d == 0.0));
+    }
+
+    #[test]
+    fn test_densities_cached_until_document_changes() {
+        let mut engine = MockRiveEngine::new();
+        let mut minimap = Minimap::new();
+        let layers = [LayerId::new("layer1")];
+        let before = minimap.layer_densities(&engine, &layers, 100, 10, 0).to_vec();
+
+        // An edit the minimap hasn't been told about keeps the cached densities
+        engine.insert_keyframe(layers[0].clone(), 12);
+        assert_eq!(minimap.layer_densities(&engine, &layers, 100, 10, 0), before.as_slice());
+        // A new revision, length or width reads the engine again
+        assert_ne!(minimap.layer_densities(&engine, &layers, 100, 10, 1), before.as_slice());
+        assert_eq!(minimap.layer_densities(&engine, &layers, 100, 5, 1)[0].len(), 5);
+    }
+}
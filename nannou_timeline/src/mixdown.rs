@@ -0,0 +1,215 @@
+//! Offline mixdown of audio layers into a single WAV
+//!
+//! Renders what the timeline would play over a frame range: each clip is
+//! placed at its `start_frame`, trimmed, looped, scaled by its volume,
+//! envelope and crossfades, resampled to the output rate by linear
+//! interpolation and summed. To render a scene, pass the audio tracks of its
+//! layers:
+//!
+//! ```ignore
+//! let tracks = scene.layers.iter().filter_map(|id| controller.audio_tracks.get(id));
+//! let mix = mixdown(tracks, |id| engine.wav_data(id), 0..scene_frames, &MixdownSettings::default())?;
+//! ```
+
+use crate::audio::{AudioError, AudioId, AudioLayer, AudioTrack};
+use crate::wav::{self, WavData};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Output format of a mixdown
+#[derive(Clone, Debug, PartialEq)]
+pub struct MixdownSettings {
+    /// Timeline frame rate the frame range and layers are measured in
+    pub fps: f32,
+    pub sample_rate: u32,
+    /// 1 for mono, 2 for stereo
+    pub channels: u16,
+    /// 16 for PCM or 32 for float when written to a file
+    pub bits_per_sample: u16,
+}
+
+impl Default for MixdownSettings {
+    fn default() -> Self {
+        Self {
+            fps: 24.0,
+            sample_rate: 44100,
+            channels: 2,
+            bits_per_sample: 16,
+        }
+    }
+}
+
+/// Mix `tracks` over timeline `frames`. `sources` returns the decoded audio
+/// of a clip's source; a clip whose source it can't find fails the mix
+/// with [`AudioError::AudioNotLoaded`]. Samples are not clipped.
+pub fn mixdown<'a>(
+    tracks: impl IntoIterator<Item = &'a AudioTrack>,
+    sources: impl Fn(&AudioId) -> Option<Arc<WavData>>,
+    frames: Range<u32>,
+    settings: &MixdownSettings,
+) -> Result<WavData, AudioError> {
+    if !(1..=2).contains(&settings.channels) || settings.sample_rate == 0 || settings.fps <= 0.0 {
+        return Err(AudioError::UnsupportedFormat);
+    }
+    let length = frames.end.saturating_sub(frames.start) as f64 / settings.fps as f64 * settings.sample_rate as f64;
+    let mut mix = WavData {
+        sample_rate: settings.sample_rate,
+        channels: settings.channels,
+        bits_per_sample: settings.bits_per_sample,
+        samples: vec![0.0; length.round() as usize * settings.channels as usize],
+    };
+    for track in tracks {
+        for (index, clip) in track.clips().iter().enumerate() {
+            let data = sources(&clip.source.id).ok_or(AudioError::AudioNotLoaded)?;
+            let crossfade = |frame: f64| track.crossfade_gain(index, frame as f32, settings.fps);
+            mix_clip(&mut mix, clip, &data, frames.start, settings.fps as f64, crossfade);
+        }
+    }
+    Ok(mix)
+}
+
+/// Mix `tracks` over timeline `frames` and write the result to `path`
+pub fn mixdown_to_file<'a>(
+    path: &Path,
+    tracks: impl IntoIterator<Item = &'a AudioTrack>,
+    sources: impl Fn(&AudioId) -> Option<Arc<WavData>>,
+    frames: Range<u32>,
+    settings: &MixdownSettings,
+) -> Result<(), AudioError> {
+    wav::write(path, &mixdown(tracks, sources, frames, settings)?)
+}
+
+/// Add one clip into `mix`, which starts at timeline frame `first_frame`.
+/// `crossfade` gives the clip's crossfade level at a timeline frame.
+fn mix_clip(mix: &mut WavData, layer: &AudioLayer, data: &WavData, first_frame: u32, fps: f64, crossfade: impl Fn(f64) -> f32) {
+    let source_rate = data.sample_rate as f64;
+    let output_rate = mix.sample_rate as f64;
+    let source_channels = data.channels as usize;
+    let output_channels = mix.channels as usize;
+
+    // Playable source samples after trimming
+    let first = ((layer.trim_start as f64 * source_rate).round() as usize).min(data.frames());
+    let last = data.frames().saturating_sub((layer.trim_end as f64 * source_rate).round() as usize);
+    if last <= first {
+        return;
+    }
+    let window = (last - first) as f64;
+
+    // Frames from the layer's start to the mix's first sample
+    let lead = first_frame as f64 - layer.start_frame as f64;
+    let sample = |index: usize, channel: usize| -> f32 {
+        let frame = &data.samples[index * source_channels..(index + 1) * source_channels];
+        match (source_channels, output_channels) {
+            (2, 1) => (frame[0] + frame[1]) / 2.0,
+            _ => frame[channel.min(source_channels - 1)],
+        }
+    };
+
+    for (n, out) in mix.samples.chunks_exact_mut(output_channels).enumerate() {
+        // Kept as two products so whole-sample positions stay exact
+        let mut offset = lead * source_rate / fps + n as f64 * source_rate / output_rate;
+        if offset < 0.0 {
+            continue;
+        }
+        if offset >= window {
+            if !layer.loop_audio {
+                break;
+            }
+            offset %= window;
+        }
+
+        let position = first as f64 + offset;
+        let index = position as usize;
+        let fraction = (position - index as f64) as f32;
+        let next = match index + 1 {
+            next if next < last => next,
+            _ if layer.loop_audio => first,
+            _ => index,
+        };
+        // The envelope is keyed by frames since the clip started
+        let elapsed = lead + n as f64 * fps / output_rate;
+        let gain = layer.volume
+            * layer.volume_envelope.volume_at_frame((elapsed + 1e-9) as u32)
+            * crossfade(layer.start_frame as f64 + elapsed);
+
+        for (channel, out) in out.iter_mut().enumerate() {
+            let (a, b) = (sample(index, channel), sample(next, channel));
+            *out += (a + (b - a) * fraction) * gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSource;
+
+    fn source(sample_rate: u32, samples: Vec<f32>) -> (AudioLayer, Arc<WavData>) {
+        let data = WavData { sample_rate, channels: 1, bits_per_sample: 32, samples };
+        let mut source = AudioSource::new("clip.wav".into());
+        source.duration = data.duration();
+        (AudioLayer::new(source, 0), Arc::new(data))
+    }
+
+    fn settings(sample_rate: u32, channels: u16) -> MixdownSettings {
+        MixdownSettings { fps: 10.0, sample_rate, channels, bits_per_sample: 32 }
+    }
+
+    fn mix(layer: &AudioLayer, data: &Arc<WavData>, frames: Range<u32>, settings: &MixdownSettings) -> Vec<f32> {
+        mixdown([&AudioTrack::from(layer.clone())], |_| Some(data.clone()), frames, settings).unwrap().samples
+    }
+
+    #[test]
+    fn test_start_trim_and_volume() {
+        let (mut layer, data) = source(100, (0..30).map(|i| i as f32 / 32.0).collect());
+        layer.start_frame = 1;
+        layer.trim_start = 0.05;
+        layer.trim_end = 0.1;
+        layer.volume = 0.5;
+
+        let expected: Vec<f32> = (0..40)
+            .map(|n| if (10..25).contains(&n) { 0.5 * (n - 5) as f32 / 32.0 } else { 0.0 })
+            .collect();
+        assert_eq!(mix(&layer, &data, 0..4, &settings(100, 1)), expected);
+    }
+
+    #[test]
+    fn test_loop_and_envelope() {
+        let (mut layer, data) = source(40, vec![1.0, 0.5, -0.5, -1.0]);
+        layer.loop_audio = true;
+        layer.volume_envelope.set_point(2, 0.0);
+
+        let expected = [1.0, 0.5, -0.5, -1.0, 0.5, 0.25, -0.25, -0.5, 0.0, 0.0, 0.0, 0.0];
+        assert_eq!(mix(&layer, &data, 0..3, &settings(40, 1)), expected);
+    }
+
+    #[test]
+    fn test_resamples_into_stereo() {
+        let (layer, data) = source(40, vec![0.0, 0.5, -0.5, -1.0]);
+        let samples = mix(&layer, &data, 0..1, &settings(80, 2));
+        let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+        assert_eq!(left, [0.0, 0.25, 0.5, 0.0, -0.5, -0.75, -1.0, -1.0]);
+        assert!(samples.chunks(2).all(|frame| frame[0] == frame[1]));
+    }
+
+    #[test]
+    fn test_missing_source() {
+        let (layer, _) = source(40, vec![0.0]);
+        let result = mixdown([&AudioTrack::from(layer)], |_| None, 0..1, &settings(40, 1));
+        assert!(matches!(result, Err(AudioError::AudioNotLoaded)));
+    }
+
+    #[test]
+    fn test_crossfade_between_clips() {
+        let (first, data) = source(40, vec![1.0; 8]);
+        let mut second = first.clone();
+        second.start_frame = 1;
+        let mut track = AudioTrack::from(first);
+        track.add_clip(second, 10.0).unwrap();
+
+        // Over frame 1 the first clip falls as the second rises, summing to 1
+        let samples = mixdown([&track], |_| Some(data.clone()), 0..3, &settings(40, 1)).unwrap().samples;
+        assert_eq!(samples, [1.0; 12]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Piano roll note tracks
+//!
+//! A [`PianoRoll`] holds the notes of a layer. Each [`Note`] has a start
+//! frame, a length, a MIDI key and a velocity. The roll shows `visible_keys`
+//! keys from `lowest_key` and scrolls through the rest.
+//!
+//! As the playhead moves the controller sends note-on and note-off events to
+//! the engine (see
+//! [`TimelineController::play_notes`](crate::TimelineController::play_notes)),
+//! so a note can trigger an action of the layer's character.
+
+use serde::{Deserialize, Serialize};
+
+/// Height of one key row in the frame grid
+pub const KEY_HEIGHT: f32 = 8.0;
+
+/// A note from `frame` for `length` frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub frame: u32,
+    /// At least one frame
+    pub length: u32,
+    /// MIDI key, with middle C (60) as C4
+    pub key: u8,
+    /// 1 to 127
+    pub velocity: u8,
+}
+
+impl Note {
+    pub fn new(frame: u32, length: u32, key: u8, velocity: u8) -> Self {
+        Self { frame, length: length.max(1), key: key.min(127), velocity: velocity.clamp(1, 127) }
+    }
+
+    /// First frame after the note
+    pub fn end(&self) -> u32 {
+        self.frame + self.length
+    }
+
+    /// Whether the note sounds at `frame`
+    pub fn contains(&self, frame: u32) -> bool {
+        (self.frame..self.end()).contains(&frame)
+    }
+}
+
+/// Whether a key is a black key of the piano
+pub fn is_black_key(key: u8) -> bool {
+    matches!(key % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+/// The notes of a layer and the keys shown
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PianoRoll {
+    /// Sorted by frame, then key
+    pub notes: Vec<Note>,
+    /// Lowest key shown
+    pub lowest_key: u8,
+    /// Number of keys shown
+    pub visible_keys: u8,
+}
+
+impl Default for PianoRoll {
+    /// Two octaves from C3
+    fn default() -> Self {
+        Self { notes: Vec::new(), lowest_key: 48, visible_keys: 24 }
+    }
+}
+
+impl PianoRoll {
+    /// Height of the roll in the frame grid
+    pub fn height(&self) -> f32 {
+        self.visible_keys as f32 * KEY_HEIGHT
+    }
+
+    /// Highest key shown
+    pub fn highest_key(&self) -> u8 {
+        (self.lowest_key as u32 + self.visible_keys.max(1) as u32 - 1).min(127) as u8
+    }
+
+    /// Scroll the keys shown up (positive) or down, staying within 0..=127
+    pub fn scroll(&mut self, keys: i32) {
+        let top = 128 - self.visible_keys.clamp(1, 128) as i32;
+        self.lowest_key = (self.lowest_key as i32 + keys).clamp(0, top) as u8;
+    }
+
+    /// Add a note in order. Returns its index.
+    pub fn add_note(&mut self, note: Note) -> usize {
+        let index = self.notes.partition_point(|other| (other.frame, other.key) <= (note.frame, note.key));
+        self.notes.insert(index, note);
+        index
+    }
+
+    pub fn remove_note(&mut self, index: usize) -> Option<Note> {
+        (index < self.notes.len()).then(|| self.notes.remove(index))
+    }
+
+    /// Move a note to `frame` and `key`. Returns its new index.
+    pub fn move_note(&mut self, index: usize, frame: u32, key: u8) -> Option<usize> {
+        let note = self.remove_note(index)?;
+        Some(self.add_note(Note { frame, key: key.min(127), ..note }))
+    }
+
+    /// Set the length of a note, at least one frame
+    pub fn resize_note(&mut self, index: usize, length: u32) {
+        if let Some(note) = self.notes.get_mut(index) {
+            note.length = length.max(1);
+        }
+    }
+
+    pub fn set_velocity(&mut self, index: usize, velocity: u8) {
+        if let Some(note) = self.notes.get_mut(index) {
+            note.velocity = velocity.clamp(1, 127);
+        }
+    }
+
+    /// Index of the latest note on `key` sounding at `frame`
+    pub fn note_at(&self, frame: u32, key: u8) -> Option<usize> {
+        self.notes.iter().rposition(|note| note.key == key && note.contains(frame))
+    }
+
+    /// Notes sounding at `frame`
+    pub fn sounding(&self, frame: u32) -> impl Iterator<Item = &Note> + '_ {
+        self.notes.iter().filter(move |note| note.contains(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_editing() {
+        let mut roll = PianoRoll::default();
+        assert_eq!(roll.add_note(Note::new(10, 4, 60, 100)), 0);
+        assert_eq!(roll.add_note(Note::new(0, 0, 64, 200)), 0);
+        assert_eq!(roll.notes[0], Note::new(0, 1, 64, 127));
+        assert_eq!(roll.note_at(12, 60), Some(1));
+        assert_eq!(roll.note_at(14, 60), None);
+
+        // Moving keeps the notes in order
+        assert_eq!(roll.move_note(1, 0, 62), Some(0));
+        assert_eq!(roll.notes.iter().map(|note| note.key).collect::<Vec<_>>(), [62, 64]);
+        roll.resize_note(0, 0);
+        roll.set_velocity(0, 0);
+        assert_eq!(roll.notes[0], Note::new(0, 1, 62, 1));
+        assert_eq!(roll.sounding(0).count(), 2);
+        assert_eq!(roll.remove_note(5), None);
+    }
+
+    #[test]
+    fn test_key_scrolling() {
+        let mut roll = PianoRoll::default();
+        assert_eq!((roll.lowest_key, roll.highest_key()), (48, 71));
+        roll.scroll(100);
+        assert_eq!((roll.lowest_key, roll.highest_key()), (104, 127));
+        roll.scroll(-200);
+        assert_eq!(roll.lowest_key, 0);
+        assert!(is_black_key(61) && !is_black_key(60));
+    }
+}
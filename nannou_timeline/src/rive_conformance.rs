@@ -0,0 +1,302 @@
+//! Conformance checks for [`RiveEngine`] implementations.
+//!
+//! Each check drives a fresh engine through the [`RiveController`] API and
+//! asserts on what the engine reports back, both through
+//! [`RiveDataProvider`] and through the [`TimelineEventHandler`] it was given.
+//! A failed check panics with a message naming the broken expectation.
+//!
+//! Engine authors enable the `testkit` feature in their dev-dependencies and
+//! run the whole suite from a test of their own:
+//!
+//! ```ignore
+//! #[test]
+//! fn engine_conforms() {
+//!     nannou_timeline::rive_conformance::run_all(MyEngine::new);
+//! }
+//! ```
+//!
+//! The handler has no callback for moves, so a moved keyframe is reported as
+//! `on_keyframe_removed` followed by `on_keyframe_added` with the same id.
+
+use crate::engine_host::{ChannelEventHandler, EngineEvent};
+use crate::rive_integration::{
+    EasingFunction, KeyframeType, LayerHierarchyNode, RiveConfig, RiveEngine, RiveError, TweenId, TweenType,
+};
+use crate::{KeyframeId, LayerId};
+use std::sync::mpsc::{self, Receiver};
+
+/// Run every check, each on a new engine from `make_engine`
+pub fn run_all<E: RiveEngine>(mut make_engine: impl FnMut() -> E) {
+    check_playback(&mut make_engine());
+    check_layers(&mut make_engine());
+    check_keyframes(&mut make_engine());
+    check_frame_edits(&mut make_engine());
+    check_tweens(&mut make_engine());
+    check_layer_hierarchy(&make_engine());
+    check_initialize(&mut make_engine());
+}
+
+/// Install a handler on `engine` and return the events it receives
+pub fn record_events(engine: &mut dyn RiveEngine) -> Receiver<EngineEvent> {
+    let (sender, receiver) = mpsc::channel();
+    engine.set_event_handler(Box::new(ChannelEventHandler::new(sender)));
+    receiver
+}
+
+fn drain(events: &Receiver<EngineEvent>) -> Vec<EngineEvent> {
+    events.try_iter().collect()
+}
+
+/// Play, pause, stop and seek, including seeking past the end
+pub fn check_playback(engine: &mut dyn RiveEngine) {
+    let events = record_events(engine);
+    let last_frame = engine.get_total_frames().saturating_sub(1);
+
+    engine.play();
+    assert!(engine.is_playing(), "is_playing() should be true after play()");
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::PlaybackStarted]),
+        "play() should fire on_playback_started"
+    );
+
+    engine.pause();
+    assert!(!engine.is_playing(), "is_playing() should be false after pause()");
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::PlaybackStopped]),
+        "pause() should fire on_playback_stopped"
+    );
+
+    engine.seek_to_frame(1);
+    assert_eq!(engine.get_current_frame(), 1);
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::FrameChanged { frame: 1 }]),
+        "seek_to_frame() should fire on_frame_changed"
+    );
+
+    engine.seek_to_frame(u32::MAX);
+    assert_eq!(engine.get_current_frame(), last_frame, "seeking past the end should clamp to the last frame");
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::FrameChanged { frame }] if *frame == last_frame),
+        "on_frame_changed should report the clamped frame"
+    );
+
+    engine.play();
+    engine.stop();
+    assert!(!engine.is_playing(), "is_playing() should be false after stop()");
+    assert_eq!(engine.get_current_frame(), 0, "stop() should rewind to the first frame");
+    let stopped = drain(&events);
+    assert!(stopped.iter().any(|e| matches!(e, EngineEvent::PlaybackStopped)), "stop() should fire on_playback_stopped");
+    assert!(
+        matches!(stopped.last(), Some(EngineEvent::FrameChanged { frame: 0 })),
+        "stop() should fire on_frame_changed for frame 0"
+    );
+
+    // Engines that can't change fps at runtime must say so
+    let fps = engine.get_fps();
+    engine.set_fps(fps * 2.0);
+    if engine.get_fps() == fps {
+        assert!(
+            drain(&events).iter().any(|e| matches!(e, EngineEvent::AnimationError { .. })),
+            "an unapplied set_fps() should fire on_animation_error"
+        );
+    }
+}
+
+/// Add, rename, hide, lock, reorder and remove layers
+pub fn check_layers(engine: &mut dyn RiveEngine) {
+    let events = record_events(engine);
+
+    let layer_id = engine.add_layer("Conformance");
+    let layer = engine.get_layers().into_iter().find(|l| l.id == layer_id).expect("added layer is listed");
+    assert_eq!(layer.name, "Conformance");
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::LayerAdded { layer }] if layer.id == layer_id),
+        "add_layer() should fire on_layer_added"
+    );
+
+    engine.rename_layer(layer_id.clone(), "Renamed".to_string());
+    engine.set_layer_visibility(layer_id.clone(), false);
+    engine.set_layer_locked(layer_id.clone(), true);
+    let layer = engine.get_layers().into_iter().find(|l| l.id == layer_id).expect("layer is still listed");
+    assert_eq!(layer.name, "Renamed");
+    assert!(!layer.visible, "set_layer_visibility(false) should hide the layer");
+    assert!(layer.locked, "set_layer_locked(true) should lock the layer");
+    drain(&events);
+
+    let reversed: Vec<LayerId> = engine.get_layers().into_iter().rev().map(|l| l.id).collect();
+    engine.reorder_layers(reversed.clone());
+    let order: Vec<LayerId> = engine.get_layers().into_iter().map(|l| l.id).collect();
+    assert_eq!(order, reversed, "reorder_layers() should apply the given order");
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::LayerReordered { layer_ids }] if *layer_ids == reversed),
+        "reorder_layers() should fire on_layer_reordered with the new order"
+    );
+
+    engine.remove_layer(layer_id.clone());
+    assert!(engine.get_layers().iter().all(|l| l.id != layer_id), "removed layer is still listed");
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::LayerRemoved { layer_id: removed }] if *removed == layer_id),
+        "remove_layer() should fire on_layer_removed"
+    );
+}
+
+/// Frames of a layer without a keyframe, excluding frame 0
+fn free_frames(engine: &dyn RiveEngine, layer_id: &LayerId, count: usize) -> Vec<u32> {
+    let keyframes: Vec<u32> = engine.get_keyframes(layer_id.clone()).iter().map(|k| k.frame).collect();
+    let frames: Vec<u32> = (1..engine.get_total_frames()).filter(|f| !keyframes.contains(f)).take(count).collect();
+    assert_eq!(frames.len(), count, "layer has too few frames to test with");
+    frames
+}
+
+fn keyframe_at(engine: &dyn RiveEngine, layer_id: &LayerId, keyframe_id: &KeyframeId) -> Option<u32> {
+    engine.get_keyframes(layer_id.clone()).iter().find(|k| k.id == *keyframe_id).map(|k| k.frame)
+}
+
+fn added_keyframe(events: &[EngineEvent], layer: &LayerId, at: u32) -> Option<KeyframeId> {
+    events.iter().find_map(|event| match event {
+        EngineEvent::KeyframeAdded { layer_id, frame, keyframe_id } if layer_id == layer && *frame == at => {
+            Some(keyframe_id.clone())
+        }
+        _ => None,
+    })
+}
+
+fn removed_keyframe(events: &[EngineEvent], id: &KeyframeId) -> bool {
+    events.iter().any(|e| matches!(e, EngineEvent::KeyframeRemoved { keyframe_id } if keyframe_id == id))
+}
+
+/// Add, move, copy, paste and remove a keyframe, keeping its id throughout
+pub fn check_keyframes(engine: &mut dyn RiveEngine) {
+    let events = record_events(engine);
+    let layer_id = engine.add_layer("Keyframes");
+    let [first, second, third] = free_frames(engine, &layer_id, 3)[..] else { unreachable!() };
+    drain(&events);
+
+    let keyframe_id = engine.add_keyframe(layer_id.clone(), first);
+    assert_eq!(keyframe_at(engine, &layer_id, &keyframe_id), Some(first), "added keyframe is listed");
+    assert_eq!(
+        added_keyframe(&drain(&events), &layer_id, first),
+        Some(keyframe_id.clone()),
+        "add_keyframe() should fire on_keyframe_added with the returned id"
+    );
+
+    engine.move_keyframe(keyframe_id.clone(), second);
+    assert_eq!(keyframe_at(engine, &layer_id, &keyframe_id), Some(second), "moved keyframe keeps its id");
+    let moved = drain(&events);
+    assert!(removed_keyframe(&moved, &keyframe_id), "move_keyframe() should fire on_keyframe_removed");
+    assert_eq!(
+        added_keyframe(&moved, &layer_id, second),
+        Some(keyframe_id.clone()),
+        "move_keyframe() should fire on_keyframe_added at the new frame"
+    );
+
+    let data = engine.copy_keyframe(keyframe_id.clone()).expect("copy_keyframe() returns the keyframe's data");
+    assert_ne!(data.keyframe_type, KeyframeType::Empty);
+    engine.paste_keyframe(layer_id.clone(), third, data);
+    let pasted = added_keyframe(&drain(&events), &layer_id, third).expect("paste_keyframe() should fire on_keyframe_added");
+    assert_ne!(pasted, keyframe_id, "a pasted keyframe gets a new id");
+    assert_eq!(keyframe_at(engine, &layer_id, &pasted), Some(third));
+
+    engine.remove_keyframe(keyframe_id.clone());
+    assert_eq!(keyframe_at(engine, &layer_id, &keyframe_id), None, "removed keyframe is still listed");
+    assert!(removed_keyframe(&drain(&events), &keyframe_id), "remove_keyframe() should fire on_keyframe_removed");
+
+    engine.remove_keyframe(keyframe_id.clone());
+    assert!(
+        matches!(
+            drain(&events).as_slice(),
+            [EngineEvent::AnimationError { error: RiveError::KeyframeNotFound { keyframe_id: missing } }] if *missing == keyframe_id
+        ),
+        "removing an unknown keyframe should fire on_animation_error"
+    );
+    assert!(engine.copy_keyframe(keyframe_id).is_none(), "copying an unknown keyframe should return None");
+}
+
+/// Inserting and removing frames shifts later keyframes without changing their ids
+pub fn check_frame_edits(engine: &mut dyn RiveEngine) {
+    let layer_id = engine.add_layer("Frames");
+    let frame = free_frames(engine, &layer_id, 1)[0];
+    let keyframe_id = engine.add_keyframe(layer_id.clone(), frame);
+
+    engine.insert_frame(layer_id.clone(), frame);
+    assert_eq!(keyframe_at(engine, &layer_id, &keyframe_id), Some(frame + 1), "insert_frame() should push the keyframe back");
+
+    engine.remove_frame(layer_id.clone(), frame);
+    assert_eq!(keyframe_at(engine, &layer_id, &keyframe_id), Some(frame), "remove_frame() should pull the keyframe forward");
+}
+
+/// Create a tween, change its easing and remove it
+pub fn check_tweens(engine: &mut dyn RiveEngine) {
+    let events = record_events(engine);
+    let layer_id = engine.add_layer("Tweens");
+    let [start, end] = free_frames(engine, &layer_id, 2)[..] else { unreachable!() };
+    let keyframe_id = engine.add_keyframe(layer_id.clone(), start);
+
+    engine.create_tween(layer_id.clone(), start, end, TweenType::Motion);
+    let tweens = engine.get_tweens(layer_id.clone());
+    let [tween] = tweens.as_slice() else { panic!("create_tween() should add one tween, got {:?}", tweens) };
+    assert_eq!((tween.start_frame, tween.end_frame, tween.tween_type), (start, end, TweenType::Motion));
+    let keyframe = engine.get_keyframes(layer_id.clone()).into_iter().find(|k| k.id == keyframe_id).expect("keyframe is listed");
+    assert_eq!(keyframe.tween_id, Some(tween.id), "the keyframe starting a tween should reference it");
+
+    engine.set_easing(tween.id, EasingFunction::EaseIn);
+    assert!(matches!(engine.get_tweens(layer_id.clone())[0].easing, EasingFunction::EaseIn), "set_easing() should apply");
+
+    engine.remove_tween(tween.id);
+    assert!(engine.get_tweens(layer_id.clone()).is_empty(), "remove_tween() should drop the tween");
+    drain(&events);
+
+    let unknown = TweenId::new();
+    engine.set_easing(unknown, EasingFunction::Linear);
+    assert!(
+        matches!(drain(&events).as_slice(), [EngineEvent::AnimationError { .. }]),
+        "easing an unknown tween should fire on_animation_error"
+    );
+}
+
+/// The hierarchy lists every layer once, under its parent
+pub fn check_layer_hierarchy(engine: &dyn RiveEngine) {
+    fn visit(node: &LayerHierarchyNode, parent: Option<&LayerId>, depth: u32, seen: &mut Vec<LayerId>) {
+        assert_eq!(node.layer_info.parent_id.as_ref(), parent, "{:?} is listed under the wrong parent", node.layer_info.id);
+        assert_eq!(node.depth, depth, "{:?} has the wrong depth", node.layer_info.id);
+        seen.push(node.layer_info.id.clone());
+        for child in &node.children {
+            visit(child, Some(&node.layer_info.id), depth + 1, seen);
+        }
+    }
+
+    let mut seen = Vec::new();
+    for root in engine.get_layer_hierarchy() {
+        visit(&root, None, 0, &mut seen);
+    }
+    let mut expected: Vec<LayerId> = engine.get_layers().into_iter().map(|l| l.id).collect();
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    seen.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(seen, expected, "the hierarchy should list every layer exactly once");
+}
+
+/// initialize() succeeds and describes the loaded animation
+pub fn check_initialize(engine: &mut dyn RiveEngine) {
+    let events = record_events(engine);
+    engine.initialize(RiveConfig::default()).expect("initialize() with the default config");
+
+    let events = drain(&events);
+    let [EngineEvent::AnimationLoaded { animation_info }] = events.as_slice() else {
+        panic!("initialize() should fire on_animation_loaded, got {:?}", events);
+    };
+    assert_eq!(animation_info.duration_frames, engine.get_total_frames());
+    assert_eq!(animation_info.fps, engine.get_fps());
+    assert_eq!(animation_info.layer_count, engine.get_layers().len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rive_integration::LegacyRiveEngineAdapter;
+    use crate::ui::MockRiveEngine;
+
+    #[test]
+    fn test_legacy_adapter_conforms() {
+        run_all(|| LegacyRiveEngineAdapter::new(MockRiveEngine::new()));
+    }
+}
@@ -5,6 +5,8 @@
 
 use crate::{LayerId, KeyframeId, layer::LayerInfo};
 use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
 
 /// Commands that the timeline sends to the Rive engine for animation control
 pub trait RiveController: Send + Sync {
@@ -268,6 +270,9 @@ pub enum RiveError {
     
     #[error("Keyframe not found: {keyframe_id:?}")]
     KeyframeNotFound { keyframe_id: KeyframeId },
+
+    #[error("Tween not found: {tween_id:?}")]
+    TweenNotFound { tween_id: TweenId },
     
     #[error("Invalid frame number: {frame} (max: {max_frame})")]
     InvalidFrame { frame: u32, max_frame: u32 },
@@ -280,6 +285,9 @@ pub enum RiveError {
     
     #[error("Rive runtime error: {error}")]
     RiveRuntimeError { error: String },
+
+    #[error("Engine thread has stopped")]
+    EngineStopped,
 }
 
 // ================== Default Implementations ==================
@@ -339,12 +347,59 @@ impl From<BezierCurve> for crate::easing::BezierCurve {
 
 // ================== Legacy Adapter ==================
 
+/// Keyframe ids the adapter hands out for a legacy engine, which only knows
+/// keyframes by layer and frame
+#[derive(Default)]
+struct KeyframeIds {
+    layers: HashMap<LayerId, BTreeMap<u32, KeyframeId>>,
+}
+
+impl KeyframeIds {
+    fn position(&self, keyframe_id: &KeyframeId) -> Option<(LayerId, u32)> {
+        self.layers.iter().find_map(|(layer_id, frames)| {
+            frames.iter()
+                .find(|(_, id)| *id == keyframe_id)
+                .map(|(frame, _)| (layer_id.clone(), *frame))
+        })
+    }
+
+    /// Shift the ids at or after `frame` by `delta` frames. Ids pushed
+    /// before `frame` or past `total_frames` are dropped and returned.
+    fn shift(&mut self, layer_id: &LayerId, frame: u32, delta: i64, total_frames: u32) -> Vec<KeyframeId> {
+        let Some(frames) = self.layers.get_mut(layer_id) else { return Vec::new() };
+        let moved = frames.split_off(&frame);
+        let mut dropped = Vec::new();
+        for (old, id) in moved {
+            let new = old as i64 + delta;
+            if new < frame as i64 || new >= total_frames as i64 {
+                dropped.push(id);
+            } else {
+                frames.insert(new as u32, id);
+            }
+        }
+        dropped
+    }
+}
+
+/// Event waiting to be sent once the id table lock is released
+enum KeyframeChange {
+    Added(LayerId, u32, KeyframeId),
+    Removed(KeyframeId),
+}
+
 /// Adapter that implements the new interface using the legacy RiveEngine trait
+///
+/// The legacy engine addresses keyframes by layer and frame and has no tween
+/// objects, so the adapter keeps stable [`KeyframeId`]s and a tween table
+/// alongside it. Ids are assigned to a layer's existing keyframes the first
+/// time the layer is read or edited; after every edit the layer is rescanned
+/// and `on_keyframe_added`/`on_keyframe_removed` fire for each difference.
 pub struct LegacyRiveEngineAdapter<T: crate::RiveEngine> {
     engine: T,
     event_handler: Option<Box<dyn TimelineEventHandler>>,
-    keyframe_counter: std::sync::atomic::AtomicU32,
-    tween_counter: std::sync::atomic::AtomicU32,
+    keyframe_ids: Mutex<KeyframeIds>,
+    tweens: HashMap<TweenId, TweenInfo>,
+    is_playing: bool,
 }
 
 impl<T: crate::RiveEngine> LegacyRiveEngineAdapter<T> {
@@ -352,67 +407,180 @@ impl<T: crate::RiveEngine> LegacyRiveEngineAdapter<T> {
         Self {
             engine,
             event_handler: None,
-            keyframe_counter: std::sync::atomic::AtomicU32::new(0),
-            tween_counter: std::sync::atomic::AtomicU32::new(0),
+            keyframe_ids: Mutex::new(KeyframeIds::default()),
+            tweens: HashMap::new(),
+            is_playing: false,
         }
     }
-    
-    fn next_keyframe_id(&self) -> KeyframeId {
-        let _id = self.keyframe_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        KeyframeId::new()
+
+    pub fn engine(&self) -> &T {
+        &self.engine
     }
-    
-    fn next_tween_id(&self) -> TweenId {
-        let _id = self.tween_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        TweenId::new()
+
+    pub fn engine_mut(&mut self) -> &mut T {
+        &mut self.engine
+    }
+
+    pub fn into_inner(self) -> T {
+        self.engine
+    }
+
+    fn notify(&mut self, event: impl FnOnce(&mut dyn TimelineEventHandler)) {
+        if let Some(handler) = &mut self.event_handler {
+            event(handler.as_mut());
+        }
+    }
+
+    fn ids(&self) -> std::sync::MutexGuard<'_, KeyframeIds> {
+        self.keyframe_ids.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Frames of `layer_id` that hold a keyframe in the legacy engine
+    fn scan_keyframes(&self, layer_id: &LayerId) -> Vec<u32> {
+        (0..self.engine.get_total_frames())
+            .filter(|&frame| {
+                self.engine.get_frame_data(layer_id.clone(), frame).frame_type == crate::frame::FrameType::Keyframe
+            })
+            .collect()
+    }
+
+    /// Assign ids to a layer's keyframes the first time it is seen, without events
+    fn track(&self, layer_id: &LayerId) {
+        if self.ids().layers.contains_key(layer_id) {
+            return;
+        }
+        let frames = self.scan_keyframes(layer_id)
+            .into_iter()
+            .map(|frame| (frame, KeyframeId::new()))
+            .collect();
+        self.ids().layers.entry(layer_id.clone()).or_insert(frames);
+    }
+
+    fn keyframe_id_at(&self, layer_id: &LayerId, frame: u32) -> Option<KeyframeId> {
+        self.track(layer_id);
+        self.ids().layers.get(layer_id).and_then(|frames| frames.get(&frame).cloned())
+    }
+
+    /// Rescan a tracked layer after an edit and report keyframes that appeared
+    /// or disappeared
+    fn sync(&mut self, layer_id: &LayerId) {
+        let current = self.scan_keyframes(layer_id);
+        let mut changes = Vec::new();
+        {
+            let mut ids = self.ids();
+            let frames = ids.layers.entry(layer_id.clone()).or_default();
+            frames.retain(|frame, id| {
+                let keep = current.contains(frame);
+                if !keep {
+                    changes.push(KeyframeChange::Removed(id.clone()));
+                }
+                keep
+            });
+            for frame in current {
+                frames.entry(frame).or_insert_with(|| {
+                    let id = KeyframeId::new();
+                    changes.push(KeyframeChange::Added(layer_id.clone(), frame, id.clone()));
+                    id
+                });
+            }
+        }
+        self.fire(changes);
+    }
+
+    fn fire(&mut self, changes: Vec<KeyframeChange>) {
+        for change in changes {
+            match change {
+                KeyframeChange::Added(layer_id, frame, id) => self.notify(|h| h.on_keyframe_added(layer_id, frame, id)),
+                KeyframeChange::Removed(id) => self.notify(|h| h.on_keyframe_removed(id)),
+            }
+        }
+    }
+
+    /// Move keyframe ids and tweens at or after `frame` by `delta` frames
+    fn shift_frames(&mut self, layer_id: &LayerId, frame: u32, delta: i64) {
+        let total_frames = self.engine.get_total_frames();
+        let dropped = self.ids().shift(layer_id, frame, delta, total_frames);
+        self.fire(dropped.into_iter().map(KeyframeChange::Removed).collect());
+
+        let shift = |f: u32| if f >= frame { (f as i64 + delta).max(frame as i64) as u32 } else { f };
+        self.tweens.retain(|_, tween| {
+            if tween.layer_id == *layer_id {
+                tween.start_frame = shift(tween.start_frame);
+                tween.end_frame = shift(tween.end_frame);
+            }
+            tween.start_frame < tween.end_frame
+        });
+    }
+
+    fn tween_starting_at(&self, layer_id: &LayerId, frame: u32) -> Option<&TweenInfo> {
+        self.tweens.values().find(|t| t.layer_id == *layer_id && t.start_frame == frame)
+    }
+
+    fn hierarchy_nodes(layers: &[LayerInfo], parent: Option<&LayerId>, depth: u32) -> Vec<LayerHierarchyNode> {
+        layers.iter()
+            .filter(|layer| layer.parent_id.as_ref() == parent)
+            .map(|layer| LayerHierarchyNode {
+                layer_info: layer.clone(),
+                children: Self::hierarchy_nodes(layers, Some(&layer.id), depth + 1),
+                depth,
+            })
+            .collect()
     }
 }
 
 impl<T: crate::RiveEngine> RiveController for LegacyRiveEngineAdapter<T> {
     fn play(&mut self) {
         self.engine.play();
-        if let Some(handler) = &mut self.event_handler {
-            handler.on_playback_started();
-        }
+        self.is_playing = true;
+        self.notify(|h| h.on_playback_started());
     }
     
     fn pause(&mut self) {
         self.engine.pause();
-        if let Some(handler) = &mut self.event_handler {
-            handler.on_playback_stopped();
-        }
+        self.is_playing = false;
+        self.notify(|h| h.on_playback_stopped());
     }
     
     fn stop(&mut self) {
-        self.pause(); // Legacy engine doesn't distinguish stop from pause
+        // Legacy engine has no stop; pause and rewind instead
+        self.pause();
+        self.seek_to_frame(0);
     }
     
     fn seek_to_frame(&mut self, frame: u32) {
         self.engine.seek(frame);
-        if let Some(handler) = &mut self.event_handler {
-            handler.on_frame_changed(frame);
-        }
+        let frame = self.engine.get_current_frame();
+        self.notify(|h| h.on_frame_changed(frame));
     }
     
-    fn set_fps(&mut self, _fps: f32) {
-        // Legacy engine doesn't support runtime FPS changes
-        eprintln!("Warning: Legacy RiveEngine doesn't support runtime FPS changes");
+    fn set_fps(&mut self, fps: f32) {
+        if fps != self.engine.get_fps() {
+            let error = RiveError::OperationFailed {
+                message: "Legacy RiveEngine doesn't support runtime FPS changes".to_string(),
+            };
+            self.notify(|h| h.on_animation_error(error));
+        }
     }
     
     fn add_layer(&mut self, name: &str) -> LayerId {
-        self.engine.add_layer(name.to_string(), crate::layer::LayerType::Normal)
+        let layer_id = self.engine.add_layer(name.to_string(), crate::layer::LayerType::Normal);
+        if let Some(layer) = self.engine.get_layers().into_iter().find(|l| l.id == layer_id) {
+            self.notify(|h| h.on_layer_added(layer));
+        }
+        layer_id
     }
     
     fn remove_layer(&mut self, layer_id: LayerId) {
-        let layer_id_clone = layer_id.clone();
-        self.engine.delete_layer(layer_id);
-        if let Some(handler) = &mut self.event_handler {
-            handler.on_layer_removed(layer_id_clone);
-        }
+        self.engine.delete_layer(layer_id.clone());
+        self.ids().layers.remove(&layer_id);
+        self.tweens.retain(|_, tween| tween.layer_id != layer_id);
+        self.notify(|h| h.on_layer_removed(layer_id));
     }
     
-    fn reorder_layers(&mut self, _layer_ids: Vec<LayerId>) {
-        eprintln!("Warning: Legacy RiveEngine doesn't support layer reordering");
+    fn reorder_layers(&mut self, layer_ids: Vec<LayerId>) {
+        self.engine.reorder_layers(layer_ids);
+        let order = self.engine.get_layers().into_iter().map(|l| l.id).collect();
+        self.notify(|h| h.on_layer_reordered(order));
     }
     
     fn set_layer_visibility(&mut self, layer_id: LayerId, visible: bool) {
@@ -428,62 +596,140 @@ impl<T: crate::RiveEngine> RiveController for LegacyRiveEngineAdapter<T> {
     }
     
     fn add_keyframe(&mut self, layer_id: LayerId, frame: u32) -> KeyframeId {
+        self.track(&layer_id);
         self.engine.insert_keyframe(layer_id.clone(), frame);
-        let keyframe_id = self.next_keyframe_id();
-        if let Some(handler) = &mut self.event_handler {
-            handler.on_keyframe_added(layer_id, frame, keyframe_id.clone());
-        }
-        keyframe_id
+        self.sync(&layer_id);
+        // Frames past the end are ignored by the engine; the id then refers to nothing
+        self.keyframe_id_at(&layer_id, frame).unwrap_or_else(KeyframeId::new)
     }
     
     fn remove_keyframe(&mut self, keyframe_id: KeyframeId) {
-        // Legacy engine doesn't track keyframe IDs, so this is a no-op
-        eprintln!("Warning: Legacy RiveEngine doesn't support keyframe ID-based removal");
-        if let Some(handler) = &mut self.event_handler {
-            handler.on_keyframe_removed(keyframe_id);
+        let Some((layer_id, frame)) = self.ids().position(&keyframe_id) else {
+            self.notify(|h| h.on_animation_error(RiveError::KeyframeNotFound { keyframe_id }));
+            return;
+        };
+        self.engine.delete_keyframe(layer_id.clone(), frame);
+        self.sync(&layer_id);
+    }
+    
+    fn move_keyframe(&mut self, keyframe_id: KeyframeId, new_frame: u32) {
+        let Some((layer_id, frame)) = self.ids().position(&keyframe_id) else {
+            self.notify(|h| h.on_animation_error(RiveError::KeyframeNotFound { keyframe_id }));
+            return;
+        };
+        if frame == new_frame {
+            return;
         }
+        self.engine.move_keyframe(layer_id.clone(), frame, new_frame);
+
+        // The moved keyframe keeps its id; one it replaces is removed
+        let replaced = {
+            let mut ids = self.ids();
+            let frames = ids.layers.entry(layer_id.clone()).or_default();
+            frames.remove(&frame);
+            frames.insert(new_frame, keyframe_id.clone())
+        };
+        if let Some(replaced) = replaced {
+            self.notify(|h| h.on_keyframe_removed(replaced));
+        }
+        let id = keyframe_id.clone();
+        self.notify(|h| h.on_keyframe_removed(id));
+        self.notify(|h| h.on_keyframe_added(layer_id.clone(), new_frame, keyframe_id));
+        for tween in self.tweens.values_mut() {
+            if tween.layer_id == layer_id && tween.start_frame == frame {
+                tween.end_frame = new_frame + (tween.end_frame - tween.start_frame);
+                tween.start_frame = new_frame;
+            }
+        }
+        self.sync(&layer_id);
+    }
+    
+    fn copy_keyframe(&mut self, keyframe_id: KeyframeId) -> Option<KeyframeData> {
+        let (layer_id, frame) = self.ids().position(&keyframe_id)?;
+        let data = self.engine.copy_keyframe(layer_id.clone(), frame)?;
+        Some(KeyframeData {
+            keyframe_type: if data.has_content { KeyframeType::Keyframe } else { KeyframeType::BlankKeyframe },
+            properties: std::collections::HashMap::new(),
+            tween_data: self.tween_starting_at(&layer_id, frame).map(|tween| TweenData {
+                tween_type: tween.tween_type,
+                duration_frames: tween.end_frame - tween.start_frame,
+                easing: tween.easing.clone(),
+            }),
+        })
     }
     
-    fn move_keyframe(&mut self, _keyframe_id: KeyframeId, _new_frame: u32) {
-        eprintln!("Warning: Legacy RiveEngine doesn't support keyframe ID-based operations");
-    }
-    
-    fn copy_keyframe(&mut self, _keyframe_id: KeyframeId) -> Option<KeyframeData> {
-        eprintln!("Warning: Legacy RiveEngine doesn't support keyframe ID-based copy");
-        None
-    }
-    
-    fn paste_keyframe(&mut self, layer_id: LayerId, frame: u32, _data: KeyframeData) {
-        // Convert to legacy operation
-        self.engine.insert_keyframe(layer_id, frame);
+    fn paste_keyframe(&mut self, layer_id: LayerId, frame: u32, data: KeyframeData) {
+        self.track(&layer_id);
+        let frame_type = match data.keyframe_type {
+            KeyframeType::Empty => crate::frame::FrameType::Empty,
+            KeyframeType::Keyframe | KeyframeType::BlankKeyframe => crate::frame::FrameType::Keyframe,
+        };
+        self.engine.paste_keyframe(layer_id.clone(), frame, crate::frame::FrameData {
+            frame_number: frame,
+            frame_type,
+            has_content: data.keyframe_type == KeyframeType::Keyframe,
+            id: KeyframeId::new(),
+        });
+        self.sync(&layer_id);
+        if let Some(tween) = data.tween_data {
+            self.create_tween(layer_id.clone(), frame, frame + tween.duration_frames, tween.tween_type);
+            if let Some(id) = self.tween_starting_at(&layer_id, frame).map(|t| t.id) {
+                self.set_easing(id, tween.easing);
+            }
+        }
     }
     
     fn insert_frame(&mut self, layer_id: LayerId, frame: u32) {
-        self.engine.insert_frame(layer_id, frame);
+        self.track(&layer_id);
+        self.engine.insert_frame(layer_id.clone(), frame);
+        self.shift_frames(&layer_id, frame, 1);
+        self.sync(&layer_id);
     }
     
     fn remove_frame(&mut self, layer_id: LayerId, frame: u32) {
-        self.engine.remove_frame(layer_id, frame);
+        self.track(&layer_id);
+        self.engine.remove_frame(layer_id.clone(), frame);
+        self.shift_frames(&layer_id, frame, -1);
+        self.sync(&layer_id);
     }
     
     fn extend_frame(&mut self, layer_id: LayerId, frame: u32) {
-        self.engine.insert_frame(layer_id, frame);
+        self.insert_frame(layer_id, frame);
     }
     
-    fn create_tween(&mut self, layer_id: LayerId, start_frame: u32, _end_frame: u32, tween_type: TweenType) {
+    fn create_tween(&mut self, layer_id: LayerId, start_frame: u32, end_frame: u32, tween_type: TweenType) {
+        self.track(&layer_id);
         match tween_type {
-            TweenType::Motion => self.engine.create_motion_tween(layer_id, start_frame),
-            TweenType::Shape => self.engine.create_shape_tween(layer_id, start_frame),
-            TweenType::Classic => self.engine.create_motion_tween(layer_id, start_frame), // Fallback
+            TweenType::Motion => self.engine.create_motion_tween(layer_id.clone(), start_frame),
+            TweenType::Shape => self.engine.create_shape_tween(layer_id.clone(), start_frame),
+            TweenType::Classic => self.engine.create_motion_tween(layer_id.clone(), start_frame), // Fallback
+        }
+        // A tween replaces any other tween starting on the same frame
+        self.tweens.retain(|_, t| !(t.layer_id == layer_id && t.start_frame == start_frame));
+        let tween = TweenInfo {
+            id: TweenId::new(),
+            layer_id: layer_id.clone(),
+            start_frame,
+            end_frame,
+            tween_type,
+            easing: EasingFunction::default(),
+        };
+        self.tweens.insert(tween.id, tween);
+        self.sync(&layer_id);
+    }
+    
+    fn set_easing(&mut self, tween_id: TweenId, easing: EasingFunction) {
+        match self.tweens.get_mut(&tween_id) {
+            Some(tween) => tween.easing = easing,
+            None => self.notify(|h| h.on_animation_error(RiveError::TweenNotFound { tween_id })),
         }
     }
     
-    fn set_easing(&mut self, _tween_id: TweenId, _easing: EasingFunction) {
-        eprintln!("Warning: Legacy RiveEngine doesn't support easing configuration");
-    }
-    
-    fn remove_tween(&mut self, _tween_id: TweenId) {
-        eprintln!("Warning: Legacy RiveEngine doesn't support tween removal");
+    fn remove_tween(&mut self, tween_id: TweenId) {
+        // Legacy engine has no tween removal; the frames keep their content
+        if self.tweens.remove(&tween_id).is_none() {
+            self.notify(|h| h.on_animation_error(RiveError::TweenNotFound { tween_id }));
+        }
     }
 }
 
@@ -492,16 +738,33 @@ impl<T: crate::RiveEngine> RiveDataProvider for LegacyRiveEngineAdapter<T> {
         self.engine.get_layers()
     }
     
-    fn get_keyframes(&self, _layer_id: LayerId) -> Vec<KeyframeInfo> {
-        // Legacy engine doesn't have a way to enumerate keyframes
-        // Return empty for now
-        Vec::new()
+    fn get_keyframes(&self, layer_id: LayerId) -> Vec<KeyframeInfo> {
+        self.track(&layer_id);
+        let frames = self.ids().layers.get(&layer_id).cloned().unwrap_or_default();
+        frames.into_iter()
+            .map(|(frame, id)| {
+                let data = self.engine.get_frame_data(layer_id.clone(), frame);
+                let tween_id = self.tween_starting_at(&layer_id, frame).map(|t| t.id);
+                KeyframeInfo {
+                    id,
+                    layer_id: layer_id.clone(),
+                    frame,
+                    keyframe_type: if data.has_content { KeyframeType::Keyframe } else { KeyframeType::BlankKeyframe },
+                    has_tween: tween_id.is_some(),
+                    tween_id,
+                }
+            })
+            .collect()
     }
     
     fn get_frame_data(&self, layer_id: LayerId, frame: u32) -> Option<RiveFrameData> {
-        let data = self.engine.get_frame_data(layer_id, frame);
+        let data = self.engine.get_frame_data(layer_id.clone(), frame);
+        let id = match data.frame_type {
+            crate::frame::FrameType::Keyframe => self.keyframe_id_at(&layer_id, frame).unwrap_or(data.id),
+            _ => data.id,
+        };
         Some(RiveFrameData {
-            id: data.id,
+            id,
             frame_number: data.frame_number,
             frame_type: match data.frame_type {
                 crate::frame::FrameType::Empty => KeyframeType::Empty,
@@ -529,24 +792,21 @@ impl<T: crate::RiveEngine> RiveDataProvider for LegacyRiveEngineAdapter<T> {
     }
     
     fn get_layer_hierarchy(&self) -> Vec<LayerHierarchyNode> {
-        // Convert flat layer list to hierarchy
-        self.engine.get_layers().into_iter()
-            .map(|layer| LayerHierarchyNode {
-                layer_info: layer,
-                children: Vec::new(),
-                depth: 0,
-            })
-            .collect()
+        let layers = self.engine.get_layers();
+        Self::hierarchy_nodes(&layers, None, 0)
     }
     
-    fn get_tweens(&self, _layer_id: LayerId) -> Vec<TweenInfo> {
-        // Legacy engine doesn't track tweens separately
-        Vec::new()
+    fn get_tweens(&self, layer_id: LayerId) -> Vec<TweenInfo> {
+        let mut tweens: Vec<TweenInfo> = self.tweens.values()
+            .filter(|tween| tween.layer_id == layer_id)
+            .cloned()
+            .collect();
+        tweens.sort_by_key(|tween| tween.start_frame);
+        tweens
     }
     
     fn is_playing(&self) -> bool {
-        // Legacy engine doesn't track playing state
-        false
+        self.is_playing
     }
 }
 
@@ -559,13 +819,28 @@ impl<T: crate::RiveEngine> RiveEngine for LegacyRiveEngineAdapter<T> {
         self.event_handler.as_ref().map(|h| h.as_ref())
     }
     
-    fn initialize(&mut self, _config: RiveConfig) -> Result<(), RiveError> {
-        // Legacy engine is always initialized
+    fn initialize(&mut self, config: RiveConfig) -> Result<(), RiveError> {
+        // Legacy engine is always loaded; report what it already holds
+        let animation_info = AnimationInfo {
+            name: String::new(),
+            duration_frames: self.engine.get_total_frames(),
+            fps: self.engine.get_fps(),
+            layer_count: self.engine.get_layers().len(),
+            bounds: AnimationBounds {
+                width: config.canvas_size.0,
+                height: config.canvas_size.1,
+                min_x: 0.0,
+                min_y: 0.0,
+            },
+        };
+        self.notify(|h| h.on_animation_loaded(animation_info));
         Ok(())
     }
     
     fn shutdown(&mut self) {
-        // Legacy engine doesn't need shutdown
+        if self.is_playing {
+            self.pause();
+        }
     }
 }
 
@@ -9,6 +9,12 @@ pub struct Ruler {
     pub font_size: f32,
     pub major_tick_interval: u32,
     pub minor_tick_interval: u32,
+    /// Timeline style to draw with; falls back to the egui visuals when `None`
+    pub style: Option<crate::TimelineStyle>,
+    /// Frames of beat markers, drawn as small triangles
+    pub beat_markers: Vec<u32>,
+    /// Tempo map to count bars and beats in instead of frames
+    pub tempo_map: Option<crate::TempoMap>,
 }
 
 impl Default for Ruler {
@@ -18,6 +24,9 @@ impl Default for Ruler {
             font_size: 10.0,
             major_tick_interval: 10,
             minor_tick_interval: 5,
+            style: None,
+            beat_markers: Vec::new(),
+            tempo_map: None,
         }
     }
 }
@@ -27,6 +36,52 @@ impl Ruler {
         Self::default()
     }
 
+    /// Draw using the colors of a timeline style
+    pub fn with_style(mut self, style: &crate::TimelineStyle) -> Self {
+        self.style = Some(style.clone());
+        self
+    }
+
+    /// Draw beat markers at these frames
+    pub fn with_beat_markers(mut self, markers: &[u32]) -> Self {
+        self.beat_markers = markers.to_vec();
+        self
+    }
+
+    /// Count bars and beats of a tempo map instead of frames
+    pub fn with_tempo_map(mut self, tempo_map: &crate::TempoMap) -> Self {
+        self.tempo_map = Some(tempo_map.clone());
+        self
+    }
+
+    fn background_color(&self, ui: &Ui) -> Color32 {
+        self.style.as_ref().map_or(ui.style().visuals.extreme_bg_color, |s| s.ruler_background)
+    }
+
+    fn text_color(&self, ui: &Ui) -> Color32 {
+        self.style.as_ref().map_or(ui.style().visuals.text_color(), |s| s.ruler_text_color)
+    }
+
+    fn weak_text_color(&self, ui: &Ui) -> Color32 {
+        self.style.as_ref().map_or(ui.style().visuals.weak_text_color(), |s| s.ruler_text_color.gamma_multiply(0.7))
+    }
+
+    fn tick_stroke(&self, ui: &Ui) -> Stroke {
+        self.style.as_ref().map_or(ui.style().visuals.widgets.noninteractive.bg_stroke, |s| Stroke::new(1.0, s.ruler_tick_color))
+    }
+
+    fn label_color(&self, ui: &Ui) -> Color32 {
+        self.style.as_ref().map_or(ui.style().visuals.warn_fg_color, |s| s.label_color)
+    }
+
+    fn marker_color(&self, ui: &Ui) -> Color32 {
+        self.style.as_ref().map_or(ui.style().visuals.error_fg_color, |s| s.marker_color)
+    }
+
+    fn comment_color(&self, ui: &Ui) -> Color32 {
+        self.style.as_ref().map_or(ui.style().visuals.hyperlink_color, |s| s.comment_color)
+    }
+
     /// Draw the ruler with frame numbers and time display
     pub fn draw(
         &self,
@@ -73,13 +128,13 @@ impl Ruler {
         ui.painter().rect_filled(
             rect,
             0.0,
-            ui.style().visuals.extreme_bg_color,
+            self.background_color(ui),
         );
 
         // Bottom border
         ui.painter().line_segment(
             [rect.left_bottom(), rect.right_bottom()],
-            ui.style().visuals.widgets.noninteractive.bg_stroke,
+            self.tick_stroke(ui),
         );
 
         // Calculate visible frame range
@@ -87,65 +142,7 @@ impl Ruler {
         let visible_end = visible_start + ((rect.width() / frame_width) as u32) + 2;
 
         // Draw frame numbers and ticks
-        for frame in visible_start..=visible_end.min(end_frame) {
-            let x = rect.min.x + (frame as f32 * frame_width) - scroll_offset;
-            
-            if x < rect.min.x - frame_width || x > rect.max.x + frame_width {
-                continue;
-            }
-
-            // Major ticks with numbers (every 10 frames by default, or 5 for wider spacing)
-            let major_interval = if frame_width > 15.0 { 5 } else { 10 };
-            if frame % major_interval == 0 {
-                // Calculate time in seconds
-                let time_seconds = frame as f32 / fps;
-                
-                // Format time display based on available space
-                let time_text = if time_seconds >= 60.0 {
-                    // Show minutes:seconds for longer times
-                    let minutes = (time_seconds / 60.0) as u32;
-                    let seconds = time_seconds % 60.0;
-                    format!("{}:{:04.1}", minutes, seconds)
-                } else {
-                    // Show seconds with one decimal place
-                    format!("{:.1}s", time_seconds)
-                };
-                
-                // Draw frame number
-                let frame_text = frame.to_string();
-                ui.painter().text(
-                    pos2(x, rect.center().y - 6.0),
-                    Align2::CENTER_CENTER,
-                    frame_text,
-                    FontId::proportional(self.font_size),
-                    ui.style().visuals.text_color(),
-                );
-                
-                // Draw time below frame number if there's space
-                if rect.height() > 20.0 {
-                    ui.painter().text(
-                        pos2(x, rect.center().y + 4.0),
-                        Align2::CENTER_CENTER,
-                        time_text,
-                        FontId::proportional(self.font_size * 0.8),
-                        ui.style().visuals.weak_text_color(),
-                    );
-                }
-
-                // Draw major tick
-                ui.painter().line_segment(
-                    [pos2(x, rect.bottom() - 8.0), pos2(x, rect.bottom())],
-                    ui.style().visuals.widgets.noninteractive.bg_stroke,
-                );
-            }
-            // Minor ticks (every frame when zoomed in)
-            else if frame_width > 8.0 {
-                ui.painter().line_segment(
-                    [pos2(x, rect.bottom() - 4.0), pos2(x, rect.bottom())],
-                    ui.style().visuals.widgets.noninteractive.bg_stroke,
-                );
-            }
-        }
+        self.draw_ticks(ui, rect, visible_start..=visible_end.min(end_frame), frame_width, scroll_offset, fps);
 
         // Check for right-click on ruler for context menu
         let response = ui.allocate_rect(rect, Sense::click());
@@ -204,13 +201,26 @@ impl Ruler {
             });
         }
 
+        // Draw beat markers
+        let marker_color = self.marker_color(ui);
+        for &frame in &self.beat_markers {
+            if frame >= visible_start && frame <= visible_end {
+                let x = rect.min.x + (frame as f32 * frame_width) - scroll_offset;
+                ui.painter().add(Shape::convex_polygon(
+                    vec![pos2(x - 3.0, rect.bottom() - 6.0), pos2(x + 3.0, rect.bottom() - 6.0), pos2(x, rect.bottom())],
+                    marker_color,
+                    Stroke::NONE,
+                ));
+            }
+        }
+
         // Draw frame labels
         for label in frame_labels {
             if label.frame >= visible_start && label.frame <= visible_end {
                 let x = rect.min.x + (label.frame as f32 * frame_width) - scroll_offset;
                 
                 // Draw label marker
-                let color = label.color.unwrap_or(ui.style().visuals.warn_fg_color);
+                let color = label.color.unwrap_or(self.label_color(ui));
                 ui.painter().line_segment(
                     [pos2(x, rect.top()), pos2(x, rect.bottom())],
                     Stroke::new(2.0, color),
@@ -233,7 +243,7 @@ impl Ruler {
                 let x = rect.min.x + (comment.frame as f32 * frame_width) - scroll_offset;
                 
                 // Draw comment marker (slightly different from labels)
-                let color = comment.color.unwrap_or(ui.style().visuals.hyperlink_color);
+                let color = comment.color.unwrap_or(self.comment_color(ui));
                 ui.painter().circle_filled(
                     pos2(x, rect.center().y),
                     3.0,
@@ -264,6 +274,142 @@ impl Ruler {
         }
     }
 
+    /// Frame numbers and times on major ticks, or bars and beats when the
+    /// ruler has a tempo map
+    fn draw_ticks(
+        &self,
+        ui: &mut Ui,
+        rect: Rect,
+        frames: std::ops::RangeInclusive<u32>,
+        frame_width: f32,
+        scroll_offset: f32,
+        fps: f32,
+    ) {
+        if self.tempo_map.is_some() {
+            self.draw_bars_beats(ui, rect, *frames.start()..*frames.end(), frame_width, scroll_offset, fps);
+            return;
+        }
+
+        for frame in frames {
+            let x = rect.min.x + (frame as f32 * frame_width) - scroll_offset;
+            
+            if x < rect.min.x - frame_width || x > rect.max.x + frame_width {
+                continue;
+            }
+
+            // Major ticks with numbers (every 10 frames by default, or 5 for wider spacing)
+            let major_interval = if frame_width > 15.0 { 5 } else { 10 };
+            if frame % major_interval == 0 {
+                // Calculate time in seconds
+                let time_seconds = frame as f32 / fps;
+                
+                // Format time display based on available space
+                let time_text = if time_seconds >= 60.0 {
+                    // Show minutes:seconds for longer times
+                    let minutes = (time_seconds / 60.0) as u32;
+                    let seconds = time_seconds % 60.0;
+                    format!("{}:{:04.1}", minutes, seconds)
+                } else {
+                    // Show seconds with one decimal place
+                    format!("{:.1}s", time_seconds)
+                };
+                
+                // Draw frame number
+                let frame_text = frame.to_string();
+                ui.painter().text(
+                    pos2(x, rect.center().y - 6.0),
+                    Align2::CENTER_CENTER,
+                    frame_text,
+                    FontId::proportional(self.font_size),
+                    self.text_color(ui),
+                );
+                
+                // Draw time below frame number if there's space
+                if rect.height() > 20.0 {
+                    ui.painter().text(
+                        pos2(x, rect.center().y + 4.0),
+                        Align2::CENTER_CENTER,
+                        time_text,
+                        FontId::proportional(self.font_size * 0.8),
+                        self.weak_text_color(ui),
+                    );
+                }
+
+                // Draw major tick
+                ui.painter().line_segment(
+                    [pos2(x, rect.bottom() - 8.0), pos2(x, rect.bottom())],
+                    self.tick_stroke(ui),
+                );
+            }
+            // Minor ticks (every frame when zoomed in)
+            else if frame_width > 8.0 {
+                ui.painter().line_segment(
+                    [pos2(x, rect.bottom() - 4.0), pos2(x, rect.bottom())],
+                    self.tick_stroke(ui),
+                );
+            }
+        }
+    }
+
+    /// Bar numbers on bar ticks, bar|beat on beat ticks where there's room,
+    /// and tempo and time signature changes below them
+    fn draw_bars_beats(
+        &self,
+        ui: &mut Ui,
+        rect: Rect,
+        frames: std::ops::Range<u32>,
+        frame_width: f32,
+        scroll_offset: f32,
+        fps: f32,
+    ) {
+        let Some(tempo_map) = &self.tempo_map else {
+            return;
+        };
+        let x_at = |frame: f64| rect.min.x + frame as f32 * frame_width - scroll_offset;
+        let mut last_label_x = f32::NEG_INFINITY;
+        for line in tempo_map.grid(frames.start as f64..frames.end as f64, fps, 1) {
+            let x = x_at(line.frame);
+            let bar = line.kind == crate::GridKind::Bar;
+            let tick = if bar { 8.0 } else { 4.0 };
+            ui.painter().line_segment([pos2(x, rect.bottom() - tick), pos2(x, rect.bottom())], self.tick_stroke(ui));
+
+            // Skip labels that would overlap the previous one
+            let text = if bar { line.bar.to_string() } else { format!("{}|{}", line.bar, line.beat) };
+            let min_gap = if bar { 24.0 } else { 36.0 };
+            if x - last_label_x >= min_gap {
+                let color = if bar { self.text_color(ui) } else { self.weak_text_color(ui) };
+                let size = if bar { self.font_size } else { self.font_size * 0.8 };
+                ui.painter().text(pos2(x, rect.center().y - 6.0), Align2::CENTER_CENTER, text, FontId::proportional(size), color);
+                last_label_x = x;
+            }
+        }
+
+        if rect.height() <= 20.0 {
+            return;
+        }
+        let visible = |frame: u32| frames.contains(&frame);
+        let tempos = tempo_map.tempos().iter().filter(|mark| visible(mark.frame)).map(|mark| (mark.frame, format!("{:.0} BPM", mark.bpm)));
+        let meters = tempo_map.meters().iter().filter(|mark| visible(mark.frame)).map(|mark| (mark.frame, format!("{}/{}", mark.numerator, mark.denominator)));
+        let mut changes: Vec<(u32, String)> = tempos.chain(meters).collect();
+        changes.sort_by_key(|(frame, _)| *frame);
+        let mut previous: Option<(u32, Rect)> = None;
+        for (frame, text) in changes {
+            // Changes at the same frame sit side by side
+            let x = match previous {
+                Some((previous_frame, text_rect)) if previous_frame == frame => text_rect.right() + 4.0,
+                _ => x_at(frame as f64) + 2.0,
+            };
+            let text_rect = ui.painter().text(
+                pos2(x, rect.center().y + 4.0),
+                Align2::LEFT_CENTER,
+                text,
+                FontId::proportional(self.font_size * 0.8),
+                self.weak_text_color(ui),
+            );
+            previous = Some((frame, text_rect));
+        }
+    }
+
     /// Add a frame label at the specified position
     pub fn add_label(&self, ui: &mut Ui, _frame: u32, label: &str, x: f32, y: f32) {
         ui.painter().text(
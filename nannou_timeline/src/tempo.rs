@@ -0,0 +1,456 @@
+//! Musical time for the bars|beats ruler
+//!
+//! A [`TempoMap`] holds tempo changes and time signature changes at
+//! timeline frames. Tempos are in quarter notes per minute, and musical
+//! positions are counted in quarter notes from frame 0 by integrating the
+//! tempo. A time signature change always starts a new bar, even mid-bar, so
+//! bars are numbered from 1 across every signature.
+
+use crate::midi::MidiFile;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A tempo from a frame on
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TempoMark {
+    pub frame: u32,
+    /// Quarter notes per minute
+    pub bpm: f64,
+}
+
+/// A time signature from a frame on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeterMark {
+    pub frame: u32,
+    pub numerator: u32,
+    /// Note value of a beat: 4 for quarter notes, 8 for eighths
+    pub denominator: u32,
+}
+
+impl MeterMark {
+    /// Length of a beat in quarter notes
+    fn beat_quarters(&self) -> f64 {
+        4.0 / self.denominator.max(1) as f64
+    }
+
+    /// Length of a bar in quarter notes
+    fn bar_quarters(&self) -> f64 {
+        self.beat_quarters() * self.numerator.max(1) as f64
+    }
+}
+
+/// A bar and beat, both counted from 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MusicalPosition {
+    pub bar: u32,
+    pub beat: u32,
+    /// How far into the beat, from 0.0 to 1.0
+    pub fraction: f64,
+}
+
+impl std::fmt::Display for MusicalPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}|{}", self.bar, self.beat)
+    }
+}
+
+/// What a beat grid line marks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridKind {
+    Bar,
+    Beat,
+    Subdivision,
+}
+
+/// A line of the beat grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridLine {
+    /// Fractional timeline frame
+    pub frame: f64,
+    pub kind: GridKind,
+    pub bar: u32,
+    pub beat: u32,
+}
+
+/// Tempo and time signature changes of a document
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TempoMapData")]
+pub struct TempoMap {
+    /// Sorted by frame, the first at frame 0
+    tempos: Vec<TempoMark>,
+    /// Sorted by frame, the first at frame 0
+    meters: Vec<MeterMark>,
+}
+
+/// Why a tempo map or a change to it was rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TempoMapError {
+    /// No tempo or time signature at frame 0
+    MissingStart,
+    /// Changes out of order, or two at the same frame
+    Unsorted,
+    /// A tempo at or below zero, or a zero in a time signature
+    InvalidValue,
+}
+
+impl std::fmt::Display for TempoMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TempoMapError::MissingStart => write!(f, "Tempo map needs a tempo and time signature at frame 0"),
+            TempoMapError::Unsorted => write!(f, "Tempo map changes are out of order"),
+            TempoMapError::InvalidValue => write!(f, "Tempo map has an invalid tempo or time signature"),
+        }
+    }
+}
+
+impl std::error::Error for TempoMapError {}
+
+/// Serialized form of a [`TempoMap`], checked before use
+#[derive(Deserialize)]
+struct TempoMapData {
+    tempos: Vec<TempoMark>,
+    meters: Vec<MeterMark>,
+}
+
+impl TryFrom<TempoMapData> for TempoMap {
+    type Error = TempoMapError;
+
+    fn try_from(data: TempoMapData) -> Result<Self, Self::Error> {
+        let tempo_frames: Vec<u32> = data.tempos.iter().map(|mark| mark.frame).collect();
+        let meter_frames: Vec<u32> = data.meters.iter().map(|mark| mark.frame).collect();
+        for frames in [&tempo_frames, &meter_frames] {
+            if frames.first() != Some(&0) {
+                return Err(TempoMapError::MissingStart);
+            }
+            if frames.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(TempoMapError::Unsorted);
+            }
+        }
+        for mark in &data.tempos {
+            check_tempo(mark.bpm)?;
+        }
+        for mark in &data.meters {
+            check_meter(mark.numerator, mark.denominator)?;
+        }
+        Ok(Self { tempos: data.tempos, meters: data.meters })
+    }
+}
+
+/// A tempo must be positive and finite; the negated comparison also catches NaN
+#[allow(clippy::neg_cmp_op_on_partial_ord)]
+fn check_tempo(bpm: f64) -> Result<(), TempoMapError> {
+    if !(bpm > 0.0) || !bpm.is_finite() {
+        return Err(TempoMapError::InvalidValue);
+    }
+    Ok(())
+}
+
+/// A time signature needs at least one beat of a non-zero note value
+fn check_meter(numerator: u32, denominator: u32) -> Result<(), TempoMapError> {
+    if numerator == 0 || denominator == 0 {
+        return Err(TempoMapError::InvalidValue);
+    }
+    Ok(())
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        Self {
+            tempos: vec![TempoMark { frame: 0, bpm: 120.0 }],
+            meters: vec![MeterMark { frame: 0, numerator: 4, denominator: 4 }],
+        }
+    }
+}
+
+impl TempoMap {
+    /// A constant tempo and time signature
+    pub fn new(bpm: f64, numerator: u32, denominator: u32) -> Result<Self, TempoMapError> {
+        check_tempo(bpm)?;
+        check_meter(numerator, denominator)?;
+        Ok(Self {
+            tempos: vec![TempoMark { frame: 0, bpm }],
+            meters: vec![MeterMark { frame: 0, numerator, denominator }],
+        })
+    }
+
+    /// The tempo map of a MIDI file, with its ticks placed at `fps`. Invalid
+    /// tempos and time signatures in the file are skipped.
+    pub fn from_midi(file: &MidiFile, fps: f32) -> Self {
+        let mut map = Self::default();
+        for tempo in &file.tempo_map {
+            let _ = map.set_tempo(file.frame_at(tempo.tick, fps), tempo.bpm());
+        }
+        for signature in &file.time_signatures {
+            let _ = map.set_meter(file.frame_at(signature.tick, fps), signature.numerator as u32, signature.denominator as u32);
+        }
+        map
+    }
+
+    pub fn tempos(&self) -> &[TempoMark] {
+        &self.tempos
+    }
+
+    pub fn meters(&self) -> &[MeterMark] {
+        &self.meters
+    }
+
+    /// Set the tempo from `frame` on, replacing a change at the same frame
+    pub fn set_tempo(&mut self, frame: u32, bpm: f64) -> Result<(), TempoMapError> {
+        check_tempo(bpm)?;
+        match self.tempos.binary_search_by_key(&frame, |mark| mark.frame) {
+            Ok(index) => self.tempos[index].bpm = bpm,
+            Err(index) => self.tempos.insert(index, TempoMark { frame, bpm }),
+        }
+        Ok(())
+    }
+
+    /// Remove the tempo change at `frame`; the one at frame 0 stays
+    pub fn remove_tempo(&mut self, frame: u32) {
+        if frame > 0 {
+            self.tempos.retain(|mark| mark.frame != frame);
+        }
+    }
+
+    /// Set the time signature from `frame` on, replacing a change at the same frame
+    pub fn set_meter(&mut self, frame: u32, numerator: u32, denominator: u32) -> Result<(), TempoMapError> {
+        check_meter(numerator, denominator)?;
+        let mark = MeterMark { frame, numerator, denominator };
+        match self.meters.binary_search_by_key(&frame, |mark| mark.frame) {
+            Ok(index) => self.meters[index] = mark,
+            Err(index) => self.meters.insert(index, mark),
+        }
+        Ok(())
+    }
+
+    /// Remove the time signature change at `frame`; the one at frame 0 stays
+    pub fn remove_meter(&mut self, frame: u32) {
+        if frame > 0 {
+            self.meters.retain(|mark| mark.frame != frame);
+        }
+    }
+
+    /// Tempo at `frame`
+    pub fn bpm_at(&self, frame: f64) -> f64 {
+        self.tempos.iter().rev().find(|mark| mark.frame as f64 <= frame).unwrap_or(&self.tempos[0]).bpm
+    }
+
+    /// Quarter notes from frame 0 to `frame`
+    pub fn quarters_at(&self, frame: f64, fps: f32) -> f64 {
+        let mut quarters = 0.0;
+        for (index, mark) in self.tempos.iter().enumerate() {
+            let start = mark.frame as f64;
+            if frame <= start && index > 0 {
+                break;
+            }
+            let end = self.tempos.get(index + 1).map_or(frame, |next| (next.frame as f64).min(frame));
+            quarters += (end - start) / fps as f64 * mark.bpm / 60.0;
+        }
+        quarters
+    }
+
+    /// Frame at which `quarters` quarter notes have passed
+    pub fn frame_at_quarters(&self, quarters: f64, fps: f32) -> f64 {
+        let mut passed = 0.0;
+        for (index, mark) in self.tempos.iter().enumerate() {
+            let frames_per_quarter = fps as f64 * 60.0 / mark.bpm;
+            if let Some(next) = self.tempos.get(index + 1) {
+                let length = (next.frame - mark.frame) as f64 / frames_per_quarter;
+                if passed + length < quarters {
+                    passed += length;
+                    continue;
+                }
+            }
+            return mark.frame as f64 + (quarters - passed) * frames_per_quarter;
+        }
+        0.0
+    }
+
+    /// Each meter with the quarter note it starts at and the number of its first bar
+    fn meter_starts(&self, fps: f32) -> Vec<(MeterMark, f64, u32)> {
+        let mut starts: Vec<(MeterMark, f64, u32)> = Vec::with_capacity(self.meters.len());
+        for meter in &self.meters {
+            let quarters = self.quarters_at(meter.frame as f64, fps);
+            let bar = match starts.last() {
+                // A partial bar before the change still counts as a bar
+                Some((previous, start, bar)) => bar + ((quarters - start) / previous.bar_quarters() - 1e-9).ceil().max(0.0) as u32,
+                None => 1,
+            };
+            starts.push((*meter, quarters, bar));
+        }
+        starts
+    }
+
+    /// Bar and beat at `frame`
+    pub fn position_at(&self, frame: f64, fps: f32) -> MusicalPosition {
+        let quarters = self.quarters_at(frame, fps);
+        let starts = self.meter_starts(fps);
+        let (meter, start, first_bar) = starts.iter().rev().find(|(_, start, _)| *start <= quarters + 1e-9).unwrap_or(&starts[0]);
+        let beats = ((quarters - start) / meter.beat_quarters()).max(0.0) + 1e-9;
+        let numerator = meter.numerator.max(1);
+        let whole_beats = beats.floor() as u32;
+        MusicalPosition {
+            bar: first_bar + whole_beats / numerator,
+            beat: whole_beats % numerator + 1,
+            fraction: (beats.fract() - 1e-9).max(0.0),
+        }
+    }
+
+    /// Grid lines from `frames.start` up to `frames.end`, with `subdivision`
+    /// lines per beat
+    pub fn grid(&self, frames: Range<f64>, fps: f32, subdivision: u32) -> Vec<GridLine> {
+        let subdivision = subdivision.max(1);
+        let (from, to) = (self.quarters_at(frames.start, fps), self.quarters_at(frames.end, fps));
+        let starts = self.meter_starts(fps);
+        let mut lines = Vec::new();
+        for (index, (meter, start, first_bar)) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).map_or(f64::INFINITY, |next| next.1);
+            if end <= from || *start > to {
+                continue;
+            }
+            let step = meter.beat_quarters() / subdivision as f64;
+            let per_bar = subdivision * meter.numerator.max(1);
+            let mut line = ((from - start) / step - 1e-9).ceil().max(0.0) as u32;
+            loop {
+                let quarters = start + line as f64 * step;
+                if quarters >= end - 1e-9 || quarters > to + 1e-9 {
+                    break;
+                }
+                let kind = match line {
+                    line if line % per_bar == 0 => GridKind::Bar,
+                    line if line % subdivision == 0 => GridKind::Beat,
+                    _ => GridKind::Subdivision,
+                };
+                lines.push(GridLine {
+                    frame: self.frame_at_quarters(quarters, fps),
+                    kind,
+                    bar: first_bar + line / per_bar,
+                    beat: (line / subdivision) % meter.numerator.max(1) + 1,
+                });
+                line += 1;
+            }
+        }
+        lines
+    }
+
+    /// The grid line nearest `frame`, with `subdivision` lines per beat
+    pub fn snap(&self, frame: f64, fps: f32, subdivision: u32) -> f64 {
+        let starts = self.meter_starts(fps);
+        let quarters = self.quarters_at(frame, fps);
+        let index = starts.iter().rposition(|(_, start, _)| *start <= quarters + 1e-9).unwrap_or(0);
+        let (meter, start, _) = starts[index];
+        let step = meter.beat_quarters() / subdivision.max(1) as f64;
+
+        let mut candidates = vec![start + ((quarters - start) / step).floor() * step, start + ((quarters - start) / step).ceil() * step];
+        if let Some((_, next_start, _)) = starts.get(index + 1) {
+            candidates.retain(|candidate| candidate < next_start);
+            candidates.push(*next_start);
+        }
+        candidates
+            .into_iter()
+            .map(|candidate| self.frame_at_quarters(candidate, fps))
+            .min_by(|a, b| (a - frame).abs().total_cmp(&(b - frame).abs()))
+            .unwrap_or(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_deserialize_checks_invariants() {
+        let mut map = TempoMap::default();
+        map.set_tempo(48, 60.0).unwrap();
+        map.set_meter(96, 3, 4).unwrap();
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<TempoMap>(&json).unwrap(), map);
+
+        let parse = |json: &str| serde_json::from_str::<TempoMap>(json).unwrap_err().to_string();
+        let meters = r#""meters":[{"frame":0,"numerator":4,"denominator":4}]"#;
+        assert!(parse(&format!(r#"{{"tempos":[],{}}}"#, meters)).contains("frame 0"));
+        let unsorted = r#""tempos":[{"frame":0,"bpm":120.0},{"frame":48,"bpm":60.0},{"frame":24,"bpm":90.0}]"#;
+        assert!(parse(&format!("{{{},{}}}", unsorted, meters)).contains("out of order"));
+        let zero = r#""tempos":[{"frame":0,"bpm":0.0}]"#;
+        assert!(parse(&format!("{{{},{}}}", zero, meters)).contains("invalid"));
+        let tempos = r#""tempos":[{"frame":0,"bpm":120.0}]"#;
+        let no_beats = r#""meters":[{"frame":0,"numerator":0,"denominator":4}]"#;
+        assert!(parse(&format!("{{{},{}}}", tempos, no_beats)).contains("invalid"));
+    }
+
+    #[test]
+    fn test_edits_share_the_checks() {
+        assert_eq!(TempoMap::new(f64::NAN, 4, 4), Err(TempoMapError::InvalidValue));
+        assert_eq!(TempoMap::new(120.0, 4, 0), Err(TempoMapError::InvalidValue));
+
+        let mut map = TempoMap::default();
+        assert_eq!(map.set_tempo(48, -60.0), Err(TempoMapError::InvalidValue));
+        assert_eq!(map.set_tempo(48, f64::INFINITY), Err(TempoMapError::InvalidValue));
+        assert_eq!(map.set_meter(48, 0, 4), Err(TempoMapError::InvalidValue));
+        assert_eq!(map, TempoMap::default());
+    }
+
+    #[test]
+    fn test_tempo_changes_convert_both_ways() {
+        let mut map = TempoMap::default();
+        // 120 BPM for 48 frames at 24 fps is 4 quarters, then 60 BPM
+        map.set_tempo(48, 60.0).unwrap();
+        assert!(close(map.quarters_at(48.0, 24.0), 4.0));
+        assert!(close(map.quarters_at(72.0, 24.0), 5.0));
+        assert!(close(map.frame_at_quarters(5.0, 24.0), 72.0));
+        assert!(close(map.frame_at_quarters(2.0, 24.0), 24.0));
+        assert_eq!(map.bpm_at(47.0), 120.0);
+
+        map.remove_tempo(48);
+        map.remove_tempo(0);
+        assert_eq!(map.tempos().len(), 1);
+    }
+
+    #[test]
+    fn test_meter_changes_start_new_bars() {
+        // 4/4 at 120 BPM and 24 fps: a bar is 48 frames. 3/4 starts half way
+        // through bar 2, which still counts as a bar.
+        let mut map = TempoMap::default();
+        map.set_meter(72, 3, 4).unwrap();
+        let position = |frame: f64| map.position_at(frame, 24.0);
+
+        assert_eq!(position(0.0).to_string(), "1|1");
+        assert_eq!(position(60.0).to_string(), "2|2");
+        assert!(close(position(66.0).fraction, 0.5));
+        assert_eq!(position(72.0).to_string(), "3|1");
+        assert_eq!(position(108.0).to_string(), "4|1");
+    }
+
+    #[test]
+    fn test_grid_and_snapping() {
+        let mut map = TempoMap::new(120.0, 2, 4).unwrap();
+        map.set_meter(36, 6, 8).unwrap();
+        let lines = map.grid(0.0..48.0, 24.0, 2);
+        let bars: Vec<f64> = lines.iter().filter(|line| line.kind == GridKind::Bar).map(|line| line.frame).collect();
+        assert!(bars.iter().zip([0.0, 24.0, 36.0]).all(|(a, b)| close(*a, b)));
+        assert_eq!(lines.iter().filter(|line| line.kind == GridKind::Beat).count(), 3);
+        assert_eq!((lines[2].bar, lines[2].beat, lines[2].kind), (1, 2, GridKind::Beat));
+
+        // Eighths of 2/4 are 6 frames apart; the 6/8 change at 36 ends a half bar
+        assert!(close(map.snap(8.0, 24.0, 2), 6.0));
+        assert!(close(map.snap(34.0, 24.0, 1), 36.0));
+        assert!(close(map.snap(40.0, 24.0, 1), 42.0));
+    }
+
+    #[test]
+    fn test_from_midi() {
+        let file = MidiFile {
+            format: 1,
+            division: crate::midi::Division::TicksPerQuarter(96),
+            tracks: Vec::new(),
+            tempo_map: vec![crate::midi::TempoChange { tick: 192, micros_per_quarter: 1_000_000 }],
+            time_signatures: vec![crate::midi::TimeSignature { tick: 0, numerator: 3, denominator: 4 }],
+        };
+        let map = TempoMap::from_midi(&file, 24.0);
+        assert_eq!(map.tempos(), [TempoMark { frame: 0, bpm: 120.0 }, TempoMark { frame: 24, bpm: 60.0 }]);
+        assert_eq!(map.meters(), [MeterMark { frame: 0, numerator: 3, denominator: 4 }]);
+    }
+}
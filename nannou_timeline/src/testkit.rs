@@ -0,0 +1,240 @@
+//! Reusable behaviour tests for [`RiveEngine`](crate::RiveEngine) implementations.
+//!
+//! Each function drives an engine through the legacy trait and panics if it
+//! misbehaves. [`rive_engine_tests!`](crate::rive_engine_tests) turns the
+//! whole set into `#[test]` functions for one engine type:
+//!
+//! ```ignore
+//! nannou_timeline::rive_engine_tests!(my_engine, MyEngine::new());
+//! ```
+//!
+//! The expression is evaluated once per test, so every test gets a fresh
+//! engine. Engines for the newer [`NewRiveEngine`](crate::NewRiveEngine)
+//! interface are checked by [`rive_conformance`](crate::rive_conformance) instead.
+//!
+//! Requires the `testkit` feature.
+
+use crate::frame::FrameType;
+use crate::layer::{LayerInfo, LayerType};
+use crate::{LayerId, RiveEngine};
+
+/// Instantiate every [`testkit`](crate::testkit) test for an engine.
+///
+/// Expands to a module named `$name` with one `#[test]` per check. `$engine`
+/// is an expression evaluating to the engine, evaluated once per test.
+#[macro_export]
+macro_rules! rive_engine_tests {
+    ($name:ident, $engine:expr) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $crate::rive_engine_tests!(@tests $engine;
+                insert_keyframe,
+                clear_keyframe,
+                move_keyframe,
+                copy_paste_keyframe,
+                add_layer,
+                delete_layer,
+                duplicate_layer,
+                rename_layer,
+                folder_children,
+                seek_bounds,
+                motion_tween,
+                shape_tween,
+            );
+        }
+    };
+    (@tests $engine:expr; $($test:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $test() {
+                let mut engine = $engine;
+                $crate::testkit::$test(&mut engine);
+            }
+        )*
+    };
+}
+
+fn frame_type(engine: &dyn RiveEngine, layer_id: &LayerId, frame: u32) -> FrameType {
+    engine.get_frame_data(layer_id.clone(), frame).frame_type
+}
+
+fn find_layer(engine: &dyn RiveEngine, layer_id: &LayerId) -> Option<LayerInfo> {
+    engine.get_layers().into_iter().find(|l| l.id == *layer_id)
+}
+
+/// A new layer to edit, so tests don't depend on the engine's initial content
+fn scratch_layer(engine: &mut dyn RiveEngine) -> LayerId {
+    engine.add_layer("Testkit".to_string(), LayerType::Normal)
+}
+
+/// First frame at or after `from` where it and the next frame hold no keyframe
+fn free_frame(engine: &dyn RiveEngine, layer_id: &LayerId, from: u32) -> u32 {
+    let total_frames = engine.get_total_frames();
+    (from.max(1)..total_frames.saturating_sub(1))
+        .find(|&f| {
+            frame_type(engine, layer_id, f) != FrameType::Keyframe
+                && frame_type(engine, layer_id, f + 1) != FrameType::Keyframe
+        })
+        .expect("layer has no free frames to test with")
+}
+
+pub fn insert_keyframe(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+    let frame = free_frame(engine, &layer_id, 1);
+
+    engine.insert_keyframe(layer_id.clone(), frame);
+    assert_eq!(frame_type(engine, &layer_id, frame), FrameType::Keyframe, "insert_keyframe() should create a keyframe");
+}
+
+pub fn clear_keyframe(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+    let frame = free_frame(engine, &layer_id, 1);
+    engine.insert_keyframe(layer_id.clone(), frame);
+
+    engine.clear_keyframe(layer_id.clone(), frame);
+    assert_ne!(frame_type(engine, &layer_id, frame), FrameType::Keyframe, "clear_keyframe() should remove the keyframe");
+}
+
+pub fn move_keyframe(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+    let from = free_frame(engine, &layer_id, 1);
+    let to = free_frame(engine, &layer_id, from + 2);
+    engine.insert_keyframe(layer_id.clone(), from);
+
+    engine.move_keyframe(layer_id.clone(), from, to);
+    assert_eq!(frame_type(engine, &layer_id, to), FrameType::Keyframe, "the keyframe should arrive at the target frame");
+    assert_ne!(frame_type(engine, &layer_id, from), FrameType::Keyframe, "the keyframe should leave its old frame");
+}
+
+pub fn copy_paste_keyframe(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+    let source = free_frame(engine, &layer_id, 1);
+    let target = free_frame(engine, &layer_id, source + 2);
+    engine.insert_keyframe(layer_id.clone(), source);
+
+    let data = engine.copy_keyframe(layer_id.clone(), source).expect("copy_keyframe() should return the keyframe");
+    assert_eq!(data.frame_type, FrameType::Keyframe);
+    engine.paste_keyframe(layer_id.clone(), target, data);
+    assert_eq!(frame_type(engine, &layer_id, target), FrameType::Keyframe, "paste_keyframe() should create a keyframe");
+    assert_eq!(frame_type(engine, &layer_id, source), FrameType::Keyframe, "the copied keyframe should stay in place");
+}
+
+pub fn add_layer(engine: &mut dyn RiveEngine) {
+    let layer_count = engine.get_layers().len();
+
+    let layer_id = engine.add_layer("Added".to_string(), LayerType::Normal);
+    let layer = find_layer(engine, &layer_id).expect("added layer should be listed");
+    assert_eq!(layer.name, "Added");
+    assert_eq!(layer.layer_type, LayerType::Normal);
+    assert_eq!(engine.get_layers().len(), layer_count + 1);
+
+    let guide_id = engine.add_motion_guide_layer("Guide".to_string());
+    let guide = find_layer(engine, &guide_id).expect("added motion guide should be listed");
+    assert_eq!(guide.layer_type, LayerType::MotionGuide);
+}
+
+pub fn delete_layer(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+    let layer_count = engine.get_layers().len();
+
+    engine.delete_layer(layer_id.clone());
+    assert!(find_layer(engine, &layer_id).is_none(), "deleted layer should not be listed");
+    assert_eq!(engine.get_layers().len(), layer_count - 1);
+}
+
+pub fn duplicate_layer(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+    let frame = free_frame(engine, &layer_id, 1);
+    engine.insert_keyframe(layer_id.clone(), frame);
+    let layer_count = engine.get_layers().len();
+
+    let copy_id = engine.duplicate_layer(layer_id.clone());
+    assert_ne!(copy_id, layer_id, "the duplicate needs its own id");
+    let copy = find_layer(engine, &copy_id).expect("duplicate should be listed");
+    assert_eq!(copy.layer_type, LayerType::Normal);
+    assert_eq!(engine.get_layers().len(), layer_count + 1);
+    assert_eq!(frame_type(engine, &copy_id, frame), FrameType::Keyframe, "the duplicate should copy the frames");
+}
+
+pub fn rename_layer(engine: &mut dyn RiveEngine) {
+    let layer_id = scratch_layer(engine);
+
+    engine.rename_layer(layer_id.clone(), "Renamed".to_string());
+    assert_eq!(find_layer(engine, &layer_id).map(|l| l.name), Some("Renamed".to_string()));
+}
+
+/// `parent_id` and `children` agree for every layer
+fn assert_hierarchy_consistent(engine: &dyn RiveEngine) {
+    let layers = engine.get_layers();
+    for layer in &layers {
+        if let Some(parent_id) = &layer.parent_id {
+            let parent = layers.iter().find(|l| l.id == *parent_id)
+                .unwrap_or_else(|| panic!("{:?} has a parent that isn't listed", layer.id));
+            assert!(parent.children.contains(&layer.id), "{:?} is missing from its parent's children", layer.id);
+        }
+        for child_id in &layer.children {
+            let child = layers.iter().find(|l| l.id == *child_id)
+                .unwrap_or_else(|| panic!("{:?} lists a child that isn't listed", layer.id));
+            assert_eq!(child.parent_id.as_ref(), Some(&layer.id), "{:?} doesn't point back at its folder", child_id);
+        }
+    }
+}
+
+pub fn folder_children(engine: &mut dyn RiveEngine) {
+    assert_hierarchy_consistent(engine);
+
+    let folder_id = engine.add_folder_layer("Folder".to_string());
+    let folder = find_layer(engine, &folder_id).expect("added folder should be listed");
+    assert_eq!(folder.layer_type, LayerType::Folder);
+    assert!(folder.children.is_empty(), "a new folder should be empty");
+
+    // The legacy trait can't move layers into folders, so use one the engine starts with
+    let Some(child) = engine.get_layers().into_iter().find(|l| l.parent_id.is_some()) else { return };
+    let parent_id = child.parent_id.clone().unwrap();
+
+    let copy_id = engine.duplicate_layer(child.id.clone());
+    assert_eq!(find_layer(engine, &copy_id).and_then(|l| l.parent_id), Some(parent_id.clone()));
+    assert_hierarchy_consistent(engine);
+
+    engine.delete_layer(copy_id);
+    assert_hierarchy_consistent(engine);
+
+    let children = find_layer(engine, &parent_id).map(|l| l.children).unwrap_or_default();
+    engine.delete_layer(parent_id);
+    for child_id in children {
+        assert!(find_layer(engine, &child_id).is_none(), "deleting a folder should delete its contents");
+    }
+    assert_hierarchy_consistent(engine);
+}
+
+pub fn seek_bounds(engine: &mut dyn RiveEngine) {
+    let last_frame = engine.get_total_frames().saturating_sub(1);
+
+    engine.seek(last_frame);
+    assert_eq!(engine.get_current_frame(), last_frame);
+    engine.seek(0);
+    assert_eq!(engine.get_current_frame(), 0);
+    engine.seek(u32::MAX);
+    assert_eq!(engine.get_current_frame(), last_frame, "seeking past the end should clamp to the last frame");
+}
+
+/// A tween started on a keyframe fills the frame after it
+fn check_tween(engine: &mut dyn RiveEngine, create: fn(&mut dyn RiveEngine, LayerId, u32)) {
+    let layer_id = scratch_layer(engine);
+    let frame = free_frame(engine, &layer_id, 1);
+    engine.insert_keyframe(layer_id.clone(), frame);
+
+    create(engine, layer_id.clone(), frame);
+    assert_eq!(frame_type(engine, &layer_id, frame), FrameType::Keyframe, "the tween should start on the keyframe");
+    assert_eq!(frame_type(engine, &layer_id, frame + 1), FrameType::Tween, "the frame after the keyframe should be tweened");
+}
+
+pub fn motion_tween(engine: &mut dyn RiveEngine) {
+    check_tween(engine, |engine, layer_id, frame| engine.create_motion_tween(layer_id, frame));
+}
+
+pub fn shape_tween(engine: &mut dyn RiveEngine) {
+    check_tween(engine, |engine, layer_id, frame| engine.create_shape_tween(layer_id, frame));
+}
@@ -0,0 +1,298 @@
+//! Timeline themes
+//! Every color and visual metric used by the timeline widget lives in
+//! [`TimelineStyle`]. Styles serialize to JSON or TOML, and the bundled
+//! dark, light and high-contrast themes are embedded JSON files.
+
+use std::path::Path;
+use ecolor::Color32;
+use serde::{Deserialize, Serialize};
+
+/// Visual styling for the timeline
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimelineStyle {
+    /// Theme name shown in the theme switcher
+    pub name: String,
+    /// Whether egui widgets inside the timeline use dark visuals
+    pub dark_mode: bool,
+
+    // Frame grid
+    pub background_color: Color32,
+    pub grid_color: Color32,
+    pub layer_background: Color32,
+    pub layer_selected: Color32,
+    pub frame_empty: Color32,
+    pub frame_keyframe: Color32,
+    pub frame_tween: Color32,
+    pub playhead_color: Color32,
+    pub border_color: Color32,
+    pub text_color: Color32,
+    /// Outline and dot color of selected keyframes
+    pub selection_color: Color32,
+    /// Vertical snap guide lines
+    pub snap_guide_color: Color32,
+
+    // Panels
+    pub toolbar_background: Color32,
+    pub layer_panel_background: Color32,
+    pub controls_background: Color32,
+    /// Line beneath each layer row
+    pub separator_color: Color32,
+    /// Insertion line while reordering layers
+    pub drop_indicator_color: Color32,
+
+    // Ruler
+    pub ruler_background: Color32,
+    pub ruler_text_color: Color32,
+    pub ruler_tick_color: Color32,
+    /// Frame labels without an explicit color
+    pub label_color: Color32,
+    /// Frame comments without an explicit color
+    pub comment_color: Color32,
+    /// Beat markers on the ruler
+    pub marker_color: Color32,
+
+    // Audio
+    pub waveform_color: Color32,
+    pub waveform_background: Color32,
+    pub audio_label_color: Color32,
+    /// Volume envelope line and its handles
+    pub envelope_color: Color32,
+
+    // Piano roll keyboard in the layer panel
+    pub piano_white_key_color: Color32,
+    pub piano_black_key_color: Color32,
+
+    // Onion skinning (alpha is taken from the onion skin opacity)
+    pub onion_before_color: Color32,
+    pub onion_after_color: Color32,
+
+    // Overview minimap
+    pub minimap_background: Color32,
+    /// Outline of the visible-range rectangle (filled with a translucent tint)
+    pub minimap_viewport_color: Color32,
+
+    // Metrics
+    pub toolbar_height: f32,
+    /// Playback controls along the bottom
+    pub controls_height: f32,
+    pub ruler_height: f32,
+    pub layer_panel_width: f32,
+    pub layer_controls_height: f32,
+    /// Horizontal indent per nesting level in the layer panel
+    pub layer_indent: f32,
+    pub keyframe_radius: f32,
+    pub frame_rounding: f32,
+    pub playhead_width: f32,
+    pub scrub_playhead_width: f32,
+}
+
+impl Default for TimelineStyle {
+    fn default() -> Self {
+        Self {
+            name: "Dark".to_string(),
+            dark_mode: true,
+            background_color: Color32::from_gray(40),
+            grid_color: Color32::from_gray(60),
+            layer_background: Color32::from_gray(50),
+            layer_selected: Color32::from_rgb(70, 130, 180),
+            frame_empty: Color32::from_gray(45),
+            frame_keyframe: Color32::from_gray(20),
+            frame_tween: Color32::from_rgb(100, 100, 150),
+            playhead_color: Color32::from_rgb(255, 0, 0),
+            border_color: Color32::from_gray(80),
+            text_color: Color32::from_gray(220),
+            selection_color: Color32::from_rgb(100, 150, 255),
+            snap_guide_color: Color32::from_rgb(255, 255, 0),
+            toolbar_background: Color32::from_gray(55),
+            layer_panel_background: Color32::from_gray(45),
+            controls_background: Color32::from_gray(50),
+            separator_color: Color32::from_gray(60),
+            drop_indicator_color: Color32::from_rgb(100, 200, 255),
+            ruler_background: Color32::from_gray(10),
+            ruler_text_color: Color32::from_gray(140),
+            ruler_tick_color: Color32::from_gray(60),
+            label_color: Color32::from_rgb(255, 143, 0),
+            comment_color: Color32::from_rgb(100, 150, 255),
+            marker_color: Color32::from_rgb(255, 90, 140),
+            waveform_color: Color32::from_rgb(100, 200, 255),
+            waveform_background: Color32::from_gray(35),
+            audio_label_color: Color32::WHITE,
+            envelope_color: Color32::from_rgb(255, 200, 0),
+            piano_white_key_color: Color32::WHITE,
+            piano_black_key_color: Color32::BLACK,
+            onion_before_color: Color32::from_rgb(100, 150, 255),
+            onion_after_color: Color32::from_rgb(100, 255, 150),
+            minimap_background: Color32::from_gray(30),
+            minimap_viewport_color: Color32::from_rgb(220, 220, 220),
+            toolbar_height: 35.0,
+            controls_height: 40.0,
+            ruler_height: 25.0,
+            layer_panel_width: 250.0,
+            layer_controls_height: 35.0,
+            layer_indent: 16.0,
+            keyframe_radius: 3.0,
+            frame_rounding: 2.0,
+            playhead_width: 2.0,
+            scrub_playhead_width: 3.0,
+        }
+    }
+}
+
+impl TimelineStyle {
+    /// Parse a style from JSON. Missing fields fall back to the dark theme.
+    pub fn from_json(json: &str) -> Result<Self, ThemeError> {
+        serde_json::from_str(json).map_err(|e| ThemeError::Parse(e.to_string()))
+    }
+
+    /// Parse a style from TOML. Missing fields fall back to the dark theme.
+    pub fn from_toml(toml: &str) -> Result<Self, ThemeError> {
+        toml::from_str(toml).map_err(|e| ThemeError::Parse(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String, ThemeError> {
+        serde_json::to_string_pretty(self).map_err(|e| ThemeError::Serialize(e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> Result<String, ThemeError> {
+        toml::to_string_pretty(self).map_err(|e| ThemeError::Serialize(e.to_string()))
+    }
+
+    /// Load a style from a `.json` or `.toml` file
+    pub fn load(path: &Path) -> Result<Self, ThemeError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| ThemeError::Io(e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("toml") => Self::from_toml(&contents),
+            other => Err(ThemeError::UnsupportedFormat(other.unwrap_or("").to_string())),
+        }
+    }
+
+    /// Save the style as `.json` or `.toml` depending on the file extension
+    pub fn save(&self, path: &Path) -> Result<(), ThemeError> {
+        let contents = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => self.to_json()?,
+            Some("toml") => self.to_toml()?,
+            other => return Err(ThemeError::UnsupportedFormat(other.unwrap_or("").to_string())),
+        };
+        std::fs::write(path, contents).map_err(|e| ThemeError::Io(e.to_string()))
+    }
+
+    /// Bundled dark theme (the default)
+    pub fn dark() -> Self {
+        BuiltinTheme::Dark.style()
+    }
+
+    /// Bundled light theme
+    pub fn light() -> Self {
+        BuiltinTheme::Light.style()
+    }
+
+    /// Bundled high-contrast theme
+    pub fn high_contrast() -> Self {
+        BuiltinTheme::HighContrast.style()
+    }
+
+    /// egui visuals matching this style, used for widgets drawn inside the timeline
+    #[cfg(feature = "ui")]
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut visuals = if self.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        visuals.override_text_color = Some(self.text_color);
+        visuals.extreme_bg_color = self.ruler_background;
+        visuals.selection.bg_fill = self.layer_selected;
+        visuals.widgets.noninteractive.bg_stroke.color = self.border_color;
+        visuals
+    }
+}
+
+/// Themes bundled with the crate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuiltinTheme {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl BuiltinTheme {
+    pub fn all() -> [BuiltinTheme; 3] {
+        [BuiltinTheme::Dark, BuiltinTheme::Light, BuiltinTheme::HighContrast]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuiltinTheme::Dark => "Dark",
+            BuiltinTheme::Light => "Light",
+            BuiltinTheme::HighContrast => "High Contrast",
+        }
+    }
+
+    /// Load the bundled style for this theme
+    pub fn style(&self) -> TimelineStyle {
+        let json = match self {
+            BuiltinTheme::Dark => include_str!("dark.json"),
+            BuiltinTheme::Light => include_str!("light.json"),
+            BuiltinTheme::HighContrast => include_str!("high_contrast.json"),
+        };
+        TimelineStyle::from_json(json).unwrap_or_default()
+    }
+}
+
+/// Errors from loading or saving themes
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum ThemeError {
+    #[error("Theme file error: {0}")]
+    Io(String),
+
+    #[error("Invalid theme: {0}")]
+    Parse(String),
+
+    #[error("Failed to serialize theme: {0}")]
+    Serialize(String),
+
+    #[error("Unsupported theme format: {0:?} (expected json or toml)")]
+    UnsupportedFormat(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_dark_matches_default() {
+        assert_eq!(TimelineStyle::dark(), TimelineStyle::default());
+    }
+
+    #[test]
+    fn test_bundled_themes_parse() {
+        for theme in BuiltinTheme::all() {
+            let json = match theme {
+                BuiltinTheme::Dark => include_str!("dark.json"),
+                BuiltinTheme::Light => include_str!("light.json"),
+                BuiltinTheme::HighContrast => include_str!("high_contrast.json"),
+            };
+            let style = TimelineStyle::from_json(json).expect("bundled theme should parse");
+            assert_eq!(style.name, theme.label());
+        }
+        assert!(!TimelineStyle::light().dark_mode);
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let style = TimelineStyle::high_contrast();
+        let toml = style.to_toml().unwrap();
+        assert_eq!(TimelineStyle::from_toml(&toml).unwrap(), style);
+    }
+
+    #[test]
+    fn test_partial_theme_uses_defaults() {
+        let style = TimelineStyle::from_json(r#"{ "name": "Custom", "playhead_color": [0, 255, 0, 255] }"#).unwrap();
+        assert_eq!(style.name, "Custom");
+        assert_eq!(style.playhead_color, Color32::from_rgb(0, 255, 0));
+        assert_eq!(style.grid_color, TimelineStyle::default().grid_color);
+        assert_eq!(style.layer_panel_width, TimelineStyle::default().layer_panel_width);
+    }
+}
@@ -115,7 +115,7 @@ pub struct FrameLabel {
     pub frame: u32,
     pub label: String,
     #[serde(skip)]
-    pub color: Option<egui::Color32>,
+    pub color: Option<ecolor::Color32>,
 }
 
 impl FrameLabel {
@@ -127,7 +127,7 @@ impl FrameLabel {
         }
     }
 
-    pub fn with_color(mut self, color: egui::Color32) -> Self {
+    pub fn with_color(mut self, color: ecolor::Color32) -> Self {
         self.color = Some(color);
         self
     }
@@ -141,7 +141,7 @@ pub struct FrameComment {
     pub author: Option<String>,
     pub timestamp: Option<String>,
     #[serde(skip)]
-    pub color: Option<egui::Color32>,
+    pub color: Option<ecolor::Color32>,
 }
 
 impl FrameComment {
@@ -151,7 +151,7 @@ impl FrameComment {
             comment: comment.into(),
             author: None,
             timestamp: None,
-            color: Some(egui::Color32::from_rgb(100, 150, 255)), // Light blue for comments
+            color: None, // Use the theme's comment color
         }
     }
 
@@ -165,7 +165,7 @@ impl FrameComment {
         self
     }
 
-    pub fn with_color(mut self, color: egui::Color32) -> Self {
+    pub fn with_color(mut self, color: ecolor::Color32) -> Self {
         self.color = Some(color);
         self
     }
@@ -2,187 +2,134 @@
 //! Fixed version with proper layout and no crashes
 
 use egui::{*, self};
-use crate::{TimelineConfig, RiveEngine, LayerId, KeyframeId, MotionEditor, layer::{LayerType, LayerInfo}};
+use crate::{TimelineConfig, RiveEngine, AudioEngine, MotionEditor, layer::{LayerType, LayerInfo}};
+use crate::command_palette::{CommandContext, CommandPalette};
+use crate::automation::{AutomationEnvelope, AutomationLane, LANE_HEIGHT};
+use crate::controller::{AudioDragMode, AutomationDrag, NoteDrag, NoteDragMode, TimelineController};
+use crate::piano_roll::{self, Note, KEY_HEIGHT};
+use crate::minimap_egui::{Minimap, MinimapPosition};
+use crate::events::{TimelineEvent, TimelineOutput};
+use crate::lip_sync::MouthSet;
+use crate::midi::{MidiFile, MidiSelection, MidiTarget};
 use std::collections::HashMap;
 
-/// Keyframe selection state for interactive manipulation
-#[derive(Clone, Debug, Default)]
-pub struct KeyframeSelection {
-    /// Currently selected keyframes (layer_id, frame) -> keyframe_id
-    pub selected: HashMap<(LayerId, u32), KeyframeId>,
-    /// Drag operation state
-    pub drag_state: Option<DragState>,
-    /// Copied keyframes for paste operations
-    pub clipboard: Vec<KeyframeClipboardItem>,
+// State types now live with the headless controller
+pub use crate::controller::{
+    TimelineState, LayerPanelState, KeyframeSelection, DragState, KeyframeClipboardItem,
+    ContextMenuState, ContextMenuType, FrameSizePreset, RulerMode, MIN_ZOOM, MAX_ZOOM,
+};
+
+/// Where lip sync takes its mouth cues from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LipSyncSource {
+    /// The loudness of the sound
+    Amplitude,
+    /// A Rhubarb or Papagayo transcript file
+    Transcript,
 }
 
-/// State tracking an active drag operation
+/// Settings of the Lip Sync dialog
 #[derive(Clone, Debug)]
-pub struct DragState {
-    /// Original positions of all selected keyframes
-    pub original_positions: HashMap<KeyframeId, (LayerId, u32)>,
-    /// Current drag offset in frames
-    pub frame_offset: i32,
-    /// Mouse position where drag started
-    pub start_pos: egui::Pos2,
+pub struct LipSyncDialog {
+    /// Audio layer and clip index of the dialogue
+    pub layer_id: crate::LayerId,
+    pub clip: usize,
+    /// Layer that receives the mouth keyframes
+    pub target: Option<crate::LayerId>,
+    pub mouths: MouthSet,
+    pub source: LipSyncSource,
+    pub transcript_path: String,
+    /// Outcome of the last run
+    pub status: Option<String>,
+}
+
+impl LipSyncDialog {
+    pub fn new(layer_id: crate::LayerId, clip: usize) -> Self {
+        Self {
+            layer_id,
+            clip,
+            target: None,
+            mouths: MouthSet::default(),
+            source: LipSyncSource::Amplitude,
+            transcript_path: String::new(),
+            status: None,
+        }
+    }
 }
 
-/// Clipboard item for copy/paste operations
+/// Settings of the Import MIDI dialog
 #[derive(Clone, Debug)]
-pub struct KeyframeClipboardItem {
-    pub layer_id: LayerId,
-    pub relative_frame: u32,
-    pub data: crate::frame::FrameData,
+pub struct MidiImportDialog {
+    pub path: String,
+    /// The file read from `path`
+    pub file: Option<MidiFile>,
+    pub selection: MidiSelection,
+    pub target: MidiTarget,
+    /// Frame the start of the file lands on
+    pub start_frame: u32,
+    /// Outcome of the last load or import
+    pub status: Option<String>,
 }
 
-impl KeyframeSelection {
-    pub fn new() -> Self {
-        Self::default()
-    }
-    
-    /// Clear all selections
-    pub fn clear(&mut self) {
-        self.selected.clear();
-        self.drag_state = None;
-    }
-    
-    /// Add a keyframe to selection
-    pub fn add(&mut self, layer_id: LayerId, frame: u32, keyframe_id: KeyframeId) {
-        self.selected.insert((layer_id, frame), keyframe_id);
-    }
-    
-    /// Remove a keyframe from selection
-    pub fn remove(&mut self, layer_id: LayerId, frame: u32) {
-        self.selected.remove(&(layer_id, frame));
-    }
-    
-    /// Check if a keyframe is selected
-    pub fn is_selected(&self, layer_id: LayerId, frame: u32) -> bool {
-        self.selected.contains_key(&(layer_id, frame))
-    }
-    
-    /// Get selected keyframes as list
-    pub fn get_selected(&self) -> Vec<(LayerId, u32, KeyframeId)> {
-        self.selected.iter()
-            .map(|((layer_id, frame), keyframe_id)| (layer_id.clone(), *frame, keyframe_id.clone()))
-            .collect()
+impl Default for MidiImportDialog {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            file: None,
+            selection: MidiSelection::default(),
+            target: MidiTarget::Markers,
+            start_frame: 0,
+            status: None,
+        }
     }
 }
 
+/// How strongly keyframe spans are tinted with their layer's color
+const LAYER_TINT_AMOUNT: f32 = 0.35;
+
 /// Main timeline widget that displays layers, frames, and playback controls
+///
+/// The widget is a view over a [`TimelineController`], which holds the config,
+/// state and logic; the controller's fields are reachable through `Deref`
+/// (`timeline.state`, `timeline.config`, ...).
 pub struct Timeline {
-    pub config: TimelineConfig,
-    pub state: TimelineState,
+    pub controller: TimelineController,
     pub i18n: I18n,
-}
-
-/// Persistent state for the timeline
-#[derive(Clone, Debug)]
-pub struct TimelineState {
-    /// Currently selected layers
-    pub selected_layers: Vec<LayerId>,
-    /// Currently selected frames (layer_id -> frame numbers)
-    pub selected_frames: HashMap<LayerId, Vec<u32>>,
-    /// Current playhead position
-    pub playhead_frame: u32,
-    /// Is timeline playing
-    pub is_playing: bool,
-    /// Current zoom level
-    pub zoom_level: f32,
-    /// Horizontal scroll position
-    pub scroll_x: f32,
-    /// Vertical scroll position  
-    pub scroll_y: f32,
-    /// Track heights that have been manually adjusted
-    pub track_heights: HashMap<LayerId, f32>,
-    /// Right-click context menu state
-    pub context_menu: Option<ContextMenuState>,
-    /// Active snap guides (frame positions)
-    pub snap_guides: Vec<f32>,
-    /// Keyframe selection and manipulation state
-    pub keyframe_selection: KeyframeSelection,
+    /// Command palette popup (Ctrl+Shift+P)
+    pub command_palette: CommandPalette,
+    /// Overview strip with a draggable viewport
+    pub minimap: Minimap,
     /// Motion Editor for easing curves
     pub motion_editor: MotionEditor,
-    /// Layer panel state
-    pub layer_panel_state: LayerPanelState,
-    /// Show onion skinning
-    pub onion_skinning: bool,
-    /// Loop playback
-    pub loop_playback: bool,
-    /// Show label management panel
-    pub show_label_panel: bool,
-    /// Frame range selection (start_frame, end_frame)
-    pub frame_range_selection: Option<(u32, u32)>,
-    /// Frame range selection mode enabled
-    pub frame_range_mode: bool,
-    /// Onion skin settings
-    pub onion_skin_frames_before: u32,
-    pub onion_skin_frames_after: u32,
-    pub onion_skin_opacity: f32,
-    /// Show onion skin settings panel
-    pub show_onion_settings: bool,
-    /// Onion skin outline mode
-    pub onion_skin_outline_mode: bool,
-    /// Currently scrubbing the timeline
-    pub is_scrubbing: bool,
-    /// Was playing before scrubbing started
-    pub was_playing: bool,
+    /// Audio layer and clip index shown in the Edit Envelope dialog
+    pub envelope_dialog: Option<(crate::LayerId, usize)>,
+    /// Fade length used by the Edit Envelope dialog's presets
+    pub envelope_fade_frames: u32,
+    /// Lip Sync dialog, when open
+    pub lip_sync_dialog: Option<LipSyncDialog>,
+    /// Import MIDI dialog, when open
+    pub midi_import_dialog: Option<MidiImportDialog>,
+    /// Outcome of the last Detect Beats, shown until dismissed: the
+    /// estimated tempo, if any, or why detection or applying the tempo failed
+    pub beat_detection_result: Option<Result<Option<f64>, String>>,
+    /// Why audio scrubbing stopped during the current playhead drag. Audio
+    /// stays silent until the next drag, which clears it.
+    pub audio_scrub_error: Option<crate::AudioError>,
+    /// Plays audio while the playhead is scrubbed
+    audio_engine: Option<Box<dyn AudioEngine>>,
 }
 
-/// State for the enhanced layer panel
-#[derive(Clone, Debug, Default)]
-pub struct LayerPanelState {
-    /// Layer visibility states
-    pub layer_visibility: HashMap<LayerId, bool>,
-    /// Layer lock states
-    pub layer_locked: HashMap<LayerId, bool>,
-    /// Layer outline mode
-    pub layer_outline: HashMap<LayerId, bool>,
-    /// Expanded folders (track by LayerId)
-    pub expanded_folders: Vec<LayerId>,
-    /// Layer being dragged for reordering (layer_id, initial_y_position)
-    pub dragging_layer: Option<(LayerId, f32)>,
-    /// Drop target position during drag
-    pub drop_target_index: Option<usize>,
-    /// Layer being renamed (layer_id, new_name)
-    pub renaming_layer: Option<(LayerId, String)>,
-    /// Newly created layer that should be focused and renamed
-    pub newly_created_layer: Option<LayerId>,
+impl std::ops::Deref for Timeline {
+    type Target = TimelineController;
+
+    fn deref(&self) -> &TimelineController {
+        &self.controller
+    }
 }
 
-impl Default for TimelineState {
-    fn default() -> Self {
-        let mut layer_panel_state = LayerPanelState::default();
-        // Expand the Effects folder by default (layer3 in mock data)
-        layer_panel_state.expanded_folders.push(LayerId::new("layer3"));
-        
-        Self {
-            selected_layers: Vec::new(),
-            selected_frames: HashMap::new(),
-            playhead_frame: 0,
-            is_playing: false,
-            zoom_level: 1.0,
-            scroll_x: 0.0,
-            scroll_y: 0.0,
-            track_heights: HashMap::new(),
-            context_menu: None,
-            snap_guides: Vec::new(),
-            keyframe_selection: KeyframeSelection::new(),
-            motion_editor: MotionEditor::new(),
-            layer_panel_state,
-            onion_skinning: false,
-            loop_playback: false,
-            show_label_panel: false,
-            frame_range_selection: None,
-            frame_range_mode: false,
-            onion_skin_frames_before: 3,
-            onion_skin_frames_after: 3,
-            onion_skin_opacity: 0.3,
-            show_onion_settings: false,
-            onion_skin_outline_mode: false,
-            is_scrubbing: false,
-            was_playing: false,
-        }
+impl std::ops::DerefMut for Timeline {
+    fn deref_mut(&mut self) -> &mut TimelineController {
+        &mut self.controller
     }
 }
 
@@ -222,34 +169,83 @@ impl Timeline {
     }
     /// Create a new timeline with default configuration
     pub fn new() -> Self {
-        Self {
-            config: TimelineConfig::default(),
-            state: TimelineState::default(),
-            i18n: I18n::new("en"),
-        }
+        Self::with_config(TimelineConfig::default())
     }
 
     /// Create timeline with custom configuration
     pub fn with_config(config: TimelineConfig) -> Self {
+        Self::with_controller(TimelineController::with_config(config))
+    }
+
+    /// Create a view over an existing controller
+    pub fn with_controller(controller: TimelineController) -> Self {
         Self {
-            config,
-            state: TimelineState::default(),
+            controller,
             i18n: I18n::new("en"),
+            command_palette: CommandPalette::new(),
+            minimap: Minimap::new(),
+            motion_editor: MotionEditor::new(),
+            envelope_dialog: None,
+            envelope_fade_frames: 12,
+            lip_sync_dialog: None,
+            midi_import_dialog: None,
+            beat_detection_result: None,
+            audio_scrub_error: None,
+            audio_engine: None,
         }
     }
     
+    /// Enable controlled mode, where the widget never mutates the engine itself
+    /// and the host applies the events returned from `show`
+    pub fn controlled(mut self, controlled: bool) -> Self {
+        self.controller.controlled = controlled;
+        self
+    }
+    
+    /// Use `engine` to play audio snippets while the playhead is scrubbed
+    pub fn set_audio_engine(&mut self, engine: Box<dyn AudioEngine>) {
+        self.audio_engine = Some(engine);
+    }
+
+    /// The audio engine used for scrubbing, if one was set
+    pub fn audio_engine_mut(&mut self) -> Option<&mut (dyn AudioEngine + 'static)> {
+        self.audio_engine.as_deref_mut()
+    }
+    
+    /// Switch the timeline to a different style at runtime
+    pub fn set_style(&mut self, style: crate::TimelineStyle) {
+        self.controller.config.style = style;
+    }
+
+    /// Load a JSON or TOML theme file and apply it
+    pub fn load_theme(&mut self, path: &std::path::Path) -> Result<(), crate::ThemeError> {
+        self.set_style(crate::TimelineStyle::load(path)?);
+        Ok(())
+    }
+    
     /// Get localized tooltip text
     fn get_tooltip(&self, key: &str) -> String {
         self.i18n.get(key)
     }
 
-    /// Show the timeline UI
-    pub fn show(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>) -> Response {
+    /// Show the timeline UI, returning the events emitted this frame
+    pub fn show(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>) -> TimelineOutput {
         let available_rect = ui.available_rect_before_wrap();
+        let layers_before = self.controller.state.selected_layers.clone();
+        let keyframes_before = self.controller.state.selected_keyframe_positions();
+        
+        // Restyle egui widgets drawn inside the timeline, restoring the host's visuals afterwards
+        let host_visuals = ui.visuals().clone();
+        *ui.visuals_mut() = self.controller.config.style.visuals();
         
         // Handle keyboard shortcuts
         self.handle_keyboard_shortcuts(ui, engine);
         
+        // Drive automated properties from the engine's frame
+        let current_frame = engine.get_current_frame();
+        self.controller.apply_automation(engine.as_mut(), current_frame);
+        self.controller.play_notes(engine.as_mut(), current_frame);
+        
         // Allocate space for the timeline
         let response = ui.allocate_rect(available_rect, Sense::click_and_drag());
         
@@ -257,14 +253,17 @@ impl Timeline {
         ui.painter().rect_filled(
             available_rect,
             0.0,
-            self.config.style.background_color,
+            self.controller.config.style.background_color,
         );
 
         // Calculate layout regions
-        let toolbar_height = 35.0;
-        let controls_height = 40.0;
-        let ruler_height = 25.0;
-        let layer_panel_width = 250.0; // Wider for Flash-style controls
+        let style = &self.controller.config.style;
+        let toolbar_height = style.toolbar_height;
+        let controls_height = style.controls_height;
+        let ruler_height = style.ruler_height;
+        let layer_panel_width = style.layer_panel_width;
+        let minimap_height = self.minimap.layout_height();
+        let minimap_top = if self.minimap.position == MinimapPosition::Top { minimap_height } else { 0.0 };
         
         // Timeline toolbar (top)
         let toolbar_rect = Rect::from_min_size(
@@ -274,25 +273,37 @@ impl Timeline {
         
         // Layer panel (left side, below toolbar)
         let layer_panel_rect = Rect::from_min_size(
-            available_rect.min + vec2(0.0, toolbar_height),
-            vec2(layer_panel_width, available_rect.height() - toolbar_height - controls_height),
+            available_rect.min + vec2(0.0, toolbar_height + minimap_top),
+            vec2(layer_panel_width, available_rect.height() - toolbar_height - controls_height - minimap_height),
         );
 
         // Ruler (top of frame area)
         let ruler_rect = Rect::from_min_size(
-            available_rect.min + vec2(layer_panel_width, toolbar_height),
+            available_rect.min + vec2(layer_panel_width, toolbar_height + minimap_top),
             vec2(available_rect.width() - layer_panel_width, ruler_height),
         );
 
         // Frame grid (main timeline area)
         let frame_grid_rect = Rect::from_min_size(
-            available_rect.min + vec2(layer_panel_width, toolbar_height + ruler_height),
+            available_rect.min + vec2(layer_panel_width, toolbar_height + minimap_top + ruler_height),
             vec2(
                 available_rect.width() - layer_panel_width,
-                available_rect.height() - toolbar_height - ruler_height - controls_height,
+                available_rect.height() - toolbar_height - ruler_height - controls_height - minimap_height,
             ),
         );
 
+        // Overview minimap (frame column, above the ruler or below the grid)
+        let minimap_rect = match self.minimap.position {
+            MinimapPosition::Top => Rect::from_min_size(
+                available_rect.min + vec2(layer_panel_width, toolbar_height),
+                vec2(available_rect.width() - layer_panel_width, minimap_height),
+            ),
+            MinimapPosition::Bottom => Rect::from_min_size(
+                pos2(frame_grid_rect.min.x, frame_grid_rect.max.y),
+                vec2(frame_grid_rect.width(), minimap_height),
+            ),
+        };
+
         // Playback controls (bottom)
         let controls_rect = Rect::from_min_size(
             available_rect.min + vec2(0.0, available_rect.height() - controls_height),
@@ -300,10 +311,13 @@ impl Timeline {
         );
 
         // Draw each section
-        self.draw_timeline_toolbar(ui, toolbar_rect);
+        self.draw_timeline_toolbar(ui, toolbar_rect, engine);
         self.draw_enhanced_layer_panel(ui, layer_panel_rect, engine);
         self.draw_ruler(ui, ruler_rect, engine);
         self.draw_frame_grid_fixed(ui, frame_grid_rect, engine);
+        if self.minimap.visible {
+            self.minimap.show(ui, minimap_rect, frame_grid_rect.width(), engine.as_ref(), &self.controller.config, &mut self.controller.state);
+        }
         self.draw_enhanced_playback_controls(ui, controls_rect, engine);
         self.draw_playhead(ui, ruler_rect, frame_grid_rect, engine);
         
@@ -311,34 +325,71 @@ impl Timeline {
         self.handle_context_menu(ui, engine);
         
         // Draw label management panel if visible
-        if self.state.show_label_panel {
+        if self.controller.state.show_label_panel {
             self.draw_label_management_panel(ui, engine);
         }
         
         // Draw onion skin settings panel if visible
-        if self.state.show_onion_settings {
+        if self.controller.state.show_onion_settings {
             self.draw_onion_settings_panel(ui);
         }
         
+        // Draw the Edit Envelope dialog if open
+        if let Some((layer_id, clip)) = self.envelope_dialog.clone() {
+            self.draw_envelope_dialog(ui, engine, &layer_id, clip);
+        }
+        
+        // Draw the Lip Sync dialog if open
+        if self.lip_sync_dialog.is_some() {
+            self.draw_lip_sync_dialog(ui, engine);
+        }
+        
+        // Draw the Import MIDI dialog if open
+        if self.midi_import_dialog.is_some() {
+            self.draw_midi_import_dialog(ui, engine);
+        }
+        
+        // Draw the Detect Beats result if there is one
+        if self.beat_detection_result.is_some() {
+            self.draw_beat_detection_dialog(ui);
+        }
+        
         // Draw snap guides
         self.draw_snap_guides(ui, frame_grid_rect);
         
         // Show Motion Editor if open
-        self.state.motion_editor.show(ui.ctx());
+        self.motion_editor.show(ui.ctx());
+        
+        // Show command palette and run the chosen command
+        let command_context = CommandContext::from_state(&self.controller.state);
+        if let Some(command_id) = self.command_palette.show(ui.ctx(), &self.controller.commands, &command_context) {
+            self.execute_command(command_id, engine);
+        }
+        
+        *ui.visuals_mut() = host_visuals;
+
+        let keyframes = self.controller.state.selected_keyframe_positions();
+        if self.controller.state.selected_layers != layers_before || keyframes != keyframes_before {
+            let layers = self.controller.state.selected_layers.clone();
+            self.controller.emit(TimelineEvent::SelectionChanged { layers, keyframes }, engine.as_mut());
+        }
 
-        response
+        TimelineOutput {
+            response,
+            events: self.controller.take_events(),
+        }
     }
 
     /// Draw the Flash-style timeline toolbar
-    fn draw_timeline_toolbar(&mut self, ui: &mut Ui, rect: Rect) {
+    fn draw_timeline_toolbar(&mut self, ui: &mut Ui, rect: Rect, engine: &mut Box<dyn RiveEngine>) {
         ui.scope_builder(UiBuilder::new().max_rect(rect), |ui| {
             // Background
-            ui.painter().rect_filled(rect, 0.0, Color32::from_gray(55));
+            ui.painter().rect_filled(rect, 0.0, self.controller.config.style.toolbar_background);
             
             // Border
             ui.painter().line_segment(
                 [rect.left_bottom(), rect.right_bottom()],
-                Stroke::new(1.0, Color32::from_gray(80)),
+                Stroke::new(1.0, self.controller.config.style.border_color),
             );
             
             ui.horizontal(|ui| {
@@ -346,55 +397,55 @@ impl Timeline {
                 
                 // Frame navigation buttons
                 if ui.button("⏮").on_hover_text(self.get_tooltip("timeline.toolbar.first_frame")).clicked() {
-                    self.go_to_first_frame(ui.ctx());
+                    self.controller.go_to_first_frame(engine.as_mut());
                 }
                 
                 if ui.button("◀").on_hover_text(self.get_tooltip("timeline.toolbar.previous_frame")).clicked() {
-                    self.go_to_previous_frame(ui.ctx());
+                    self.controller.go_to_previous_frame(engine.as_mut());
                 }
                 
                 if ui.button("▶").on_hover_text(self.get_tooltip("timeline.toolbar.next_frame")).clicked() {
-                    self.go_to_next_frame(ui.ctx());
+                    self.controller.go_to_next_frame(engine.as_mut());
                 }
                 
                 if ui.button("⏭").on_hover_text(self.get_tooltip("timeline.toolbar.last_frame")).clicked() {
-                    self.go_to_last_frame(ui.ctx());
+                    self.controller.go_to_last_frame(engine.as_mut());
                 }
                 
                 ui.separator();
                 
                 // Onion skinning toggle
-                let onion_label = if self.state.onion_skinning { "🧅 On" } else { "🧅 Off" };
-                if ui.selectable_label(self.state.onion_skinning, onion_label)
+                let onion_label = if self.controller.state.onion_skinning { "🧅 On" } else { "🧅 Off" };
+                if ui.selectable_label(self.controller.state.onion_skinning, onion_label)
                     .on_hover_text(self.get_tooltip("timeline.toolbar.onion_skinning"))
                     .clicked() 
                 {
-                    self.state.onion_skinning = !self.state.onion_skinning;
+                    self.controller.state.onion_skinning = !self.controller.state.onion_skinning;
                 }
                 
                 // Onion skin settings button (only show when enabled)
-                if self.state.onion_skinning {
+                if self.controller.state.onion_skinning {
                     if ui.button("⚙").on_hover_text("Onion Skin Settings").clicked() {
-                        self.state.show_onion_settings = !self.state.show_onion_settings;
+                        self.controller.state.show_onion_settings = !self.controller.state.show_onion_settings;
                     }
                 }
                 
                 ui.separator();
                 
                 // Loop toggle
-                let loop_label = if self.state.loop_playback { "🔁" } else { "➡️" };
-                if ui.selectable_label(self.state.loop_playback, loop_label)
+                let loop_label = if self.controller.state.loop_playback { "🔁" } else { "➡️" };
+                if ui.selectable_label(self.controller.state.loop_playback, loop_label)
                     .on_hover_text(self.get_tooltip("timeline.toolbar.loop_playback"))
                     .clicked() 
                 {
-                    self.state.loop_playback = !self.state.loop_playback;
+                    self.controller.state.loop_playback = !self.controller.state.loop_playback;
                 }
                 
                 ui.separator();
                 
                 // Center frame button
                 if ui.button("⊡").on_hover_text(self.get_tooltip("timeline.toolbar.center_playhead")).clicked() {
-                    self.center_playhead();
+                    self.controller.center_playhead(engine.as_ref());
                 }
                 
                 ui.separator();
@@ -405,27 +456,48 @@ impl Timeline {
                 }
                 
                 // Frame-based selection toggle  
-                let frame_selection_icon = if self.state.frame_range_mode { "✓⬚" } else { "⬚" };
-                if ui.selectable_label(self.state.frame_range_mode, frame_selection_icon)
+                let frame_selection_icon = if self.controller.state.frame_range_mode { "✓⬚" } else { "⬚" };
+                if ui.selectable_label(self.controller.state.frame_range_mode, frame_selection_icon)
                     .on_hover_text(self.get_tooltip("timeline.toolbar.frame_selection"))
                     .clicked() 
                 {
-                    self.state.frame_range_mode = !self.state.frame_range_mode;
-                    if !self.state.frame_range_mode {
-                        self.state.frame_range_selection = None;
+                    self.controller.state.frame_range_mode = !self.controller.state.frame_range_mode;
+                    if !self.controller.state.frame_range_mode {
+                        self.controller.state.frame_range_selection = None;
                     }
                 }
                 
                 ui.separator();
                 
                 // Label management panel toggle
-                let label_panel_icon = if self.state.show_label_panel { "📋" } else { "📃" };
-                if ui.selectable_label(self.state.show_label_panel, label_panel_icon)
+                let label_panel_icon = if self.controller.state.show_label_panel { "📋" } else { "📃" };
+                if ui.selectable_label(self.controller.state.show_label_panel, label_panel_icon)
                     .on_hover_text(self.get_tooltip("timeline.toolbar.label_panel"))
                     .clicked() 
                 {
-                    self.state.show_label_panel = !self.state.show_label_panel;
+                    self.controller.state.show_label_panel = !self.controller.state.show_label_panel;
+                }
+                
+                // Overview minimap toggle
+                if ui.selectable_label(self.minimap.visible, "🗺")
+                    .on_hover_text(self.get_tooltip("timeline.toolbar.minimap"))
+                    .clicked()
+                {
+                    self.minimap.visible = !self.minimap.visible;
                 }
+                
+                ui.separator();
+                
+                // Theme switcher
+                let theme_tooltip = self.get_tooltip("timeline.toolbar.theme");
+                ui.menu_button("🎨", |ui| {
+                    for theme in crate::BuiltinTheme::all() {
+                        if ui.selectable_label(self.controller.config.style.name == theme.label(), theme.label()).clicked() {
+                            self.set_style(theme.style());
+                            ui.close();
+                        }
+                    }
+                }).response.on_hover_text(theme_tooltip);
             });
         });
     }
@@ -434,16 +506,16 @@ impl Timeline {
     fn draw_enhanced_layer_panel(&mut self, ui: &mut Ui, rect: Rect, engine: &mut Box<dyn RiveEngine>) {
         ui.scope_builder(UiBuilder::new().max_rect(rect), |ui| {
             // Background
-            ui.painter().rect_filled(rect, 0.0, Color32::from_gray(45));
+            ui.painter().rect_filled(rect, 0.0, self.controller.config.style.layer_panel_background);
             
             // Border
             ui.painter().line_segment(
                 [rect.right_top(), rect.right_bottom()],
-                Stroke::new(1.0, Color32::from_gray(80)),
+                Stroke::new(1.0, self.controller.config.style.border_color),
             );
             
             // Layer controls at bottom
-            let controls_height = 35.0;
+            let controls_height = self.controller.config.style.layer_controls_height;
             let controls_rect = Rect::from_min_size(
                 pos2(rect.min.x, rect.max.y - controls_height),
                 vec2(rect.width(), controls_height),
@@ -460,38 +532,35 @@ impl Timeline {
                 ScrollArea::vertical()
                     .id_salt("layer_list")
                     .show(ui, |ui| {
-                        let layers = engine.get_layers();
+                        let layers = self.controller.layers(engine.as_ref());
                         
-                        // Track if we're currently dragging
-                        let mut layer_order_changed = false;
-                        let mut new_layer_order = layers.clone();
+                        // Layer dropped this frame and where it landed
+                        let mut dropped_layer = None;
                         
                         for (idx, layer) in layers.iter().enumerate() {
-                            let layer_height = self.state.track_heights
+                            let layer_height = self.controller.state.track_heights
                                 .get(&layer.id)
                                 .copied()
-                                .unwrap_or(self.config.default_track_height);
+                                .unwrap_or(self.controller.config.default_track_height);
                             
-                            let is_selected = self.state.selected_layers.contains(&layer.id);
-                            let is_visible = *self.state.layer_panel_state.layer_visibility
+                            let is_selected = self.controller.state.selected_layers.contains(&layer.id);
+                            let is_visible = *self.controller.state.layer_panel_state.layer_visibility
                                 .get(&layer.id)
                                 .unwrap_or(&true);
-                            let is_locked = *self.state.layer_panel_state.layer_locked
-                                .get(&layer.id)
-                                .unwrap_or(&false);
-                            let is_outline = *self.state.layer_panel_state.layer_outline
+                            let is_locked = *self.controller.state.layer_panel_state.layer_locked
                                 .get(&layer.id)
                                 .unwrap_or(&false);
+                            let is_outline = self.controller.state.is_layer_outline(&layer.id);
                             
                             // Check if this is the drop target position
-                            if let Some(drop_idx) = self.state.layer_panel_state.drop_target_index {
+                            if let Some(drop_idx) = self.controller.state.layer_panel_state.drop_target_index {
                                 if drop_idx == idx {
                                     // Draw insertion line
                                     let rect = ui.available_rect_before_wrap();
                                     let insertion_y = rect.min.y;
                                     ui.painter().line_segment(
                                         [pos2(rect.min.x, insertion_y), pos2(rect.max.x, insertion_y)],
-                                        Stroke::new(2.0, Color32::from_rgb(100, 200, 255)),
+                                        Stroke::new(2.0, self.controller.config.style.drop_indicator_color),
                                     );
                                     ui.add_space(3.0);
                                 }
@@ -508,11 +577,11 @@ impl Timeline {
                             
                             if response.drag_started() && !is_locked {
                                 // Start dragging this layer
-                                self.state.layer_panel_state.dragging_layer = Some((layer.id.clone(), response.interact_pointer_pos().unwrap_or_default().y));
+                                self.controller.state.layer_panel_state.dragging_layer = Some((layer.id.clone(), response.interact_pointer_pos().unwrap_or_default().y));
                             }
                             
                             // Handle drop target
-                            if self.state.layer_panel_state.dragging_layer.is_some() {
+                            if self.controller.state.layer_panel_state.dragging_layer.is_some() {
                                 if let Some(hover_pos) = response.hover_pos() {
                                     // Calculate which side of the layer we're hovering on
                                     let relative_y = hover_pos.y - layer_rect.min.y;
@@ -521,26 +590,19 @@ impl Timeline {
                                     } else {
                                         idx + 1
                                     };
-                                    self.state.layer_panel_state.drop_target_index = Some(drop_index);
+                                    self.controller.state.layer_panel_state.drop_target_index = Some(drop_index);
                                 }
                             }
                             
                             // Check if drag ended on this position
                             if response.drag_stopped() {
-                                if let Some((dragged_layer_id, _)) = &self.state.layer_panel_state.dragging_layer {
-                                    if let Some(drop_idx) = self.state.layer_panel_state.drop_target_index {
-                                        // Find the dragged layer's current index
-                                        if let Some(drag_idx) = new_layer_order.iter().position(|l| &l.id == dragged_layer_id) {
-                                            // Perform the reorder
-                                            let dragged_layer = new_layer_order.remove(drag_idx);
-                                            let insert_idx = if drag_idx < drop_idx { drop_idx - 1 } else { drop_idx };
-                                            new_layer_order.insert(insert_idx.min(new_layer_order.len()), dragged_layer);
-                                            layer_order_changed = true;
-                                        }
+                                if let Some((dragged_layer_id, _)) = &self.controller.state.layer_panel_state.dragging_layer {
+                                    if let Some(drop_idx) = self.controller.state.layer_panel_state.drop_target_index {
+                                        dropped_layer = Some((dragged_layer_id.clone(), drop_idx));
                                     }
                                     // Clear drag state
-                                    self.state.layer_panel_state.dragging_layer = None;
-                                    self.state.layer_panel_state.drop_target_index = None;
+                                    self.controller.state.layer_panel_state.dragging_layer = None;
+                                    self.controller.state.layer_panel_state.drop_target_index = None;
                                 }
                             }
                             
@@ -552,7 +614,7 @@ impl Timeline {
                                 while let Some(pid) = current_parent {
                                     if let Some(parent_layer) = layers.iter().find(|l| &l.id == pid) {
                                         if parent_layer.layer_type == LayerType::Folder && 
-                                           !self.state.layer_panel_state.expanded_folders.contains(pid) {
+                                           !self.controller.state.layer_panel_state.expanded_folders.contains(pid) {
                                             skip_layer = true;
                                             break;
                                         }
@@ -575,27 +637,23 @@ impl Timeline {
                                     ui.painter().rect_filled(
                                         rect,
                                         0.0,
-                                        Color32::from_rgb(70, 130, 180),
+                                        self.controller.config.style.layer_selected,
                                     );
                                 }
                                 
                                 // Calculate indentation
                                 let indent_level = self.calculate_layer_indent_level(&layer, &layers);
-                                ui.add_space(indent_level as f32 * 16.0); // 16 pixels per indent level
+                                ui.add_space(indent_level as f32 * self.controller.config.style.layer_indent);
                                 
                                 // Expand/collapse arrow for folders
                                 if layer.layer_type == LayerType::Folder {
-                                    let is_expanded = self.state.layer_panel_state.expanded_folders.contains(&layer.id);
+                                    let is_expanded = self.controller.state.layer_panel_state.expanded_folders.contains(&layer.id);
                                     let arrow_icon = if is_expanded { "▼" } else { "▶" };
                                     if ui.button(arrow_icon)
                                         .on_hover_text(self.get_tooltip("timeline.layer.toggle_folder"))
                                         .clicked() 
                                     {
-                                        if is_expanded {
-                                            self.state.layer_panel_state.expanded_folders.retain(|id| id != &layer.id);
-                                        } else {
-                                            self.state.layer_panel_state.expanded_folders.push(layer.id.clone());
-                                        }
+                                        self.controller.toggle_folder(&layer.id);
                                     }
                                 }
                                 
@@ -616,8 +674,7 @@ impl Timeline {
                                     .on_hover_text(self.i18n.get("timeline.layer.visibility_tooltip"))
                                     .clicked() 
                                 {
-                                    self.state.layer_panel_state.layer_visibility
-                                        .insert(layer.id.clone(), !is_visible);
+                                    self.controller.toggle_layer_visibility(&layer.id);
                                 }
                                 
                                 // Lock icon
@@ -626,8 +683,7 @@ impl Timeline {
                                     .on_hover_text(self.i18n.get("timeline.layer.lock_tooltip"))
                                     .clicked() 
                                 {
-                                    self.state.layer_panel_state.layer_locked
-                                        .insert(layer.id.clone(), !is_locked);
+                                    self.controller.toggle_layer_lock(&layer.id);
                                 }
                                 
                                 // Outline icon
@@ -636,12 +692,36 @@ impl Timeline {
                                     .on_hover_text(self.get_tooltip("timeline.layer.outline_mode"))
                                     .clicked() 
                                 {
-                                    self.state.layer_panel_state.layer_outline
-                                        .insert(layer.id.clone(), !is_outline);
+                                    self.controller.toggle_layer_outline(&layer.id);
+                                }
+                                
+                                // Piano roll toggle
+                                if self.controller.piano_roll(&layer.id).is_some() {
+                                    let shown = self.controller.state.layer_panel_state.piano_roll_shown.contains(&layer.id);
+                                    if ui.selectable_label(shown, "🎹").on_hover_text("Show piano roll").clicked() {
+                                        self.controller.toggle_piano_roll(&layer.id);
+                                    }
+                                }
+                                
+                                // Automation lanes toggle
+                                if !self.controller.automation_lanes(&layer.id).is_empty() {
+                                    let shown = self.controller.state.layer_panel_state.automation_shown.contains(&layer.id);
+                                    if ui.selectable_label(shown, "〰").on_hover_text("Show automation lanes").clicked() {
+                                        self.controller.toggle_automation_lanes(&layer.id);
+                                    }
+                                }
+                                
+                                // Layer color swatch
+                                let mut outline_color = layer.outline_color;
+                                if color_picker::color_edit_button_srgba(ui, &mut outline_color, color_picker::Alpha::Opaque)
+                                    .on_hover_text(self.get_tooltip("timeline.layer.outline_color"))
+                                    .changed()
+                                {
+                                    self.controller.set_layer_outline_color(engine.as_mut(), layer.id.clone(), outline_color);
                                 }
                                 
                                 // Layer name (selectable or editable if renaming)
-                                if let Some((renaming_id, ref mut new_name)) = &mut self.state.layer_panel_state.renaming_layer {
+                                if let Some((renaming_id, ref mut new_name)) = &mut self.controller.state.layer_panel_state.renaming_layer {
                                     if renaming_id == &layer.id {
                                         // Show text edit for renaming
                                         let response = ui.text_edit_singleline(new_name);
@@ -650,11 +730,9 @@ impl Timeline {
                                         if response.lost_focus() {
                                             if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                                                 // Apply the rename through the engine
-                                                engine.rename_layer(layer.id.clone(), new_name.clone());
-                                                self.state.layer_panel_state.renaming_layer = None;
+                                                self.controller.commit_rename(engine.as_mut());
                                             } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                                                // Cancel rename
-                                                self.state.layer_panel_state.renaming_layer = None;
+                                                self.controller.cancel_rename();
                                             }
                                         }
                                         
@@ -664,23 +742,23 @@ impl Timeline {
                                         }
                                         
                                         // If this is a newly created layer, scroll to it
-                                        if let Some(newly_created_id) = &self.state.layer_panel_state.newly_created_layer {
+                                        if let Some(newly_created_id) = &self.controller.state.layer_panel_state.newly_created_layer {
                                             if newly_created_id == &layer.id {
                                                 // Scroll to this layer
                                                 ui.scroll_to_rect(layer_rect, Some(Align::Center));
                                                 // Clear the newly created flag after scrolling
-                                                self.state.layer_panel_state.newly_created_layer = None;
+                                                self.controller.state.layer_panel_state.newly_created_layer = None;
                                             }
                                         }
                                     } else {
                                         // Show normal label for other layers  
                                         let response = ui.selectable_label(is_selected, &layer.name);
-                                        self.handle_layer_name_interaction(ui, response, layer, idx, &layers);
+                                        self.handle_layer_name_interaction(ui, response, layer, &layers);
                                     }
                                 } else {
                                     // Normal label when not renaming - handle double-click and selection
                                     let response = ui.selectable_label(is_selected, &layer.name);
-                                    self.handle_layer_name_interaction(ui, response, layer, idx, &layers);
+                                    self.handle_layer_name_interaction(ui, response, layer, &layers);
                                 }
                                 
                                 // Right-click context menu - use layer_rect for better precision
@@ -688,7 +766,7 @@ impl Timeline {
                                     .secondary_clicked() 
                                 {
                                     if let Some(pos) = ui.input(|i| i.pointer.interact_pos()) {
-                                        self.state.context_menu = Some(ContextMenuState {
+                                        self.controller.state.context_menu = Some(ContextMenuState {
                                             position: pos,
                                             menu_type: ContextMenuType::Layer(layer.id.clone()),
                                         });
@@ -696,25 +774,28 @@ impl Timeline {
                                 }
                             });
                             
+                            // Piano roll keys and automation lane names, beside them in the grid
+                            if self.controller.piano_roll_height(&layer.id) > 0.0 {
+                                self.draw_piano_roll_header(ui, &layer.id);
+                            }
+                            if self.controller.automation_height(&layer.id) > 0.0 {
+                                self.draw_automation_lane_headers(ui, &layer.id);
+                            }
+                            
                             // Add horizontal separator line beneath each layer
                             let separator_rect = ui.available_rect_before_wrap();
                             let separator_y = separator_rect.min.y;
                             ui.painter().line_segment(
                                 [pos2(separator_rect.min.x + 10.0, separator_y), pos2(separator_rect.max.x - 10.0, separator_y)],
-                                Stroke::new(0.5, Color32::from_gray(60)),
+                                Stroke::new(0.5, self.controller.config.style.separator_color),
                             );
                             
                             ui.add_space(2.0);
                         }
                         
                         // Apply layer order changes if any
-                        if layer_order_changed {
-                            // TODO: Add a reorder_layers method to RiveEngine trait
-                            // For now, we'll just print the new order
-                            println!("New layer order:");
-                            for (i, layer) in new_layer_order.iter().enumerate() {
-                                println!("  {}: {}", i, layer.name);
-                            }
+                        if let Some((layer_id, drop_idx)) = dropped_layer {
+                            self.controller.reorder_layer(engine.as_mut(), &layers, &layer_id, drop_idx);
                         }
                     });
             });
@@ -726,39 +807,27 @@ impl Timeline {
                     
                     // Add layer
                     if ui.button("➕").on_hover_text(self.get_tooltip("timeline.layer.new_layer")).clicked() {
-                        let layer_id = engine.add_layer("New Layer".to_string(), crate::layer::LayerType::Normal);
-                        // Set this layer for auto-focus and renaming
-                        self.state.layer_panel_state.newly_created_layer = Some(layer_id.clone());
-                        self.state.layer_panel_state.renaming_layer = Some((layer_id.clone(), "New Layer".to_string()));
-                        // Select the newly created layer
-                        self.state.selected_layers.clear();
-                        self.state.selected_layers.push(layer_id.clone());
-                        println!("Added new layer: {:?}", layer_id);
+                        // No id in controlled mode; the host creates the layer
+                        if let Some(layer_id) = self.controller.add_layer(engine.as_mut(), "New Layer", LayerType::Normal) {
+                            println!("Added new layer: {:?}", layer_id);
+                        }
                     }
                     
                     // Add folder
                     if ui.button("📁").on_hover_text(self.get_tooltip("timeline.layer.new_folder")).clicked() {
-                        let layer_id = engine.add_folder_layer("New Folder".to_string());
-                        // Set this folder for auto-focus and renaming
-                        self.state.layer_panel_state.newly_created_layer = Some(layer_id.clone());
-                        self.state.layer_panel_state.renaming_layer = Some((layer_id.clone(), "New Folder".to_string()));
-                        // Select the newly created folder
-                        self.state.selected_layers.clear();
-                        self.state.selected_layers.push(layer_id.clone());
-                        println!("Added new folder layer: {:?}", layer_id);
+                        // No id in controlled mode; the host creates the folder
+                        if let Some(layer_id) = self.controller.add_layer(engine.as_mut(), "New Folder", LayerType::Folder) {
+                            println!("Added new folder layer: {:?}", layer_id);
+                        }
                     }
                     
                     // Delete layer
-                    let can_delete = !self.state.selected_layers.is_empty();
+                    let can_delete = !self.controller.state.selected_layers.is_empty();
                     if ui.add_enabled(can_delete, Button::new("🗑"))
                         .on_hover_text(self.get_tooltip("timeline.layer.delete_layer"))
                         .clicked() 
                     {
-                        for layer_id in self.state.selected_layers.clone() {
-                            engine.delete_layer(layer_id.clone());
-                            println!("Deleted layer: {:?}", layer_id);
-                        }
-                        self.state.selected_layers.clear();
+                        self.controller.delete_selected_layers(engine.as_mut());
                     }
                     
                     // Duplicate layer
@@ -766,34 +835,19 @@ impl Timeline {
                         .on_hover_text(self.get_tooltip("timeline.layer.duplicate_layer"))
                         .clicked() 
                     {
-                        for layer_id in self.state.selected_layers.clone() {
-                            let new_layer_id = engine.duplicate_layer(layer_id.clone());
-                            println!("Duplicated layer {:?} to {:?}", layer_id, new_layer_id);
-                        }
+                        self.controller.duplicate_selected_layers(engine.as_mut());
                     }
                     
                     ui.separator();
                     
                     // Show/hide all layers
                     if ui.button("👁").on_hover_text(self.get_tooltip("timeline.layer.toggle_visibility_all")).clicked() {
-                        let all_visible = engine.get_layers().iter()
-                            .all(|l| *self.state.layer_panel_state.layer_visibility.get(&l.id).unwrap_or(&true));
-                        
-                        for layer in engine.get_layers() {
-                            self.state.layer_panel_state.layer_visibility
-                                .insert(layer.id.clone(), !all_visible);
-                        }
+                        self.controller.toggle_visibility_all(&engine.get_layers());
                     }
                     
                     // Lock/unlock all layers
                     if ui.button("🔒").on_hover_text(self.get_tooltip("timeline.layer.toggle_lock_all")).clicked() {
-                        let all_locked = engine.get_layers().iter()
-                            .all(|l| *self.state.layer_panel_state.layer_locked.get(&l.id).unwrap_or(&false));
-                        
-                        for layer in engine.get_layers() {
-                            self.state.layer_panel_state.layer_locked
-                                .insert(layer.id.clone(), !all_locked);
-                        }
+                        self.controller.toggle_lock_all(&engine.get_layers());
                     }
                 });
             });
@@ -806,7 +860,7 @@ impl Timeline {
         ui.painter().rect_filled(
             rect,
             0.0,
-            Color32::from_gray(40), // Slightly lighter gray
+            self.controller.config.style.background_color,
         );
         
         // Draw a prominent border to debug the area
@@ -817,22 +871,33 @@ impl Timeline {
             egui::StrokeKind::Outside,
         );
         
+        // Ctrl+wheel and pinch zoom, anchored at the frame under the cursor
+        let zoom_delta = ui.input(|i| i.zoom_delta());
+        if zoom_delta != 1.0 {
+            if let Some(pointer) = ui.input(|i| i.pointer.hover_pos()).filter(|p| rect.contains(*p)) {
+                let total_frames = engine.get_total_frames();
+                self.controller.state.zoom_around(zoom_delta, pointer.x - rect.min.x, self.controller.config.frame_width, total_frames);
+            }
+        }
+        self.controller.state.set_view_width(rect.width(), self.controller.config.frame_width);
+        
         // Use scope_builder to properly constrain the ScrollArea
         ui.scope_builder(UiBuilder::new().max_rect(rect), |ui| {
-            ScrollArea::both()
+            // Keep the grid in sync with `scroll_x`, which the ruler, playhead and minimap also use
+            let output = ScrollArea::both()
                 .id_salt("timeline_frame_grid")
                 .auto_shrink([false, false])
                 .scroll_bar_visibility(scroll_area::ScrollBarVisibility::AlwaysVisible)
+                .horizontal_scroll_offset(self.controller.state.scroll_x)
                 .show(ui, |ui| {
-                    let layers = engine.get_layers();
-                    let frame_width = self.config.frame_width * self.state.zoom_level;
+                    let layers = self.controller.layers(engine.as_ref());
+                    let frame_width = self.controller.config.frame_width * self.controller.state.zoom_level;
                     let total_frames = engine.get_total_frames();
                     
                     // Calculate content size
                     let total_width = total_frames as f32 * frame_width;
                     let total_height = layers.iter()
-                        .map(|l| self.state.track_heights.get(&l.id).copied()
-                            .unwrap_or(self.config.default_track_height))
+                        .map(|l| self.controller.track_height(&l.id))
                         .sum::<f32>()
                         .max(rect.height()); // Ensure minimum height
                     
@@ -867,17 +932,22 @@ impl Timeline {
                     ui.painter().rect_filled(
                         Rect::from_min_size(pos2(0.0, 0.0), vec2(total_width, total_height)),
                         0.0,
-                        self.config.style.background_color,
+                        self.controller.config.style.background_color,
                     );
                     
-                    // Draw vertical grid lines
+                    // Draw vertical grid lines; in bars|beats mode frames fade
+                    // behind the beat grid
+                    let musical = self.controller.state.ruler_mode == RulerMode::BarsBeats;
+                    let grid_color = self.controller.config.style.grid_color;
                     for frame in visible_start_frame..=visible_end_frame {
                         let x = frame as f32 * frame_width;
                         
-                        let color = if frame % 5 == 0 {
-                            self.config.style.grid_color
+                        let color = if musical {
+                            grid_color.gamma_multiply(0.3)
+                        } else if frame % 5 == 0 {
+                            grid_color
                         } else {
-                            self.config.style.grid_color.gamma_multiply(0.5)
+                            grid_color.gamma_multiply(0.5)
                         };
                         
                         ui.painter().line_segment(
@@ -885,18 +955,33 @@ impl Timeline {
                             Stroke::new(1.0, color),
                         );
                     }
+                    if musical {
+                        let beats = self.controller.config.tempo_map.grid(
+                            visible_start_frame as f64..visible_end_frame as f64,
+                            self.controller.config.fps.to_fps(),
+                            self.controller.config.snap.beat_subdivision,
+                        );
+                        for line in beats {
+                            let x = line.frame as f32 * frame_width;
+                            let stroke = match line.kind {
+                                crate::GridKind::Bar => Stroke::new(2.0, grid_color),
+                                crate::GridKind::Beat => Stroke::new(1.0, grid_color),
+                                crate::GridKind::Subdivision => Stroke::new(1.0, grid_color.gamma_multiply(0.6)),
+                            };
+                            ui.painter().line_segment([pos2(x, 0.0), pos2(x, total_height)], stroke);
+                        }
+                    }
                     
                     // Draw frames for each layer
                     let mut y_offset = 0.0;
                     for (layer_idx, layer) in layers.iter().enumerate() {
-                        let layer_height = self.state.track_heights
-                            .get(&layer.id)
-                            .copied()
-                            .unwrap_or(self.config.default_track_height);
+                        // Frames fill the top of the track, then the piano roll and automation lanes
+                        let track_height = self.controller.track_height(&layer.id);
+                        let layer_height = self.controller.frame_row_height(&layer.id);
                         
                         // Skip layers outside visible area
-                        if y_offset > viewport.max.y || y_offset + layer_height < viewport.min.y {
-                            y_offset += layer_height;
+                        if y_offset > viewport.max.y || y_offset + track_height < viewport.min.y {
+                            y_offset += track_height;
                             continue;
                         }
                         
@@ -908,30 +993,36 @@ impl Timeline {
                                     vec2(total_width, layer_height),
                                 ),
                                 0.0,
-                                self.config.style.background_color.gamma_multiply(1.1),
+                                self.controller.config.style.background_color.gamma_multiply(1.1),
                             );
                         }
                         
                         // Draw horizontal grid line
                         ui.painter().line_segment(
                             [pos2(0.0, y_offset + layer_height), pos2(total_width, y_offset + layer_height)],
-                            Stroke::new(1.0, self.config.style.grid_color.gamma_multiply(0.3)),
+                            Stroke::new(1.0, self.controller.config.style.grid_color.gamma_multiply(0.3)),
                         );
                         
                         // Check if layer is visible
-                        let is_visible = *self.state.layer_panel_state.layer_visibility
+                        let is_visible = *self.controller.state.layer_panel_state.layer_visibility
                             .get(&layer.id)
                             .unwrap_or(&true);
                         
                         if is_visible {
                             // Draw onion skinning if enabled
-                            if self.state.onion_skinning && !matches!(layer.layer_type, crate::LayerType::Audio) {
+                            if self.controller.state.onion_skinning && !matches!(layer.layer_type, crate::LayerType::Audio) {
                                 self.draw_onion_skins(ui, engine, layer, y_offset, layer_height, frame_width);
                             }
                             
                             // Draw frames based on layer type
                             if matches!(layer.layer_type, crate::LayerType::Audio) {
                                 self.draw_audio_waveform(ui, layer, y_offset, layer_height, visible_start_frame..=visible_end_frame, frame_width);
+                                let row = Rect::from_min_size(pos2(0.0, y_offset), vec2(total_width, layer_height));
+                                let clip_count = self.controller.audio_track(&layer.id).map_or(0, |track| track.clips().len());
+                                for clip in 0..clip_count {
+                                    self.draw_volume_envelope(ui, engine, &layer.id, clip, row, frame_width);
+                                    self.draw_audio_clip_edges(ui, engine, &layer.id, clip, row, frame_width);
+                                }
                             } else {
                                 // Draw regular frames
                                 for frame in visible_start_frame..=visible_end_frame {
@@ -959,19 +1050,15 @@ impl Timeline {
                                         }
                                         crate::frame::FrameType::Keyframe => {
                                             // Keyframe
-                                            ui.painter().rect_filled(
-                                                frame_rect,
-                                                2.0,
-                                                self.config.style.frame_keyframe,
-                                            );
+                                            self.draw_layer_span(ui, frame_rect, layer, self.controller.config.style.frame_keyframe);
                                             
                                             // Draw keyframe indicator
-                                            let is_selected = self.state.keyframe_selection.is_selected(layer.id.clone(), frame);
+                                            let is_selected = self.controller.state.keyframe_selection.is_selected(layer.id.clone(), frame);
                                             
                                             if is_selected {
                                                 // Draw selection border with line segments (egui 0.32 workaround)
                                                 let selection_rect = frame_rect.expand(1.0);
-                                                let selection_stroke = Stroke::new(2.0, Color32::from_rgb(100, 150, 255));
+                                                let selection_stroke = Stroke::new(2.0, self.controller.config.style.selection_color);
                                                 ui.painter().line_segment([selection_rect.left_top(), selection_rect.right_top()], selection_stroke);
                                                 ui.painter().line_segment([selection_rect.right_top(), selection_rect.right_bottom()], selection_stroke);
                                                 ui.painter().line_segment([selection_rect.right_bottom(), selection_rect.left_bottom()], selection_stroke);
@@ -979,26 +1066,26 @@ impl Timeline {
                                             }
                                             
                                             // Highlight keyframe at playhead position
-                                            let is_at_playhead = frame == self.state.playhead_frame;
+                                            let is_at_playhead = frame == self.controller.state.playhead_frame;
                                             if is_at_playhead {
                                                 // Draw glow effect around keyframe at playhead
                                                 let glow_rect = frame_rect.expand(2.0);
                                                 ui.painter().rect_filled(
                                                     glow_rect,
-                                                    2.0,
-                                                    self.config.style.playhead_color.gamma_multiply(0.3),
+                                                    self.controller.config.style.frame_rounding,
+                                                    self.controller.config.style.playhead_color.gamma_multiply(0.3),
                                                 );
                                             }
                                             
                                             ui.painter().circle_filled(
                                                 frame_rect.center(),
-                                                3.0,
+                                                self.controller.config.style.keyframe_radius,
                                                 if is_at_playhead {
-                                                    self.config.style.playhead_color
+                                                    self.controller.config.style.playhead_color
                                                 } else if is_selected {
-                                                    Color32::from_rgb(100, 150, 255)
+                                                    self.controller.config.style.selection_color
                                                 } else {
-                                                    self.config.style.text_color
+                                                    self.controller.config.style.text_color
                                                 },
                                             );
                                             
@@ -1008,11 +1095,7 @@ impl Timeline {
                                         }
                                         crate::frame::FrameType::Tween => {
                                             // Tween frame
-                                            ui.painter().rect_filled(
-                                                frame_rect,
-                                                2.0,
-                                                self.config.style.frame_tween,
-                                            );
+                                            self.draw_layer_span(ui, frame_rect, layer, self.controller.config.style.frame_tween);
                                             
                                             // Draw tween arrow
                                             let arrow_start = frame_rect.left_center() + vec2(5.0, 0.0);
@@ -1020,7 +1103,7 @@ impl Timeline {
                                             ui.painter().arrow(
                                                 arrow_start,
                                                 arrow_end - arrow_start,
-                                                Stroke::new(1.0, self.config.style.text_color),
+                                                Stroke::new(1.0, self.controller.config.style.text_color),
                                             );
                                             
                                             if frame_response.hovered() {
@@ -1035,7 +1118,7 @@ impl Timeline {
                                         // For now, just clear and add a keyframe as a placeholder
                                         match frame_data.frame_type {
                                             crate::frame::FrameType::Empty => {
-                                                engine.insert_keyframe(layer.id.clone(), frame);
+                                                self.controller.insert_keyframe(engine.as_mut(), layer.id.clone(), frame);
                                             }
                                             crate::frame::FrameType::Keyframe => {
                                                 // Open content editor in the future
@@ -1049,9 +1132,20 @@ impl Timeline {
                             }
                         }
                         
-                        y_offset += layer_height;
+                        let roll_height = self.controller.piano_roll_height(&layer.id);
+                        if roll_height > 0.0 {
+                            let roll = Rect::from_min_size(pos2(0.0, y_offset + layer_height), vec2(total_width, roll_height));
+                            self.draw_piano_roll(ui, engine, &layer.id, roll, frame_width, visible_start_frame..visible_end_frame + 1);
+                        }
+                        if track_height > layer_height + roll_height {
+                            let lanes = Rect::from_min_size(pos2(0.0, y_offset + layer_height + roll_height), vec2(total_width, track_height - layer_height - roll_height));
+                            self.draw_automation_lanes(ui, engine, &layer.id, lanes, frame_width, visible_start_frame..visible_end_frame + 1);
+                        }
+                        
+                        y_offset += track_height;
                     }
                 });
+            self.controller.state.scroll_x = output.state.offset.x;
         });
         
         // Handle interactions outside the scroll area
@@ -1077,12 +1171,12 @@ impl Timeline {
     fn draw_enhanced_playback_controls(&mut self, ui: &mut Ui, rect: Rect, engine: &mut Box<dyn RiveEngine>) {
         ui.scope_builder(UiBuilder::new().max_rect(rect), |ui| {
             // Background
-            ui.painter().rect_filled(rect, 0.0, Color32::from_gray(50));
+            ui.painter().rect_filled(rect, 0.0, self.controller.config.style.controls_background);
             
             // Border
             ui.painter().line_segment(
                 [rect.left_top(), rect.right_top()],
-                Stroke::new(1.0, Color32::from_gray(80)),
+                Stroke::new(1.0, self.controller.config.style.border_color),
             );
             
             ui.horizontal_centered(|ui| {
@@ -1090,19 +1184,12 @@ impl Timeline {
                 
                 // Playback controls
                 if ui.button("⏹").on_hover_text(self.get_tooltip("timeline.controls.stop")).clicked() {
-                    self.state.is_playing = false;
-                    self.state.playhead_frame = 0;
-                    engine.seek(0);
+                    self.controller.stop(engine.as_mut());
                 }
                 
-                let play_text = if self.state.is_playing { "⏸" } else { "▶" };
+                let play_text = if self.controller.state.is_playing { "⏸" } else { "▶" };
                 if ui.button(play_text).on_hover_text(self.get_tooltip("timeline.controls.play_pause")).clicked() {
-                    self.state.is_playing = !self.state.is_playing;
-                    if self.state.is_playing {
-                        engine.play();
-                    } else {
-                        engine.pause();
-                    }
+                    self.controller.toggle_playback(engine.as_mut());
                 }
                 
                 ui.separator();
@@ -1117,7 +1204,7 @@ impl Timeline {
                 ).on_hover_text("Enter frame number to jump to");
                 if response.changed() {
                     if let Ok(frame) = frame_text.parse::<u32>() {
-                        engine.seek(frame.min(engine.get_total_frames() - 1));
+                        self.controller.seek(engine.as_mut(), frame);
                     }
                 }
                 ui.label(format!("/ {}", engine.get_total_frames()))
@@ -1128,10 +1215,10 @@ impl Timeline {
                 // FPS selector
                 ui.label("FPS:");
                 ComboBox::from_id_salt("fps_selector")
-                    .selected_text(self.config.fps.label())
+                    .selected_text(self.controller.config.fps.label())
                     .show_ui(ui, |ui| {
                         for preset in crate::FpsPreset::all_presets() {
-                            if ui.selectable_value(&mut self.config.fps, preset, preset.label()).clicked() {
+                            if ui.selectable_value(&mut self.controller.config.fps, preset, preset.label()).clicked() {
                                 println!("FPS changed to: {}", preset.to_fps());
                             }
                         }
@@ -1148,66 +1235,185 @@ impl Timeline {
                 // Zoom controls
                 ui.label("Zoom:");
                 if ui.button("−").clicked() {
-                    self.state.zoom_level = (self.state.zoom_level * 0.8).max(0.1);
+                    self.controller.state.zoom_level = (self.controller.state.zoom_level * 0.8).max(MIN_ZOOM);
                 }
                 
-                let mut zoom_text = format!("{:.0}%", self.state.zoom_level * 100.0);
+                let mut zoom_text = format!("{:.0}%", self.controller.state.zoom_level * 100.0);
                 if ui.add(
                     TextEdit::singleline(&mut zoom_text)
                         .desired_width(50.0)
                         .horizontal_align(Align::Center)
                 ).changed() {
                     if let Ok(percent) = zoom_text.trim_end_matches('%').parse::<f32>() {
-                        self.state.zoom_level = (percent / 100.0).clamp(0.1, 5.0);
+                        self.controller.state.zoom_level = (percent / 100.0).clamp(MIN_ZOOM, MAX_ZOOM);
                     }
                 }
                 
                 if ui.button("+").clicked() {
-                    self.state.zoom_level = (self.state.zoom_level * 1.2).min(5.0);
+                    self.controller.state.zoom_level = (self.controller.state.zoom_level * 1.2).min(MAX_ZOOM);
                 }
                 
                 // Zoom slider
                 ui.add(
-                    Slider::new(&mut self.state.zoom_level, 0.1..=5.0)
+                    Slider::new(&mut self.controller.state.zoom_level, MIN_ZOOM..=MAX_ZOOM)
                         .show_value(false)
                         .clamping(egui::SliderClamping::Always)
                 );
                 
+                // Flash frame size presets
+                let preset_text = FrameSizePreset::from_zoom_level(self.controller.state.zoom_level)
+                    .map_or("Custom", |p| p.label());
+                ComboBox::from_id_salt("frame_size_preset")
+                    .selected_text(preset_text)
+                    .show_ui(ui, |ui| {
+                        for preset in FrameSizePreset::all() {
+                            if ui.selectable_label(preset_text == preset.label(), preset.label()).clicked() {
+                                self.controller.state.zoom_level = preset.zoom_level();
+                            }
+                        }
+                    });
+                
+                let total_frames = engine.get_total_frames();
+                if ui.button("⛶").on_hover_text("Fit all frames").clicked() {
+                    self.controller.state.zoom_to_frames(0, total_frames.saturating_sub(1), self.controller.config.frame_width, self.controller.state.view_width);
+                }
+                let selection = self.controller.state.selection_frame_range();
+                if ui.add_enabled(selection.is_some(), Button::new("🔍")).on_hover_text("Fit selection").clicked() {
+                    if let Some((start, end)) = selection {
+                        self.controller.state.zoom_to_frames(start, end, self.controller.config.frame_width, self.controller.state.view_width);
+                    }
+                }
+                
                 ui.separator();
                 
                 // Snap controls
-                let snap_icon = if self.config.snap.enabled { "🧲" } else { "⚫" };
-                if ui.selectable_label(self.config.snap.enabled, format!("{} Snap", snap_icon)).clicked() {
-                    self.config.snap.enabled = !self.config.snap.enabled;
+                let snap_icon = if self.controller.config.snap.enabled { "🧲" } else { "⚫" };
+                if ui.selectable_label(self.controller.config.snap.enabled, format!("{} Snap", snap_icon)).clicked() {
+                    self.controller.config.snap.enabled = !self.controller.config.snap.enabled;
                 }
                 
-                if self.config.snap.enabled {
+                if self.controller.config.snap.enabled {
                     ui.menu_button("⚙", |ui| {
                         ui.label("Snap Settings:");
                         ui.separator();
                         
-                        ui.checkbox(&mut self.config.snap.snap_to_frames, "Snap to frames");
-                        ui.checkbox(&mut self.config.snap.snap_to_keyframes, "Snap to keyframes");
-                        ui.checkbox(&mut self.config.snap.snap_to_markers, "Snap to markers");
-                        ui.checkbox(&mut self.config.snap.show_guides, "Show guides");
+                        ui.checkbox(&mut self.controller.config.snap.snap_to_frames, "Snap to frames");
+                        ui.checkbox(&mut self.controller.config.snap.snap_to_keyframes, "Snap to keyframes");
+                        ui.checkbox(&mut self.controller.config.snap.snap_to_markers, "Snap to markers");
+                        ui.checkbox(&mut self.controller.config.snap.snap_to_beats, "Snap to beats (bars|beats ruler)");
+                        ui.checkbox(&mut self.controller.config.snap.show_guides, "Show guides");
                         
                         ui.separator();
                         ui.label("Snap distance:");
-                        ui.add(Slider::new(&mut self.config.snap.threshold_pixels, 1.0..=20.0)
+                        ui.add(Slider::new(&mut self.controller.config.snap.threshold_pixels, 1.0..=20.0)
                             .suffix(" px"));
                     });
                 }
                 
+                let scrubbing = self.controller.state.audio_scrubbing;
+                if ui.selectable_label(scrubbing, "🔊 Scrub").on_hover_text("Play audio while dragging the playhead").clicked() {
+                    self.controller.state.audio_scrubbing = !scrubbing;
+                }
+                
+                ui.separator();
+                
+                // Musical time
+                let musical = self.controller.state.ruler_mode == RulerMode::BarsBeats;
+                if ui.selectable_label(musical, "🎵 Bars").on_hover_text("Count the ruler in bars and beats").clicked() {
+                    self.controller.state.ruler_mode = if musical { RulerMode::Frames } else { RulerMode::BarsBeats };
+                }
+                if musical {
+                    let playhead = self.controller.state.playhead_frame;
+                    let position = self.controller.musical_position(playhead);
+                    ui.monospace(format!("{}|{}", position, (position.fraction * 100.0) as u32));
+                    ui.menu_button("⚙", |ui| self.draw_tempo_map_menu(ui, playhead));
+                }
+                
                 ui.add_space(10.0);
             });
         });
     }
 
+    /// Tempo and time signature changes, and the beat grid subdivision
+    fn draw_tempo_map_menu(&mut self, ui: &mut Ui, playhead: u32) {
+        ui.label("Beat grid:");
+        let subdivision = &mut self.controller.config.snap.beat_subdivision;
+        ui.horizontal(|ui| {
+            for (value, name) in [(1, "Beats"), (2, "½"), (3, "⅓"), (4, "¼")] {
+                ui.selectable_value(subdivision, value, name);
+            }
+        });
+        
+        let tempo_map = &mut self.controller.config.tempo_map;
+        ui.separator();
+        ui.label("Tempo changes:");
+        let mut remove = None;
+        for mark in tempo_map.tempos().to_vec() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Frame {}", mark.frame));
+                let mut bpm = mark.bpm;
+                if ui.add(DragValue::new(&mut bpm).range(20.0..=400.0).speed(0.5).suffix(" BPM")).changed() {
+                    // The drag range keeps the tempo valid
+                    let _ = tempo_map.set_tempo(mark.frame, bpm);
+                }
+                if mark.frame > 0 && ui.small_button("🗑").clicked() {
+                    remove = Some(mark.frame);
+                }
+            });
+        }
+        if let Some(frame) = remove {
+            tempo_map.remove_tempo(frame);
+        }
+        if ui.button(format!("Add Tempo at Frame {}", playhead)).clicked() {
+            let bpm = tempo_map.bpm_at(playhead as f64);
+            let _ = tempo_map.set_tempo(playhead, bpm);
+        }
+        
+        ui.separator();
+        ui.label("Time signatures:");
+        let mut remove = None;
+        for mark in tempo_map.meters().to_vec() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Frame {}", mark.frame));
+                let (mut numerator, mut denominator) = (mark.numerator, mark.denominator);
+                let changed = ui.add(DragValue::new(&mut numerator).range(1..=32)).changed();
+                ui.label("/");
+                let changed = changed | ComboBox::from_id_salt(("meter_denominator", mark.frame))
+                    .selected_text(denominator.to_string())
+                    .width(40.0)
+                    .show_ui(ui, |ui| {
+                        [1, 2, 4, 8, 16].into_iter().any(|value| ui.selectable_value(&mut denominator, value, value.to_string()).changed())
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if changed {
+                    let _ = tempo_map.set_meter(mark.frame, numerator, denominator);
+                }
+                if mark.frame > 0 && ui.small_button("🗑").clicked() {
+                    remove = Some(mark.frame);
+                }
+            });
+        }
+        if let Some(frame) = remove {
+            tempo_map.remove_meter(frame);
+        }
+        if ui.button(format!("Add Time Signature at Frame {}", playhead)).clicked() {
+            if let Some(meter) = tempo_map.meters().iter().rev().find(|mark| mark.frame <= playhead).copied() {
+                let _ = tempo_map.set_meter(playhead, meter.numerator, meter.denominator);
+            }
+        }
+    }
+
     /// Draw the ruler at the top
     fn draw_ruler(&mut self, ui: &mut Ui, rect: Rect, engine: &Box<dyn RiveEngine>) {
-        let ruler = crate::Ruler::new();
+        let mut ruler = crate::Ruler::new()
+            .with_style(&self.controller.config.style)
+            .with_beat_markers(&self.controller.config.beat_markers);
+        if self.controller.state.ruler_mode == RulerMode::BarsBeats {
+            ruler = ruler.with_tempo_map(&self.controller.config.tempo_map);
+        }
         let total_frames = engine.get_total_frames();
-        let frame_width = self.config.frame_width * self.state.zoom_level;
+        let frame_width = self.controller.config.frame_width * self.controller.state.zoom_level;
         let fps = engine.get_fps();
         
         ruler.draw_with_fps_and_comments(
@@ -1216,9 +1422,9 @@ impl Timeline {
             0,
             total_frames,
             frame_width,
-            self.state.scroll_x,
-            &self.config.frame_labels,
-            &self.config.frame_comments,
+            self.controller.state.scroll_x,
+            &self.controller.config.frame_labels,
+            &self.controller.config.frame_comments,
             fps,
         );
     }
@@ -1226,17 +1432,21 @@ impl Timeline {
     /// Draw the playhead
     fn draw_playhead(&mut self, ui: &mut Ui, ruler_rect: Rect, grid_rect: Rect, engine: &mut Box<dyn RiveEngine>) {
         let current_frame = engine.get_current_frame();
-        let frame_width = self.config.frame_width * self.state.zoom_level;
-        let x = ruler_rect.min.x + (current_frame as f32 * frame_width) - self.state.scroll_x;
+        let frame_width = self.controller.config.frame_width * self.controller.state.zoom_level;
+        let x = ruler_rect.min.x + (current_frame as f32 * frame_width) - self.controller.state.scroll_x;
 
         // Only draw if visible
         if x >= ruler_rect.min.x && x <= ruler_rect.max.x {
             // Draw playhead line - thicker when scrubbing
-            let line_width = if self.state.is_scrubbing { 3.0 } else { 2.0 };
-            let playhead_color = if self.state.is_scrubbing {
-                self.config.style.playhead_color.gamma_multiply(1.2)
+            let line_width = if self.controller.state.is_scrubbing {
+                self.controller.config.style.scrub_playhead_width
             } else {
-                self.config.style.playhead_color
+                self.controller.config.style.playhead_width
+            };
+            let playhead_color = if self.controller.state.is_scrubbing {
+                self.controller.config.style.playhead_color.gamma_multiply(1.2)
+            } else {
+                self.controller.config.style.playhead_color
             };
             
             ui.painter().line_segment(
@@ -1257,7 +1467,7 @@ impl Timeline {
             ));
             
             // Draw scrub indicator when scrubbing
-            if self.state.is_scrubbing {
+            if self.controller.state.is_scrubbing {
                 // Draw a small "SCRUB" text above the playhead
                 ui.painter().text(
                     pos2(x, ruler_rect.min.y - 15.0),
@@ -1274,25 +1484,22 @@ impl Timeline {
         
         // Start scrubbing on drag begin
         if ruler_response.drag_started() {
-            self.state.is_scrubbing = true;
-            // Store the current playing state before pausing
-            self.state.was_playing = self.state.is_playing;
-            if self.state.was_playing {
-                engine.pause();
-            }
+            self.controller.begin_scrub(engine.as_mut());
+            self.audio_scrub_error = None;
         }
         
         // Update position while dragging
         if ruler_response.dragged() {
             if let Some(pos) = ruler_response.interact_pointer_pos() {
-                let raw_x = pos.x - ruler_rect.min.x + self.state.scroll_x;
-                let modifiers = ui.input(|i| i.modifiers);
-                let snapped_x = self.snap_position(raw_x, &modifiers);
-                let frame = (snapped_x / frame_width).round() as u32;
-                let clamped_frame = frame.min(engine.get_total_frames() - 1);
-                
-                // Update position while scrubbing
-                engine.seek(clamped_frame);
+                let raw_x = pos.x - ruler_rect.min.x + self.controller.state.scroll_x;
+                let bypass_snap = ui.input(|i| i.modifiers.shift);
+                let clamped_frame = self.controller.scrub_to(engine.as_mut(), raw_x, bypass_snap);
+                if let Some(audio) = self.audio_engine.as_deref_mut().filter(|_| self.audio_scrub_error.is_none()) {
+                    let time = ui.input(|i| i.time);
+                    if let Err(err) = self.controller.scrub_audio(audio, clamped_frame, time) {
+                        self.audio_scrub_error = Some(err);
+                    }
+                }
                 
                 // Show tooltip while scrubbing
                 ui.painter().text(
@@ -1302,6 +1509,16 @@ impl Timeline {
                     FontId::proportional(11.0),
                     ui.style().visuals.text_color(),
                 );
+                // and why the audio went quiet, if it did
+                if let Some(err) = &self.audio_scrub_error {
+                    ui.painter().text(
+                        pos2(pos.x, pos.y - 34.0),
+                        Align2::CENTER_BOTTOM,
+                        format!("{}: {}", self.get_tooltip("timeline.tooltips.audio_scrub_failed"), err),
+                        FontId::proportional(11.0),
+                        ui.style().visuals.error_fg_color,
+                    );
+                }
                 
                 // Force immediate redraw for responsive scrubbing
                 ui.ctx().request_repaint();
@@ -1310,43 +1527,45 @@ impl Timeline {
         
         // End scrubbing
         if ruler_response.drag_stopped() {
-            self.state.is_scrubbing = false;
-            if self.state.was_playing {
-                engine.play();
+            self.controller.end_scrub(engine.as_mut());
+            if let Some(audio) = self.audio_engine.as_deref_mut() {
+                if let Err(err) = self.controller.end_scrub_audio(audio) {
+                    self.audio_scrub_error.get_or_insert(err);
+                }
             }
         }
         
         // Handle single click to jump to position
         if ruler_response.clicked() && !ruler_response.dragged() {
             if let Some(pos) = ruler_response.interact_pointer_pos() {
-                let raw_x = pos.x - ruler_rect.min.x + self.state.scroll_x;
+                let raw_x = pos.x - ruler_rect.min.x + self.controller.state.scroll_x;
                 let modifiers = ui.input(|i| i.modifiers);
                 let snapped_x = self.snap_position(raw_x, &modifiers);
                 let frame = (snapped_x / frame_width).round() as u32;
-                engine.seek(frame.min(engine.get_total_frames() - 1));
+                self.controller.seek(engine.as_mut(), frame);
             }
         }
     }
     
     /// Draw snap guides
     fn draw_snap_guides(&self, ui: &mut Ui, grid_rect: Rect) {
-        if !self.config.snap.show_guides {
+        if !self.controller.config.snap.show_guides {
             return;
         }
         
-        for &guide_x in &self.state.snap_guides {
-            let x = grid_rect.min.x + guide_x - self.state.scroll_x;
+        for &guide_x in &self.controller.state.snap_guides {
+            let x = grid_rect.min.x + guide_x - self.controller.state.scroll_x;
             
             if x >= grid_rect.min.x && x <= grid_rect.max.x {
                 ui.painter().line_segment(
                     [pos2(x, grid_rect.min.y), pos2(x, grid_rect.max.y)],
-                    Stroke::new(1.0, Color32::from_rgb(255, 255, 0)),
+                    Stroke::new(1.0, self.controller.config.style.snap_guide_color),
                 );
                 
                 ui.painter().circle_filled(
                     pos2(x, grid_rect.min.y + 3.0),
                     2.0,
-                    Color32::from_rgb(255, 255, 0),
+                    self.controller.config.style.snap_guide_color,
                 );
             }
         }
@@ -1356,141 +1575,117 @@ impl Timeline {
     fn handle_keyboard_shortcuts(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>) {
         let ctx = ui.ctx();
         
+        // Ctrl+Shift+P: Command palette
+        if ctx.input(|i| i.key_pressed(Key::P) && i.modifiers.command && i.modifiers.shift) {
+            self.command_palette.toggle();
+        }
+        
+        // The palette owns the keyboard while it is open
+        if self.command_palette.open {
+            return;
+        }
+        
         // Spacebar: Play/Pause
         if ctx.input(|i| i.key_pressed(Key::Space)) {
-            self.state.is_playing = !self.state.is_playing;
-            if self.state.is_playing {
-                engine.play();
-            } else {
-                engine.pause();
-            }
+            self.controller.toggle_playback(engine.as_mut());
         }
         
         // Home: First frame
         if ctx.input(|i| i.key_pressed(Key::Home)) {
-            engine.seek(0);
+            self.controller.go_to_first_frame(engine.as_mut());
         }
         
         // End: Last frame
         if ctx.input(|i| i.key_pressed(Key::End)) {
-            engine.seek(engine.get_total_frames().saturating_sub(1));
+            self.controller.go_to_last_frame(engine.as_mut());
         }
         
         // Left/Right arrows for frame navigation
         if ctx.input(|i| i.key_pressed(Key::ArrowLeft)) {
-            let current = engine.get_current_frame();
-            if current > 0 {
-                engine.seek(current - 1);
-            }
+            self.controller.go_to_previous_frame(engine.as_mut());
         }
         
         if ctx.input(|i| i.key_pressed(Key::ArrowRight)) {
-            let current = engine.get_current_frame();
-            if current < engine.get_total_frames() - 1 {
-                engine.seek(current + 1);
-            }
+            self.controller.go_to_next_frame(engine.as_mut());
         }
         
         // Frame operations (if layer selected)
-        if let Some(layer_id) = self.state.selected_layers.first() {
+        if let Some(layer_id) = self.controller.state.selected_layers.first().cloned() {
             let current_frame = engine.get_current_frame();
             
             // F5: Insert Frame
             if ctx.input(|i| i.key_pressed(Key::F5) && !i.modifiers.shift) {
-                engine.insert_frame(layer_id.clone(), current_frame);
+                self.controller.insert_frame(engine.as_mut(), layer_id.clone(), current_frame);
             }
             
             // Shift+F5: Remove Frame
             if ctx.input(|i| i.key_pressed(Key::F5) && i.modifiers.shift) {
-                engine.remove_frame(layer_id.clone(), current_frame);
+                self.controller.remove_frame(engine.as_mut(), layer_id.clone(), current_frame);
             }
             
             // F6: Insert Keyframe
             if ctx.input(|i| i.key_pressed(Key::F6) && !i.modifiers.shift) {
-                engine.insert_keyframe(layer_id.clone(), current_frame);
+                self.controller.insert_keyframe(engine.as_mut(), layer_id.clone(), current_frame);
             }
             
             // Shift+F6: Clear Keyframe
             if ctx.input(|i| i.key_pressed(Key::F6) && i.modifiers.shift) {
-                engine.clear_keyframe(layer_id.clone(), current_frame);
+                self.controller.clear_keyframe(engine.as_mut(), layer_id, current_frame);
             }
         }
     }
     
+    /// Run a command from the command registry.
+    ///
+    /// Returns `false` if the command is unknown or disabled in the current state.
+    pub fn execute_command(&mut self, command_id: &str, engine: &mut Box<dyn RiveEngine>) -> bool {
+        // Commands that only affect the egui view; everything else is handled by the controller
+        let ui_action: Option<fn(&mut Timeline)> = match command_id {
+            "tween.edit_easing" => Some(|timeline| timeline.motion_editor.open()),
+            "view.toggle_minimap" => Some(|timeline| timeline.minimap.visible = !timeline.minimap.visible),
+            "file.import_midi" => Some(|timeline| timeline.midi_import_dialog = Some(MidiImportDialog::default())),
+            _ => None,
+        };
+
+        let Some(action) = ui_action else {
+            return self.controller.execute_command(command_id, engine.as_mut());
+        };
+        let context = CommandContext::from_state(&self.controller.state);
+        if !self.controller.commands.is_enabled(command_id, &context) {
+            return false;
+        }
+        action(self);
+        self.controller.commands.record_used(command_id);
+        true
+    }
+
     /// Handle frame click
     fn handle_frame_click(&mut self, pos: Pos2, rect: Rect, modifiers: &Modifiers, engine: &Box<dyn RiveEngine>) {
-        let frame_width = self.config.frame_width * self.state.zoom_level;
-        let clicked_frame = ((pos.x - rect.min.x + self.state.scroll_x) / frame_width) as u32;
-        
-        // Find which layer was clicked
+        let clicked_frame = self.controller.frame_at_x(pos.x - rect.min.x);
         let layers = engine.get_layers();
-        let mut y_offset = self.state.scroll_y;
-        
-        for layer in &layers {
-            let layer_height = self.state.track_heights
-                .get(&layer.id)
-                .copied()
-                .unwrap_or(self.config.default_track_height);
-            
-            if pos.y >= rect.min.y + y_offset && pos.y < rect.min.y + y_offset + layer_height {
-                let frame_data = engine.get_frame_data(layer.id.clone(), clicked_frame);
-                
-                if matches!(frame_data.frame_type, crate::frame::FrameType::Keyframe) {
-                    let keyframe_id = frame_data.id;
-                    
-                    if modifiers.ctrl || modifiers.command {
-                        // Toggle selection
-                        if self.state.keyframe_selection.is_selected(layer.id.clone(), clicked_frame) {
-                            self.state.keyframe_selection.remove(layer.id.clone(), clicked_frame);
-                        } else {
-                            self.state.keyframe_selection.add(layer.id.clone(), clicked_frame, keyframe_id);
-                        }
-                    } else {
-                        // Single selection
-                        self.state.keyframe_selection.clear();
-                        self.state.keyframe_selection.add(layer.id.clone(), clicked_frame, keyframe_id);
-                    }
-                } else if !modifiers.ctrl && !modifiers.command {
-                    self.state.keyframe_selection.clear();
-                }
-                break;
-            }
-            y_offset += layer_height;
+        if let Some(layer_id) = self.controller.layer_at_y(pos.y - rect.min.y, &layers) {
+            self.controller.click_frame(engine.as_ref(), &layer_id, clicked_frame, modifiers.ctrl || modifiers.command);
         }
     }
     
     /// Handle frame right-click
     fn handle_frame_right_click(&mut self, pos: Pos2, rect: Rect, engine: &Box<dyn RiveEngine>) {
-        let frame_width = self.config.frame_width * self.state.zoom_level;
-        let clicked_frame = ((pos.x - rect.min.x + self.state.scroll_x) / frame_width) as u32;
-        
-        // Find which layer was clicked
+        let clicked_frame = self.controller.frame_at_x(pos.x - rect.min.x);
         let layers = engine.get_layers();
-        let mut y_offset = self.state.scroll_y;
-        
-        for layer in &layers {
-            let layer_height = self.state.track_heights
-                .get(&layer.id)
-                .copied()
-                .unwrap_or(self.config.default_track_height);
-            
-            if pos.y >= rect.min.y + y_offset && pos.y < rect.min.y + y_offset + layer_height {
-                self.state.context_menu = Some(ContextMenuState {
-                    position: pos,
-                    menu_type: ContextMenuType::Frame {
-                        layer_id: layer.id.clone(),
-                        frame: clicked_frame,
-                    },
-                });
-                break;
-            }
-            y_offset += layer_height;
+        if let Some(layer_id) = self.controller.layer_at_y(pos.y - rect.min.y, &layers) {
+            self.controller.state.context_menu = Some(ContextMenuState {
+                position: pos,
+                menu_type: ContextMenuType::Frame {
+                    layer_id,
+                    frame: clicked_frame,
+                },
+            });
         }
     }
     
     /// Handle context menu
     fn handle_context_menu(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>) {
-        if let Some(menu_state) = self.state.context_menu.clone() {
+        if let Some(menu_state) = self.controller.state.context_menu.clone() {
             let mut close_menu = false;
             
             Area::new(ui.id().with("timeline_context_menu"))
@@ -1516,7 +1711,7 @@ impl Timeline {
                                 }
                                 
                                 if ui.button("📁 Insert Folder").clicked() {
-                                    let layer_id = engine.add_folder_layer("New Folder".to_string());
+                                    let layer_id = self.controller.add_layer(engine.as_mut(), "New Folder", LayerType::Folder);
                                     println!("Added new folder layer: {:?}", layer_id);
                                     close_menu = true;
                                 }
@@ -1524,13 +1719,13 @@ impl Timeline {
                                 ui.separator();
                                 
                                 if ui.button("📋 Duplicate Layer").clicked() {
-                                    let new_layer_id = engine.duplicate_layer(layer_id.clone());
+                                    let new_layer_id = self.controller.duplicate_layer(engine.as_mut(), layer_id.clone());
                                     println!("Duplicated layer {:?} to {:?}", layer_id, new_layer_id);
                                     close_menu = true;
                                 }
                                 
                                 if ui.button("🗑 Delete Layer").clicked() {
-                                    engine.delete_layer(layer_id.clone());
+                                    self.controller.delete_layer(engine.as_mut(), layer_id.clone());
                                     println!("Deleted layer: {:?}", layer_id);
                                     close_menu = true;
                                 }
@@ -1554,7 +1749,7 @@ impl Timeline {
                                 if ui.button("✏️ Rename Layer...").clicked() {
                                     // Start renaming - find the layer name
                                     if let Some(layer) = engine.get_layers().iter().find(|l| &l.id == layer_id) {
-                                        self.state.layer_panel_state.renaming_layer = Some((layer_id.clone(), layer.name.clone()));
+                                        self.controller.begin_rename(layer_id.clone(), layer.name.clone());
                                     }
                                     close_menu = true;
                                 }
@@ -1577,23 +1772,40 @@ impl Timeline {
                                 }
                                 
                                 if ui.button("🛤 Add Motion Guide").clicked() {
-                                    let guide_id = engine.add_motion_guide_layer("Motion Guide".to_string());
+                                    let guide_id = self.controller.add_layer(engine.as_mut(), "Motion Guide", LayerType::MotionGuide);
                                     println!("Added motion guide layer: {:?}", guide_id);
                                     close_menu = true;
                                 }
                                 
+                                if self.controller.piano_roll(layer_id).is_none() && ui.button("🎹 Add Piano Roll").clicked() {
+                                    self.controller.add_piano_roll(layer_id.clone());
+                                    close_menu = true;
+                                }
+                                
+                                ui.menu_button("〰 Add Automation Lane", |ui| {
+                                    for property in crate::PropertyId::all_properties() {
+                                        ui.menu_button(property.name(), |ui| {
+                                            let lane = if ui.button("〰 Numeric").clicked() {
+                                                Some(AutomationLane::numeric_for(property.clone()))
+                                            } else if ui.button("⏻ Toggle").clicked() {
+                                                Some(AutomationLane::toggle(property.clone()))
+                                            } else if ui.button("❗ Bang").clicked() {
+                                                Some(AutomationLane::bang(property.clone()))
+                                            } else {
+                                                None
+                                            };
+                                            if let Some(lane) = lane {
+                                                self.controller.add_automation_lane(layer_id.clone(), lane);
+                                                close_menu = true;
+                                            }
+                                        });
+                                    }
+                                });
+                                
                                 ui.separator();
                                 
                                 if ui.button("📋 Select All Frames").clicked() {
-                                    // Select all keyframes in this layer
-                                    self.state.keyframe_selection.clear();
-                                    let total_frames = engine.get_total_frames();
-                                    for frame in 0..=total_frames {
-                                        let frame_data = engine.get_frame_data(layer_id.clone(), frame);
-                                        if matches!(frame_data.frame_type, crate::frame::FrameType::Keyframe) {
-                                            self.state.keyframe_selection.add(layer_id.clone(), frame, frame_data.id);
-                                        }
-                                    }
+                                    self.controller.select_layer_keyframes(engine.as_ref(), layer_id);
                                     close_menu = true;
                                 }
                             }
@@ -1604,13 +1816,52 @@ impl Timeline {
                                 ui.label(format!("Frame {}", frame));
                                 ui.separator();
                                 
+                                if let Some(track) = self.controller.audio_tracks.get(layer_id) {
+                                    let fps = self.controller.config.fps.to_fps();
+                                    let clip = track.clip_at(*frame, fps);
+                                    let can_split = track.clip_at(self.controller.state.playhead_frame, fps).is_some();
+                                    if ui.add_enabled(clip.is_some(), egui::Button::new("🔊 Edit Envelope...")).clicked() {
+                                        self.envelope_dialog = clip.map(|clip| (layer_id.clone(), clip));
+                                        close_menu = true;
+                                    }
+                                    if ui.add_enabled(clip.is_some(), egui::Button::new("👄 Lip Sync...")).clicked() {
+                                        self.lip_sync_dialog = clip.map(|clip| LipSyncDialog::new(layer_id.clone(), clip));
+                                        close_menu = true;
+                                    }
+                                    if ui.add_enabled(can_split, egui::Button::new("✂ Split Audio at Playhead")).clicked() {
+                                        self.controller.split_audio_at_playhead(engine.as_mut(), layer_id);
+                                        close_menu = true;
+                                    }
+                                    ui.menu_button("🥁 Beat Markers", |ui| {
+                                        ui.add(Slider::new(&mut self.controller.state.beat_sensitivity, 0.0..=1.0).text("Sensitivity"));
+                                        let audio = self.audio_engine.as_deref();
+                                        if ui.add_enabled(audio.is_some(), egui::Button::new("Detect Beats")).clicked() {
+                                            if let Some(audio) = audio {
+                                                let result = self.controller.detect_beats(layer_id, |id| audio.decoded_audio(id));
+                                                self.beat_detection_result = Some(result.map_err(|err| err.to_string()));
+                                            }
+                                            close_menu = true;
+                                        }
+                                        let has_markers = !self.controller.config.beat_markers.is_empty();
+                                        if ui.add_enabled(has_markers, egui::Button::new("Convert Markers to Labels")).clicked() {
+                                            self.controller.beat_markers_to_labels(engine.as_mut());
+                                            close_menu = true;
+                                        }
+                                        if ui.add_enabled(has_markers, egui::Button::new("Clear Markers")).clicked() {
+                                            self.controller.config.beat_markers.clear();
+                                            close_menu = true;
+                                        }
+                                    });
+                                    ui.separator();
+                                }
+                                
                                 if ui.button("⬜ Insert Frame (F5)").clicked() {
-                                    engine.insert_frame(layer_id.clone(), *frame);
+                                    self.controller.insert_frame(engine.as_mut(), layer_id.clone(), *frame);
                                     close_menu = true;
                                 }
                                 
                                 if ui.button("❌ Remove Frame (Shift+F5)").clicked() {
-                                    engine.remove_frame(layer_id.clone(), *frame);
+                                    self.controller.remove_frame(engine.as_mut(), layer_id.clone(), *frame);
                                     close_menu = true;
                                 }
                                 
@@ -1618,17 +1869,17 @@ impl Timeline {
                                 
                                 if is_keyframe {
                                     if ui.button("🚫 Clear Keyframe (Shift+F6)").clicked() {
-                                        engine.clear_keyframe(layer_id.clone(), *frame);
+                                        self.controller.clear_keyframe(engine.as_mut(), layer_id.clone(), *frame);
                                         close_menu = true;
                                     }
                                 } else {
                                     if ui.button("🔑 Insert Keyframe (F6)").clicked() {
-                                        engine.insert_keyframe(layer_id.clone(), *frame);
+                                        self.controller.insert_keyframe(engine.as_mut(), layer_id.clone(), *frame);
                                         close_menu = true;
                                     }
                                     
                                     if ui.button("⬜ Insert Blank Keyframe (F7)").clicked() {
-                                        engine.insert_keyframe(layer_id.clone(), *frame);
+                                        self.controller.insert_keyframe(engine.as_mut(), layer_id.clone(), *frame);
                                         close_menu = true;
                                     }
                                     
@@ -1636,9 +1887,7 @@ impl Timeline {
                                     let frame_data = engine.get_frame_data(layer_id.clone(), *frame);
                                     if !matches!(frame_data.frame_type, crate::frame::FrameType::Keyframe) {
                                         if ui.button("🔄 Convert to Keyframe").clicked() {
-                                            // Clear existing frame first, then insert keyframe
-                                            engine.remove_frame(layer_id.clone(), *frame);
-                                            engine.insert_keyframe(layer_id.clone(), *frame);
+                                            self.controller.convert_to_keyframe(engine.as_mut(), layer_id.clone(), *frame);
                                             close_menu = true;
                                         }
                                     }
@@ -1647,12 +1896,12 @@ impl Timeline {
                                 ui.separator();
                                 
                                 if ui.button("➡️ Create Motion Tween").clicked() {
-                                    engine.create_motion_tween(layer_id.clone(), *frame);
+                                    self.controller.create_motion_tween(engine.as_mut(), layer_id.clone(), *frame);
                                     close_menu = true;
                                 }
                                 
                                 if ui.button("🔄 Create Shape Tween").clicked() {
-                                    engine.create_shape_tween(layer_id.clone(), *frame);
+                                    self.controller.create_shape_tween(engine.as_mut(), layer_id.clone(), *frame);
                                     close_menu = true;
                                 }
                                 
@@ -1686,7 +1935,7 @@ impl Timeline {
                                 }
                                 
                                 if ui.button("📈 Edit Easing...").clicked() {
-                                    self.state.motion_editor.open();
+                                    self.motion_editor.open();
                                     close_menu = true;
                                 }
                             }
@@ -1696,156 +1945,524 @@ impl Timeline {
             
             // Close menu if clicked outside or action taken
             if close_menu || ui.input(|i| i.pointer.primary_clicked()) {
-                self.state.context_menu = None;
+                self.controller.state.context_menu = None;
             }
         }
     }
     
+    /// Draw a keyframe or tween cell tinted with the layer's color.
+    /// Layers in outline mode get a hollow cell in their outline color.
+    fn draw_layer_span(&self, ui: &mut Ui, frame_rect: Rect, layer: &LayerInfo, base_color: Color32) {
+        if self.controller.state.is_layer_outline(&layer.id) {
+            ui.painter().rect_stroke(
+                frame_rect.shrink(0.5),
+                self.controller.config.style.frame_rounding,
+                Stroke::new(1.0, layer.outline_color),
+                StrokeKind::Inside,
+            );
+        } else {
+            ui.painter().rect_filled(
+                frame_rect,
+                self.controller.config.style.frame_rounding,
+                base_color.lerp_to_gamma(layer.outline_color, LAYER_TINT_AMOUNT),
+            );
+        }
+    }
+    
     /// Draw audio waveform
     fn draw_audio_waveform(&self, ui: &mut Ui, layer: &crate::layer::LayerInfo, y_offset: f32, layer_height: f32, frame_range: std::ops::RangeInclusive<u32>, frame_width: f32) {
-        let waveform_color = Color32::from_rgb(100, 200, 255);
+        let waveform_color = self.controller.config.style.waveform_color;
         let center_y = y_offset + layer_height / 2.0;
-        let amplitude_scale = layer_height * 0.4;
         
         // Draw background
         let layer_rect = Rect::from_min_size(
             pos2(*frame_range.start() as f32 * frame_width, y_offset),
             vec2((*frame_range.end() - *frame_range.start()) as f32 * frame_width, layer_height),
         );
-        ui.painter().rect_filled(layer_rect, 2.0, Color32::from_gray(35));
-        
-        // Generate mock waveform
-        let mut waveform_points = Vec::new();
-        let sample_count = ((*frame_range.end() - *frame_range.start()) as f32 * frame_width / 2.0) as usize;
+        ui.painter().rect_filled(layer_rect, self.controller.config.style.frame_rounding, self.controller.config.style.waveform_background);
         
-        for i in 0..sample_count {
-            let x = *frame_range.start() as f32 * frame_width + (i as f32 * 2.0);
-            let time = i as f32 * 0.1;
-            
-            let base_frequency = if layer.name.contains("Music") { 220.0 } else { 440.0 };
-            let envelope = 1.0 - (time * 0.05).min(1.0);
-            let signal = envelope * (
-                0.6 * (2.0 * std::f32::consts::PI * base_frequency * time * 0.01).sin() +
-                0.3 * (2.0 * std::f32::consts::PI * base_frequency * 2.0 * time * 0.01).sin() +
-                0.1 * (time * 17.3).sin()
+        let track = self.controller.audio_track(&layer.id);
+        let clips = track.map(|track| track.clips()).unwrap_or_default();
+        let mut drawn = false;
+        for clip in clips {
+            if let Some(waveform) = self.controller.clip_waveform(clip) {
+                self.draw_waveform_peaks(ui, clip, waveform, layer_rect, frame_width);
+                drawn = true;
+            }
+        }
+        if !drawn {
+            ui.painter().line_segment(
+                [pos2(layer_rect.left(), center_y), pos2(layer_rect.right(), center_y)],
+                Stroke::new(0.5, waveform_color.gamma_multiply(0.3)),
             );
-            
-            let amplitude = signal.abs() * amplitude_scale;
-            let y_min = center_y - amplitude;
-            let y_max = center_y + amplitude;
-            
-            waveform_points.push((pos2(x, y_min), pos2(x, y_max)));
         }
         
-        // Draw waveform
-        for (top, bottom) in waveform_points {
-            ui.painter().line_segment([top, bottom], Stroke::new(1.0, waveform_color));
+        // Mark crossfades with an X between the clips' edges
+        for crossfade in track.map(|track| track.crossfades(self.controller.config.fps.to_fps())).unwrap_or_default() {
+            let area = Rect::from_x_y_ranges(
+                crossfade.frames.start as f32 * frame_width..=crossfade.frames.end as f32 * frame_width,
+                y_offset..=y_offset + layer_height,
+            );
+            ui.painter().rect_filled(area, 0.0, waveform_color.gamma_multiply(0.15));
+            let stroke = Stroke::new(1.0, waveform_color.gamma_multiply(0.8));
+            ui.painter().line_segment([area.left_bottom(), area.right_top()], stroke);
+            ui.painter().line_segment([area.left_top(), area.right_bottom()], stroke);
         }
         
-        // Draw center line
-        ui.painter().line_segment(
-            [pos2(*frame_range.start() as f32 * frame_width, center_y), 
-             pos2(*frame_range.end() as f32 * frame_width, center_y)],
-            Stroke::new(0.5, waveform_color.gamma_multiply(0.3)),
-        );
-        
         // Highlight playhead position if it's in this layer's audio
-        let playhead_x = self.state.playhead_frame as f32 * frame_width;
-        if self.state.playhead_frame >= *frame_range.start() && self.state.playhead_frame <= *frame_range.end() {
+        let playhead_x = self.controller.state.playhead_frame as f32 * frame_width;
+        if self.controller.state.playhead_frame >= *frame_range.start() && self.controller.state.playhead_frame <= *frame_range.end() {
             // Draw vertical line at playhead position
             ui.painter().line_segment(
                 [pos2(playhead_x, y_offset), pos2(playhead_x, y_offset + layer_height)],
-                Stroke::new(2.0, self.config.style.playhead_color),
+                Stroke::new(2.0, self.controller.config.style.playhead_color),
             );
             
             // Draw a small indicator circle at the waveform center
             ui.painter().circle_filled(
                 pos2(playhead_x, center_y),
                 4.0,
-                self.config.style.playhead_color,
+                self.controller.config.style.playhead_color,
             );
         }
         
         // Draw audio label with high contrast color
         if layer_rect.width() > 100.0 {
+            let analyzing = clips.iter().any(|clip| self.controller.clip_waveform(clip).is_some_and(|w| !w.complete));
             ui.painter().text(
                 layer_rect.min + vec2(5.0, 5.0),
                 Align2::LEFT_TOP,
-                format!("♪ {}", layer.name),
+                if analyzing { format!("♪ {} …", layer.name) } else { format!("♪ {}", layer.name) },
                 FontId::monospace(12.0),
-                Color32::WHITE, // Changed from waveform_color to white for better contrast
+                self.controller.config.style.audio_label_color,
             );
         }
     }
     
-    /// Snap position to grid
-    pub fn snap_position(&self, pos: f32, modifiers: &Modifiers) -> f32 {
-        if modifiers.shift || !self.config.snap.enabled {
-            return pos;
+    /// Draw a sound's peaks over the part of `rect` its clip covers, one
+    /// lane per channel. Falls back to per-frame peaks without a pyramid.
+    fn draw_waveform_peaks(&self, ui: &mut Ui, audio: &crate::AudioLayer, waveform: &crate::WaveformData, rect: Rect, frame_width: f32) {
+        let color = self.controller.config.style.waveform_color;
+        let fps = self.controller.config.fps.to_fps();
+        let clip = audio.frame_range(fps);
+        let left = rect.left().max(clip.start as f32 * frame_width);
+        let right = rect.right().min(clip.end as f32 * frame_width);
+        if right <= left {
+            return;
         }
-        
-        let frame_width = self.config.frame_width * self.state.zoom_level;
-        let frame_pos = pos / frame_width;
-        
-        if self.config.snap.snap_to_frames {
-            let nearest_frame = frame_pos.round();
-            let snapped_pos = nearest_frame * frame_width;
-            
-            if (pos - snapped_pos).abs() < self.config.snap.threshold_pixels {
-                return snapped_pos;
+        // Audio time under an x position
+        let time_at = |x: f32| audio.trim_start + (x / frame_width - audio.start_frame as f32) / fps;
+
+        if waveform.channels.is_empty() {
+            let center_y = rect.center().y;
+            let scale = rect.height() * 0.45;
+            let first = (left / frame_width) as u32;
+            for frame in first..(right / frame_width).ceil() as u32 {
+                let index = (time_at(frame as f32 * frame_width) * waveform.fps).round() as usize;
+                let Some(&(min, max)) = waveform.peaks.get(index) else { break };
+                let x = frame as f32 * frame_width;
+                ui.painter().rect_filled(
+                    Rect::from_min_max(pos2(x, center_y - max * scale), pos2(x + frame_width, center_y - min * scale)),
+                    0.0,
+                    color,
+                );
+            }
+            return;
+        }
+
+        let lanes = waveform.channels.len();
+        let lane_height = rect.height() / lanes as f32;
+        let columns = (right - left).ceil() as usize;
+        for channel in 0..lanes {
+            let center_y = rect.top() + lane_height * (channel as f32 + 0.5);
+            let scale = lane_height * 0.45;
+            let peaks = waveform.peaks_between(channel, time_at(left), time_at(left + columns as f32), columns);
+            for (column, (min, max)) in peaks.into_iter().enumerate() {
+                let x = left + column as f32 + 0.5;
+                // Keep silent stretches visible as a hairline
+                let (top, bottom) = (center_y - max * scale, (center_y - min * scale).max(center_y - max * scale + 1.0));
+                ui.painter().line_segment([pos2(x, top), pos2(x, bottom)], Stroke::new(1.0, color));
+            }
+            ui.painter().line_segment(
+                [pos2(left, center_y), pos2(right, center_y)],
+                Stroke::new(0.5, color.gamma_multiply(0.3)),
+            );
+            if channel > 0 {
+                let y = rect.top() + lane_height * channel as f32;
+                ui.painter().line_segment([pos2(left, y), pos2(right, y)], Stroke::new(0.5, color.gamma_multiply(0.5)));
             }
         }
-        
-        pos
     }
     
-    /// Update snap guides for visual feedback
-    pub fn update_snap_guides(&mut self, pos: f32) {
-        self.state.snap_guides.clear();
-        
-        if !self.config.snap.enabled || !self.config.snap.show_guides {
-            return;
+    /// Draw an audio layer's volume envelope over its waveform. Clicking adds
+    /// a point, dragging moves one and double-clicking removes one; frames
+    /// and levels snap unless Shift is held. Dragging away from the points
+    /// moves the clip, or with Alt slips the sound under it.
+    fn draw_volume_envelope(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>, layer_id: &crate::LayerId, clip: usize, row: Rect, frame_width: f32) {
+        let fps = self.controller.config.fps.to_fps();
+        let Some(audio) = self.controller.audio_track(layer_id).and_then(|track| track.clips().get(clip)) else { return };
+        let start = audio.start_frame;
+        let end = audio.frame_range(fps).end.max(start + 1);
+        let area = Rect::from_x_y_ranges(start as f32 * frame_width..=end as f32 * frame_width, row.y_range())
+            .shrink2(vec2(0.0, 4.0));
+        let handles: Vec<(u32, Pos2)> = audio.volume_envelope.points.iter()
+            .map(|&(frame, level)| (start + frame, pos2((start + frame) as f32 * frame_width, area.bottom() - level * area.height())))
+            .collect();
+        let (Some(first), Some(last)) = (handles.first(), handles.last()) else { return };
+
+        // Held flat before the first point and after the last
+        let color = self.controller.config.style.envelope_color;
+        let mut line = vec![pos2(area.left(), first.1.y)];
+        line.extend(handles.iter().map(|(_, pos)| *pos));
+        line.push(pos2(area.right().max(last.1.x), last.1.y));
+        ui.painter().add(Shape::line(line, Stroke::new(1.5, color)));
+        for (_, pos) in &handles {
+            ui.painter().rect_stroke(Rect::from_center_size(*pos, vec2(6.0, 6.0)), 0.0, Stroke::new(1.5, color), StrokeKind::Middle);
         }
-        
-        let frame_width = self.config.frame_width * self.state.zoom_level;
-        let frame_pos = pos / frame_width;
-        
-        if self.config.snap.snap_to_frames {
-            let nearest_frame = frame_pos.round();
-            let snapped_pos = nearest_frame * frame_width;
-            
-            if (pos - snapped_pos).abs() < self.config.snap.threshold_pixels {
-                self.state.snap_guides.push(snapped_pos);
+
+        let response = ui.interact(row.intersect(area.expand(4.0)), ui.id().with(("envelope", layer_id.clone(), clip)), Sense::click_and_drag());
+        let Some(pointer) = response.interact_pointer_pos().or(response.hover_pos()) else { return };
+        let handle = handles.iter().find(|(_, pos)| pos.distance(pointer) <= 5.0).map(|(frame, _)| *frame);
+        let bypass = ui.input(|i| i.modifiers.shift);
+        let frame = ((pointer.x / frame_width).round().max(0.0) as u32).max(start);
+        let level = self.controller.snap_envelope_level((area.bottom() - pointer.y) / area.height(), bypass);
+
+        if response.double_clicked() {
+            if let Some(frame) = handle {
+                self.controller.remove_envelope_point(engine.as_mut(), layer_id, clip, frame);
+            }
+        } else if response.drag_started() {
+            self.controller.state.envelope_drag = handle.map(|frame| (layer_id.clone(), clip, frame));
+            if handle.is_none() {
+                let mode = if ui.input(|i| i.modifiers.alt) { AudioDragMode::Slip } else { AudioDragMode::Move };
+                self.controller.begin_audio_drag(layer_id, clip, mode, pointer.x);
             }
+        } else if response.drag_stopped() {
+            self.controller.state.envelope_drag = None;
+            self.controller.end_audio_drag(engine.as_mut());
+        } else if response.dragged() {
+            if let Some((_, clip, from)) = self.controller.state.envelope_drag.clone().filter(|(id, _, _)| id == layer_id) {
+                let moved = self.controller.move_envelope_point(engine.as_mut(), layer_id, clip, from, frame, level);
+                self.controller.state.envelope_drag = moved.map(|frame| (layer_id.clone(), clip, frame));
+            } else {
+                self.controller.update_audio_drag(pointer.x);
+            }
+        } else if response.clicked() && handle.is_none() {
+            self.controller.set_envelope_point(engine.as_mut(), layer_id, frame, level);
+        }
+
+        if handle.is_some() || self.controller.state.envelope_drag.is_some() {
+            response.on_hover_cursor(CursorIcon::Grab);
         }
     }
     
-    // Navigation helpers
-    fn go_to_first_frame(&mut self, _ctx: &Context) {
-        // Will be implemented with engine
-        println!("Go to first frame");
+    /// Draw an audio clip's outline with grips on its edges that trim the
+    /// head and tail of the sound
+    fn draw_audio_clip_edges(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>, layer_id: &crate::LayerId, clip_index: usize, row: Rect, frame_width: f32) {
+        let fps = self.controller.config.fps.to_fps();
+        let Some(audio) = self.controller.audio_track(layer_id).and_then(|track| track.clips().get(clip_index)) else { return };
+        let clip = audio.frame_range(fps);
+        let clip_rect = Rect::from_x_y_ranges(
+            clip.start as f32 * frame_width..=clip.end.max(clip.start + 1) as f32 * frame_width,
+            row.y_range(),
+        ).shrink(1.0);
+        let color = self.controller.config.style.waveform_color;
+        ui.painter().rect_stroke(clip_rect, self.controller.config.style.frame_rounding, Stroke::new(1.0, color.gamma_multiply(0.6)), StrokeKind::Inside);
+
+        for (mode, x) in [(AudioDragMode::Head, clip_rect.left()), (AudioDragMode::Tail, clip_rect.right())] {
+            let grip = Rect::from_center_size(pos2(x, clip_rect.center().y), vec2(6.0, clip_rect.height()));
+            let response = ui.interact(grip, ui.id().with(("audio_edge", layer_id.clone(), clip_index, mode == AudioDragMode::Head)), Sense::drag())
+                .on_hover_cursor(CursorIcon::ResizeHorizontal);
+            if response.hovered() || response.dragged() {
+                ui.painter().rect_filled(grip.shrink2(vec2(1.5, 4.0)), 1.0, color);
+            }
+            let Some(pointer) = response.interact_pointer_pos() else { continue };
+            if response.drag_started() {
+                self.controller.begin_audio_drag(layer_id, clip_index, mode, pointer.x);
+            } else if response.drag_stopped() {
+                self.controller.end_audio_drag(engine.as_mut());
+            } else if response.dragged() {
+                self.controller.update_audio_drag(pointer.x);
+            }
+        }
     }
     
-    fn go_to_previous_frame(&mut self, _ctx: &Context) {
-        // Will be implemented with engine
-        println!("Go to previous frame");
+    /// The piano roll's keys in the layer panel, with buttons to scroll them
+    /// and remove the roll
+    fn draw_piano_roll_header(&mut self, ui: &mut Ui, layer_id: &crate::LayerId) {
+        let Some(roll) = self.controller.piano_roll(layer_id).cloned() else { return };
+        let (rect, _) = ui.allocate_exact_size(vec2(ui.available_width(), roll.height()), Sense::hover());
+        let style = &self.controller.config.style;
+        let keys = Rect::from_min_max(pos2(rect.right() - 36.0, rect.top()), rect.max);
+        ui.painter().rect_filled(keys, 0.0, style.piano_white_key_color);
+        for row in 0..roll.visible_keys {
+            let key = roll.highest_key().saturating_sub(row);
+            let y = rect.top() + row as f32 * KEY_HEIGHT;
+            if piano_roll::is_black_key(key) {
+                let black = Rect::from_min_size(pos2(keys.left(), y), vec2(keys.width() * 0.6, KEY_HEIGHT));
+                ui.painter().rect_filled(black, 0.0, style.piano_black_key_color);
+            }
+            if key % 12 == 0 {
+                ui.painter().line_segment([pos2(keys.left(), y + KEY_HEIGHT), pos2(keys.right(), y + KEY_HEIGHT)], Stroke::new(1.0, style.grid_color));
+                ui.painter().text(pos2(keys.left() - 4.0, y + KEY_HEIGHT / 2.0), Align2::RIGHT_CENTER, crate::midi::note_name(key), FontId::proportional(9.0), style.text_color);
+            }
+        }
+        
+        let mut controls = ui.new_child(UiBuilder::new().max_rect(rect.shrink(2.0)).layout(Layout::top_down(Align::Min)));
+        controls.horizontal(|ui| {
+            ui.add_space(self.controller.config.style.layer_indent * 2.0);
+            ui.label("🎹");
+            ui.weak(format!("{}–{}", crate::midi::note_name(roll.lowest_key), crate::midi::note_name(roll.highest_key())));
+        });
+        controls.horizontal(|ui| {
+            ui.add_space(self.controller.config.style.layer_indent * 2.0);
+            let mut scroll = 0;
+            if ui.small_button("⏶").on_hover_text(self.get_tooltip("timeline.piano_roll.scroll_up")).clicked() {
+                scroll = 12;
+            }
+            if ui.small_button("⏷").on_hover_text(self.get_tooltip("timeline.piano_roll.scroll_down")).clicked() {
+                scroll = -12;
+            }
+            if let Some(roll) = self.controller.piano_rolls.get_mut(layer_id) {
+                roll.scroll(scroll);
+            }
+            if ui.small_button("🗑").on_hover_text(self.get_tooltip("timeline.piano_roll.remove")).clicked() {
+                self.controller.remove_piano_roll(layer_id);
+            }
+        });
+    }
+    
+    /// Draw a layer's piano roll and edit its notes. A click adds a note a
+    /// beat long and dragging on empty space draws one; dragging a note
+    /// moves it, dragging its right edge resizes it and Alt-drag changes its
+    /// velocity. Double-click removes a note and Alt+wheel scrolls the keys.
+    /// Notes under the playhead are outlined.
+    fn draw_piano_roll(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>, layer_id: &crate::LayerId, rect: Rect, frame_width: f32, frames: std::ops::Range<u32>) {
+        let style = &self.controller.config.style;
+        let (note_color, playhead_color, grid_color) = (style.frame_keyframe, style.playhead_color, style.grid_color);
+        let playhead = self.controller.state.playhead_frame;
+        let fps = self.controller.config.fps.to_fps();
+        let x_at = |frame: u32| frame as f32 * frame_width;
+        
+        let response = ui.interact(rect, ui.id().with(("piano_roll", layer_id.clone())), Sense::click_and_drag());
+        let pointer = response.interact_pointer_pos().or(response.hover_pos());
+        let bypass = ui.input(|i| i.modifiers.shift);
+        let alt = ui.input(|i| i.modifiers.alt);
+        let snapped = pointer.map(|pointer| (self.controller.snap(pointer.x, bypass) / frame_width).round().max(0.0) as u32);
+        let drag = self.controller.state.note_drag.clone().filter(|drag| &drag.layer_id == layer_id);
+        // Where a moved note starts: its original start shifted with the pointer, snapped
+        let moved = drag.as_ref().zip(pointer).map(|(drag, pointer)| {
+            (self.controller.snap(x_at(drag.original.frame) + pointer.x - drag.origin.x, bypass) / frame_width).round().max(0.0) as u32
+        });
+        let beat = (fps * 60.0 / self.controller.config.tempo_map.bpm_at(snapped.unwrap_or(0) as f64) as f32).round() as u32;
+        
+        // Alt+wheel scrolls the keys instead of the timeline
+        if alt && response.hovered() {
+            let scroll = ui.input_mut(|i| std::mem::take(&mut i.smooth_scroll_delta.y));
+            if let Some(roll) = self.controller.piano_rolls.get_mut(layer_id) {
+                roll.scroll((scroll / KEY_HEIGHT).round() as i32);
+            }
+        }
+        // Edits go through the controller as events; draw and hit-test a copy
+        let Some(roll) = self.controller.piano_roll(layer_id).cloned() else { return };
+        let highest = roll.highest_key();
+        let row_top = |key: u8| rect.top() + (highest - key) as f32 * KEY_HEIGHT;
+        
+        // Key rows, darker for black keys, with a line under each C
+        for row in 0..roll.visible_keys {
+            let key = highest.saturating_sub(row);
+            let y = row_top(key);
+            if piano_roll::is_black_key(key) {
+                let row_rect = Rect::from_min_size(pos2(rect.left(), y), vec2(rect.width(), KEY_HEIGHT));
+                ui.painter().rect_filled(row_rect, 0.0, grid_color.gamma_multiply(0.15));
+            }
+            if key % 12 == 0 {
+                ui.painter().line_segment([pos2(rect.left(), y + KEY_HEIGHT), pos2(rect.right(), y + KEY_HEIGHT)], Stroke::new(1.0, grid_color.gamma_multiply(0.4)));
+            }
+        }
+        ui.painter().line_segment([rect.left_bottom(), rect.right_bottom()], Stroke::new(1.0, grid_color.gamma_multiply(0.3)));
+        
+        // Notes, more opaque the louder they are
+        for note in roll.notes.iter().filter(|note| note.end() > frames.start && note.frame < frames.end) {
+            if !(roll.lowest_key..=highest).contains(&note.key) {
+                continue;
+            }
+            let note_rect = Rect::from_min_max(pos2(x_at(note.frame), row_top(note.key)), pos2(x_at(note.end()), row_top(note.key) + KEY_HEIGHT)).shrink(0.5);
+            let fill = note_color.gamma_multiply(0.35 + 0.65 * note.velocity as f32 / 127.0);
+            ui.painter().rect_filled(note_rect, 2.0, fill);
+            let stroke = if note.contains(playhead) { Stroke::new(1.5, playhead_color) } else { Stroke::new(1.0, note_color) };
+            ui.painter().rect_stroke(note_rect, 2.0, stroke, egui::StrokeKind::Inside);
+        }
+        
+        let (Some(pointer), Some(frame)) = (pointer, snapped) else { return };
+        let key = highest.saturating_sub(((pointer.y - rect.top()) / KEY_HEIGHT).floor().max(0.0) as u8);
+        let hit = roll.note_at((pointer.x / frame_width).max(0.0) as u32, key);
+        let on_edge = hit.is_some_and(|index| (x_at(roll.notes[index].end()) - pointer.x).abs() <= 4.0);
+        
+        let engine = engine.as_mut();
+        if response.double_clicked() {
+            if let Some(index) = hit {
+                self.controller.remove_note(engine, layer_id, index);
+            }
+        } else if response.drag_started() {
+            let started = match hit {
+                Some(index) if on_edge => Some((index, NoteDragMode::Resize, roll.notes[index])),
+                Some(index) if alt => Some((index, NoteDragMode::Velocity, roll.notes[index])),
+                Some(index) => Some((index, NoteDragMode::Move, roll.notes[index])),
+                // Dragging on empty space draws a new note
+                None => {
+                    let note = Note::new(frame, 1, key, 100);
+                    self.controller.add_note(engine, layer_id, note).map(|index| (index, NoteDragMode::Resize, note))
+                }
+            };
+            if let Some((note, mode, original)) = started {
+                self.controller.state.note_drag = Some(NoteDrag { layer_id: layer_id.clone(), note, mode, original, origin: pointer });
+            }
+        } else if response.drag_stopped() {
+            self.controller.state.note_drag = None;
+        } else if let Some(mut drag) = drag.filter(|_| response.dragged()) {
+            let original = drag.original;
+            match drag.mode {
+                NoteDragMode::Move => {
+                    let keys = ((drag.origin.y - pointer.y) / KEY_HEIGHT).round() as i32;
+                    let key = (original.key as i32 + keys).clamp(0, 127) as u8;
+                    if let Some(index) = self.controller.move_note(engine, layer_id, drag.note, moved.unwrap_or(original.frame), key) {
+                        drag.note = index;
+                    }
+                }
+                NoteDragMode::Resize => self.controller.resize_note(engine, layer_id, drag.note, frame.saturating_sub(original.frame)),
+                NoteDragMode::Velocity => {
+                    let velocity = original.velocity as f32 + (drag.origin.y - pointer.y) / 2.0;
+                    self.controller.set_note_velocity(engine, layer_id, drag.note, velocity.clamp(1.0, 127.0) as u8);
+                }
+            }
+            self.controller.state.note_drag = Some(drag);
+        } else if response.clicked() && hit.is_none() {
+            self.controller.add_note(engine, layer_id, Note::new(frame, beat, key, 100));
+        }
+        
+        if let Some(note) = hit.and_then(|index| roll.notes.get(index)) {
+            let cursor = if on_edge { CursorIcon::ResizeHorizontal } else { CursorIcon::Grab };
+            let text = format!("{} · {} {}", crate::midi::note_name(note.key), self.get_tooltip("timeline.piano_roll.velocity"), note.velocity);
+            response.on_hover_cursor(cursor).on_hover_text(text);
+        }
     }
     
-    fn go_to_next_frame(&mut self, _ctx: &Context) {
-        // Will be implemented with engine
-        println!("Go to next frame");
+    /// A row per automation lane in the layer panel, with its property,
+    /// value at the playhead and a remove button
+    fn draw_automation_lane_headers(&mut self, ui: &mut Ui, layer_id: &crate::LayerId) {
+        let playhead = self.controller.state.playhead_frame;
+        let mut removed = None;
+        for (index, lane) in self.controller.automation_lanes(layer_id).iter().enumerate() {
+            ui.allocate_ui_with_layout(vec2(ui.available_width(), LANE_HEIGHT), Layout::left_to_right(Align::Center), |ui| {
+                ui.add_space(self.controller.config.style.layer_indent * 2.0);
+                ui.label(lane.icon());
+                ui.label(lane.property.name());
+                match lane.value_at(playhead) {
+                    Some(crate::AutomationValue::Number(value)) => ui.weak(format!("{:.2}", value)),
+                    Some(crate::AutomationValue::Toggle(on)) => {
+                        ui.weak(self.get_tooltip(if on { "timeline.automation.on" } else { "timeline.automation.off" }))
+                    }
+                    _ => ui.weak(""),
+                };
+                if ui.small_button("🗑").on_hover_text(self.get_tooltip("timeline.automation.remove_lane")).clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            self.controller.remove_automation_lane(layer_id, index);
+        }
     }
     
-    fn go_to_last_frame(&mut self, _ctx: &Context) {
-        // Will be implemented with engine
-        println!("Go to last frame");
+    /// Draw the automation lanes under a layer and edit their envelopes.
+    /// Numeric lanes add a point on click, remove one on double-click, move
+    /// points by dragging and bend the segment under the pointer with
+    /// Alt-drag; toggle and bang lanes flip the clicked frame.
+    fn draw_automation_lanes(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>, layer_id: &crate::LayerId, rect: Rect, frame_width: f32, frames: std::ops::Range<u32>) {
+        let style = &self.controller.config.style;
+        let (line_color, marker_color, grid_color) = (style.envelope_color, style.marker_color, style.grid_color);
+        let x_at = |frame: u32| frame as f32 * frame_width;
+        
+        for index in 0..self.controller.automation_lanes(layer_id).len() {
+            let lane_rect = Rect::from_min_size(rect.min + vec2(0.0, index as f32 * LANE_HEIGHT), vec2(rect.width(), LANE_HEIGHT));
+            ui.painter().line_segment([lane_rect.left_bottom(), lane_rect.right_bottom()], Stroke::new(1.0, grid_color.gamma_multiply(0.3)));
+            let area = lane_rect.shrink2(vec2(0.0, 4.0));
+            
+            let response = ui.interact(lane_rect, ui.id().with(("automation", layer_id.clone(), index)), Sense::click_and_drag());
+            let pointer = response.interact_pointer_pos().or(response.hover_pos());
+            let bypass = ui.input(|i| i.modifiers.shift);
+            let frame = pointer.map(|pointer| (self.controller.snap(pointer.x, bypass) / frame_width).round().max(0.0) as u32);
+            let drag = self.controller.state.automation_drag.clone().filter(|drag| &drag.layer_id == layer_id && drag.lane == index);
+            let Some(lane) = self.controller.automation_lanes(layer_id).get(index).cloned() else { return };
+            
+            match &lane.envelope {
+                AutomationEnvelope::Numeric(envelope) => {
+                    let range = (envelope.max - envelope.min).max(f32::EPSILON);
+                    let y_at = |value: f32| area.bottom() - (value - envelope.min) / range * area.height();
+                    let line: Vec<Pos2> = frames.clone().map(|frame| pos2(x_at(frame), y_at(envelope.value_at(frame)))).collect();
+                    ui.painter().add(Shape::line(line, Stroke::new(1.5, line_color)));
+                    let handles: Vec<Pos2> = envelope.points.iter().map(|point| pos2(x_at(point.frame), y_at(point.value))).collect();
+                    for pos in &handles {
+                        ui.painter().circle_stroke(*pos, 3.5, Stroke::new(1.5, line_color));
+                    }
+                    
+                    let (Some(pointer), Some(frame)) = (pointer, frame) else { continue };
+                    let handle = handles.iter().position(|pos| pos.distance(pointer) <= 5.0);
+                    let value = envelope.min + (area.bottom() - pointer.y) / area.height() * range;
+                    if response.double_clicked() {
+                        if let Some(point) = handle.and_then(|handle| envelope.points.get(handle)) {
+                            self.controller.remove_automation_point(engine.as_mut(), layer_id, index, point.frame);
+                        }
+                    } else if response.drag_started() {
+                        let bend = ui.input(|i| i.modifiers.alt);
+                        let point = if bend { envelope.segment_at(frame) } else { handle };
+                        self.controller.state.automation_drag = point.map(|point| AutomationDrag { layer_id: layer_id.clone(), lane: index, point, bend });
+                    } else if response.drag_stopped() {
+                        self.controller.state.automation_drag = None;
+                    } else if let Some(drag) = drag.filter(|_| response.dragged()) {
+                        if drag.bend {
+                            let Some(point) = envelope.points.get(drag.point) else { continue };
+                            // Dragging up bends the segment upwards, whichever way it runs
+                            let rising = envelope.points.get(drag.point + 1).is_some_and(|next| next.value >= point.value);
+                            let bend = -response.drag_delta().y / area.height() * 2.0 * if rising { 1.0 } else { -1.0 };
+                            self.controller.set_automation_curve(engine.as_mut(), layer_id, index, point.frame, point.curve + bend);
+                        } else {
+                            self.controller.move_automation_point(engine.as_mut(), layer_id, index, drag.point, frame, value);
+                        }
+                    } else if response.clicked() && handle.is_none() {
+                        self.controller.set_automation_point(engine.as_mut(), layer_id, index, frame, crate::AutomationValue::Number(value));
+                    }
+                    if handle.is_some() {
+                        response.on_hover_cursor(CursorIcon::Grab);
+                    }
+                }
+                AutomationEnvelope::Toggle(envelope) => {
+                    for span in envelope.spans(frames.end).into_iter().filter(|span| span.end > frames.start) {
+                        let span_rect = Rect::from_x_y_ranges(x_at(span.start.max(frames.start))..=x_at(span.end), area.y_range());
+                        ui.painter().rect_filled(span_rect, self.controller.config.style.frame_rounding, line_color.gamma_multiply(0.4));
+                    }
+                    if let Some(frame) = frame.filter(|_| response.clicked()) {
+                        self.controller.toggle_automation_at(engine.as_mut(), layer_id, index, frame);
+                    }
+                }
+                AutomationEnvelope::Bang(envelope) => {
+                    for frame in envelope.in_range(frames.clone()) {
+                        let x = x_at(frame);
+                        ui.painter().line_segment([pos2(x, area.top()), pos2(x, area.bottom())], Stroke::new(1.5, marker_color));
+                        ui.painter().circle_filled(pos2(x, area.top() + 3.0), 3.0, marker_color);
+                    }
+                    if let Some(frame) = frame.filter(|_| response.clicked()) {
+                        self.controller.toggle_automation_at(engine.as_mut(), layer_id, index, frame);
+                    }
+                }
+            }
+        }
     }
     
-    fn center_playhead(&mut self) {
-        // Center the playhead in the visible area
-        let _frame_width = self.config.frame_width * self.state.zoom_level;
-        // This would need the visible width from the UI
-        println!("Center playhead");
+    /// Snap position to grid (Shift bypasses snapping)
+    pub fn snap_position(&self, pos: f32, modifiers: &Modifiers) -> f32 {
+        self.controller.snap(pos, modifiers.shift)
     }
     
     /// Draw label management panel as a popup window
@@ -1865,7 +2482,7 @@ impl Timeline {
                         let mut labels_to_remove = Vec::new();
                         let mut jump_to_frame = None;
                         
-                        for (index, label) in self.config.frame_labels.iter().enumerate() {
+                        for (index, label) in self.controller.config.frame_labels.iter().enumerate() {
                             ui.horizontal(|ui| {
                                 // Frame number
                                 ui.label(format!("F{}", label.frame));
@@ -1874,9 +2491,8 @@ impl Timeline {
                                 ui.label(&label.label);
                                 
                                 // Color indicator
-                                if let Some(color) = label.color {
-                                    ui.painter().circle_filled(ui.cursor().min + egui::vec2(5.0, 5.0), 4.0, color);
-                                }
+                                let color = label.color.unwrap_or(self.controller.config.style.label_color);
+                                ui.painter().circle_filled(ui.cursor().min + egui::vec2(5.0, 5.0), 4.0, color);
                                 
                                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                     // Jump to frame button
@@ -1895,13 +2511,12 @@ impl Timeline {
                         
                         // Handle deletions
                         for &index in labels_to_remove.iter().rev() {
-                            self.config.frame_labels.remove(index);
+                            self.controller.remove_label(engine.as_mut(), index);
                         }
                         
                         // Handle jump to frame
                         if let Some(frame) = jump_to_frame {
-                            self.state.playhead_frame = frame;
-                            engine.seek(frame);
+                            self.controller.seek(engine.as_mut(), frame);
                         }
                     });
                     
@@ -1913,7 +2528,7 @@ impl Timeline {
                         let mut comments_to_remove = Vec::new();
                         let mut jump_to_frame = None;
                         
-                        for (index, comment) in self.config.frame_comments.iter().enumerate() {
+                        for (index, comment) in self.controller.config.frame_comments.iter().enumerate() {
                             ui.horizontal(|ui| {
                                 // Frame number
                                 ui.label(format!("F{}", comment.frame));
@@ -1927,9 +2542,8 @@ impl Timeline {
                                 ui.label(comment_text).on_hover_text(&comment.comment);
                                 
                                 // Color indicator
-                                if let Some(color) = comment.color {
-                                    ui.painter().circle_filled(ui.cursor().min + egui::vec2(5.0, 5.0), 4.0, color);
-                                }
+                                let color = comment.color.unwrap_or(self.controller.config.style.comment_color);
+                                ui.painter().circle_filled(ui.cursor().min + egui::vec2(5.0, 5.0), 4.0, color);
                                 
                                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                     // Jump to frame button
@@ -1961,13 +2575,12 @@ impl Timeline {
                         
                         // Handle deletions
                         for &index in comments_to_remove.iter().rev() {
-                            self.config.frame_comments.remove(index);
+                            self.controller.remove_comment(engine.as_mut(), index);
                         }
                         
                         // Handle jump to frame
                         if let Some(frame) = jump_to_frame {
-                            self.state.playhead_frame = frame;
-                            engine.seek(frame);
+                            self.controller.seek(engine.as_mut(), frame);
                         }
                     });
                     
@@ -1976,13 +2589,13 @@ impl Timeline {
                     // Add new label/comment section
                     ui.horizontal(|ui| {
                         if ui.button("➕ Add Label").clicked() {
-                            let new_label = crate::FrameLabel::new(self.state.playhead_frame, "New Label");
-                            self.config.frame_labels.push(new_label);
+                            let frame = self.controller.state.playhead_frame;
+                            self.controller.add_label(engine.as_mut(), frame, "New Label");
                         }
                         
                         if ui.button("💬 Add Comment").clicked() {
-                            let new_comment = crate::FrameComment::new(self.state.playhead_frame, "New comment");
-                            self.config.frame_comments.push(new_comment);
+                            let frame = self.controller.state.playhead_frame;
+                            self.controller.add_comment(engine.as_mut(), frame, "New comment");
                         }
                     });
                     
@@ -1991,8 +2604,8 @@ impl Timeline {
                     // Statistics
                     ui.horizontal(|ui| {
                         ui.label(format!("{} labels, {} comments", 
-                            self.config.frame_labels.len(), 
-                            self.config.frame_comments.len()
+                            self.controller.config.frame_labels.len(), 
+                            self.controller.config.frame_comments.len()
                         ));
                     });
                 });
@@ -2009,25 +2622,25 @@ impl Timeline {
         layer_height: f32,
         frame_width: f32,
     ) {
-        let current_frame = self.state.playhead_frame;
+        let current_frame = self.controller.state.playhead_frame;
         
         // Draw previous frames (blue tint)
-        for i in 1..=self.state.onion_skin_frames_before {
+        for i in 1..=self.controller.state.onion_skin_frames_before {
             if let Some(prev_frame) = current_frame.checked_sub(i) {
                 let frame_data = engine.get_frame_data(layer.id.clone(), prev_frame);
                 if !matches!(frame_data.frame_type, crate::frame::FrameType::Empty) {
                     let x = prev_frame as f32 * frame_width;
-                    let opacity = self.state.onion_skin_opacity / (i as f32); // Farther frames are more transparent
+                    let opacity = self.controller.state.onion_skin_opacity / (i as f32); // Farther frames are more transparent
                     
                     let frame_rect = Rect::from_min_size(
                         pos2(x, y_offset),
                         vec2(frame_width - 1.0, layer_height - 1.0),
                     );
                     
-                    // Blue tint for previous frames
-                    let color = Color32::from_rgba_unmultiplied(100, 150, 255, (opacity * 255.0) as u8);
+                    // Tint for previous frames
+                    let color = self.controller.config.style.onion_before_color.gamma_multiply(opacity);
                     
-                    if self.state.onion_skin_outline_mode {
+                    if self.controller.state.onion_skin_outline_mode {
                         // Outline mode - draw only border
                         let stroke = Stroke::new(2.0, color);
                         ui.painter().line_segment([frame_rect.left_top(), frame_rect.right_top()], stroke);
@@ -2036,30 +2649,30 @@ impl Timeline {
                         ui.painter().line_segment([frame_rect.left_bottom(), frame_rect.left_top()], stroke);
                     } else {
                         // Solid mode - fill the frame
-                        ui.painter().rect_filled(frame_rect, 2.0, color);
+                        ui.painter().rect_filled(frame_rect, self.controller.config.style.frame_rounding, color);
                     }
                 }
             }
         }
         
         // Draw next frames (green tint)
-        for i in 1..=self.state.onion_skin_frames_after {
+        for i in 1..=self.controller.state.onion_skin_frames_after {
             let next_frame = current_frame + i;
             if next_frame < engine.get_total_frames() {
                 let frame_data = engine.get_frame_data(layer.id.clone(), next_frame);
                 if !matches!(frame_data.frame_type, crate::frame::FrameType::Empty) {
                     let x = next_frame as f32 * frame_width;
-                    let opacity = self.state.onion_skin_opacity / (i as f32); // Farther frames are more transparent
+                    let opacity = self.controller.state.onion_skin_opacity / (i as f32); // Farther frames are more transparent
                     
                     let frame_rect = Rect::from_min_size(
                         pos2(x, y_offset),
                         vec2(frame_width - 1.0, layer_height - 1.0),
                     );
                     
-                    // Green tint for next frames
-                    let color = Color32::from_rgba_unmultiplied(100, 255, 150, (opacity * 255.0) as u8);
+                    // Tint for next frames
+                    let color = self.controller.config.style.onion_after_color.gamma_multiply(opacity);
                     
-                    if self.state.onion_skin_outline_mode {
+                    if self.controller.state.onion_skin_outline_mode {
                         // Outline mode - draw only border
                         let stroke = Stroke::new(2.0, color);
                         ui.painter().line_segment([frame_rect.left_top(), frame_rect.right_top()], stroke);
@@ -2068,7 +2681,7 @@ impl Timeline {
                         ui.painter().line_segment([frame_rect.left_bottom(), frame_rect.left_top()], stroke);
                     } else {
                         // Solid mode - fill the frame
-                        ui.painter().rect_filled(frame_rect, 2.0, color);
+                        ui.painter().rect_filled(frame_rect, self.controller.config.style.frame_rounding, color);
                     }
                 }
             }
@@ -2092,7 +2705,7 @@ impl Timeline {
                     ui.label("Frame Range");
                     ui.horizontal(|ui| {
                         ui.label("Previous Frames:");
-                        ui.add(egui::DragValue::new(&mut self.state.onion_skin_frames_before)
+                        ui.add(egui::DragValue::new(&mut self.controller.state.onion_skin_frames_before)
                             .speed(1.0)
                             .range(0..=10)
                             .suffix(" frames"));
@@ -2100,7 +2713,7 @@ impl Timeline {
                     
                     ui.horizontal(|ui| {
                         ui.label("Next Frames:");
-                        ui.add(egui::DragValue::new(&mut self.state.onion_skin_frames_after)
+                        ui.add(egui::DragValue::new(&mut self.controller.state.onion_skin_frames_after)
                             .speed(1.0)
                             .range(0..=10)
                             .suffix(" frames"));
@@ -2112,7 +2725,7 @@ impl Timeline {
                     ui.label("Opacity Settings");
                     ui.horizontal(|ui| {
                         ui.label("Base Opacity:");
-                        ui.add(egui::Slider::new(&mut self.state.onion_skin_opacity, 0.1..=0.8)
+                        ui.add(egui::Slider::new(&mut self.controller.state.onion_skin_opacity, 0.1..=0.8)
                             .show_value(true)
                             .suffix(""));
                     });
@@ -2121,7 +2734,7 @@ impl Timeline {
                     
                     // Display mode
                     ui.label("Display Mode");
-                    ui.checkbox(&mut self.state.onion_skin_outline_mode, "Outline Mode");
+                    ui.checkbox(&mut self.controller.state.onion_skin_outline_mode, "Outline Mode");
                     ui.label("When enabled, shows only object outlines");
                     
                     ui.separator();
@@ -2132,9 +2745,9 @@ impl Timeline {
                         // Previous frames preview
                         ui.vertical(|ui| {
                             ui.label("Previous Frames");
-                            for i in 1..=3.min(self.state.onion_skin_frames_before) {
-                                let opacity = self.state.onion_skin_opacity / (i as f32);
-                                let color = Color32::from_rgba_unmultiplied(100, 150, 255, (opacity * 255.0) as u8);
+                            for i in 1..=3.min(self.controller.state.onion_skin_frames_before) {
+                                let opacity = self.controller.state.onion_skin_opacity / (i as f32);
+                                let color = self.controller.config.style.onion_before_color.gamma_multiply(opacity);
                                 ui.horizontal(|ui| {
                                     ui.colored_label(color, format!("Frame -{}", i));
                                     ui.label(format!("({:.0}% opacity)", opacity * 100.0));
@@ -2147,9 +2760,9 @@ impl Timeline {
                         // Next frames preview
                         ui.vertical(|ui| {
                             ui.label("Next Frames");
-                            for i in 1..=3.min(self.state.onion_skin_frames_after) {
-                                let opacity = self.state.onion_skin_opacity / (i as f32);
-                                let color = Color32::from_rgba_unmultiplied(100, 255, 150, (opacity * 255.0) as u8);
+                            for i in 1..=3.min(self.controller.state.onion_skin_frames_after) {
+                                let opacity = self.controller.state.onion_skin_opacity / (i as f32);
+                                let color = self.controller.config.style.onion_after_color.gamma_multiply(opacity);
                                 ui.horizontal(|ui| {
                                     ui.colored_label(color, format!("Frame +{}", i));
                                     ui.label(format!("({:.0}% opacity)", opacity * 100.0));
@@ -2164,13 +2777,13 @@ impl Timeline {
                     ui.label("Quick Presets");
                     ui.horizontal(|ui| {
                         if ui.button("Light").clicked() {
-                            self.state.onion_skin_opacity = 0.2;
+                            self.controller.state.onion_skin_opacity = 0.2;
                         }
                         if ui.button("Medium").clicked() {
-                            self.state.onion_skin_opacity = 0.3;
+                            self.controller.state.onion_skin_opacity = 0.3;
                         }
                         if ui.button("Strong").clicked() {
-                            self.state.onion_skin_opacity = 0.5;
+                            self.controller.state.onion_skin_opacity = 0.5;
                         }
                     });
                     
@@ -2178,71 +2791,371 @@ impl Timeline {
                     
                     // Close button
                     if ui.button("Close").clicked() {
-                        self.state.show_onion_settings = false;
+                        self.controller.state.show_onion_settings = false;
                     }
                 });
             });
     }
     
-    /// Handle layer name interaction (selection, double-click to rename)
-    fn handle_layer_name_interaction(&mut self, ui: &mut egui::Ui, response: egui::Response, layer: &LayerInfo, idx: usize, layers: &Vec<LayerInfo>) {
-        // Check for double-click to start renaming
-        if response.double_clicked() {
-            self.state.layer_panel_state.renaming_layer = Some((layer.id.clone(), layer.name.clone()));
-            return;
+    /// Draw the Edit Envelope dialog for a clip on an audio layer
+    fn draw_envelope_dialog(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>, layer_id: &crate::LayerId, clip: usize) {
+        let ctx = ui.ctx();
+        let mut open = true;
+        
+        egui::Window::new("🔊 Edit Envelope")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                let Some(audio) = self.controller.audio_tracks.get(layer_id).and_then(|track| track.clips().get(clip)) else {
+                    ui.label("This clip no longer exists");
+                    return;
+                };
+                ui.label(format!("♪ {}", audio.source.display_name()));
+                let start = audio.start_frame;
+                let points = audio.volume_envelope.points.clone();
+                ui.separator();
+                
+                // Presets
+                ui.label("Effect");
+                ui.horizontal(|ui| {
+                    ui.label("Fade length:");
+                    ui.add(egui::DragValue::new(&mut self.envelope_fade_frames)
+                        .speed(1.0)
+                        .range(1..=600)
+                        .suffix(" frames"));
+                });
+                ui.horizontal_wrapped(|ui| {
+                    for preset in crate::EnvelopePreset::ALL {
+                        if ui.button(preset.name()).clicked() {
+                            let fade = self.envelope_fade_frames;
+                            self.controller.apply_envelope_preset(engine.as_mut(), layer_id, clip, preset, fade);
+                        }
+                    }
+                });
+                
+                ui.separator();
+                
+                // Points, in frames from the start of the clip
+                ui.label("Points");
+                for &(frame, level) in &points {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Frame {}", frame));
+                        let mut level = level;
+                        if ui.add(egui::Slider::new(&mut level, 0.0..=1.0).show_value(true)).changed() {
+                            let event = TimelineEvent::EnvelopePointSet { layer_id: layer_id.clone(), clip, frame: start + frame, volume: level };
+                            self.controller.emit(event, engine.as_mut());
+                        }
+                        if points.len() > 1 && ui.small_button("🗑").clicked() {
+                            self.controller.remove_envelope_point(engine.as_mut(), layer_id, clip, start + frame);
+                        }
+                    });
+                }
+            });
+        
+        if !open {
+            self.envelope_dialog = None;
         }
+    }
+    
+    /// Result of Detect Beats: the estimated tempo, with a button to use it
+    /// from the start of the tempo map, or the error
+    fn draw_beat_detection_dialog(&mut self, ui: &mut Ui) {
+        let Some(result) = self.beat_detection_result.clone() else { return };
+        let ctx = ui.ctx().clone();
+        let mut open = true;
+        let mut close = false;
         
-        // Handle single click for selection
-        if response.clicked() {
-            if ui.input(|i| i.modifiers.shift) && !self.state.selected_layers.is_empty() {
-                // Range select with Shift
-                let last_selected = self.state.selected_layers.last().unwrap();
-                let last_idx = layers.iter().position(|l| &l.id == last_selected).unwrap_or(0);
-                let current_idx = idx;
+        egui::Window::new(format!("🥁 {}", self.get_tooltip("timeline.beat_detection.title")))
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(&ctx, |ui| {
+                let markers = self.controller.config.beat_markers.len();
+                match result {
+                    Ok(Some(bpm)) => {
+                        ui.label(format!("{}: {:.1} BPM", self.get_tooltip("timeline.beat_detection.tempo"), bpm));
+                        ui.weak(format!("{}: {}", self.get_tooltip("timeline.beat_detection.markers"), markers));
+                        ui.horizontal(|ui| {
+                            if ui.button(self.get_tooltip("timeline.beat_detection.apply_tempo")).clicked() {
+                                match self.controller.config.tempo_map.set_tempo(0, bpm) {
+                                    Ok(()) => close = true,
+                                    Err(err) => self.beat_detection_result = Some(Err(err.to_string())),
+                                }
+                            }
+                            if ui.button(self.get_tooltip("timeline.beat_detection.close")).clicked() {
+                                close = true;
+                            }
+                        });
+                    }
+                    Ok(None) => {
+                        ui.label(self.get_tooltip("timeline.beat_detection.no_tempo"));
+                        ui.weak(format!("{}: {}", self.get_tooltip("timeline.beat_detection.markers"), markers));
+                    }
+                    Err(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("{}: {}", self.get_tooltip("timeline.beat_detection.failed"), err));
+                    }
+                }
+            });
+        
+        if !open || close {
+            self.beat_detection_result = None;
+        }
+    }
+    
+    /// Dialog that keys mouth shapes on a layer from an audio clip
+    fn draw_lip_sync_dialog(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>) {
+        let Some(mut dialog) = self.lip_sync_dialog.take() else { return };
+        let ctx = ui.ctx().clone();
+        let mut open = true;
+        let mut generate = false;
+        
+        egui::Window::new("👄 Lip Sync")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(320.0)
+            .show(&ctx, |ui| {
+                let Some(audio) = self.controller.audio_tracks.get(&dialog.layer_id).and_then(|track| track.clips().get(dialog.clip)) else {
+                    ui.label("This clip no longer exists");
+                    return;
+                };
+                ui.label(format!("♪ {}", audio.source.display_name()));
+                ui.separator();
                 
-                let start = last_idx.min(current_idx);
-                let end = last_idx.max(current_idx);
+                // Layer that receives the mouth keyframes
+                let layers: Vec<LayerInfo> = engine.get_layers().into_iter()
+                    .filter(|layer| !matches!(layer.layer_type, LayerType::Audio | LayerType::Folder))
+                    .collect();
+                let target_name = dialog.target.as_ref()
+                    .and_then(|target| layers.iter().find(|layer| &layer.id == target))
+                    .map_or("Choose a layer", |layer| layer.name.as_str());
+                ui.horizontal(|ui| {
+                    ui.label("Mouth layer:");
+                    ComboBox::from_id_salt("lip_sync_target")
+                        .selected_text(target_name)
+                        .show_ui(ui, |ui| {
+                            for layer in &layers {
+                                ui.selectable_value(&mut dialog.target, Some(layer.id.clone()), &layer.name);
+                            }
+                        });
+                });
                 
-                // Clear existing selection
-                self.state.selected_layers.clear();
+                // Mouth shapes and their symbol frames
+                ui.horizontal(|ui| {
+                    ui.label("Mouth set:");
+                    if ui.button("Preston Blair").clicked() {
+                        dialog.mouths = crate::MouthSet::preston_blair();
+                    }
+                    if ui.button("Rhubarb").clicked() {
+                        dialog.mouths = crate::MouthSet::rhubarb();
+                    }
+                });
+                egui::Grid::new("lip_sync_shapes").num_columns(4).show(ui, |ui| {
+                    for (index, (shape, frame)) in dialog.mouths.shapes.iter_mut().enumerate() {
+                        ui.label(shape.as_str());
+                        ui.add(egui::DragValue::new(frame).speed(0.1).prefix("frame "));
+                        if index % 2 == 1 {
+                            ui.end_row();
+                        }
+                    }
+                });
+                
+                ui.separator();
+                
+                // Where the cues come from
+                ui.radio_value(&mut dialog.source, LipSyncSource::Amplitude, "Amplitude");
+                ui.radio_value(&mut dialog.source, LipSyncSource::Transcript, "Phoneme transcript (Rhubarb TSV or Papagayo MOHO)");
+                if dialog.source == LipSyncSource::Transcript {
+                    ui.horizontal(|ui| {
+                        ui.label("File:");
+                        ui.text_edit_singleline(&mut dialog.transcript_path);
+                    });
+                }
+                
+                ui.separator();
+                let can_generate = dialog.target.is_some()
+                    && (dialog.source == LipSyncSource::Transcript || self.audio_engine.is_some());
+                if ui.add_enabled(can_generate, egui::Button::new("Generate Keyframes")).clicked() {
+                    generate = true;
+                }
+                if let Some(status) = &dialog.status {
+                    ui.label(status);
+                }
+            });
+        
+        if let (true, Some(target)) = (generate, dialog.target.clone()) {
+            let result = match dialog.source {
+                LipSyncSource::Amplitude => {
+                    let audio = self.audio_engine.as_deref();
+                    self.controller
+                        .lip_sync_from_audio(engine.as_mut(), &dialog.layer_id, dialog.clip, &target, &dialog.mouths, |id| {
+                            audio.and_then(|audio| audio.decoded_audio(id))
+                        })
+                        .map_err(|err| err.to_string())
+                }
+                LipSyncSource::Transcript => std::fs::read_to_string(&dialog.transcript_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| crate::lip_sync::parse_transcript(&text, self.controller.config.fps.to_fps()).map_err(|err| err.to_string()))
+                    .and_then(|cues| {
+                        self.controller
+                            .lip_sync_from_transcript(engine.as_mut(), &dialog.layer_id, dialog.clip, &target, &dialog.mouths, &cues)
+                            .map_err(|err| err.to_string())
+                    }),
+            };
+            dialog.status = Some(match result {
+                Ok(keyed) => format!("Made {} mouth keyframes", keyed),
+                Err(err) => err,
+            });
+        }
+        
+        if open {
+            self.lip_sync_dialog = Some(dialog);
+        }
+    }
+    
+    /// Dialog that places the notes of a MIDI file as keyframes, markers or labels
+    fn draw_midi_import_dialog(&mut self, ui: &mut Ui, engine: &mut Box<dyn RiveEngine>) {
+        let Some(mut dialog) = self.midi_import_dialog.take() else { return };
+        let ctx = ui.ctx().clone();
+        let mut open = true;
+        let mut import = false;
+        
+        egui::Window::new("🎹 Import MIDI")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(340.0)
+            .show(&ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.text_edit_singleline(&mut dialog.path);
+                    if ui.button("Load").clicked() {
+                        match crate::midi::read(std::path::Path::new(&dialog.path)) {
+                            Ok(file) => {
+                                dialog.status = Some(format!("{} tracks", file.tracks.len()));
+                                dialog.selection.tracks.clear();
+                                dialog.file = Some(file);
+                            }
+                            Err(err) => dialog.status = Some(err.to_string()),
+                        }
+                    }
+                });
                 
-                // Select all layers in range
-                for i in start..=end {
-                    if let Some(l) = layers.get(i) {
-                        self.state.selected_layers.push(l.id.clone());
+                if let Some(file) = &dialog.file {
+                    ui.separator();
+                    
+                    // Tracks; none checked imports every track
+                    ui.label("Tracks:");
+                    for (index, track) in file.tracks.iter().enumerate() {
+                        if track.notes.is_empty() {
+                            continue;
+                        }
+                        let name = track.name.clone().unwrap_or_else(|| format!("Track {}", index + 1));
+                        let mut checked = dialog.selection.tracks.contains(&index);
+                        if ui.checkbox(&mut checked, format!("{} ({} notes)", name, track.notes.len())).changed() {
+                            if checked {
+                                dialog.selection.tracks.push(index);
+                            } else {
+                                dialog.selection.tracks.retain(|&track| track != index);
+                            }
+                        }
                     }
+                    
+                    // Pitches and velocity
+                    ui.horizontal(|ui| {
+                        ui.label("Notes:");
+                        ui.add(egui::DragValue::new(&mut dialog.selection.lowest_key).range(0..=127)
+                            .custom_formatter(|key, _| crate::midi::note_name(key as u8)));
+                        ui.label("to");
+                        ui.add(egui::DragValue::new(&mut dialog.selection.highest_key).range(0..=127)
+                            .custom_formatter(|key, _| crate::midi::note_name(key as u8)));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Minimum velocity:");
+                        ui.add(egui::DragValue::new(&mut dialog.selection.min_velocity).range(1..=127));
+                    });
+                    
+                    ui.separator();
+                    
+                    // What the notes become
+                    ui.radio_value(&mut dialog.target, crate::MidiTarget::Markers, "Beat markers");
+                    ui.radio_value(&mut dialog.target, crate::MidiTarget::Labels, "Frame labels");
+                    let layers: Vec<LayerInfo> = engine.get_layers().into_iter()
+                        .filter(|layer| !matches!(layer.layer_type, LayerType::Audio | LayerType::Folder))
+                        .collect();
+                    ui.horizontal(|ui| {
+                        let keyframes = matches!(dialog.target, crate::MidiTarget::Keyframes(_));
+                        if ui.radio(keyframes, "Keyframes on").clicked() && !keyframes {
+                            if let Some(layer) = layers.first() {
+                                dialog.target = crate::MidiTarget::Keyframes(layer.id.clone());
+                            }
+                        }
+                        if let crate::MidiTarget::Keyframes(target) = &mut dialog.target {
+                            let name = layers.iter().find(|layer| &layer.id == target).map_or("", |layer| layer.name.as_str());
+                            ComboBox::from_id_salt("midi_target_layer")
+                                .selected_text(name)
+                                .show_ui(ui, |ui| {
+                                    for layer in &layers {
+                                        ui.selectable_value(target, layer.id.clone(), &layer.name);
+                                    }
+                                });
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Start at frame:");
+                        ui.add(egui::DragValue::new(&mut dialog.start_frame));
+                    });
+                    
+                    let notes = file.selected_notes(&dialog.selection).len();
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(notes > 0, egui::Button::new(format!("Import {} Notes", notes))).clicked() {
+                            import = true;
+                        }
+                        // The tempo map counts from frame 0, so only a file starting there lines up
+                        if ui.add_enabled(dialog.start_frame == 0, egui::Button::new("Use Tempo Map"))
+                            .on_hover_text("Replace the document tempo map with the file's tempo and time signature changes")
+                            .clicked()
+                        {
+                            self.controller.config.tempo_map = crate::TempoMap::from_midi(file, self.controller.config.fps.to_fps());
+                            dialog.status = Some("Tempo map set from the file".to_string());
+                        }
+                    });
                 }
-            } else if ui.input(|i| i.modifiers.ctrl || i.modifiers.command) {
-                // Multi-select with Ctrl/Cmd
-                let is_selected = self.state.selected_layers.contains(&layer.id);
-                if is_selected {
-                    self.state.selected_layers.retain(|id| id != &layer.id);
-                } else {
-                    self.state.selected_layers.push(layer.id.clone());
+                
+                if let Some(status) = &dialog.status {
+                    ui.label(status);
                 }
+            });
+        
+        if let (true, Some(file)) = (import, &dialog.file) {
+            let placed = self.controller.import_midi(engine.as_mut(), file, &dialog.selection, &dialog.target, dialog.start_frame);
+            dialog.status = Some(format!("Placed {} notes", placed));
+        }
+        
+        if open {
+            self.midi_import_dialog = Some(dialog);
+        }
+    }
+    
+    /// Handle layer name interaction (selection, double-click to rename)
+    fn handle_layer_name_interaction(&mut self, ui: &mut egui::Ui, response: egui::Response, layer: &LayerInfo, layers: &[LayerInfo]) {
+        // Check for double-click to start renaming
+        if response.double_clicked() {
+            self.controller.begin_rename(layer.id.clone(), layer.name.clone());
+            return;
+        }
+        
+        // Handle single click for selection
+        if response.clicked() {
+            let modifiers = ui.input(|i| i.modifiers);
+            if modifiers.shift {
+                self.controller.select_layer_range(layers, &layer.id);
             } else {
-                // Single select
-                self.state.selected_layers.clear();
-                self.state.selected_layers.push(layer.id.clone());
+                self.controller.select_layer(&layer.id, modifiers.ctrl || modifiers.command);
             }
         }
     }
 }
 
-/// State for the right-click context menu
-#[derive(Clone, Debug)]
-pub struct ContextMenuState {
-    pub position: Pos2,
-    pub menu_type: ContextMenuType,
-}
-
-/// Type of context menu to show
-#[derive(Clone, Debug)]
-pub enum ContextMenuType {
-    Layer(LayerId),
-    Frame { layer_id: LayerId, frame: u32 },
-}
-
 // Re-export the original implementation's audio waveform and other methods
 // use crate::timeline_egui::Timeline as OriginalTimeline; // Unused import
 
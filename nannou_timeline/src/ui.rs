@@ -1,18 +1,22 @@
 //! UI helper utilities for the timeline
 
+#[cfg(feature = "ui")]
 use egui::{*, self};
 
 /// Helper to create consistent button styles
+#[cfg(feature = "ui")]
 pub fn timeline_button(ui: &mut Ui, text: &str) -> Response {
     ui.add(Button::new(text).min_size(vec2(30.0, 20.0)))
 }
 
 /// Helper to create icon buttons
+#[cfg(feature = "ui")]
 pub fn icon_button(ui: &mut Ui, icon: &str, size: f32) -> Response {
     ui.add(Button::new(icon).min_size(vec2(size, size)))
 }
 
 /// Helper to draw a separator line
+#[cfg(feature = "ui")]
 pub fn separator_line(ui: &mut Ui, vertical: bool) {
     let rect = ui.available_rect_before_wrap();
     let stroke = ui.style().visuals.widgets.noninteractive.bg_stroke;
@@ -31,12 +35,20 @@ pub fn separator_line(ui: &mut Ui, vertical: bool) {
 }
 
 /// Mock Rive engine for testing
+///
+/// Frames follow the generated pattern of [`create_mock_frame_data`](crate::frame::create_mock_frame_data)
+/// until a layer is edited. From then on that layer's frames are stored, so
+/// edits can be read back.
 pub struct MockRiveEngine {
     layers: Vec<crate::layer::LayerInfo>,
     current_frame: u32,
     total_frames: u32,
     fps: f32,
     is_playing: bool,
+    edited_frames: std::collections::HashMap<crate::LayerId, Vec<crate::frame::FrameData>>,
+    symbol_frames: std::collections::HashMap<(crate::LayerId, u32), u32>,
+    automated: Vec<(crate::LayerId, u32, crate::PropertyId, crate::AutomationValue)>,
+    played_notes: Vec<(crate::LayerId, u32, crate::Note, bool)>,
 }
 
 impl MockRiveEngine {
@@ -47,6 +59,76 @@ impl MockRiveEngine {
             total_frames: 100,
             fps: 24.0,
             is_playing: false,
+            edited_frames: std::collections::HashMap::new(),
+            symbol_frames: std::collections::HashMap::new(),
+            automated: Vec::new(),
+            played_notes: Vec::new(),
+        }
+    }
+
+    /// Symbol frame set on a keyframe with `set_symbol_frame`
+    pub fn symbol_frame(&self, layer_id: &crate::LayerId, frame: u32) -> Option<u32> {
+        self.symbol_frames.get(&(layer_id.clone(), frame)).copied()
+    }
+
+    /// Property values and triggers applied with `apply_automation`, in order:
+    /// layer, frame, property and value
+    pub fn automated(&self) -> &[(crate::LayerId, u32, crate::PropertyId, crate::AutomationValue)] {
+        &self.automated
+    }
+
+    /// Notes started and stopped with `note_on` and `note_off`, in order:
+    /// layer, frame, note and whether it started
+    pub fn played_notes(&self) -> &[(crate::LayerId, u32, crate::Note, bool)] {
+        &self.played_notes
+    }
+
+    /// Stored frames of a layer, copied from the generated pattern on first edit
+    fn frames_mut(&mut self, layer_id: &crate::LayerId) -> &mut Vec<crate::frame::FrameData> {
+        let total_frames = self.total_frames;
+        self.edited_frames.entry(layer_id.clone()).or_insert_with(|| {
+            (0..total_frames)
+                .map(|frame| crate::frame::create_mock_frame_data(layer_id, frame))
+                .collect()
+        })
+    }
+
+    /// Replace one frame of a layer; frames past the end are ignored
+    fn set_frame(&mut self, layer_id: &crate::LayerId, frame: u32, frame_type: crate::frame::FrameType) {
+        let frames = self.frames_mut(layer_id);
+        if let Some(data) = frames.get_mut(frame as usize) {
+            *data = crate::frame::FrameData {
+                frame_number: frame,
+                frame_type,
+                has_content: frame_type != crate::frame::FrameType::Empty,
+                id: crate::frame::KeyframeId::new(),
+            };
+        }
+    }
+
+    /// Frame type that continues the span before `frame`
+    fn continuation_type(&mut self, layer_id: &crate::LayerId, frame: u32) -> crate::frame::FrameType {
+        let previous = frame
+            .checked_sub(1)
+            .map(|f| crate::RiveEngine::get_frame_data(self, layer_id.clone(), f).has_content);
+        if previous == Some(true) { crate::frame::FrameType::Tween } else { crate::frame::FrameType::Empty }
+    }
+
+    /// Turn the frames after `frame` into tween frames, up to the next keyframe
+    fn fill_tween(&mut self, layer_id: &crate::LayerId, frame: u32) {
+        let frames = self.frames_mut(layer_id);
+        for data in frames.iter_mut().skip(frame as usize + 1) {
+            if data.frame_type == crate::frame::FrameType::Keyframe {
+                break;
+            }
+            data.frame_type = crate::frame::FrameType::Tween;
+            data.has_content = true;
+        }
+    }
+
+    fn renumber(frames: &mut [crate::frame::FrameData]) {
+        for (frame, data) in frames.iter_mut().enumerate() {
+            data.frame_number = frame as u32;
         }
     }
 }
@@ -57,7 +139,13 @@ impl crate::RiveEngine for MockRiveEngine {
     }
 
     fn get_frame_data(&self, layer_id: crate::LayerId, frame: u32) -> crate::frame::FrameData {
-        crate::frame::create_mock_frame_data(&layer_id, frame)
+        match self.edited_frames.get(&layer_id) {
+            Some(frames) => frames.get(frame as usize).cloned().unwrap_or_else(|| crate::frame::FrameData {
+                frame_number: frame,
+                ..Default::default()
+            }),
+            None => crate::frame::create_mock_frame_data(&layer_id, frame),
+        }
     }
 
     fn play(&mut self) {
@@ -71,7 +159,7 @@ impl crate::RiveEngine for MockRiveEngine {
     }
 
     fn seek(&mut self, frame: u32) {
-        self.current_frame = frame.min(self.total_frames);
+        self.current_frame = frame.min(self.total_frames.saturating_sub(1));
         println!("MockRiveEngine: Seeking to frame {}", self.current_frame);
     }
 
@@ -89,68 +177,123 @@ impl crate::RiveEngine for MockRiveEngine {
     
     fn insert_frame(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Inserting frame at {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would modify the timeline data
+        let frame_type = self.continuation_type(&layer_id, frame);
+        let total_frames = self.total_frames as usize;
+        let frames = self.frames_mut(&layer_id);
+        if (frame as usize) < frames.len() {
+            frames.insert(frame as usize, crate::frame::FrameData {
+                frame_number: frame,
+                frame_type,
+                has_content: frame_type != crate::frame::FrameType::Empty,
+                id: crate::frame::KeyframeId::new(),
+            });
+            frames.truncate(total_frames);
+            Self::renumber(frames);
+        }
     }
     
     fn remove_frame(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Removing frame at {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would modify the timeline data
+        let frames = self.frames_mut(&layer_id);
+        if (frame as usize) < frames.len() {
+            frames.remove(frame as usize);
+            frames.push(crate::frame::FrameData::default());
+            Self::renumber(frames);
+        }
     }
     
     fn insert_keyframe(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Inserting keyframe at {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would create a new keyframe
+        self.set_frame(&layer_id, frame, crate::frame::FrameType::Keyframe);
     }
     
     fn clear_keyframe(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Clearing keyframe at {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would remove the keyframe
+        // The frame keeps showing the previous keyframe's content
+        let frame_type = self.continuation_type(&layer_id, frame);
+        self.set_frame(&layer_id, frame, frame_type);
     }
     
     fn create_motion_tween(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Creating motion tween at {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would create a motion tween between keyframes
+        self.fill_tween(&layer_id, frame);
     }
     
     fn create_shape_tween(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Creating shape tween at {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would create a shape tween between keyframes
+        self.fill_tween(&layer_id, frame);
     }
     
     // New keyframe manipulation methods
     fn move_keyframe(&mut self, layer_id: crate::LayerId, from_frame: u32, to_frame: u32) {
         println!("MockRiveEngine: Moving keyframe from frame {} to frame {} on layer {:?}", from_frame, to_frame, layer_id);
-        // In a real implementation, this would move keyframe data in the timeline
+        let frames = self.frames_mut(&layer_id);
+        let in_range = |frame: u32| (frame as usize) < frames.len();
+        if from_frame != to_frame && in_range(from_frame) && in_range(to_frame) {
+            let mut moved = std::mem::take(&mut frames[from_frame as usize]);
+            moved.frame_number = to_frame;
+            frames[from_frame as usize].frame_number = from_frame;
+            frames[to_frame as usize] = moved;
+        }
     }
     
     fn copy_keyframe(&mut self, layer_id: crate::LayerId, frame: u32) -> Option<crate::frame::FrameData> {
         println!("MockRiveEngine: Copying keyframe at frame {} on layer {:?}", frame, layer_id);
-        // Return mock frame data for copy operation
-        Some(crate::frame::create_mock_frame_data(&layer_id, frame))
+        Some(self.get_frame_data(layer_id, frame))
     }
     
     fn paste_keyframe(&mut self, layer_id: crate::LayerId, frame: u32, data: crate::frame::FrameData) {
         println!("MockRiveEngine: Pasting keyframe at frame {} on layer {:?} with data {:?}", frame, layer_id, data);
-        // In a real implementation, this would paste the frame data
+        if let Some(target) = self.frames_mut(&layer_id).get_mut(frame as usize) {
+            *target = crate::frame::FrameData {
+                frame_number: frame,
+                id: crate::frame::KeyframeId::new(),
+                ..data
+            };
+        }
     }
     
     fn delete_keyframe(&mut self, layer_id: crate::LayerId, frame: u32) {
         println!("MockRiveEngine: Deleting keyframe at frame {} on layer {:?}", frame, layer_id);
-        // In a real implementation, this would remove the keyframe
+        self.set_frame(&layer_id, frame, crate::frame::FrameType::Empty);
     }
     
     // Property manipulation methods
     fn set_property(&mut self, layer_id: crate::LayerId, frame: u32, property: &str, value: bool) {
         println!("MockRiveEngine: Setting property '{}' to {} at frame {} on layer {:?}", property, value, frame, layer_id);
-        // In a real implementation, this would set layer properties at specific frames
+        // Only layer-wide properties are modelled
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.id == layer_id) {
+            match property {
+                "visible" => layer.visible = value,
+                "locked" => layer.locked = value,
+                _ => {}
+            }
+        }
+    }
+    
+    fn set_symbol_frame(&mut self, layer_id: crate::LayerId, frame: u32, symbol_frame: u32) {
+        println!("MockRiveEngine: Showing symbol frame {} at frame {} on layer {:?}", symbol_frame, frame, layer_id);
+        self.symbol_frames.insert((layer_id, frame), symbol_frame);
+    }
+    
+    fn apply_automation(&mut self, layer_id: crate::LayerId, frame: u32, property: &crate::PropertyId, value: crate::AutomationValue) {
+        self.automated.push((layer_id, frame, property.clone(), value));
+    }
+    
+    fn note_on(&mut self, layer_id: crate::LayerId, frame: u32, note: &crate::Note) {
+        self.played_notes.push((layer_id, frame, *note, true));
+    }
+    
+    fn note_off(&mut self, layer_id: crate::LayerId, frame: u32, note: &crate::Note) {
+        self.played_notes.push((layer_id, frame, *note, false));
     }
     
     fn get_property(&self, layer_id: crate::LayerId, frame: u32, property: &str) -> bool {
         println!("MockRiveEngine: Getting property '{}' at frame {} on layer {:?}", property, frame, layer_id);
-        // Return mock property value
+        let layer = self.layers.iter().find(|l| l.id == layer_id);
         match property {
-            "visible" => true,
-            "locked" => false,
+            "visible" => layer.is_none_or(|l| l.visible),
+            "locked" => layer.is_some_and(|l| l.locked),
             _ => false,
         }
     }
@@ -163,10 +306,25 @@ impl crate::RiveEngine for MockRiveEngine {
         }
     }
     
+    fn set_layer_outline_color(&mut self, layer_id: crate::LayerId, color: ecolor::Color32) {
+        println!("MockRiveEngine: Setting outline color of layer {:?} to {:?}", layer_id, color);
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.id == layer_id) {
+            layer.outline_color = color;
+        }
+    }
+    
+    fn reorder_layers(&mut self, layer_ids: Vec<crate::LayerId>) {
+        println!("MockRiveEngine: Reordering layers to {:?}", layer_ids);
+        // Listed layers move to the front in the given order; the rest keep their order after them
+        let rank = |id: &crate::LayerId| layer_ids.iter().position(|l| l == id).unwrap_or(layer_ids.len());
+        self.layers.sort_by_key(|layer| rank(&layer.id));
+    }
+    
     fn add_layer(&mut self, name: String, layer_type: crate::layer::LayerType) -> crate::LayerId {
         println!("MockRiveEngine: Adding new {:?} layer '{}'", layer_type, name);
         let mut new_layer = crate::layer::LayerInfo::new(name);
         new_layer.layer_type = layer_type;
+        new_layer.outline_color = crate::layer::palette_color(self.layers.len());
         let layer_id = new_layer.id.clone();
         self.layers.push(new_layer);
         layer_id
@@ -174,7 +332,19 @@ impl crate::RiveEngine for MockRiveEngine {
     
     fn delete_layer(&mut self, layer_id: crate::LayerId) {
         println!("MockRiveEngine: Deleting layer {:?}", layer_id);
+        // A folder takes its contents with it
+        let children = self.layers.iter()
+            .find(|l| l.id == layer_id)
+            .map(|l| l.children.clone())
+            .unwrap_or_default();
+        for child in children {
+            self.delete_layer(child);
+        }
         self.layers.retain(|layer| layer.id != layer_id);
+        for layer in &mut self.layers {
+            layer.children.retain(|child| *child != layer_id);
+        }
+        self.edited_frames.remove(&layer_id);
     }
     
     fn duplicate_layer(&mut self, layer_id: crate::LayerId) -> crate::LayerId {
@@ -183,8 +353,15 @@ impl crate::RiveEngine for MockRiveEngine {
             let mut new_layer = layer;
             new_layer.id = crate::LayerId::new(format!("layer_{}", uuid::Uuid::new_v4()));
             new_layer.name = format!("{} copy", new_layer.name);
+            // Only the layer itself is copied, not a folder's contents
+            new_layer.children.clear();
             let new_id = new_layer.id.clone();
+            if let Some(parent) = self.layers.iter_mut().find(|l| Some(&l.id) == new_layer.parent_id.as_ref()) {
+                parent.children.push(new_id.clone());
+            }
             self.layers.push(new_layer);
+            let frames = self.frames_mut(&layer_id).clone();
+            self.edited_frames.insert(new_id.clone(), frames);
             new_id
         } else {
             // Return a default layer if original not found
@@ -194,7 +371,8 @@ impl crate::RiveEngine for MockRiveEngine {
     
     fn add_folder_layer(&mut self, name: String) -> crate::LayerId {
         println!("MockRiveEngine: Adding new folder layer '{}'", name);
-        let new_layer = crate::layer::LayerInfo::new_folder(name);
+        let mut new_layer = crate::layer::LayerInfo::new_folder(name);
+        new_layer.outline_color = crate::layer::palette_color(self.layers.len());
         let layer_id = new_layer.id.clone();
         self.layers.push(new_layer);
         layer_id
@@ -204,6 +382,7 @@ impl crate::RiveEngine for MockRiveEngine {
         println!("MockRiveEngine: Adding new motion guide layer '{}'", name);
         let mut new_layer = crate::layer::LayerInfo::new(name);
         new_layer.layer_type = crate::layer::LayerType::MotionGuide;
+        new_layer.outline_color = crate::layer::palette_color(self.layers.len());
         let layer_id = new_layer.id.clone();
         self.layers.push(new_layer);
         layer_id
@@ -214,6 +393,18 @@ impl crate::RiveEngine for MockRiveEngine {
     }
 }
 
+/// A call made on [`MockAudioEngine`], in the order received
+#[derive(Clone, Debug, PartialEq)]
+pub enum MockAudioCall {
+    LoadAudio { file_path: std::path::PathBuf },
+    UnloadAudio { audio_id: crate::audio::AudioId },
+    PlaySegment { audio_id: crate::audio::AudioId, start_time: f32, duration: f32, volume: f32 },
+    PlayScrubSnippet { audio_id: crate::audio::AudioId, start_time: f32, duration: f32, volume: f32, fade: f32 },
+    StopAudio { audio_id: crate::audio::AudioId },
+    SetGlobalVolume { volume: f32 },
+    GenerateWaveform { audio_id: crate::audio::AudioId, fps: f32 },
+}
+
 /// Mock audio engine for testing and demo
 pub struct MockAudioEngine {
     /// Loaded audio sources
@@ -224,6 +415,8 @@ pub struct MockAudioEngine {
     playing: std::collections::HashSet<crate::audio::AudioId>,
     /// Global volume
     global_volume: f32,
+    /// Every call received, for tests
+    calls: Vec<MockAudioCall>,
 }
 
 impl MockAudioEngine {
@@ -233,8 +426,19 @@ impl MockAudioEngine {
             waveforms: std::collections::HashMap::new(),
             playing: std::collections::HashSet::new(),
             global_volume: 1.0,
+            calls: Vec::new(),
         }
     }
+
+    /// Calls received so far
+    pub fn calls(&self) -> &[MockAudioCall] {
+        &self.calls
+    }
+
+    /// Return and clear the recorded calls
+    pub fn take_calls(&mut self) -> Vec<MockAudioCall> {
+        std::mem::take(&mut self.calls)
+    }
     
     /// Create mock audio source for testing
     pub fn create_mock_audio(&mut self, filename: &str, duration: f32) -> crate::audio::AudioSource {
@@ -284,6 +488,7 @@ impl MockAudioEngine {
 impl crate::audio::AudioEngine for MockAudioEngine {
     fn load_audio(&mut self, file_path: &std::path::Path) -> Result<crate::audio::AudioSource, crate::audio::AudioError> {
         println!("MockAudioEngine: Loading audio from {:?}", file_path);
+        self.calls.push(MockAudioCall::LoadAudio { file_path: file_path.to_path_buf() });
         
         // Simulate loading different audio files
         let filename = file_path.file_name()
@@ -313,6 +518,7 @@ impl crate::audio::AudioEngine for MockAudioEngine {
     
     fn unload_audio(&mut self, audio_id: &crate::audio::AudioId) -> Result<(), crate::audio::AudioError> {
         println!("MockAudioEngine: Unloading audio {:?}", audio_id);
+        self.calls.push(MockAudioCall::UnloadAudio { audio_id: audio_id.clone() });
         self.loaded_audio.remove(audio_id);
         self.waveforms.remove(audio_id);
         self.playing.remove(audio_id);
@@ -320,6 +526,7 @@ impl crate::audio::AudioEngine for MockAudioEngine {
     }
     
     fn play_segment(&mut self, audio_id: &crate::audio::AudioId, start_time: f32, duration: f32, volume: f32) -> Result<(), crate::audio::AudioError> {
+        self.calls.push(MockAudioCall::PlaySegment { audio_id: audio_id.clone(), start_time, duration, volume });
         if !self.loaded_audio.contains_key(audio_id) {
             return Err(crate::audio::AudioError::AudioNotLoaded);
         }
@@ -331,18 +538,30 @@ impl crate::audio::AudioEngine for MockAudioEngine {
         Ok(())
     }
     
+    fn play_scrub_snippet(&mut self, audio_id: &crate::audio::AudioId, start_time: f32, duration: f32, volume: f32, fade: f32) -> Result<(), crate::audio::AudioError> {
+        self.calls.push(MockAudioCall::PlayScrubSnippet { audio_id: audio_id.clone(), start_time, duration, volume, fade });
+        if !self.loaded_audio.contains_key(audio_id) {
+            return Err(crate::audio::AudioError::AudioNotLoaded);
+        }
+        self.playing.insert(audio_id.clone());
+        Ok(())
+    }
+    
     fn stop_audio(&mut self, audio_id: &crate::audio::AudioId) -> Result<(), crate::audio::AudioError> {
         println!("MockAudioEngine: Stopping audio {:?}", audio_id);
+        self.calls.push(MockAudioCall::StopAudio { audio_id: audio_id.clone() });
         self.playing.remove(audio_id);
         Ok(())
     }
     
     fn set_global_volume(&mut self, volume: f32) {
+        self.calls.push(MockAudioCall::SetGlobalVolume { volume });
         self.global_volume = volume.clamp(0.0, 1.0);
         println!("MockAudioEngine: Setting global volume to {:.2}", self.global_volume);
     }
     
     fn generate_waveform(&mut self, audio_id: &crate::audio::AudioId, fps: f32) -> Result<crate::audio::WaveformData, crate::audio::AudioError> {
+        self.calls.push(MockAudioCall::GenerateWaveform { audio_id: audio_id.clone(), fps });
         if !self.loaded_audio.contains_key(audio_id) {
             return Err(crate::audio::AudioError::AudioNotLoaded);
         }
@@ -0,0 +1,408 @@
+//! WAV decoding and an in-memory [`AudioEngine`] built on it
+//!
+//! Supports RIFF/WAVE files holding 8-, 16-, 24- or 32-bit integer PCM or 32-
+//! and 64-bit IEEE float samples, mono or stereo, including
+//! `WAVE_FORMAT_EXTENSIBLE` headers. Samples are converted to `f32` in `-1.0..=1.0`.
+//! [`encode`] writes 16-bit PCM or 32-bit float files back out.
+
+use crate::audio::{AudioEngine, AudioError, AudioId, AudioSource, WaveformData};
+use crate::waveform::WaveformJob;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Decoded audio: interleaved `f32` samples and their layout
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavData {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Bit depth of the source file
+    pub bits_per_sample: u16,
+    /// Interleaved samples, `channels` per frame
+    pub samples: Vec<f32>,
+}
+
+impl WavData {
+    /// Number of sample frames (samples per channel)
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    /// Length in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    /// Samples of one channel
+    pub fn channel(&self, channel: u16) -> impl Iterator<Item = f32> + '_ {
+        self.samples
+            .iter()
+            .skip(channel as usize)
+            .step_by(self.channels.max(1) as usize)
+            .copied()
+    }
+
+    /// `duration` seconds from `start_time`, scaled by `volume` and faded in
+    /// and out over `fade` seconds (at most half the snippet each way)
+    pub fn snippet(&self, start_time: f32, duration: f32, volume: f32, fade: f32) -> WavData {
+        let rate = self.sample_rate as f32;
+        let channels = self.channels.max(1) as usize;
+        let first = ((start_time.max(0.0) * rate) as usize).min(self.frames());
+        let last = (((start_time + duration).max(0.0) * rate) as usize).clamp(first, self.frames());
+        let frames = last - first;
+        let fade_frames = ((fade.max(0.0) * rate) as usize).min(frames / 2);
+        let samples = self.samples[first * channels..last * channels]
+            .chunks(channels)
+            .enumerate()
+            .flat_map(|(i, frame)| {
+                let edge = i.min(frames - 1 - i);
+                let gain = if edge < fade_frames { volume * edge as f32 / fade_frames as f32 } else { volume };
+                frame.iter().map(move |sample| sample * gain)
+            })
+            .collect();
+        WavData { sample_rate: self.sample_rate, channels: self.channels, bits_per_sample: self.bits_per_sample, samples }
+    }
+}
+
+/// Sample encoding read from the `fmt ` chunk
+#[derive(Clone, Copy, Debug)]
+struct Format {
+    float: bool,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+fn decoding_error(message: impl Into<String>) -> AudioError {
+    AudioError::DecodingError(message.into())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn parse_format(chunk: &[u8]) -> Result<Format, AudioError> {
+    if chunk.len() < 16 {
+        return Err(decoding_error("fmt chunk is too short"));
+    }
+    let mut format_tag = u16_at(chunk, 0);
+    let format = Format {
+        float: false,
+        channels: u16_at(chunk, 2),
+        sample_rate: u32_at(chunk, 4),
+        bits_per_sample: u16_at(chunk, 14),
+    };
+    if format_tag == FORMAT_EXTENSIBLE {
+        // The real format is the first two bytes of the sub-format GUID
+        if chunk.len() < 26 {
+            return Err(decoding_error("extensible fmt chunk is too short"));
+        }
+        format_tag = u16_at(chunk, 24);
+    }
+
+    let float = match (format_tag, format.bits_per_sample) {
+        (FORMAT_PCM, 8 | 16 | 24 | 32) => false,
+        (FORMAT_IEEE_FLOAT, 32 | 64) => true,
+        _ => return Err(AudioError::UnsupportedFormat),
+    };
+    if !(1..=2).contains(&format.channels) {
+        return Err(AudioError::UnsupportedFormat);
+    }
+    if format.sample_rate == 0 {
+        return Err(decoding_error("sample rate is zero"));
+    }
+    Ok(Format { float, ..format })
+}
+
+fn decode_samples(data: &[u8], format: Format) -> Vec<f32> {
+    let width = format.bits_per_sample as usize / 8;
+    data.chunks_exact(width)
+        .map(|s| match (format.float, width) {
+            (false, 1) => (s[0] as f32 - 128.0) / 128.0,
+            (false, 2) => i16::from_le_bytes([s[0], s[1]]) as f32 / 32_768.0,
+            // Shift the 24 bits to the top of an i32 so the sign extends
+            (false, 3) => (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8_388_608.0,
+            (false, _) => i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0,
+            (true, 4) => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+            (true, _) => f64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]) as f32,
+        })
+        .collect()
+}
+
+/// Decode a WAV file held in memory
+pub fn decode(bytes: &[u8]) -> Result<WavData, AudioError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(AudioError::UnsupportedFormat);
+    }
+
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32_at(bytes, offset + 4) as usize;
+        let start = offset + 8;
+        let end = start.checked_add(size).filter(|&end| end <= bytes.len());
+
+        match id {
+            b"fmt " => {
+                let end = end.ok_or_else(|| decoding_error("fmt chunk is truncated"))?;
+                format = Some(parse_format(&bytes[start..end])?);
+            }
+            b"data" => {
+                let format = format.ok_or_else(|| decoding_error("data chunk comes before fmt chunk"))?;
+                let end = end.ok_or_else(|| decoding_error("data chunk is truncated"))?;
+                let block_align = format.channels as usize * format.bits_per_sample as usize / 8;
+                let data = &bytes[start..end];
+                // Ignore a trailing partial frame
+                let data = &data[..data.len() - data.len() % block_align];
+                return Ok(WavData {
+                    sample_rate: format.sample_rate,
+                    channels: format.channels,
+                    bits_per_sample: format.bits_per_sample,
+                    samples: decode_samples(data, format),
+                });
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        offset = start.saturating_add(size).saturating_add(size % 2);
+    }
+
+    Err(decoding_error(if format.is_some() { "missing data chunk" } else { "missing fmt chunk" }))
+}
+
+/// Read and decode a WAV file
+pub fn read(path: &Path) -> Result<WavData, AudioError> {
+    let bytes = std::fs::read(path).map_err(|error| match error.kind() {
+        std::io::ErrorKind::NotFound => AudioError::FileNotFound,
+        _ => decoding_error(error.to_string()),
+    })?;
+    decode(&bytes)
+}
+
+/// Encode audio as a WAV file: 16-bit PCM when `bits_per_sample` is 16,
+/// 32-bit float when it is 32. PCM samples are clipped to `-1.0..=1.0`.
+pub fn encode(data: &WavData) -> Result<Vec<u8>, AudioError> {
+    let format_tag = match data.bits_per_sample {
+        16 => FORMAT_PCM,
+        32 => FORMAT_IEEE_FLOAT,
+        _ => return Err(AudioError::UnsupportedFormat),
+    };
+    if !(1..=2).contains(&data.channels) || data.sample_rate == 0 {
+        return Err(AudioError::UnsupportedFormat);
+    }
+    let block_align = data.channels * data.bits_per_sample / 8;
+    let data_size = data.samples.len() * data.bits_per_sample as usize / 8;
+
+    let mut bytes = Vec::with_capacity(44 + data_size);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&format_tag.to_le_bytes());
+    bytes.extend_from_slice(&data.channels.to_le_bytes());
+    bytes.extend_from_slice(&data.sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(data.sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&data.bits_per_sample.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data_size as u32).to_le_bytes());
+    for &sample in &data.samples {
+        if format_tag == FORMAT_PCM {
+            let sample = (sample.clamp(-1.0, 1.0) * 32_768.0).round().min(32_767.0) as i16;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        } else {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+    Ok(bytes)
+}
+
+/// Encode and write a WAV file
+pub fn write(path: &Path, data: &WavData) -> Result<(), AudioError> {
+    let bytes = encode(data)?;
+    std::fs::write(path, bytes).map_err(|error| AudioError::WriteError(error.to_string()))
+}
+
+/// [`AudioEngine`] that decodes WAV files into memory.
+///
+/// It has no output device: playback calls only track which sounds are
+/// playing, and scrub snippets are rendered for an output to pick up with
+/// [`scrub_snippet`](Self::scrub_snippet). Use it for offline work such as
+/// waveforms and mixdown, or as the data source behind a real output.
+pub struct WavAudioEngine {
+    loaded: HashMap<AudioId, (AudioSource, Arc<WavData>)>,
+    playing: HashSet<AudioId>,
+    snippets: HashMap<AudioId, WavData>,
+    global_volume: f32,
+}
+
+impl WavAudioEngine {
+    pub fn new() -> Self {
+        Self {
+            loaded: HashMap::new(),
+            playing: HashSet::new(),
+            snippets: HashMap::new(),
+            global_volume: 1.0,
+        }
+    }
+
+    /// Decoded samples of a loaded sound
+    pub fn wav_data(&self, audio_id: &AudioId) -> Option<Arc<WavData>> {
+        self.loaded.get(audio_id).map(|(_, data)| data.clone())
+    }
+
+    /// Faded samples of the scrub snippet playing for a sound
+    pub fn scrub_snippet(&self, audio_id: &AudioId) -> Option<&WavData> {
+        self.snippets.get(audio_id)
+    }
+
+    pub fn global_volume(&self) -> f32 {
+        self.global_volume
+    }
+
+    /// Analyze a loaded sound's waveform on a worker thread
+    pub fn analyze_waveform(&self, audio_id: &AudioId, fps: f32) -> Result<WaveformJob, AudioError> {
+        let data = self.wav_data(audio_id).ok_or(AudioError::AudioNotLoaded)?;
+        Ok(WaveformJob::spawn(audio_id.clone(), data, fps))
+    }
+}
+
+impl Default for WavAudioEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioEngine for WavAudioEngine {
+    fn load_audio(&mut self, file_path: &Path) -> Result<AudioSource, AudioError> {
+        let data = read(file_path)?;
+        let mut source = AudioSource::new(file_path.to_path_buf());
+        source.duration = data.duration();
+        source.sample_rate = data.sample_rate;
+        source.channels = data.channels as u32;
+        source.loaded = true;
+        self.loaded.insert(source.id.clone(), (source.clone(), Arc::new(data)));
+        Ok(source)
+    }
+
+    fn unload_audio(&mut self, audio_id: &AudioId) -> Result<(), AudioError> {
+        self.playing.remove(audio_id);
+        self.snippets.remove(audio_id);
+        self.loaded.remove(audio_id).map(|_| ()).ok_or(AudioError::AudioNotLoaded)
+    }
+
+    fn play_segment(&mut self, audio_id: &AudioId, _start_time: f32, _duration: f32, _volume: f32) -> Result<(), AudioError> {
+        if !self.loaded.contains_key(audio_id) {
+            return Err(AudioError::AudioNotLoaded);
+        }
+        self.playing.insert(audio_id.clone());
+        Ok(())
+    }
+
+    fn play_scrub_snippet(&mut self, audio_id: &AudioId, start_time: f32, duration: f32, volume: f32, fade: f32) -> Result<(), AudioError> {
+        let data = self.wav_data(audio_id).ok_or(AudioError::AudioNotLoaded)?;
+        self.snippets.insert(audio_id.clone(), data.snippet(start_time, duration, volume, fade));
+        self.playing.insert(audio_id.clone());
+        Ok(())
+    }
+
+    fn stop_audio(&mut self, audio_id: &AudioId) -> Result<(), AudioError> {
+        self.playing.remove(audio_id);
+        self.snippets.remove(audio_id);
+        Ok(())
+    }
+
+    fn decoded_audio(&self, audio_id: &AudioId) -> Option<Arc<WavData>> {
+        self.wav_data(audio_id)
+    }
+
+    fn set_global_volume(&mut self, volume: f32) {
+        self.global_volume = volume.clamp(0.0, 1.0);
+    }
+
+    fn generate_waveform(&mut self, audio_id: &AudioId, fps: f32) -> Result<WaveformData, AudioError> {
+        let data = self.wav_data(audio_id).ok_or(AudioError::AudioNotLoaded)?;
+        Ok(crate::waveform::analyze(audio_id.clone(), &data, fps))
+    }
+
+    fn is_playing(&self, audio_id: &AudioId) -> bool {
+        self.playing.contains(audio_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_bytes(format_tag: u16, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&format_tag.to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&(8000 * block_align as u32).to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        for (id, chunk) in [(b"fmt ", &fmt[..]), (b"data", data)] {
+            bytes.extend_from_slice(id);
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+        let riff_size = (bytes.len() - 8) as u32;
+        bytes[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_decode_24_bit_sign_extension() {
+        let data = [0xFF, 0xFF, 0x7F, 0x00, 0x00, 0x80, 0xFF, 0xFF, 0xFF];
+        let wav = decode(&wav_bytes(FORMAT_PCM, 1, 24, &data)).unwrap();
+        assert_eq!(wav.samples, vec![8_388_607.0 / 8_388_608.0, -1.0, -1.0 / 8_388_608.0]);
+    }
+
+    #[test]
+    fn test_decode_float64() {
+        let data: Vec<u8> = [0.25f64, -0.75].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let wav = decode(&wav_bytes(FORMAT_IEEE_FLOAT, 2, 64, &data)).unwrap();
+        assert_eq!(wav.frames(), 1);
+        assert_eq!(wav.channel(1).collect::<Vec<_>>(), vec![-0.75]);
+    }
+
+    #[test]
+    fn test_rejects_unsupported_layouts() {
+        assert!(matches!(decode(&wav_bytes(FORMAT_PCM, 6, 16, &[0; 12])), Err(AudioError::UnsupportedFormat)));
+        assert!(matches!(decode(&wav_bytes(FORMAT_PCM, 1, 12, &[0; 3])), Err(AudioError::UnsupportedFormat)));
+        assert!(matches!(decode(&wav_bytes(FORMAT_IEEE_FLOAT, 1, 16, &[0; 2])), Err(AudioError::UnsupportedFormat)));
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let data = WavData { sample_rate: 8000, channels: 2, bits_per_sample: 32, samples: vec![0.25, -1.5, 0.0, 1.0] };
+        assert_eq!(decode(&encode(&data).unwrap()).unwrap(), data);
+
+        let pcm = WavData { bits_per_sample: 16, ..data };
+        let decoded = decode(&encode(&pcm).unwrap()).unwrap();
+        assert_eq!(decoded.samples, vec![0.25, -1.0, 0.0, 32_767.0 / 32_768.0]);
+    }
+
+    #[test]
+    fn test_scrub_snippet_fades() {
+        let data = WavData { sample_rate: 10, channels: 1, bits_per_sample: 16, samples: vec![1.0; 20] };
+        let snippet = data.snippet(0.5, 1.0, 0.5, 0.2);
+        assert_eq!(snippet.samples, vec![0.0, 0.25, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.25, 0.0]);
+
+        // Fades longer than half the snippet meet in the middle
+        assert_eq!(data.snippet(1.5, 1.0, 1.0, 1.0).samples, vec![0.0, 0.5, 1.0, 0.5, 0.0]);
+    }
+}
@@ -0,0 +1,333 @@
+//! Multi-resolution waveform peaks
+//!
+//! A [`PeakPyramid`] stores the min/max of every [`BASE_SAMPLES_PER_PEAK`]
+//! samples of one channel, then of every twice as many, and so on up to a
+//! single peak for the whole sound. Drawing picks the coarsest level that
+//! still has at least one peak per pixel, so a zoomed-out view reads a few
+//! hundred peaks and a zoomed-in one shows detail down to the base level.
+//!
+//! [`WaveformBuilder`] fills the pyramids a block of samples at a time, and
+//! [`WaveformJob`] runs it on a worker thread, publishing partial
+//! [`WaveformData`] with `complete == false` while it works.
+
+use crate::audio::{AudioId, WaveformData};
+use crate::wav::WavData;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Samples per peak at the finest pyramid level
+pub const BASE_SAMPLES_PER_PEAK: usize = 16;
+
+type Peak = (f32, f32);
+
+fn merge(a: Peak, b: Peak) -> Peak {
+    (a.0.min(b.0), a.1.max(b.1))
+}
+
+/// Peaks of one channel at every zoom level
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeakPyramid {
+    /// `levels[n]` holds one (min, max) per `BASE_SAMPLES_PER_PEAK << n` samples
+    pub levels: Vec<Vec<Peak>>,
+}
+
+impl PeakPyramid {
+    pub fn samples_per_peak(level: usize) -> usize {
+        BASE_SAMPLES_PER_PEAK << level
+    }
+
+    /// Append a base-level peak, merging completed pairs into the levels above
+    fn push(&mut self, level: usize, peak: Peak) {
+        if self.levels.len() == level {
+            self.levels.push(Vec::new());
+        }
+        let peaks = &mut self.levels[level];
+        peaks.push(peak);
+        if peaks.len().is_multiple_of(2) {
+            let pair = merge(peaks[peaks.len() - 2], peaks[peaks.len() - 1]);
+            self.push(level + 1, pair);
+        }
+    }
+
+    /// Carry unpaired trailing peaks up so the top level is a single peak
+    fn close(&mut self) {
+        let mut level = 0;
+        while self.levels.get(level).is_some_and(|peaks| peaks.len() > 1) {
+            if self.levels.len() == level + 1 {
+                self.levels.push(Vec::new());
+            }
+            let needed = self.levels[level].len().div_ceil(2);
+            while self.levels[level + 1].len() < needed {
+                let index = self.levels[level + 1].len() * 2;
+                let below = &self.levels[level];
+                let peak = below.get(index + 1).map_or(below[index], |&next| merge(below[index], next));
+                self.levels[level + 1].push(peak);
+            }
+            level += 1;
+        }
+        self.levels.truncate(level + 1);
+    }
+
+    /// Min/max over `buckets` equal slices of the samples `start..end`.
+    ///
+    /// Stops early where the pyramid has no data yet.
+    pub fn peaks(&self, start: usize, end: usize, buckets: usize) -> Vec<Peak> {
+        if buckets == 0 || end <= start || self.levels.is_empty() {
+            return Vec::new();
+        }
+        let per_bucket = (end - start) as f64 / buckets as f64;
+        let level = (0..self.levels.len())
+            .rev()
+            .find(|&level| Self::samples_per_peak(level) as f64 <= per_bucket)
+            .unwrap_or(0);
+        let samples_per_peak = Self::samples_per_peak(level) as f64;
+        let peaks = &self.levels[level];
+
+        let mut out = Vec::with_capacity(buckets);
+        for bucket in 0..buckets {
+            let first = ((start as f64 + bucket as f64 * per_bucket) / samples_per_peak) as usize;
+            let last = (((start as f64 + (bucket + 1) as f64 * per_bucket) / samples_per_peak).ceil() as usize).max(first + 1);
+            if first >= peaks.len() {
+                break;
+            }
+            out.push(peaks[first..last.min(peaks.len())].iter().copied().reduce(merge).unwrap_or_default());
+        }
+        out
+    }
+}
+
+/// Builds [`WaveformData`] from interleaved samples, one block at a time
+#[derive(Clone, Debug)]
+pub struct WaveformBuilder {
+    data: WaveformData,
+    channels: usize,
+    /// Samples per channel in each video frame's peak
+    samples_per_frame: usize,
+    frame_peak: Option<Peak>,
+    frame_fill: usize,
+    /// Unfinished base-level peak per channel and how many samples it holds
+    pending: Vec<(Peak, usize)>,
+    samples_pushed: usize,
+}
+
+impl WaveformBuilder {
+    pub fn new(audio_id: AudioId, fps: f32, sample_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        let mut data = WaveformData::new(audio_id, fps);
+        data.sample_rate = sample_rate;
+        data.channels = vec![PeakPyramid::default(); channels];
+        Self {
+            data,
+            channels,
+            samples_per_frame: ((sample_rate as f32 / fps).round() as usize).max(1),
+            frame_peak: None,
+            frame_fill: 0,
+            pending: vec![((0.0, 0.0), 0); channels],
+            samples_pushed: 0,
+        }
+    }
+
+    /// Samples per channel processed so far
+    pub fn samples_pushed(&self) -> usize {
+        self.samples_pushed
+    }
+
+    /// Add interleaved samples; a trailing partial frame is ignored
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let (peak, count) = &mut self.pending[channel];
+                *peak = if *count == 0 { (sample, sample) } else { merge(*peak, (sample, sample)) };
+                *count += 1;
+                if *count == BASE_SAMPLES_PER_PEAK {
+                    let peak = *peak;
+                    *count = 0;
+                    self.data.channels[channel].push(0, peak);
+                }
+                self.frame_peak = Some(self.frame_peak.map_or((sample, sample), |p| merge(p, (sample, sample))));
+            }
+            self.frame_fill += 1;
+            if self.frame_fill == self.samples_per_frame {
+                self.data.peaks.extend(self.frame_peak.take());
+                self.frame_fill = 0;
+            }
+            self.samples_pushed += 1;
+        }
+    }
+
+    /// The peaks so far, marked incomplete
+    pub fn snapshot(&self) -> WaveformData {
+        self.data.clone()
+    }
+
+    /// Flush partial peaks and mark the waveform complete
+    pub fn finish(mut self) -> WaveformData {
+        for (channel, (peak, count)) in self.pending.iter().enumerate() {
+            if *count > 0 {
+                self.data.channels[channel].push(0, *peak);
+            }
+        }
+        for pyramid in &mut self.data.channels {
+            pyramid.close();
+        }
+        self.data.peaks.extend(self.frame_peak.take());
+        self.data.complete = true;
+        self.data
+    }
+}
+
+/// Build the complete waveform of decoded audio
+pub fn analyze(audio_id: AudioId, data: &WavData, fps: f32) -> WaveformData {
+    let mut builder = WaveformBuilder::new(audio_id, fps, data.sample_rate, data.channels);
+    builder.push(&data.samples);
+    builder.finish()
+}
+
+/// Background waveform analysis.
+///
+/// [`waveform`](Self::waveform) returns the latest published result: partial
+/// ones at every tenth of the way through, then the complete one.
+pub struct WaveformJob {
+    latest: Arc<Mutex<Arc<WaveformData>>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl WaveformJob {
+    /// Seconds of audio analyzed between checks for cancellation and publishing
+    const BLOCK_SECONDS: usize = 1;
+    const PUBLISH_STEPS: usize = 10;
+
+    pub fn spawn(audio_id: AudioId, data: Arc<WavData>, fps: f32) -> Self {
+        let builder = WaveformBuilder::new(audio_id, fps, data.sample_rate, data.channels);
+        let latest = Arc::new(Mutex::new(Arc::new(builder.snapshot())));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let worker = {
+            let latest = latest.clone();
+            let cancel = cancel.clone();
+            std::thread::Builder::new()
+                .name("waveform".into())
+                .spawn(move || {
+                    let mut builder = builder;
+                    let block = data.sample_rate as usize * data.channels as usize * Self::BLOCK_SECONDS;
+                    let total = data.samples.len().max(1);
+                    let mut published = 0;
+                    for (index, samples) in data.samples.chunks(block.max(1)).enumerate() {
+                        if cancel.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        builder.push(samples);
+                        let step = ((index + 1) * block).min(total) * Self::PUBLISH_STEPS / total;
+                        if step > published && step < Self::PUBLISH_STEPS {
+                            published = step;
+                            *latest.lock().unwrap() = Arc::new(builder.snapshot());
+                        }
+                    }
+                    *latest.lock().unwrap() = Arc::new(builder.finish());
+                })
+                .expect("failed to spawn waveform thread")
+        };
+
+        Self { latest, cancel, worker: Some(worker) }
+    }
+
+    pub fn waveform(&self) -> Arc<WaveformData> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.waveform().complete
+    }
+
+    /// Block until analysis finishes and return the complete waveform
+    pub fn wait(mut self) -> Arc<WaveformData> {
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        self.waveform()
+    }
+}
+
+impl Drop for WaveformJob {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(len: usize) -> Vec<f32> {
+        (0..len).map(|i| i as f32 / len as f32).collect()
+    }
+
+    #[test]
+    fn test_pyramid_levels() {
+        let mut builder = WaveformBuilder::new(AudioId::new("ramp"), 24.0, 48_000, 1);
+        builder.push(&ramp(BASE_SAMPLES_PER_PEAK * 5));
+        let waveform = builder.finish();
+        let levels = &waveform.channels[0].levels;
+
+        // 5 base peaks, then 3, 2 and 1
+        assert_eq!(levels.iter().map(Vec::len).collect::<Vec<_>>(), vec![5, 3, 2, 1]);
+        assert_eq!(levels[3][0], (0.0, (BASE_SAMPLES_PER_PEAK * 5 - 1) as f32 / (BASE_SAMPLES_PER_PEAK * 5) as f32));
+        assert_eq!(levels[1][2], levels[0][4], "an unpaired peak is carried up as is");
+    }
+
+    #[test]
+    fn test_query_picks_level_for_zoom() {
+        let samples = ramp(BASE_SAMPLES_PER_PEAK * 64);
+        let pyramid = &analyze(AudioId::new("ramp"), &WavData { sample_rate: 8000, channels: 1, bits_per_sample: 32, samples: samples.clone() }, 24.0).channels[0];
+
+        // Zoomed out: one bucket for everything
+        assert_eq!(pyramid.peaks(0, samples.len(), 1), vec![(0.0, samples[samples.len() - 1])]);
+        // Zoomed in: buckets narrower than a base peak repeat it
+        let detail = pyramid.peaks(0, 8, 4);
+        assert_eq!(detail.len(), 4);
+        assert!(detail.iter().all(|&p| p == pyramid.levels[0][0]));
+        // Past the end of the data there is nothing to draw
+        assert!(pyramid.peaks(samples.len(), samples.len() * 2, 4).is_empty());
+    }
+
+    #[test]
+    fn test_stereo_channels_are_split() {
+        let samples: Vec<f32> = (0..BASE_SAMPLES_PER_PEAK * 2).flat_map(|_| [0.5, -0.25]).collect();
+        let wav = WavData { sample_rate: 8000, channels: 2, bits_per_sample: 32, samples };
+        let waveform = analyze(AudioId::new("stereo"), &wav, 24.0);
+        assert_eq!(waveform.channels.len(), 2);
+        assert_eq!(waveform.channels[0].levels[0][0], (0.5, 0.5));
+        assert_eq!(waveform.channels[1].levels[0][0], (-0.25, -0.25));
+    }
+
+    #[test]
+    fn test_incremental_build_matches_one_shot() {
+        let samples = ramp(10_000);
+        let wav = WavData { sample_rate: 8000, channels: 1, bits_per_sample: 32, samples: samples.clone() };
+        let mut builder = WaveformBuilder::new(AudioId::new("ramp"), 24.0, 8000, 1);
+        for block in samples.chunks(999) {
+            builder.push(block);
+            assert!(!builder.snapshot().complete);
+        }
+        let incremental = builder.finish();
+        let one_shot = analyze(AudioId::new("ramp"), &wav, 24.0);
+        assert_eq!(incremental.channels, one_shot.channels);
+        assert_eq!(incremental.peaks, one_shot.peaks);
+        assert_eq!(incremental.peaks.len(), 10_000usize.div_ceil(333), "one peak per 333 samples, the last partial");
+    }
+
+    #[test]
+    fn test_background_job_completes() {
+        let wav = Arc::new(WavData { sample_rate: 8000, channels: 1, bits_per_sample: 32, samples: ramp(8000 * 3) });
+        let job = WaveformJob::spawn(AudioId::new("job"), wav.clone(), 24.0);
+        let waveform = job.wait();
+        assert!(waveform.complete);
+        assert_eq!(waveform.channels, analyze(AudioId::new("job"), &wav, 24.0).channels);
+    }
+}
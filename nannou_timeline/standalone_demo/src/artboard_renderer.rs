@@ -5,6 +5,7 @@
 //! displayed in the timeline stage canvas.
 
 use egui::{Painter, Color32, Stroke, Pos2, Shape, Rect};
+use nannou_timeline::layer::LayerDisplayMode;
 use std::f32::consts::PI;
 
 /// Mock RustFlash types for integration (replace with actual imports when available)
@@ -87,6 +88,8 @@ pub struct ArtboardRenderer {
     pub offset: Pos2,
     /// Debug mode for showing additional info
     pub debug_mode: bool,
+    /// How the layer panel wants the artboard's layer drawn
+    pub display_mode: LayerDisplayMode,
 }
 
 impl ArtboardRenderer {
@@ -95,6 +98,7 @@ impl ArtboardRenderer {
             scale: 1.0,
             offset: Pos2::ZERO,
             debug_mode: false,
+            display_mode: LayerDisplayMode::Normal,
         }
     }
     
@@ -113,6 +117,11 @@ impl ArtboardRenderer {
         self
     }
     
+    pub fn with_display_mode(mut self, display_mode: LayerDisplayMode) -> Self {
+        self.display_mode = display_mode;
+        self
+    }
+    
     /// Main rendering function - converts artboard to egui shapes
     pub fn render_artboard(&self, painter: &Painter, artboard: &RiveArtboard, canvas_rect: Rect) {
         // Clear the canvas with a dark background
@@ -151,44 +160,58 @@ impl ArtboardRenderer {
     
     /// Renders a single RivePath to egui shapes
     fn render_path(&self, painter: &Painter, path: &RivePath, canvas_rect: Rect) {
+        painter.extend(self.path_shapes(path, canvas_rect));
+    }
+    
+    /// Shapes for a single RivePath, drawn as `display_mode` says: outline
+    /// mode strokes fills and strokes alike in the layer's outline color
+    fn path_shapes(&self, path: &RivePath, canvas_rect: Rect) -> Vec<Shape> {
+        let mut shapes = Vec::new();
         if path.commands.is_empty() {
-            return;
+            return shapes;
         }
         
         // Convert path commands to egui points
         let points = self.path_commands_to_egui_points(&path.commands, canvas_rect);
         
         if points.is_empty() {
-            return;
+            return shapes;
         }
         
         // Handle filled paths
         if let Some(fill) = &path.fill {
             let fill_color = self.convert_color(fill.color, fill.alpha);
             
-            if points.len() >= 3 {
-                // Create a filled polygon
-                let shape = Shape::convex_polygon(points.clone(), fill_color, Stroke::NONE);
-                painter.add(shape);
-            } else if points.len() == 2 {
-                // For lines, draw a thick stroke instead
-                painter.line_segment([points[0], points[1]], Stroke::new(2.0, fill_color));
+            if let Some(fill_color) = self.display_mode.fill(fill_color) {
+                if points.len() >= 3 {
+                    // Create a filled polygon
+                    shapes.push(Shape::convex_polygon(points.clone(), fill_color, Stroke::NONE));
+                } else if points.len() == 2 {
+                    // For lines, draw a thick stroke instead
+                    shapes.push(Shape::line_segment([points[0], points[1]], Stroke::new(2.0, fill_color)));
+                }
+            } else if let Some(outline) = self.display_mode.stroke(fill_color).filter(|_| points.len() >= 2) {
+                shapes.push(Shape::closed_line(points.clone(), Stroke::new(1.0, outline)));
             }
         }
         
         // Handle stroked paths
         if let Some(stroke) = &path.stroke {
             let stroke_color = self.convert_color(stroke.color, stroke.alpha);
-            let stroke_width = stroke.width * self.scale;
+            let stroke_width = match self.display_mode {
+                LayerDisplayMode::Outline(_) => 1.0,
+                _ => stroke.width * self.scale,
+            };
             
-            if points.len() >= 2 {
+            if let Some(stroke_color) = self.display_mode.stroke(stroke_color).filter(|_| points.len() >= 2) {
                 // Draw stroke as connected line segments
                 let path_stroke = Stroke::new(stroke_width, stroke_color);
                 for window in points.windows(2) {
-                    painter.line_segment([window[0], window[1]], path_stroke);
+                    shapes.push(Shape::line_segment([window[0], window[1]], path_stroke));
                 }
             }
         }
+        shapes
     }
     
     /// Converts path commands to egui screen coordinates
@@ -437,4 +460,31 @@ mod tests {
         assert_eq!(end, p2);
         assert_eq!(middle, Pos2::new(50.0, 50.0)); // Peak of the curve
     }
+    
+    #[test]
+    fn test_layer_display_mode() {
+        let path = RivePath {
+            commands: vec![
+                PathCommand::MoveTo { x: 0.0, y: 0.0 },
+                PathCommand::LineTo { x: 10.0, y: 0.0 },
+                PathCommand::LineTo { x: 10.0, y: 10.0 },
+                PathCommand::Close,
+            ],
+            fill: Some(PathFill { color: 0xFF0000, alpha: 1.0 }),
+            stroke: None,
+            bounds: Rectangle::new(0.0, 0.0, 10.0, 10.0),
+        };
+        let canvas_rect = Rect::from_min_size(Pos2::ZERO, egui::Vec2::new(100.0, 100.0));
+        
+        let normal = ArtboardRenderer::new().path_shapes(&path, canvas_rect);
+        assert!(matches!(normal.as_slice(), [Shape::Path(path)] if path.fill == Color32::RED));
+        
+        // Outline mode draws the fill's outline in the layer color
+        let magenta = Color32::from_rgb(255, 0, 255);
+        let outline = ArtboardRenderer::new().with_display_mode(LayerDisplayMode::Outline(magenta)).path_shapes(&path, canvas_rect);
+        assert!(matches!(outline.as_slice(), [Shape::Path(path)] if path.fill == Color32::TRANSPARENT && path.closed));
+        
+        let hidden = ArtboardRenderer::new().with_display_mode(LayerDisplayMode::Hidden).path_shapes(&path, canvas_rect);
+        assert!(hidden.is_empty());
+    }
 }
\ No newline at end of file
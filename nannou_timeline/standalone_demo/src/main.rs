@@ -172,6 +172,16 @@ impl RiveEngine for LoggingRiveEngine {
         self.inner.rename_layer(layer_id, new_name)
     }
     
+    fn set_layer_outline_color(&mut self, layer_id: LayerId, color: egui::Color32) {
+        self.log(LogLevel::Action, format!("Set outline color of layer {:?} to {:?}", layer_id, color));
+        self.inner.set_layer_outline_color(layer_id, color)
+    }
+    
+    fn reorder_layers(&mut self, layer_ids: Vec<LayerId>) {
+        self.log(LogLevel::Action, format!("Reordered layers to {:?}", layer_ids));
+        self.inner.reorder_layers(layer_ids)
+    }
+    
     fn add_layer(&mut self, name: String, layer_type: LayerType) -> LayerId {
         let layer_id = self.inner.add_layer(name.clone(), layer_type);
         self.log(LogLevel::Action, format!("Added new {:?} layer '{}' with id {:?}", layer_type, name, layer_id));
@@ -562,7 +572,18 @@ impl eframe::App for TimelineApp {
                 let prev_zoom = self.timeline.state.zoom_level;
                 let prev_playing = self.timeline.state.is_playing;
                 
-                self.timeline.show(ui, &mut self.engine);
+                let output = self.timeline.show(ui, &mut self.engine);
+                for event in output.events {
+                    match event {
+                        nannou_timeline::TimelineEvent::LayersReordered { layer_ids } => {
+                            self.log(LogLevel::Action, format!("Layers reordered: {:?}", layer_ids));
+                        }
+                        nannou_timeline::TimelineEvent::SelectionChanged { layers, keyframes } => {
+                            self.log(LogLevel::Action, format!("Selection: {} layers, {} keyframes", layers.len(), keyframes.len()));
+                        }
+                        _ => {}
+                    }
+                }
                 
                 let curr_frame = self.engine.get_current_frame();
                 if prev_frame != curr_frame {
@@ -2537,6 +2558,13 @@ impl TimelineApp {
                 self.artboard_renderer = Some(ArtboardRenderer::new().with_debug(true));
             }
             
+            // The artboard shows the engine's first layer, drawn as the layer panel says
+            let display_mode = self.timeline.controller.layers(self.engine.as_ref()).first()
+                .map_or(nannou_timeline::layer::LayerDisplayMode::Normal, |layer| self.timeline.state.layer_display_mode(layer));
+            if let Some(renderer) = &mut self.artboard_renderer {
+                renderer.display_mode = display_mode;
+            }
+            
             // Try to get rendered artboard from RustFlash engine
             let has_artboard = if let Some(rustflash_integration) = self.engine.as_any_mut().downcast_mut::<RustFlashIntegration>() {
                 match rustflash_integration.get_renderer_artboard() {
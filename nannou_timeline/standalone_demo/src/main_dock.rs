@@ -154,6 +154,16 @@ impl RiveEngine for LoggingRiveEngine {
         self.inner.rename_layer(layer_id, new_name)
     }
     
+    fn set_layer_outline_color(&mut self, layer_id: LayerId, color: egui::Color32) {
+        self.log(LogLevel::Action, format!("Set outline color of layer {:?} to {:?}", layer_id, color));
+        self.inner.set_layer_outline_color(layer_id, color)
+    }
+    
+    fn reorder_layers(&mut self, layer_ids: Vec<LayerId>) {
+        self.log(LogLevel::Action, format!("Reorder layers to {:?}", layer_ids));
+        self.inner.reorder_layers(layer_ids)
+    }
+    
     fn add_layer(&mut self, name: String, layer_type: LayerType) -> LayerId {
         let id = self.inner.add_layer(name.clone(), layer_type);
         self.log(LogLevel::Action, format!("Add layer '{}' of type {:?}", name, layer_type));
@@ -400,6 +400,7 @@ impl RustFlashIntegration {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: nannou_timeline::layer::palette_color(0),
         };
         layers.push(layer1);
         
@@ -411,6 +412,7 @@ impl RustFlashIntegration {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: nannou_timeline::layer::palette_color(1),
         };
         layers.push(layer2);
         
@@ -733,6 +735,20 @@ impl RiveEngine for RustFlashIntegration {
         println!("RustFlashIntegration: Renaming layer {:?} to '{}'", layer_id, new_name);
     }
     
+    fn set_layer_outline_color(&mut self, layer_id: LayerId, color: egui::Color32) {
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.id == layer_id) {
+            layer.outline_color = color;
+        }
+        println!("RustFlashIntegration: Setting outline color of layer {:?} to {:?}", layer_id, color);
+    }
+    
+    fn reorder_layers(&mut self, layer_ids: Vec<LayerId>) {
+        let order: Vec<String> = layer_ids.iter().map(|id| id.0.clone()).collect();
+        self.send_command("reorder_layers", order.iter().map(String::as_str).collect());
+        let rank = |id: &LayerId| layer_ids.iter().position(|l| l == id).unwrap_or(layer_ids.len());
+        self.layers.sort_by_key(|layer| rank(&layer.id));
+    }
+    
     fn add_layer(&mut self, name: String, layer_type: LayerType) -> LayerId {
         let layer_id = LayerId::new(format!("rustflash_layer_{}", Uuid::new_v4()));
         self.send_command("add_layer", vec![&name, &format!("{:?}", layer_type)]);
@@ -745,6 +761,7 @@ impl RiveEngine for RustFlashIntegration {
             locked: false,
             parent_id: None,
             children: vec![],
+            outline_color: nannou_timeline::layer::palette_color(self.layers.len()),
         };
         self.layers.push(new_layer);
         
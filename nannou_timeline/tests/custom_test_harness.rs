@@ -29,6 +29,8 @@ impl TimelineTestHarness {
                 show_guides: true,
                 snap_to_keyframes: true,
                 snap_to_markers: false,
+                snap_to_beats: true,
+                beat_subdivision: 1,
             },
             ..Default::default()
         };
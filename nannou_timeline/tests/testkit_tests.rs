@@ -0,0 +1,5 @@
+//! Runs the engine test kit against the bundled mock engine
+
+use nannou_timeline::MockRiveEngine;
+
+nannou_timeline::rive_engine_tests!(mock_engine, MockRiveEngine::new());
@@ -1,7 +1,10 @@
 //! Tests for the Flash-inspired timeline widget
 
-use nannou_timeline::{Timeline, TimelineConfig, ui::MockRiveEngine, LayerId, RiveEngine};
+#[cfg(feature = "ui")]
+use nannou_timeline::Timeline;
+use nannou_timeline::{TimelineConfig, ui::MockRiveEngine, LayerId, RiveEngine};
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_timeline_creation() {
     let timeline = Timeline::new();
@@ -10,6 +13,7 @@ fn test_timeline_creation() {
     assert_eq!(timeline.state.zoom_level, 1.0);
 }
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_timeline_with_custom_config() {
     let config = TimelineConfig {
@@ -66,6 +70,70 @@ fn test_layer_visibility() {
     assert!(!layers[3].visible); // Glow (should be hidden)
 }
 
+#[cfg(feature = "ui")]
+#[test]
+fn test_layer_outline_mode() {
+    use nannou_timeline::layer::LayerDisplayMode;
+
+    let mut engine = MockRiveEngine::new();
+    let mut timeline = Timeline::new();
+    let layer_id = LayerId::new("layer2");
+
+    engine.set_layer_outline_color(layer_id.clone(), egui::Color32::from_rgb(255, 0, 255));
+    let layers = engine.get_layers();
+    let character = layers.iter().find(|l| l.id == layer_id).unwrap();
+    assert_eq!(character.outline_color, egui::Color32::from_rgb(255, 0, 255));
+    assert_eq!(timeline.state.layer_display_mode(character), LayerDisplayMode::Normal);
+
+    timeline.state.layer_panel_state.layer_outline.insert(layer_id.clone(), true);
+    assert_eq!(
+        timeline.state.layer_display_mode(character),
+        LayerDisplayMode::Outline(egui::Color32::from_rgb(255, 0, 255))
+    );
+
+    // Outline mode strokes content in the layer's color instead of filling it
+    let outline = timeline.state.layer_display_mode(character);
+    assert_eq!(outline.fill(egui::Color32::RED), None);
+    assert_eq!(outline.stroke(egui::Color32::RED), Some(egui::Color32::from_rgb(255, 0, 255)));
+    assert_eq!(LayerDisplayMode::Normal.fill(egui::Color32::RED), Some(egui::Color32::RED));
+
+    // Hidden wins over outline
+    timeline.state.layer_panel_state.layer_visibility.insert(layer_id, false);
+    assert_eq!(timeline.state.layer_display_mode(character), LayerDisplayMode::Hidden);
+    assert_eq!(LayerDisplayMode::Hidden.stroke(egui::Color32::RED), None);
+}
+
+#[test]
+fn test_layer_outline_colors_per_document() {
+    use nannou_timeline::layer::{assign_outline_colors, palette_color, LayerInfo};
+
+    // Layers saved without colors get them from their index, the same every load
+    let json = r#"[
+        {"id": "a", "name": "A", "layer_type": "Normal", "visible": true, "locked": false, "parent_id": null, "children": []},
+        {"id": "b", "name": "B", "layer_type": "Normal", "visible": true, "locked": false, "parent_id": null, "children": []}
+    ]"#;
+    let load = || {
+        let mut layers: Vec<LayerInfo> = serde_json::from_str(json).unwrap();
+        assign_outline_colors(&mut layers);
+        layers.iter().map(|layer| layer.outline_color).collect::<Vec<_>>()
+    };
+    assert_eq!(load(), [palette_color(0), palette_color(1)]);
+    assert_eq!(load(), load());
+
+    // New layers take the color of their index in the engine
+    let mut engine = MockRiveEngine::new();
+    let count = engine.get_layers().len();
+    let layer_id = engine.add_layer("New".to_string(), nannou_timeline::LayerType::Normal);
+    let layer = engine.get_layers().into_iter().find(|layer| layer.id == layer_id).unwrap();
+    assert_eq!(layer.outline_color, palette_color(count));
+
+    // The widget draws layers the engine has no color for in their palette color
+    engine.set_layer_outline_color(layer_id.clone(), egui::Color32::TRANSPARENT);
+    let layers = nannou_timeline::TimelineController::new().layers(&engine);
+    assert_eq!(layers[count].outline_color, palette_color(count));
+    assert_eq!(LayerInfo::new("Loose").outline_color, egui::Color32::TRANSPARENT);
+}
+
 #[test]
 fn test_layer_hierarchy() {
     let engine = MockRiveEngine::new();
@@ -100,7 +168,7 @@ fn test_frame_types() {
 #[cfg(test)]
 mod visual_tests {
     use super::*;
-    use egui::Color32;
+    use ecolor::Color32;
     
     #[test]
     fn test_timeline_style_colors() {
@@ -190,6 +258,7 @@ fn test_frame_operations() {
     assert_eq!(engine.get_current_frame(), 0);
 }
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_timeline_scrolling_and_zoom() {
     use nannou_timeline::Timeline;
@@ -214,6 +283,63 @@ fn test_timeline_scrolling_and_zoom() {
     assert_eq!(timeline.state.scroll_y, 50.0);
 }
 
+#[cfg(feature = "ui")]
+#[test]
+fn test_zoom_around_cursor() {
+    let mut timeline = Timeline::new();
+    timeline.state.scroll_x = 100.0;
+
+    // Frame 30 sits 200px into the grid at 10px frames; it should stay there
+    timeline.state.zoom_around(2.0, 200.0, 10.0, 1000);
+    assert_eq!(timeline.state.zoom_level, 2.0);
+    assert_eq!(timeline.state.scroll_x, 30.0 * 20.0 - 200.0);
+
+    // Zoom is clamped to the timeline limits
+    timeline.state.zoom_around(100.0, 0.0, 10.0, 1000);
+    assert_eq!(timeline.state.zoom_level, nannou_timeline::timeline_egui::MAX_ZOOM);
+
+    // Zooming out near the end doesn't scroll past the last frame
+    timeline.state.zoom_level = 1.0;
+    timeline.state.view_width = 500.0;
+    timeline.state.scroll_x = 500.0;
+    timeline.state.zoom_around(0.8, 250.0, 10.0, 100);
+    assert_eq!(timeline.state.scroll_x, 100.0 * 8.0 - 500.0);
+}
+
+#[cfg(feature = "ui")]
+#[test]
+fn test_zoom_to_fit_and_selection() {
+    use nannou_timeline::FrameSizePreset;
+
+    let mut timeline = Timeline::new();
+
+    // Before the first draw there's no width to fit into yet
+    let view_width = timeline.state.view_width;
+    timeline.state.zoom_to_frames(0, 99, 10.0, view_width);
+    assert_eq!(timeline.state.zoom_level, 1.0);
+    timeline.state.set_view_width(500.0, 10.0);
+    assert_eq!(timeline.state.zoom_level, 0.5);
+
+    // Fit all: 100 frames into 500px
+    timeline.state.zoom_to_frames(0, 99, 10.0, 500.0);
+    assert_eq!(timeline.state.zoom_level, 0.5);
+    assert_eq!(timeline.state.scroll_x, 0.0);
+    assert_eq!(FrameSizePreset::from_zoom_level(timeline.state.zoom_level), Some(FrameSizePreset::Tiny));
+
+    // Selection spans keyframes first, then the frame range
+    assert_eq!(timeline.state.selection_frame_range(), None);
+    timeline.state.frame_range_selection = Some((40, 20));
+    assert_eq!(timeline.state.selection_frame_range(), Some((20, 40)));
+    timeline.state.keyframe_selection.add(LayerId::new("layer1"), 15, nannou_timeline::KeyframeId::new());
+    timeline.state.keyframe_selection.add(LayerId::new("layer2"), 24, nannou_timeline::KeyframeId::new());
+    assert_eq!(timeline.state.selection_frame_range(), Some((15, 24)));
+
+    timeline.state.zoom_to_frames(15, 24, 10.0, 200.0);
+    assert_eq!(timeline.state.zoom_level, 2.0);
+    assert_eq!(timeline.state.scroll_x, 15.0 * 20.0);
+}
+
+#[cfg(feature = "ui")]
 #[test]
 fn test_snap_to_grid_functionality() {
     use nannou_timeline::Timeline;
@@ -278,6 +404,7 @@ fn test_snap_to_grid_functionality() {
     assert_eq!(snapped, 52.0);
 }
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_snap_guides() {
     use nannou_timeline::Timeline;
@@ -297,6 +424,7 @@ fn test_snap_guides() {
     assert!(timeline.state.snap_guides.is_empty());
 }
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_keyframe_selection_basic() {
     use nannou_timeline::{Timeline, KeyframeId, LayerId};
@@ -320,6 +448,7 @@ fn test_keyframe_selection_basic() {
     assert!(timeline.state.keyframe_selection.selected.is_empty());
 }
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_keyframe_selection_multiple() {
     use nannou_timeline::{Timeline, KeyframeId, LayerId};
@@ -347,6 +476,7 @@ fn test_keyframe_selection_multiple() {
     assert!(!timeline.state.keyframe_selection.is_selected(layer1, 5));
 }
 
+#[cfg(feature = "ui")]
 #[test]
 fn test_keyframe_drag_state() {
     use nannou_timeline::{Timeline, KeyframeId, LayerId, DragState};
@@ -408,6 +538,60 @@ fn test_mock_engine_keyframe_methods() {
     assert!(!locked);
 }
 
+#[cfg(feature = "ui")]
+#[test]
+fn test_commands_emit_events() {
+    use nannou_timeline::TimelineEvent;
+
+    let mut engine: Box<dyn RiveEngine> = Box::new(MockRiveEngine::new());
+    let mut timeline = Timeline::new();
+    let layer_count = engine.get_layers().len();
+
+    assert!(timeline.execute_command("layer.add", &mut engine));
+    assert!(timeline.execute_command("playback.last_frame", &mut engine));
+    let events = timeline.take_events();
+    assert!(matches!(&events[0], TimelineEvent::LayerAdded { name, .. } if name == "New Layer"));
+    assert!(matches!(events[1], TimelineEvent::SeekRequested { frame: 99 }));
+    assert!(timeline.take_events().is_empty());
+
+    // Uncontrolled: the widget applied the events itself
+    assert_eq!(engine.get_layers().len(), layer_count + 1);
+    assert_eq!(engine.get_current_frame(), 99);
+
+    // Dialogs belong to the widget, not the controller
+    assert!(timeline.execute_command("file.import_midi", &mut engine));
+    assert!(timeline.midi_import_dialog.is_some());
+
+    let frame = timeline.controller.scrub_to(engine.as_mut(), 10.0 * timeline.frame_width(), true);
+    assert_eq!(frame, 10);
+    assert_eq!(timeline.state.playhead_frame, 10);
+}
+
+#[cfg(feature = "ui")]
+#[test]
+fn test_controlled_mode_leaves_engine_untouched() {
+    use nannou_timeline::TimelineEvent;
+
+    let mut engine: Box<dyn RiveEngine> = Box::new(MockRiveEngine::new());
+    let mut timeline = Timeline::new().controlled(true);
+    let layer_count = engine.get_layers().len();
+
+    timeline.state.selected_layers = vec![LayerId::new("layer1")];
+    assert!(timeline.execute_command("layer.delete", &mut engine));
+    assert!(timeline.execute_command("playback.last_frame", &mut engine));
+    assert_eq!(engine.get_layers().len(), layer_count);
+    assert_eq!(engine.get_current_frame(), 0);
+
+    // The host applies the events through its own store
+    let events = timeline.take_events();
+    assert!(matches!(&events[0], TimelineEvent::LayerDeleted { layer_id } if layer_id == &LayerId::new("layer1")));
+    for event in &events {
+        event.apply(engine.as_mut());
+    }
+    assert_eq!(engine.get_layers().len(), layer_count - 1);
+    assert_eq!(engine.get_current_frame(), 99);
+}
+
 #[cfg(test)]
 mod audio_tests {
     use nannou_timeline::{AudioSource, AudioLayer, AudioSyncMode, VolumeEnvelope, MockAudioEngine, AudioEngine};
@@ -481,6 +665,78 @@ mod audio_tests {
         assert_eq!(envelope.volume_at_frame(30), 0.8);
     }
     
+    #[test]
+    fn test_volume_envelope_presets_and_moves() {
+        use nannou_timeline::EnvelopePreset;
+
+        let fade_in = VolumeEnvelope::from_preset(EnvelopePreset::FadeIn, 48, 12);
+        assert_eq!(fade_in.volume_at_frame(6), 0.5);
+        assert_eq!(fade_in.volume_at_frame(40), 1.0);
+
+        let mut both = VolumeEnvelope::from_preset(EnvelopePreset::FadeInOut, 48, 30);
+        // The fades are shortened so they don't overlap
+        assert_eq!(both.points, vec![(0, 0.0), (23, 1.0), (25, 1.0), (48, 0.0)]);
+
+        // Points stay between their neighbours
+        assert_eq!(both.move_point(23, 40, 0.5), Some(24));
+        assert_eq!(both.move_point(48, 60, 2.0), Some(60));
+        assert_eq!(both.points.last(), Some(&(60, 1.0)));
+        assert_eq!(both.move_point(7, 8, 1.0), None);
+    }
+    
+    #[test]
+    fn test_audio_track_overlaps_crossfades_and_splits() {
+        use nannou_timeline::{AudioError, AudioTrack, Crossfade};
+
+        let clip = |start_frame: u32| {
+            let mut source = AudioSource::new(PathBuf::from("hit.wav"));
+            source.duration = 1.0;
+            AudioLayer::new(source, start_frame)
+        };
+        let mut track = AudioTrack::new();
+        assert_eq!(track.add_clip(clip(30), 24.0).unwrap(), 0);
+        assert_eq!(track.add_clip(clip(0), 24.0).unwrap(), 0);
+        assert_eq!(track.add_clip(clip(50), 24.0).unwrap(), 2);
+
+        // Same start, inside another, or under two clips at once
+        assert!(matches!(track.add_clip(clip(30), 24.0), Err(AudioError::ClipOverlap)));
+        assert!(matches!(track.add_clip(clip(28), 24.0), Err(AudioError::ClipOverlap)));
+        assert_eq!(track.clips().len(), 3);
+
+        assert_eq!(track.crossfades(24.0), vec![Crossfade { frames: 50..54, from: 1, to: 2 }]);
+        assert_eq!(track.crossfade_gain(1, 51.0, 24.0), 0.75);
+        assert_eq!(track.crossfade_gain(2, 51.0, 24.0), 0.25);
+        assert_eq!(track.crossfade_gain(0, 10.0, 24.0), 1.0);
+        assert_eq!(track.clip_at(52, 24.0), Some(2));
+
+        // Splitting cuts the envelope at the split's level
+        track.envelope_mut(0).unwrap().set_point(20, 0.0);
+        assert_eq!(track.split_at(10, 24.0), Some(1));
+        let clips = track.clips();
+        assert_eq!((clips[0].frame_range(24.0), clips[1].frame_range(24.0)), (0..10, 10..24));
+        assert_eq!(clips[0].volume_envelope.points, vec![(0, 1.0), (10, 0.5)]);
+        assert_eq!(clips[1].volume_envelope.points, vec![(0, 0.5), (10, 0.0)]);
+        assert_eq!(track.split_at(10, 24.0), None);
+        assert_eq!(track.split_at(26, 24.0), None);
+
+        // A split inside a crossfade would leave one half inside the other clip
+        let mut track = AudioTrack::new();
+        track.add_clip(clip(0), 24.0).unwrap();
+        track.add_clip(clip(20), 24.0).unwrap();
+        assert_eq!(track.split_at(22, 24.0), None);
+        let ranges: Vec<_> = track.clips().iter().map(|c| c.frame_range(24.0)).collect();
+        assert_eq!(ranges, vec![0..24, 20..44]);
+        assert_eq!(track.crossfade_gain(0, 22.0, 24.0) + track.crossfade_gain(1, 22.0, 24.0), 1.0);
+        assert_eq!(track.replace_clip(1, clip(22), 24.0).unwrap(), 1);
+        assert_eq!(track.split_at(30, 24.0), Some(2));
+
+        // Deserializing checks the overlap rules at the track's frame rate
+        let json = serde_json::to_string(&track).unwrap();
+        assert_eq!(serde_json::from_str::<AudioTrack>(&json).unwrap().clips().len(), 3);
+        let inside = json.replace("\"start_frame\":22", "\"start_frame\":0");
+        assert!(serde_json::from_str::<AudioTrack>(&inside).unwrap_err().to_string().contains("overlap"));
+    }
+    
     #[test]
     fn test_mock_audio_engine() {
         let mut engine = MockAudioEngine::new();
@@ -548,4 +804,142 @@ mod audio_tests {
         let end_frame = 10 + ((8.0 - 1.0 - 1.0) * 24.0) as u32; // 6 seconds * 24 fps
         assert_eq!(layer.audio_time_at_frame(end_frame + 10, 24.0), None);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod wav_tests {
+    use nannou_timeline::{AudioEngine, AudioError, WavAudioEngine};
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/audio").join(name)
+    }
+
+    const LEFT: [f32; 4] = [0.0, 0.5, -0.5, -1.0];
+    const RIGHT: [f32; 4] = [0.5, -0.5, -1.0, 0.0];
+
+    #[test]
+    fn test_decodes_every_supported_format() {
+        let cases = [
+            ("pcm8_mono.wav", 8000, 1),
+            ("pcm16_stereo.wav", 22050, 2),
+            ("pcm24_mono.wav", 48000, 1),
+            ("pcm32_stereo.wav", 8000, 2),
+            ("float32_mono.wav", 8000, 1),
+            ("float32_stereo_extensible.wav", 44100, 2),
+        ];
+        for (name, sample_rate, channels) in cases {
+            let mut engine = WavAudioEngine::new();
+            let source = engine.load_audio(&fixture(name)).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert!(source.loaded);
+            assert_eq!(source.sample_rate, sample_rate, "{}", name);
+            assert_eq!(source.channels, channels, "{}", name);
+            assert!((source.duration - 800.0 / sample_rate as f32).abs() < 1e-6, "{}", name);
+
+            let data = engine.wav_data(&source.id).unwrap();
+            assert_eq!(data.frames(), 800, "{}", name);
+            let left: Vec<f32> = data.channel(0).take(4).collect();
+            assert_eq!(left, LEFT, "{}", name);
+            if channels == 2 {
+                let right: Vec<f32> = data.channel(1).take(4).collect();
+                assert_eq!(right, RIGHT, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        let mut engine = WavAudioEngine::new();
+        assert!(matches!(engine.load_audio(&fixture("not_a_wav.wav")), Err(AudioError::UnsupportedFormat)));
+        assert!(matches!(engine.load_audio(&fixture("adpcm.wav")), Err(AudioError::UnsupportedFormat)));
+        assert!(matches!(engine.load_audio(&fixture("truncated.wav")), Err(AudioError::DecodingError(_))));
+        assert!(matches!(engine.load_audio(&fixture("missing.wav")), Err(AudioError::FileNotFound)));
+    }
+
+    #[test]
+    fn test_waveform_from_samples() {
+        let mut engine = WavAudioEngine::new();
+        let source = engine.load_audio(&fixture("pcm8_mono.wav")).unwrap();
+
+        // 8000 Hz at 80 fps is 100 samples per frame, 8 frames in all
+        let waveform = engine.generate_waveform(&source.id, 80.0).unwrap();
+        assert!(waveform.complete);
+        assert_eq!(waveform.peaks.len(), 8);
+        assert!(waveform.peaks.iter().all(|&peak| peak == (-1.0, 0.5)));
+
+        engine.play_segment(&source.id, 0.0, 0.1, 1.0).unwrap();
+        assert!(engine.is_playing(&source.id));
+        engine.unload_audio(&source.id).unwrap();
+        assert!(!engine.is_playing(&source.id));
+    }
+
+    /// `pattern` at 8000 Hz, linearly upsampled to 16000 Hz, over 800 source frames
+    fn upsampled(pattern: [f32; 4], n: usize) -> f32 {
+        let index = n / 2;
+        match (n % 2, index) {
+            (0, _) => pattern[index % 4],
+            (_, 799) => pattern[3],
+            _ => (pattern[index % 4] + pattern[(index + 1) % 4]) / 2.0,
+        }
+    }
+
+    #[test]
+    fn test_mixdown_is_sample_exact() {
+        use nannou_timeline::{mixdown_to_file, AudioLayer, AudioTrack, MixdownSettings};
+
+        let mut engine = WavAudioEngine::new();
+        let mono = engine.load_audio(&fixture("float32_mono.wav")).unwrap();
+        let stereo = engine.load_audio(&fixture("pcm32_stereo.wav")).unwrap();
+        let mut quiet = AudioLayer::new(mono, 0);
+        quiet.volume = 0.5;
+        // Starts at frame 1, when the 0.1 s mono clip ends
+        let late = AudioLayer::new(stereo, 1);
+
+        let path = std::env::temp_dir().join(format!("mixdown_{}.wav", std::process::id()));
+        let settings = MixdownSettings { fps: 10.0, sample_rate: 16000, channels: 2, bits_per_sample: 32 };
+        let tracks = [AudioTrack::from(quiet), AudioTrack::from(late)];
+        mixdown_to_file(&path, &tracks, |id| engine.wav_data(id), 0..2, &settings).unwrap();
+        let mix = nannou_timeline::wav::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected: Vec<f32> = (0..3200)
+            .flat_map(|n| match n {
+                0..=1599 => [0.5 * upsampled(LEFT, n); 2],
+                _ => [upsampled(LEFT, n - 1600), upsampled(RIGHT, n - 1600)],
+            })
+            .collect();
+        assert_eq!((mix.sample_rate, mix.channels), (16000, 2));
+        assert_eq!(mix.samples, expected);
+    }
+}
+
+#[cfg(test)]
+mod midi_tests {
+    use nannou_timeline::midi::{self, Division};
+    use nannou_timeline::{MidiSelection, MidiTarget, MockRiveEngine, TimelineController};
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/midi").join(name)
+    }
+
+    #[test]
+    fn test_import_kicks_as_labels() {
+        let file = midi::read(&fixture("groove.mid")).unwrap();
+        assert_eq!((file.format, file.division), (1, Division::TicksPerQuarter(96)));
+        let names: Vec<_> = file.tracks.iter().map(|track| track.name.as_deref()).collect();
+        assert_eq!(names, [Some("Conductor"), Some("Kit1")]);
+        assert_eq!(file.tracks[1].notes.len(), 12);
+        assert!((file.tempo_map[0].bpm() - 90.0).abs() < 1e-3);
+        assert_eq!(file.bar_beat_at(288), (2, 1.0));
+
+        let mut controller = TimelineController::new();
+        controller.config.fps = nannou_timeline::FpsPreset::Web;
+        let mut engine = MockRiveEngine::new();
+        let kicks = MidiSelection { tracks: vec![1], lowest_key: 36, highest_key: 36, ..Default::default() };
+        assert_eq!(controller.import_midi(&mut engine, &file, &kicks, &MidiTarget::Labels, 0), 4);
+
+        let labels: Vec<(u32, &str)> = controller.config.frame_labels.iter().map(|l| (l.frame, l.label.as_str())).collect();
+        assert_eq!(labels, [(0, "C2"), (30, "C2"), (60, "C2"), (90, "C2")]);
+    }
+}
@@ -19,6 +19,8 @@ fn create_test_timeline() -> (Timeline, Box<dyn RiveEngine>) {
             show_guides: true,
             snap_to_keyframes: true,
             snap_to_markers: false,
+            snap_to_beats: true,
+            beat_subdivision: 1,
         },
         ..Default::default()
     };